@@ -51,6 +51,12 @@ import {
   getAdminIrlRedeemTargetEligibility,
   type AdminIrlRedeemTargetKind,
 } from '../../../../functions/src/shared/adminIrlEligibility.js';
+import {
+  BOX_MINTER_CUSTODY_VAULT_SEED,
+  BOX_MINTER_DELIVERY_FEE_ROUTING_SEED,
+  BOX_MINTER_DELIVERY_FEES_SEED,
+  BOX_MINTER_RECEIPT_TREES_SEED,
+} from '../../../../functions/src/shared/boxMinterProtocol.js';
 import { dasAssetBoxId } from '../../../../functions/src/shared/dasAsset.js';
 import { HELIUS_COLLECTION_GROUPING_OPTIONS } from '../../../../functions/src/shared/dasAssetCollections.js';
 import { heliusSearchAssetsHasNextPage, heliusSearchAssetsItems } from '../../../../functions/src/shared/heliusDas.js';
@@ -733,6 +739,7 @@ async function mintPackReceipts(
     let lastError: unknown;
     while (batchSize >= 1) {
       const batch = pending.slice(0, batchSize);
+      const receiptTrees = await deliveryReceiptRuntime.fetchReceiptTreeBatch(connection, runtime, batch.length);
       const instructions = [
         ComputeBudgetProgram.setComputeUnitLimit({ units: 1_400_000 }),
         ...batch.map((item) => mplCoreBurn(item.asset, collection, signer.publicKey)),
@@ -743,6 +750,7 @@ async function mintPackReceipts(
           coreCollection: collection,
           boxIds: batch.map((item) => item.refId),
           dudeIds: [],
+          receiptTrees,
         }),
       ];
       let completed = false;
@@ -788,8 +796,10 @@ async function mintPackReceipts(
   return receiptTxs;
 }
 
+// Internal deliveries ship nowhere, so their address_cipher_hash stays zeroed and they take no
+// delivery_zone or receipt leaves; the assets move into the vault rather than freezing in place.
 function encodeDeliverArgs(deliveryId: number, deliveryBump: number): Buffer {
-  const data = Buffer.alloc(21);
+  const data = Buffer.alloc(21 + 32 + 1 + 4 + 1 + 4);
   IX_DELIVER.copy(data, 0);
   data.writeUInt32LE(deliveryId, 8);
   data.writeBigUInt64LE(0n, 12);
@@ -797,6 +807,13 @@ function encodeDeliverArgs(deliveryId: number, deliveryBump: number): Buffer {
   return data;
 }
 
+function deriveDropPda(runtime: Runtime, seed: string): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from(seed), runtime.boxMinterConfigPda.toBuffer()],
+    runtime.boxMinterProgramId,
+  )[0];
+}
+
 async function buildTransactionWithLookupTables(
   instructions: TransactionInstruction[],
   signer: Keypair,
@@ -843,6 +860,12 @@ async function ensureInternalDelivery(
         { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
         { pubkey: SPL_NOOP_PROGRAM_ID, isSigner: false, isWritable: false },
         { pubkey: deliveryPda, isSigner: false, isWritable: true },
+        { pubkey: deriveDropPda(runtime, BOX_MINTER_CUSTODY_VAULT_SEED), isSigner: false, isWritable: false },
+        { pubkey: deriveDropPda(runtime, BOX_MINTER_DELIVERY_FEES_SEED), isSigner: false, isWritable: false },
+        { pubkey: deriveDropPda(runtime, BOX_MINTER_DELIVERY_FEE_ROUTING_SEED), isSigner: false, isWritable: false },
+        { pubkey: deriveDropPda(runtime, BOX_MINTER_RECEIPT_TREES_SEED), isSigner: false, isWritable: false },
+        // bubblegum_program, receipts_log_wrapper, compression_program, mpl_core_cpi_signer: omitted.
+        ...Array.from({ length: 4 }, () => ({ pubkey: runtime.boxMinterProgramId, isSigner: false, isWritable: false })),
         ...assets.map((asset) => ({ pubkey: asset, isSigner: false, isWritable: true })),
       ],
       data: encodeDeliverArgs(deliveryId, bump),
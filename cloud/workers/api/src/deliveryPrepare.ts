@@ -24,6 +24,10 @@ import {
 } from '../../../../functions/src/shared/boxMinterConfigCodec.js';
 import {
   BOX_MINTER_CONFIG_SEED,
+  BOX_MINTER_CUSTODY_VAULT_SEED,
+  BOX_MINTER_DELIVERY_FEE_ROUTING_SEED,
+  BOX_MINTER_DELIVERY_FEES_SEED,
+  BOX_MINTER_RECEIPT_TREES_SEED,
   isBoxMinterDiscountMintsPerWallet,
   isConfiguredBoxMinterItemsPerBox,
 } from '../../../../functions/src/shared/boxMinterProtocol.js';
@@ -1036,6 +1040,7 @@ function encodeDeliverArgs(
   deliveryId: number,
   feeLamports: number,
   deliveryBump: number,
+  addressCipherHash: Buffer,
 ): Buffer {
   if (!Number.isInteger(deliveryId) || deliveryId < 1 || deliveryId > 0xffff_ffff) {
     throw new DeliveryPrepareError('invalid-argument', 'Invalid deliveryId');
@@ -1050,7 +1055,26 @@ function encodeDeliverArgs(
   if (!Number.isInteger(deliveryBump) || deliveryBump < 0 || deliveryBump > 255) {
     throw new DeliveryPrepareError('invalid-argument', 'Invalid delivery bump');
   }
-  return Buffer.concat([IX_DELIVER, u32LE(deliveryId), u64LE(feeLamports), Buffer.from([deliveryBump])]);
+  return Buffer.concat([
+    IX_DELIVER,
+    u32LE(deliveryId),
+    u64LE(feeLamports),
+    Buffer.from([deliveryBump]),
+    addressCipherHash,
+    Buffer.from([0]), // delivery_zone: None; the fee is quoted off-chain
+    u32LE(0), // receipts
+    Buffer.from([0]), // freeze_in_place
+    u32LE(0), // asset_id_bumps (deliver_with_receipts only)
+  ]);
+}
+
+// The delivery record keeps the sha256 of the saved address ciphertext, not the address itself.
+async function hashAddressCipher(address: AddressDocument): Promise<Buffer> {
+  const encrypted = typeof address.decoded.encrypted === 'string' ? address.decoded.encrypted : '';
+  if (!encrypted) {
+    throw new DeliveryPrepareError('failed-precondition', 'Delivery address is missing its encrypted payload.');
+  }
+  return Buffer.from(await crypto.subtle.digest('SHA-256', new TextEncoder().encode(encrypted)));
 }
 
 function isLegacySingletonConfigPda(programId: PublicKey, configPda: PublicKey): boolean {
@@ -1066,6 +1090,13 @@ function deriveDeliveryPda(runtime: DeliveryRuntime, deliveryId: number): [Publi
   return PublicKey.findProgramAddressSync(seeds, runtime.boxMinterProgramId);
 }
 
+function deriveDropPda(runtime: DeliveryRuntime, seed: string): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from(seed), runtime.boxMinterConfigPda.toBuffer()],
+    runtime.boxMinterProgramId,
+  )[0];
+}
+
 function buildInstruction(args: {
   runtime: DeliveryRuntime;
   owner: PublicKey;
@@ -1076,6 +1107,7 @@ function buildInstruction(args: {
   deliveryId: number;
   deliveryBump: number;
   deliveryLamports: number;
+  addressCipherHash: Buffer;
   assetPks: PublicKey[];
 }): TransactionInstruction {
   return new TransactionInstruction({
@@ -1090,9 +1122,22 @@ function buildInstruction(args: {
       { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
       { pubkey: SPL_NOOP_PROGRAM_ID, isSigner: false, isWritable: false },
       { pubkey: args.deliveryPda, isSigner: false, isWritable: true },
+      { pubkey: deriveDropPda(args.runtime, BOX_MINTER_CUSTODY_VAULT_SEED), isSigner: false, isWritable: false },
+      { pubkey: deriveDropPda(args.runtime, BOX_MINTER_DELIVERY_FEES_SEED), isSigner: false, isWritable: false },
+      { pubkey: deriveDropPda(args.runtime, BOX_MINTER_DELIVERY_FEE_ROUTING_SEED), isSigner: false, isWritable: false },
+      { pubkey: deriveDropPda(args.runtime, BOX_MINTER_RECEIPT_TREES_SEED), isSigner: false, isWritable: false },
+      // No receipt leaves are delivered: bubblegum_program, receipts_log_wrapper,
+      // compression_program and mpl_core_cpi_signer are omitted.
+      ...Array.from({ length: 4 }, () => ({ pubkey: args.runtime.boxMinterProgramId, isSigner: false, isWritable: false })),
       ...args.assetPks.map((pubkey) => ({ pubkey, isSigner: false, isWritable: true })),
     ],
-    data: encodeDeliverArgs(args.runtime, args.deliveryId, args.deliveryLamports, args.deliveryBump),
+    data: encodeDeliverArgs(
+      args.runtime,
+      args.deliveryId,
+      args.deliveryLamports,
+      args.deliveryBump,
+      args.addressCipherHash,
+    ),
   });
 }
 
@@ -1142,6 +1187,7 @@ function ensurePacketFits(args: {
   deliveryId: number;
   deliveryBump: number;
   deliveryLamports: number;
+  addressCipherHash: Buffer;
 }): TransactionInstruction {
   const instruction = buildInstruction({
     runtime: args.runtime,
@@ -1153,6 +1199,7 @@ function ensurePacketFits(args: {
     deliveryId: args.deliveryId,
     deliveryBump: args.deliveryBump,
     deliveryLamports: args.deliveryLamports,
+    addressCipherHash: args.addressCipherHash,
     assetPks: args.assetPks,
   });
   const instructions = [ComputeBudgetProgram.setComputeUnitLimit({ units: 1_400_000 }), instruction];
@@ -1171,6 +1218,7 @@ function ensurePacketFits(args: {
       deliveryId: args.deliveryId,
       deliveryBump: args.deliveryBump,
       deliveryLamports: args.deliveryLamports,
+      addressCipherHash: args.addressCipherHash,
       assetPks: args.assetPks.slice(0, count),
     });
     const candidateBytes = serializedTransactionSize(() => buildTransaction(
@@ -1283,6 +1331,7 @@ async function prepareDelivery(args: {
     ? address.decoded.countryCode
     : typeof address.decoded.country === 'string' ? address.decoded.country : '';
   const addressCountry = normalizeCountryCode(rawAddressCountry) || rawAddressCountry;
+  const addressCipherHash = await hashAddressCipher(address);
   const assetAbort = new AbortController();
   const assetContext = {
     ...args.providerContext,
@@ -1334,6 +1383,7 @@ async function prepareDelivery(args: {
       deliveryId,
       deliveryBump,
       deliveryLamports,
+      addressCipherHash,
     });
     const path = dropDeliveryOrderPath(dropId, deliveryId);
     let updateTime: string;
@@ -32,6 +32,14 @@ import {
   resolveDeliveryOrderDropId,
   resolveDeliveryOrderIdentity,
 } from '../../../../functions/src/deliveryOrderSummaries.js';
+import {
+  deriveReceiptLedgerPda,
+  deriveReceiptTreesPda,
+  receiptTreeRolloverAccountMetas,
+  resolveLegacyReceiptTreeBatch,
+  resolveReceiptTreeBatch,
+  type ReceiptTreeBatchAccounts,
+} from '../../../../functions/src/receiptTrees.js';
 import {
   DELIVERY_RECOVERY_PREPARED_CHECK_DELAYS_MS,
   DELIVERY_RECOVERY_PROCESSING_RETRY_DELAY_MS,
@@ -52,6 +60,7 @@ import {
 } from '../../../../functions/src/shared/boxMinterConfigCodec.js';
 import {
   BOX_MINTER_CONFIG_SEED,
+  BOX_MINTER_ROYALTIES_SEED,
   isBoxMinterDiscountMintsPerWallet,
   isConfiguredBoxMinterItemsPerBox,
 } from '../../../../functions/src/shared/boxMinterProtocol.js';
@@ -137,6 +146,11 @@ const ACCOUNT_DELIVERY_RECORD = Buffer.from('2b0f869afad50393', 'hex');
 const IX_DELIVER = Buffer.from('fa83de39d3e5d193', 'hex');
 const IX_CLOSE_DELIVERY = Buffer.from('ae641ab98ea5f208', 'hex');
 const IX_MINT_RECEIPTS = Buffer.from('c7c2556f92996a77', 'hex');
+const IX_SET_DELIVERY_STATUS = Buffer.from('6f028d1e7545a582', 'hex');
+// Records shorter than this predate delivery statuses and decode as Paid on-chain.
+const DELIVERY_RECORD_STATUS_OFFSET = 8 + 32 + 8 + 2 + 2 + 2 + 32 + 32 + 8;
+const DELIVERY_STATUS_PAID = 0;
+const DELIVERY_STATUS_PACKED = 1;
 const MPL_CORE_PROGRAM_ID = new PublicKey(MPL_CORE_PROGRAM_ADDRESS);
 const SPL_NOOP_PROGRAM_ID = new PublicKey(SPL_NOOP_PROGRAM_ADDRESS);
 const MPL_NOOP_PROGRAM_ID = new PublicKey(MPL_NOOP_PROGRAM_ADDRESS);
@@ -1651,8 +1665,11 @@ function deriveDeliveryPda(runtime: DeliveryRuntime, deliveryId: number): [Publi
   return PublicKey.findProgramAddressSync(seeds, runtime.boxMinterProgramId);
 }
 
-function deriveTreeConfigPda(merkleTree: PublicKey): PublicKey {
-  return PublicKey.findProgramAddressSync([merkleTree.toBuffer()], BUBBLEGUM_PROGRAM_ID)[0];
+function deriveRoyaltiesPda(runtime: DeliveryRuntime): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from(BOX_MINTER_ROYALTIES_SEED), runtime.boxMinterConfigPda.toBuffer()],
+    runtime.boxMinterProgramId,
+  )[0];
 }
 
 function decodeDeliverArgs(data: Buffer): { deliveryId: number; feeLamports: number; deliveryBump: number } {
@@ -1705,6 +1722,7 @@ function decodeDeliveryRecord(data: Buffer): {
   payer: PublicKey;
   deliveryFeeLamports: number;
   itemCount: number;
+  status: number;
 } {
   if (data.length < 50 || !data.subarray(0, 8).equals(ACCOUNT_DELIVERY_RECORD)) {
     throw new DeliveryReceiptError('failed-precondition', 'Delivery record account data is invalid.');
@@ -1717,6 +1735,7 @@ function decodeDeliveryRecord(data: Buffer): {
     payer: new PublicKey(data.subarray(8, 40)),
     deliveryFeeLamports: Number(fee),
     itemCount: data.readUInt16LE(48),
+    status: data.length > DELIVERY_RECORD_STATUS_OFFSET ? data[DELIVERY_RECORD_STATUS_OFFSET] : DELIVERY_STATUS_PAID,
   };
 }
 
@@ -1894,7 +1913,7 @@ async function verifyReceiptIssuanceBySignature(args: {
   }
   const [expectedDeliveryPda, expectedDeliveryBump] = deriveDeliveryPda(args.runtime, args.deliveryId);
   const keys = resolveInstructionAccounts(transaction);
-  const fixedAccountCount = 9;
+  const fixedAccountCount = 17;
   let deliverAccounts: PublicKey[] | undefined;
   let deliverData: Buffer | undefined;
   for (const instruction of transaction.transaction.message.compiledInstructions) {
@@ -2018,6 +2037,8 @@ function encodeMintReceiptsArgs(
   runtime: DeliveryRuntime,
   boxIds: readonly number[],
   dudeIds: readonly number[],
+  assetIdBumps: readonly number[],
+  delivery?: ReceiptDelivery,
 ): Buffer {
   for (const id of boxIds) {
     if (!Number.isSafeInteger(id) || id < 1 || id > 0xffff_ffff) {
@@ -2035,9 +2056,50 @@ function encodeMintReceiptsArgs(
     ...boxIds.map(u32LE),
     u32LE(dudeIds.length),
     ...dudeIds.map(u16LE),
+    Buffer.from([0]), // asset_data: None
+    u32LE(assetIdBumps.length),
+    Buffer.from(assetIdBumps),
+    ...(delivery
+      ? [Buffer.from([1]), u32LE(delivery.deliveryId), Buffer.from([delivery.deliveryBump & 0xff])]
+      : [Buffer.from([0])]), // delivery
   ]);
 }
 
+async function fetchReceiptTreeBatch(
+  connection: Connection,
+  runtime: DeliveryRuntime,
+  quantity: number,
+): Promise<ReceiptTreeBatchAccounts> {
+  const registry = deriveReceiptTreesPda(runtime.boxMinterProgramId, runtime.boxMinterConfigPda);
+  const info = await connection.getAccountInfo(registry, 'confirmed');
+  if (!info || !info.owner.equals(runtime.boxMinterProgramId)) {
+    // No registry yet: receipts still go into the drop's configured tree, which the cosigner signs for.
+    const treeConfig = PublicKey.findProgramAddressSync([runtime.receiptsMerkleTree.toBuffer()], BUBBLEGUM_PROGRAM_ID)[0];
+    const treeConfigInfo = await connection.getAccountInfo(treeConfig, 'confirmed');
+    if (!treeConfigInfo) {
+      throw new DeliveryReceiptError('failed-precondition', 'Receipts tree is not initialized.', {
+        merkleTree: runtime.receiptsMerkleTree.toBase58(),
+      });
+    }
+    return resolveLegacyReceiptTreeBatch(runtime.receiptsMerkleTree, treeConfigInfo.data, quantity);
+  }
+  try {
+    return resolveReceiptTreeBatch(info.data, quantity);
+  } catch (error) {
+    throw new DeliveryReceiptError('failed-precondition', 'Receipt tree registry has no room for this batch.', {
+      receiptTrees: registry.toBase58(),
+      reason: transactionErrorMessage(error),
+    });
+  }
+}
+
+// Delivery record `mint_receipts` marks `receipts_issued`; its assets are burned in the same transaction.
+type ReceiptDelivery = {
+  deliveryId: number;
+  deliveryBump: number;
+  deliveryPda: PublicKey;
+};
+
 function mintReceiptsInstruction(args: {
   runtime: DeliveryRuntime;
   signer: PublicKey;
@@ -2045,6 +2107,8 @@ function mintReceiptsInstruction(args: {
   coreCollection: PublicKey;
   boxIds: readonly number[];
   dudeIds: readonly number[];
+  receiptTrees: ReceiptTreeBatchAccounts;
+  delivery?: ReceiptDelivery;
 }): TransactionInstruction {
   return new TransactionInstruction({
     programId: args.runtime.boxMinterProgramId,
@@ -2052,8 +2116,8 @@ function mintReceiptsInstruction(args: {
       { pubkey: args.runtime.boxMinterConfigPda, isSigner: false, isWritable: false },
       { pubkey: args.signer, isSigner: true, isWritable: true },
       { pubkey: args.recipient, isSigner: false, isWritable: false },
-      { pubkey: args.runtime.receiptsMerkleTree, isSigner: false, isWritable: true },
-      { pubkey: deriveTreeConfigPda(args.runtime.receiptsMerkleTree), isSigner: false, isWritable: true },
+      { pubkey: args.receiptTrees.merkleTree, isSigner: false, isWritable: true },
+      { pubkey: args.receiptTrees.treeConfig, isSigner: false, isWritable: true },
       { pubkey: args.coreCollection, isSigner: false, isWritable: true },
       { pubkey: BUBBLEGUM_PROGRAM_ID, isSigner: false, isWritable: false },
       { pubkey: MPL_NOOP_PROGRAM_ID, isSigner: false, isWritable: false },
@@ -2061,8 +2125,54 @@ function mintReceiptsInstruction(args: {
       { pubkey: MPL_CORE_PROGRAM_ID, isSigner: false, isWritable: false },
       { pubkey: MPL_CORE_CPI_SIGNER, isSigner: false, isWritable: false },
       { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+      { pubkey: deriveRoyaltiesPda(args.runtime), isSigner: false, isWritable: false },
+      {
+        pubkey: deriveReceiptTreesPda(args.runtime.boxMinterProgramId, args.runtime.boxMinterConfigPda),
+        isSigner: false,
+        isWritable: true,
+      },
+      {
+        pubkey: deriveReceiptLedgerPda(args.runtime.boxMinterProgramId, args.runtime.boxMinterConfigPda),
+        isSigner: false,
+        isWritable: true,
+      },
+      ...receiptTreeRolloverAccountMetas(args.receiptTrees, args.runtime.boxMinterProgramId),
+      {
+        pubkey: args.delivery?.deliveryPda ?? args.runtime.boxMinterProgramId,
+        isSigner: false,
+        isWritable: Boolean(args.delivery),
+      },
+    ],
+    data: encodeMintReceiptsArgs(
+      args.runtime,
+      args.boxIds,
+      args.dudeIds,
+      args.receiptTrees.assetIdBumps,
+      args.delivery,
+    ),
+  });
+}
+
+// set_delivery_status(Packed) without a tracking hash: receipts are only minted for packed deliveries.
+function packDeliveryInstruction(args: {
+  runtime: DeliveryRuntime;
+  signer: PublicKey;
+  delivery: ReceiptDelivery;
+}): TransactionInstruction {
+  return new TransactionInstruction({
+    programId: args.runtime.boxMinterProgramId,
+    keys: [
+      { pubkey: args.runtime.boxMinterConfigPda, isSigner: false, isWritable: false },
+      { pubkey: args.signer, isSigner: true, isWritable: true },
+      { pubkey: args.delivery.deliveryPda, isSigner: false, isWritable: true },
+      { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
     ],
-    data: encodeMintReceiptsArgs(args.runtime, args.boxIds, args.dudeIds),
+    data: Buffer.concat([
+      IX_SET_DELIVERY_STATUS,
+      u32LE(args.delivery.deliveryId),
+      Buffer.from([args.delivery.deliveryBump & 0xff, DELIVERY_STATUS_PACKED]),
+      Buffer.from([0]), // tracking_hash: None
+    ]),
   });
 }
 
@@ -2072,6 +2182,7 @@ function closeDeliveryInstruction(args: {
   deliveryPda: PublicKey;
   deliveryId: number;
   deliveryBump: number;
+  payer: PublicKey;
 }): TransactionInstruction {
   return new TransactionInstruction({
     programId: args.runtime.boxMinterProgramId,
@@ -2080,6 +2191,7 @@ function closeDeliveryInstruction(args: {
       { pubkey: args.signer, isSigner: true, isWritable: true },
       { pubkey: args.deliveryPda, isSigner: false, isWritable: true },
       { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+      { pubkey: args.payer, isSigner: false, isWritable: true },
     ],
     data: Buffer.concat([
       IX_CLOSE_DELIVERY,
@@ -2375,6 +2487,8 @@ async function sendReceiptBatch(args: {
   owner: PublicKey;
   coreCollection: PublicKey;
   batch: readonly { asset: PublicKey; kind: 'box' | 'dude'; refId: number }[];
+  delivery: ReceiptDelivery;
+  packDelivery: boolean;
   signal: AbortSignal;
 }): Promise<string> {
   const burnInstructions = args.batch.map((item) => mplCoreBurnInstruction({
@@ -2384,8 +2498,12 @@ async function sendReceiptBatch(args: {
   }));
   const boxIds = args.batch.filter((item) => item.kind === 'box').map((item) => item.refId);
   const dudeIds = args.batch.filter((item) => item.kind === 'dude').map((item) => item.refId);
+  const receiptTrees = await fetchReceiptTreeBatch(args.connection, args.runtime, args.batch.length);
   const instructions = [
     ComputeBudgetProgram.setComputeUnitLimit({ units: 1_400_000 }),
+    ...(args.packDelivery
+      ? [packDeliveryInstruction({ runtime: args.runtime, signer: args.signer.publicKey, delivery: args.delivery })]
+      : []),
     ...burnInstructions,
     mintReceiptsInstruction({
       runtime: args.runtime,
@@ -2394,6 +2512,8 @@ async function sendReceiptBatch(args: {
       coreCollection: args.coreCollection,
       boxIds,
       dudeIds,
+      receiptTrees,
+      delivery: args.delivery,
     }),
   ];
   let lastError: unknown;
@@ -2504,10 +2624,7 @@ async function closeDeliveryPda(args: {
   deliveryBump: number;
   signal: AbortSignal;
 }): Promise<string | null> {
-  const info = await args.connection.getAccountInfo(args.deliveryPda, {
-    commitment: 'confirmed',
-    dataSlice: { offset: 0, length: 0 },
-  });
+  const info = await args.connection.getAccountInfo(args.deliveryPda, 'confirmed');
   if (!info) return null;
   const { blockhash } = await args.connection.getLatestBlockhash('confirmed');
   const transaction = buildTransaction([
@@ -2518,6 +2635,7 @@ async function closeDeliveryPda(args: {
       deliveryPda: args.deliveryPda,
       deliveryId: args.deliveryId,
       deliveryBump: args.deliveryBump,
+      payer: decodeDeliveryRecord(Buffer.from(info.data)).payer,
     }),
   ], args.signer.publicKey, blockhash, args.signer);
   return sendAndConfirmSignedTransaction(args.connection, transaction, args.signal, 'Close delivery');
@@ -2610,6 +2728,11 @@ async function retryIssueReceipts(args: {
   const alreadyProcessed = verified.targetAssetIds.length - pending.length;
   const receiptTxs: string[] = [];
   let totalProcessed = 0;
+  // A paid delivery is packed by the first receipt batch.
+  const deliveryAccount = pending.length ? await fetchDeliveryRecord(connection, runtime, deliveryId) : null;
+  let packDelivery = Boolean(
+    deliveryAccount && decodeDeliveryRecord(Buffer.from(deliveryAccount.deliveryInfo.data)).status === DELIVERY_STATUS_PAID,
+  );
   while (pending.length) {
     if (args.provider.signal.aborted) throw args.provider.signal.reason;
     let batchSize = Math.min(pending.length, 3);
@@ -2623,8 +2746,15 @@ async function retryIssueReceipts(args: {
           owner,
           coreCollection: onchain.coreCollection,
           batch: pending.slice(0, batchSize),
+          delivery: {
+            deliveryId,
+            deliveryBump: verified.expectedDeliveryBump,
+            deliveryPda: verified.expectedDeliveryPda,
+          },
+          packDelivery,
           signal: args.provider.signal,
         });
+        packDelivery = false;
         receiptTxs.push(signature);
         totalProcessed += batchSize;
         pending.splice(0, batchSize);
@@ -3210,6 +3340,7 @@ export const deliveryReceiptRuntime = {
   DeliveryReceiptError,
   deriveDeliveryPda,
   fetchOnchainConfig,
+  fetchReceiptTreeBatch,
   firestoreInteger,
   firestoreString,
   firestoreValue,
@@ -23,6 +23,14 @@ import {
   assetProofTreePublicKey,
   normalizedAssetProofAccounts,
 } from '../../../../functions/src/receiptProof.js';
+import {
+  deriveReceiptLedgerPda,
+  deriveReceiptTreesPda,
+  receiptTreeRolloverAccountMetas,
+  resolveLegacyReceiptTreeBatch,
+  resolveReceiptTreeBatch,
+  type ReceiptTreeBatchAccounts,
+} from '../../../../functions/src/receiptTrees.js';
 import {
   BoxMinterConfigCodecError,
   decodeBoxMinterConfigData,
@@ -31,6 +39,7 @@ import {
 import {
   BOX_MINTER_CONFIG_SEED,
   BOX_MINTER_MIN_OPENABLE_ITEMS_PER_BOX,
+  BOX_MINTER_ROYALTIES_SEED,
 } from '../../../../functions/src/shared/boxMinterProtocol.js';
 import {
   boxMinterMetadataBaseMatchesDrop,
@@ -191,6 +200,11 @@ type IrlClaimDependencies = {
     runtime: IrlClaimRuntime,
   ) => Promise<{ blockhash: string; blockhashContextSlot: number }>;
   loadLookupTable: (context: ProviderContext, runtime: IrlClaimRuntime) => Promise<AddressLookupTableAccount[]>;
+  loadReceiptTreeBatch: (
+    context: ProviderContext,
+    runtime: IrlClaimRuntime,
+    quantity: number,
+  ) => Promise<ReceiptTreeBatchAccounts>;
 };
 
 type IrlClaimMetrics = {
@@ -726,6 +740,42 @@ async function loadLookupTable(
   }
 }
 
+async function loadReceiptTreeBatch(
+  context: ProviderContext,
+  runtime: IrlClaimRuntime,
+  quantity: number,
+): Promise<ReceiptTreeBatchAccounts> {
+  const registry = deriveReceiptTreesPda(runtime.boxMinterProgramId, runtime.boxMinterConfigPda);
+  const result = await rpcCall(context, runtime, 'getAccountInfo', [
+    registry.toBase58(),
+    { commitment: 'confirmed', encoding: 'base64' },
+  ]);
+  const value = isRecord(result) ? result.value : undefined;
+  if (!value) {
+    // No registry yet: receipts still go into the drop's configured tree, which the cosigner signs for.
+    const treeConfig = await rpcCall(context, runtime, 'getAccountInfo', [
+      deriveTreeConfig(runtime.receiptsMerkleTree).toBase58(),
+      { commitment: 'confirmed', encoding: 'base64' },
+    ]);
+    const treeConfigValue = isRecord(treeConfig) ? treeConfig.value : undefined;
+    if (!treeConfigValue) {
+      throw new IrlClaimError('failed-precondition', 'Receipts tree is not initialized.', {
+        receiptsTree: runtime.receiptsMerkleTree.toBase58(),
+      });
+    }
+    return resolveLegacyReceiptTreeBatch(
+      runtime.receiptsMerkleTree,
+      parseRpcAccount(treeConfigValue, 'RECEIPTS_TREE_CONFIG').data,
+      quantity,
+    );
+  }
+  const account = parseRpcAccount(value, 'RECEIPT_TREES');
+  if (!account.owner.equals(runtime.boxMinterProgramId)) {
+    throw new IrlClaimError('failed-precondition', 'RECEIPT_TREES has an unexpected owner.');
+  }
+  return resolveReceiptTreeBatch(account.data, quantity);
+}
+
 async function loadWalletSession(context: FirestoreReadContext, uid: string): Promise<string> {
   const url = new URL(`${FIRESTORE_DOCUMENTS_BASE_URL}/${WALLET_SESSION_COLLECTION}/${encodeURIComponent(uid)}`);
   const document = await authenticatedFirestoreRequest({ ...context, method: 'GET', url: url.toString() });
@@ -932,6 +982,13 @@ function parseProof(
   };
 }
 
+function deriveRoyalties(runtime: IrlClaimRuntime): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from(BOX_MINTER_ROYALTIES_SEED), runtime.boxMinterConfigPda.toBuffer()],
+    runtime.boxMinterProgramId,
+  )[0];
+}
+
 function deriveTreeConfig(merkleTree: PublicKey): PublicKey {
   return PublicKey.findProgramAddressSync([merkleTree.toBuffer()], BUBBLEGUM_PROGRAM_ID)[0];
 }
@@ -978,12 +1035,17 @@ function mintReceiptsInstruction(
   owner: PublicKey,
   coreCollection: PublicKey,
   dudeIds: number[],
+  receiptTrees: ReceiptTreeBatchAccounts,
 ): TransactionInstruction {
   const data = Buffer.concat([
     IX_MINT_RECEIPTS,
     u32LE(0),
     u32LE(dudeIds.length),
     ...dudeIds.map(u16LE),
+    Buffer.from([0]), // asset_data: None
+    u32LE(receiptTrees.assetIdBumps.length),
+    Buffer.from(receiptTrees.assetIdBumps),
+    Buffer.from([0]), // delivery: None
   ]);
   return new TransactionInstruction({
     programId: runtime.boxMinterProgramId,
@@ -991,8 +1053,8 @@ function mintReceiptsInstruction(
       { pubkey: runtime.boxMinterConfigPda, isSigner: false, isWritable: false },
       { pubkey: cosigner, isSigner: true, isWritable: true },
       { pubkey: owner, isSigner: false, isWritable: false },
-      { pubkey: runtime.receiptsMerkleTree, isSigner: false, isWritable: true },
-      { pubkey: deriveTreeConfig(runtime.receiptsMerkleTree), isSigner: false, isWritable: true },
+      { pubkey: receiptTrees.merkleTree, isSigner: false, isWritable: true },
+      { pubkey: receiptTrees.treeConfig, isSigner: false, isWritable: true },
       { pubkey: coreCollection, isSigner: false, isWritable: true },
       { pubkey: BUBBLEGUM_PROGRAM_ID, isSigner: false, isWritable: false },
       { pubkey: MPL_NOOP_PROGRAM_ID, isSigner: false, isWritable: false },
@@ -1000,6 +1062,11 @@ function mintReceiptsInstruction(
       { pubkey: MPL_CORE_PROGRAM_ID, isSigner: false, isWritable: false },
       { pubkey: MPL_CORE_CPI_SIGNER, isSigner: false, isWritable: false },
       { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+      { pubkey: deriveRoyalties(runtime), isSigner: false, isWritable: false },
+      { pubkey: deriveReceiptTreesPda(runtime.boxMinterProgramId, runtime.boxMinterConfigPda), isSigner: false, isWritable: true },
+      { pubkey: deriveReceiptLedgerPda(runtime.boxMinterProgramId, runtime.boxMinterConfigPda), isSigner: false, isWritable: true },
+      ...receiptTreeRolloverAccountMetas(receiptTrees, runtime.boxMinterProgramId),
+      { pubkey: runtime.boxMinterProgramId, isSigner: false, isWritable: false }, // delivery: None
     ],
     data,
   });
@@ -1159,10 +1226,19 @@ async function prepareClaim(args: {
     owner,
   );
   const ownerKey = new PublicKey(owner);
+  let receiptTrees: ReceiptTreeBatchAccounts;
+  try {
+    receiptTrees = await args.dependencies.loadReceiptTreeBatch(args.providerContext, runtime, dudeIds.length);
+  } catch (error) {
+    if (error instanceof IrlClaimError) throw error;
+    throw new IrlClaimError('failed-precondition', 'Receipt tree registry has no room for this claim.', {
+      reason: error instanceof Error ? error.message : String(error),
+    });
+  }
   const instructions = [
     ComputeBudgetProgram.setComputeUnitLimit({ units: 1_400_000 }),
     burnInstruction(ownerKey, onchain.coreCollection, proof),
-    mintReceiptsInstruction(runtime, cosigner.publicKey, ownerKey, onchain.coreCollection, dudeIds),
+    mintReceiptsInstruction(runtime, cosigner.publicKey, ownerKey, onchain.coreCollection, dudeIds, receiptTrees),
   ];
   const latestBlockhash = await args.dependencies.loadLatestBlockhash(args.providerContext, runtime);
   const raw = await buildPreparedTransaction({
@@ -1199,6 +1275,7 @@ const defaultDependencies: IrlClaimDependencies = {
   loadOnchainState,
   loadLatestBlockhash,
   loadLookupTable,
+  loadReceiptTreeBatch,
 };
 
 export async function handleIrlClaimPrepare(
@@ -1309,6 +1386,7 @@ export const irlClaimTestHooks = {
   loadLatestBlockhash,
   loadLookupTable,
   loadOnchainState,
+  loadReceiptTreeBatch,
   loadWalletSession,
   mintReceiptsInstruction,
   parseProof,
@@ -31,8 +31,11 @@ import {
 } from '../../../../functions/src/shared/boxMinterConfigCodec.js';
 import {
   BOX_MINTER_CONFIG_SEED,
+  BOX_MINTER_CUSTODY_VAULT_SEED,
   BOX_MINTER_MIN_OPENABLE_ITEMS_PER_BOX,
+  BOX_MINTER_MINT_VARIANT_KIND_TABLE,
   BOX_MINTER_PENDING_OPEN_SEED,
+  BOX_MINTER_VARIANT_TABLE_SEED,
 } from '../../../../functions/src/shared/boxMinterProtocol.js';
 import {
   DudeAssignmentPoolExhaustedError,
@@ -556,7 +559,7 @@ function configuredRoutingMatches(runtime: RevealRuntime, decoded: DecodedBoxMin
 async function validateOnchainConfig(
   context: ProviderContext,
   runtime: RevealRuntime,
-): Promise<{ admin: PublicKey; coreCollection: PublicKey }> {
+): Promise<{ admin: PublicKey; coreCollection: PublicKey; mintVariantKind: number }> {
   const result = await rpcCall(context, runtime, 'getMultipleAccounts', [[
     runtime.collectionMint.toBase58(),
     runtime.boxMinterConfigPda.toBase58(),
@@ -608,7 +611,7 @@ async function validateOnchainConfig(
       onchainMetadataBase: normalizeBoxMinterMetadataBaseForComparison(decoded.uriBase),
     });
   }
-  return { admin: new PublicKey(decoded.admin), coreCollection };
+  return { admin: new PublicKey(decoded.admin), coreCollection, mintVariantKind: decoded.mintVariantKind };
 }
 
 function cosigner(env: Env): Keypair {
@@ -2216,6 +2219,24 @@ export async function handleRevealDudes(
         { pubkey: SPL_NOOP_PROGRAM_ID, isSigner: false, isWritable: false },
         { pubkey: pending.pendingPda, isSigner: false, isWritable: true },
         { pubkey: owner, isSigner: false, isWritable: false },
+        {
+          pubkey: onchain.mintVariantKind === BOX_MINTER_MINT_VARIANT_KIND_TABLE
+            ? PublicKey.findProgramAddressSync(
+              [Buffer.from(BOX_MINTER_VARIANT_TABLE_SEED), runtime.boxMinterConfigPda.toBuffer()],
+              runtime.boxMinterProgramId,
+            )[0]
+            : runtime.boxMinterProgramId,
+          isSigner: false,
+          isWritable: false,
+        },
+        {
+          pubkey: PublicKey.findProgramAddressSync(
+            [Buffer.from(BOX_MINTER_CUSTODY_VAULT_SEED), runtime.boxMinterConfigPda.toBuffer()],
+            runtime.boxMinterProgramId,
+          )[0],
+          isSigner: false,
+          isWritable: false,
+        },
         ...pending.dudeAssets.map((pubkey) => ({ pubkey, isSigner: false, isWritable: true })),
       ],
       data: Buffer.from(encodeFinalizeOpenBoxArgs(assignment.dudeIds, {
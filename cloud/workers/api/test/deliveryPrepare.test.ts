@@ -1,4 +1,5 @@
 import assert from 'node:assert/strict';
+import { createHash } from 'node:crypto';
 import test from 'node:test';
 import bs58 from 'bs58';
 import nacl from 'tweetnacl';
@@ -237,6 +238,8 @@ test('delivery preparation returns the server-signed owner transaction and exact
     nacl.sign.detached.verify(transaction.message.serialize(), transaction.signatures[1], COSIGNER.publicKey.toBytes()),
     true,
   );
+  const deliverData = Buffer.from(transaction.message.compiledInstructions.at(-1)!.data);
+  assert.deepEqual(deliverData.subarray(21, 53), createHash('sha256').update('cipher').digest());
 });
 
 test('delivery preparation schedules recovery from the document reservation time', async () => {
@@ -803,7 +803,7 @@ test('receipt batches shrink after transient retries are exhausted', () => {
   assert.equal(deliveryReceiptTestHooks.shouldShrinkReceiptBatch(error), true);
 });
 
-test('delivery record decoding validates discriminator, payer, fee, and item count', () => {
+test('delivery record decoding validates discriminator, payer, fee, item count, and status', () => {
   const payer = Keypair.generate().publicKey;
   const data = Buffer.alloc(50);
   Buffer.from('2b0f869afad50393', 'hex').copy(data, 0);
@@ -814,6 +814,11 @@ test('delivery record decoding validates discriminator, payer, fee, and item cou
   assert.equal(decoded.payer.toBase58(), payer.toBase58());
   assert.equal(decoded.deliveryFeeLamports, 1234);
   assert.equal(decoded.itemCount, 3);
+  assert.equal(decoded.status, 0);
+  const packed = Buffer.alloc(127);
+  data.copy(packed);
+  packed[126] = 1;
+  assert.equal(deliveryReceiptTestHooks.decodeDeliveryRecord(packed).status, 1);
   assert.throws(() => deliveryReceiptTestHooks.decodeDeliveryRecord(Buffer.alloc(50)), /invalid/);
 
   const owner = new PublicKey(OWNER);
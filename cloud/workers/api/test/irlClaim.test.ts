@@ -14,6 +14,8 @@ import {
   type FunctionsDropConfig,
 } from '../../../../functions/src/config/deployment.ts';
 import type { DecodedBoxMinterConfigData } from '../../../../functions/src/shared/boxMinterConfigCodec.ts';
+import { resolveReceiptTreeBatch } from '../../../../functions/src/receiptTrees.ts';
+import { RECEIPT_TREES_DISCRIMINATOR } from '../../../../functions/src/shared/receiptTreesCodec.ts';
 import {
   BUBBLEGUM_PROGRAM_ADDRESS,
 } from '../../../../functions/src/shared/solanaProgramAddresses.ts';
@@ -92,6 +94,16 @@ const ONCHAIN_CONFIG: DecodedBoxMinterConfigData = {
   },
 };
 
+function receiptTreesData(): Uint8Array {
+  const tree = Buffer.alloc(32 + 8 + 8);
+  RECEIPTS_TREE.toBuffer().copy(tree, 0);
+  tree.writeBigUInt64LE(1024n, 32);
+  tree.writeBigUInt64LE(5n, 40);
+  const count = Buffer.alloc(4);
+  count.writeUInt32LE(1, 0);
+  return Buffer.concat([Buffer.from(RECEIPT_TREES_DISCRIMINATOR), Buffer.alloc(32), Buffer.from([0]), count, tree, Buffer.from([255])]);
+}
+
 function certificateAsset(overrides: Record<string, unknown> = {}) {
   return {
     id: CERTIFICATE.toBase58(),
@@ -150,6 +162,7 @@ function dependencies(overrides: Record<string, unknown> = {}) {
       blockhashContextSlot: BLOCKHASH_CONTEXT_SLOT,
     }),
     loadLookupTable: async () => [],
+    loadReceiptTreeBatch: async (_context, _runtime, quantity) => resolveReceiptTreeBatch(receiptTreesData(), quantity),
     providerFetch: async () => {
       throw new Error('unexpected provider fetch');
     },
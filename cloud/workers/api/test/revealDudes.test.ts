@@ -86,6 +86,7 @@ function dependencies(overrides: Record<string, unknown> = {}) {
     validateOnchainConfig: async () => ({
       admin: COSIGNER.publicKey,
       coreCollection: new PublicKey(revealDudesTestHooks.runtimeForDrop(DROP_ID).config.collectionMint),
+      mintVariantKind: 0,
     }),
     loadPendingOpen: async () => ({
       pendingPda: PENDING,
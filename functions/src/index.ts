@@ -74,6 +74,14 @@ import {
   type ReceiptMetadataReference,
 } from './receiptProof.js';
 import { IX_BUBBLEGUM_TRANSFER_V2, bubblegumTransferV2Ix } from './bubblegum.js';
+import {
+  deriveReceiptLedgerPda,
+  deriveReceiptTreesPda,
+  receiptTreeRolloverAccountMetas,
+  resolveLegacyReceiptTreeBatch,
+  resolveReceiptTreeBatch,
+  type ReceiptTreeBatchAccounts,
+} from './receiptTrees.js';
 import {
   firstRejectedReadyToShipNotificationError,
   normalizeNotificationEmailRecipient,
@@ -126,6 +134,7 @@ import {
   BOX_MINTER_MIN_CONFIGURED_ITEMS_PER_BOX as MIN_ITEMS_PER_BOX,
   BOX_MINTER_MIN_DISCOUNT_MINTS_PER_WALLET as MIN_DISCOUNT_MINTS_PER_WALLET,
   BOX_MINTER_MIN_OPENABLE_ITEMS_PER_BOX as MIN_OPENABLE_ITEMS_PER_BOX,
+  BOX_MINTER_ROYALTIES_SEED,
   isBoxMinterDiscountMintsPerWallet,
   isConfiguredBoxMinterItemsPerBox,
   type BoxMinterMintVariantTuple,
@@ -526,6 +535,8 @@ const IX_DELIVER = Buffer.from('fa83de39d3e5d193', 'hex');
 const IX_CLOSE_DELIVERY = Buffer.from('ae641ab98ea5f208', 'hex');
 // Anchor discriminator = sha256("global:mint_receipts")[0..8]
 const IX_MINT_RECEIPTS = Buffer.from('c7c2556f92996a77', 'hex');
+// Anchor discriminator = sha256("global:set_delivery_status")[0..8]
+const IX_SET_DELIVERY_STATUS = Buffer.from('6f028d1e7545a582', 'hex');
 
 // Bubblegum v2 burn discriminator (kinobi generated).
 const IX_BURN_V2 = Buffer.from([115, 210, 34, 240, 232, 143, 183, 16]);
@@ -1464,6 +1475,13 @@ function encodeCloseDeliveryArgs(args: { deliveryId: number; deliveryBump: numbe
   return Buffer.concat([IX_CLOSE_DELIVERY, u32LE(deliveryId), Buffer.from([bump & 0xff])]);
 }
 
+// set_delivery_status(Packed) without a tracking hash; its args start with the same
+// (delivery_id, delivery_bump) as close_delivery.
+function encodePackDeliveryArgs(args: { deliveryId: number; deliveryBump: number }): Buffer {
+  const deliveryArgs = encodeCloseDeliveryArgs(args).subarray(IX_CLOSE_DELIVERY.length);
+  return Buffer.concat([IX_SET_DELIVERY_STATUS, deliveryArgs, Buffer.from([DELIVERY_STATUS_PACKED]), borshOption(null)]);
+}
+
 function isLegacySingletonConfigPda(programId: PublicKey, configPda: PublicKey): boolean {
   return configPda.equals(
     PublicKey.findProgramAddressSync([Buffer.from(BOX_MINTER_CONFIG_SEED)], programId)[0],
@@ -1490,6 +1508,13 @@ function deriveDeliveryPdaForDrop(
   return deriveDeliveryPda(dropRuntime.boxMinterProgramId, dropRuntime.boxMinterConfigPda, deliveryId);
 }
 
+function deriveRoyaltiesPdaForDrop(dropRuntime: Pick<DropRuntime, 'boxMinterProgramId' | 'boxMinterConfigPda'>): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from(BOX_MINTER_ROYALTIES_SEED), dropRuntime.boxMinterConfigPda.toBuffer()],
+    dropRuntime.boxMinterProgramId,
+  )[0];
+}
+
 function deriveTreeConfigPda(merkleTree: PublicKey): PublicKey {
   return PublicKey.findProgramAddressSync([merkleTree.toBuffer()], BUBBLEGUM_PROGRAM_ID)[0];
 }
@@ -1808,6 +1833,38 @@ function decodeBoxMinterConfigData(data: Buffer | Uint8Array): DecodedBoxMinterC
   };
 }
 
+async function fetchReceiptTreeBatch(params: {
+  dropRuntime: DropRuntime;
+  conn: Connection;
+  quantity: number;
+  context: string;
+}): Promise<ReceiptTreeBatchAccounts> {
+  const { dropRuntime, conn, quantity, context } = params;
+  const registryPda = deriveReceiptTreesPda(dropRuntime.boxMinterProgramId, dropRuntime.boxMinterConfigPda);
+  const info = await withTimeout(conn.getAccountInfo(registryPda, { commitment: 'confirmed' }), RPC_TIMEOUT_MS, context);
+  if (!info?.data || !info.owner.equals(dropRuntime.boxMinterProgramId)) {
+    // No registry yet: receipts still go into the drop's configured tree, which the cosigner signs for.
+    const treeConfigPda = deriveTreeConfigPda(dropRuntime.receiptsMerkleTree);
+    const treeConfig = await withTimeout(conn.getAccountInfo(treeConfigPda, { commitment: 'confirmed' }), RPC_TIMEOUT_MS, context);
+    if (!treeConfig?.data) {
+      throw new HttpsError('failed-precondition', 'Receipts tree is not initialized', {
+        merkleTree: dropRuntime.receiptsMerkleTreeStr,
+        dropId: dropRuntime.dropId,
+      });
+    }
+    return resolveLegacyReceiptTreeBatch(dropRuntime.receiptsMerkleTree, treeConfig.data, quantity);
+  }
+  try {
+    return resolveReceiptTreeBatch(info.data, quantity);
+  } catch (err) {
+    throw new HttpsError('failed-precondition', 'Receipt tree registry has no room for this batch', {
+      receiptTrees: registryPda.toBase58(),
+      reason: err instanceof Error ? err.message : String(err),
+      dropId: dropRuntime.dropId,
+    });
+  }
+}
+
 async function fetchDecodedBoxMinterConfigAccount(params: {
   dropRuntime: DropRuntime;
   conn: Connection;
@@ -1883,7 +1940,15 @@ function stripeCheckoutFlowDeps(): StripeCheckoutFlowDeps<DropRuntime, DecodedBo
   };
 }
 
-function encodeMintReceiptsArgs(args: { boxIds: number[]; dudeIds: number[] }, dropRuntime: DropRuntime): Buffer {
+function encodeMintReceiptsArgs(
+  args: {
+    boxIds: number[];
+    dudeIds: number[];
+    assetIdBumps: number[];
+    delivery?: { deliveryId: number; deliveryBump: number } | null;
+  },
+  dropRuntime: DropRuntime,
+): Buffer {
   const boxIds = Array.isArray(args.boxIds) ? args.boxIds.map((n) => Number(n)) : [];
   const dudeIds = Array.isArray(args.dudeIds) ? args.dudeIds.map((n) => Number(n)) : [];
 
@@ -1904,16 +1969,32 @@ function encodeMintReceiptsArgs(args: { boxIds: number[]; dudeIds: number[] }, d
     ...boxIds.map((id) => u32LE(Math.floor(id))),
     u32LE(dudeIds.length),
     ...dudeIds.map((id) => u16LE(Math.floor(id))),
+    borshOption(null), // asset_data
+    u32LE(args.assetIdBumps.length),
+    Buffer.from(args.assetIdBumps), // asset_id_bumps
+    borshOption(
+      args.delivery ? Buffer.concat([u32LE(args.delivery.deliveryId), Buffer.from([args.delivery.deliveryBump & 0xff])]) : null,
+    ), // delivery
   ]);
 }
 
+// Discriminator + payer + delivery_fee_lamports + item_count: the prefix every record layout shares.
+const DELIVERY_RECORD_HEADER_LEN = 8 + 32 + 8 + 2;
+// Header + box/figure counts + assets hash + address cipher hash + created slot; records shorter
+// than this predate delivery statuses and decode as Paid on-chain.
+const DELIVERY_RECORD_STATUS_OFFSET = DELIVERY_RECORD_HEADER_LEN + 2 + 2 + 32 + 32 + 8;
+// DeliveryStatus variants (borsh enum index).
+const DELIVERY_STATUS_PAID = 0;
+const DELIVERY_STATUS_PACKED = 1;
+
 function decodeDeliveryRecord(data: Buffer): {
   payer: PublicKey;
   deliveryFeeLamports: number;
   itemCount: number;
+  status: number;
 } {
   if (!Buffer.isBuffer(data)) data = Buffer.from(data || []);
-  const expectedLen = 8 + 32 + 8 + 2;
+  const expectedLen = DELIVERY_RECORD_HEADER_LEN;
   if (data.length < expectedLen) {
     throw new HttpsError('failed-precondition', 'Invalid DeliveryRecord account data (too short)');
   }
@@ -1931,7 +2012,8 @@ function decodeDeliveryRecord(data: Buffer): {
   const deliveryFeeLamports = Number(feeLamportsBig);
   o += 8;
   const itemCount = data.readUInt16LE(o);
-  return { payer, deliveryFeeLamports, itemCount };
+  const status = data.length > DELIVERY_RECORD_STATUS_OFFSET ? data[DELIVERY_RECORD_STATUS_OFFSET] : DELIVERY_STATUS_PAID;
+  return { payer, deliveryFeeLamports, itemCount, status };
 }
 
 async function assignDudes(dropId: string, boxAssetId: string): Promise<number[]> {
@@ -2802,7 +2884,7 @@ async function verifyReceiptIssuanceBySignature(params: {
 
   const [expectedDeliveryPda, expectedDeliveryBump] = deriveDeliveryPdaForDrop(dropRuntime, deliveryId);
   const keys = resolveInstructionAccounts(tx);
-  const FIXED_DELIVER_ACCOUNTS = 9;
+  const FIXED_DELIVER_ACCOUNTS = 17;
   const deliverIxs = (tx?.transaction?.message?.compiledInstructions || []).filter((ix: any) => {
     const program = keys[ix.programIdIndex];
     if (!program || !program.equals(dropRuntime.boxMinterProgramId)) return false;
@@ -3019,7 +3101,10 @@ export async function retryIssueReceiptsForDeliveryOrder(
     if (!closeDeliveryTx) {
       // Best-effort late cleanup: if the delivery PDA still exists, close it now.
       const deliveryInfo = await withTimeout(
-        conn.getAccountInfo(expectedDeliveryPda, { commitment: 'confirmed', dataSlice: { offset: 0, length: 0 } }),
+        conn.getAccountInfo(expectedDeliveryPda, {
+          commitment: 'confirmed',
+          dataSlice: { offset: 0, length: DELIVERY_RECORD_HEADER_LEN },
+        }),
         RPC_TIMEOUT_MS,
         'getAccountInfo:deliveryPda:lateClose',
       );
@@ -3038,6 +3123,11 @@ export async function retryIssueReceiptsForDeliveryOrder(
               { pubkey: signer.publicKey, isSigner: true, isWritable: true },
               { pubkey: expectedDeliveryPda, isSigner: false, isWritable: true },
               { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+              {
+                pubkey: decodeDeliveryRecord(Buffer.from(deliveryInfo.data)).payer,
+                isSigner: false,
+                isWritable: true,
+              },
             ],
             data: encodeCloseDeliveryArgs({ deliveryId, deliveryBump: expectedDeliveryBump }),
           });
@@ -3169,6 +3259,30 @@ export async function retryIssueReceiptsForDeliveryOrder(
   const receiptTxs: string[] = [];
   let totalProcessed = 0;
 
+  // Receipts are only minted for packed deliveries; pack a paid one in the first receipt batch.
+  const deliveryRecordAccount = pending.length
+    ? await fetchConfirmedDeliveryRecordAccount({
+        dropRuntime,
+        conn,
+        deliveryId,
+        context: 'getAccountInfo:deliveryPda:issueReceipts',
+      })
+    : null;
+  let packDelivery = Boolean(
+    deliveryRecordAccount &&
+      decodeDeliveryRecord(Buffer.from(deliveryRecordAccount.deliveryInfo.data)).status === DELIVERY_STATUS_PAID,
+  );
+  const packDeliveryIx = new TransactionInstruction({
+    programId: dropRuntime.boxMinterProgramId,
+    keys: [
+      { pubkey: dropRuntime.boxMinterConfigPda, isSigner: false, isWritable: false },
+      { pubkey: signer.publicKey, isSigner: true, isWritable: true },
+      { pubkey: expectedDeliveryPda, isSigner: false, isWritable: true },
+      { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+    ],
+    data: encodePackDeliveryArgs({ deliveryId, deliveryBump: expectedDeliveryBump }),
+  });
+
   // Process in as-large-as-possible batches, bounded by tx size + compute + transient RPC failures.
   // Strategy:
   // - start with a large batch size (<= 24)
@@ -3192,6 +3306,12 @@ export async function retryIssueReceiptsForDeliveryOrder(
       );
       const boxIds = batch.filter((it) => it.kind === 'box').map((it) => Math.floor(it.refId));
       const dudeIds = batch.filter((it) => it.kind === 'dude').map((it) => Math.floor(it.refId));
+      const receiptTrees = await fetchReceiptTreeBatch({
+        dropRuntime,
+        conn,
+        quantity: batch.length,
+        context: 'getAccountInfo:receiptTrees:issueReceipts',
+      });
       const mintReceiptsIx = buildMintReceiptsIx({
         dropRuntime,
         cosignerPk: signer.publicKey,
@@ -3199,9 +3319,12 @@ export async function retryIssueReceiptsForDeliveryOrder(
         coreCollection: cfgCoreCollection,
         boxIds,
         dudeIds,
+        receiptTrees,
+        delivery: { deliveryId, deliveryBump: expectedDeliveryBump, deliveryPda: expectedDeliveryPda },
       });
       const instructions: TransactionInstruction[] = [
         ComputeBudgetProgram.setComputeUnitLimit({ units: 1_400_000 }),
+        ...(packDelivery ? [packDeliveryIx] : []),
         ...burnIxs,
         mintReceiptsIx,
       ];
@@ -3334,6 +3457,7 @@ export async function retryIssueReceiptsForDeliveryOrder(
       }
 
       if (succeeded) {
+        packDelivery = false;
         break; // go to next `pending` chunk
       }
 
@@ -3409,7 +3533,10 @@ export async function retryIssueReceiptsForDeliveryOrder(
   // Close delivery PDA (reclaim rent) after burning + minting + Firestore marking.
   let closeDeliveryTx: string | null = null;
   const deliveryInfo = await withTimeout(
-    conn.getAccountInfo(expectedDeliveryPda, { commitment: 'confirmed', dataSlice: { offset: 0, length: 0 } }),
+    conn.getAccountInfo(expectedDeliveryPda, {
+      commitment: 'confirmed',
+      dataSlice: { offset: 0, length: DELIVERY_RECORD_HEADER_LEN },
+    }),
     RPC_TIMEOUT_MS,
     'getAccountInfo:deliveryPda',
   );
@@ -3421,6 +3548,7 @@ export async function retryIssueReceiptsForDeliveryOrder(
         { pubkey: signer.publicKey, isSigner: true, isWritable: true },
         { pubkey: expectedDeliveryPda, isSigner: false, isWritable: true },
         { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+        { pubkey: decodeDeliveryRecord(Buffer.from(deliveryInfo.data)).payer, isSigner: false, isWritable: true },
       ],
       data: encodeCloseDeliveryArgs({ deliveryId, deliveryBump: expectedDeliveryBump }),
     });
@@ -4662,16 +4790,18 @@ function buildMintReceiptsIx(args: {
   coreCollection: PublicKey;
   boxIds: number[];
   dudeIds: number[];
+  receiptTrees: ReceiptTreeBatchAccounts;
+  // Delivery whose record is marked `receipts_issued` (its assets are burned alongside).
+  delivery?: { deliveryId: number; deliveryBump: number; deliveryPda: PublicKey } | null;
 }): TransactionInstruction {
-  const treeConfig = deriveTreeConfigPda(args.dropRuntime.receiptsMerkleTree);
   return new TransactionInstruction({
     programId: args.dropRuntime.boxMinterProgramId,
     keys: [
       { pubkey: args.dropRuntime.boxMinterConfigPda, isSigner: false, isWritable: false }, // config
       { pubkey: args.cosignerPk, isSigner: true, isWritable: true }, // cosigner
       { pubkey: args.recipientPk, isSigner: false, isWritable: false }, // user
-      { pubkey: args.dropRuntime.receiptsMerkleTree, isSigner: false, isWritable: true }, // merkle_tree
-      { pubkey: treeConfig, isSigner: false, isWritable: true }, // tree_config
+      { pubkey: args.receiptTrees.merkleTree, isSigner: false, isWritable: true }, // merkle_tree
+      { pubkey: args.receiptTrees.treeConfig, isSigner: false, isWritable: true }, // tree_config
       { pubkey: args.coreCollection, isSigner: false, isWritable: true }, // core_collection
       { pubkey: BUBBLEGUM_PROGRAM_ID, isSigner: false, isWritable: false }, // bubblegum_program
       { pubkey: MPL_NOOP_PROGRAM_ID, isSigner: false, isWritable: false }, // log_wrapper
@@ -4679,8 +4809,33 @@ function buildMintReceiptsIx(args: {
       { pubkey: MPL_CORE_PROGRAM_ID, isSigner: false, isWritable: false }, // mpl_core_program
       { pubkey: MPL_CORE_CPI_SIGNER, isSigner: false, isWritable: false }, // mpl_core_cpi_signer
       { pubkey: SystemProgram.programId, isSigner: false, isWritable: false }, // system_program
+      { pubkey: deriveRoyaltiesPdaForDrop(args.dropRuntime), isSigner: false, isWritable: false }, // royalties
+      {
+        pubkey: deriveReceiptTreesPda(args.dropRuntime.boxMinterProgramId, args.dropRuntime.boxMinterConfigPda),
+        isSigner: false,
+        isWritable: true,
+      }, // receipt_trees
+      {
+        pubkey: deriveReceiptLedgerPda(args.dropRuntime.boxMinterProgramId, args.dropRuntime.boxMinterConfigPda),
+        isSigner: false,
+        isWritable: true,
+      }, // receipt_ledger
+      ...receiptTreeRolloverAccountMetas(args.receiptTrees, args.dropRuntime.boxMinterProgramId), // next_merkle_tree, next_tree_config
+      {
+        pubkey: args.delivery?.deliveryPda ?? args.dropRuntime.boxMinterProgramId,
+        isSigner: false,
+        isWritable: Boolean(args.delivery),
+      }, // delivery
     ],
-    data: encodeMintReceiptsArgs({ boxIds: args.boxIds, dudeIds: args.dudeIds }, args.dropRuntime),
+    data: encodeMintReceiptsArgs(
+      {
+        boxIds: args.boxIds,
+        dudeIds: args.dudeIds,
+        assetIdBumps: args.receiptTrees.assetIdBumps,
+        delivery: args.delivery,
+      },
+      args.dropRuntime,
+    ),
   });
 }
 
@@ -4819,6 +4974,12 @@ async function sendOpenableStripeReceiptClaimTx(params: {
     index: proofContext.index,
     proof: proofContext.proofAccounts,
   });
+  const receiptTrees = await fetchReceiptTreeBatch({
+    dropRuntime: params.dropRuntime,
+    conn: params.conn,
+    quantity: params.dudeIds.length,
+    context: 'getAccountInfo:receiptTrees:claimStripeReceipt:openable',
+  });
   const mintIx = buildMintReceiptsIx({
     dropRuntime: params.dropRuntime,
     cosignerPk: params.signer.publicKey,
@@ -4826,6 +4987,7 @@ async function sendOpenableStripeReceiptClaimTx(params: {
     coreCollection: params.coreCollection,
     boxIds: [],
     dudeIds: params.dudeIds,
+    receiptTrees,
   });
   const instructions = [ComputeBudgetProgram.setComputeUnitLimit({ units: 1_400_000 }), burnIx, mintIx];
 
@@ -0,0 +1,94 @@
+import { PublicKey, type AccountMeta } from '@solana/web3.js';
+import { BOX_MINTER_RECEIPT_LEDGER_SEED, BOX_MINTER_RECEIPT_TREES_SEED } from './shared/boxMinterProtocol.js';
+import { readU64LE } from './shared/byteCodec.js';
+import { decodeReceiptTreesData, planReceiptTreeBatch, type ReceiptTreeSegment } from './shared/receiptTreesCodec.js';
+import { BUBBLEGUM_PROGRAM_ADDRESS } from './shared/solanaProgramAddresses.js';
+
+const BUBBLEGUM_PROGRAM_ID = new PublicKey(BUBBLEGUM_PROGRAM_ADDRESS);
+// Bubblegum TreeConfig: discriminator, tree_creator, tree_delegate, total_mint_capacity, num_minted.
+const TREE_CONFIG_NUM_MINTED_OFFSET = 8 + 32 + 32 + 8;
+
+export type ReceiptTreeBatchAccounts = {
+  merkleTree: PublicKey;
+  treeConfig: PublicKey;
+  nextMerkleTree: PublicKey | null;
+  nextTreeConfig: PublicKey | null;
+  // Bubblegum asset id bumps of every leaf in the batch, in mint order. Indexed by leaf, not receipt id: when the
+  // program's ledger skips already-issued ids it mints fewer leaves and leaves the trailing bumps unused.
+  assetIdBumps: number[];
+};
+
+export function deriveReceiptTreesPda(programId: PublicKey, configPda: PublicKey): PublicKey {
+  return PublicKey.findProgramAddressSync([Buffer.from(BOX_MINTER_RECEIPT_TREES_SEED), configPda.toBuffer()], programId)[0];
+}
+
+export function deriveReceiptLedgerPda(programId: PublicKey, configPda: PublicKey): PublicKey {
+  return PublicKey.findProgramAddressSync([Buffer.from(BOX_MINTER_RECEIPT_LEDGER_SEED), configPda.toBuffer()], programId)[0];
+}
+
+function deriveTreeConfig(merkleTree: PublicKey): PublicKey {
+  return PublicKey.findProgramAddressSync([merkleTree.toBuffer()], BUBBLEGUM_PROGRAM_ID)[0];
+}
+
+function receiptAssetIdBumps(segment: ReceiptTreeSegment): number[] {
+  const merkleTree = Buffer.from(segment.merkleTree);
+  const bumps: number[] = [];
+  for (let offset = 0; offset < segment.leaves; offset += 1) {
+    const leafIndex = Buffer.alloc(8);
+    leafIndex.writeBigUInt64LE(segment.firstLeafIndex + BigInt(offset), 0);
+    bumps.push(PublicKey.findProgramAddressSync([Buffer.from('asset'), merkleTree, leafIndex], BUBBLEGUM_PROGRAM_ID)[1]);
+  }
+  return bumps;
+}
+
+// Picks the trees a batch of `quantity` receipts is minted into from the drop's receipt tree registry.
+export function resolveReceiptTreeBatch(registryData: Uint8Array, quantity: number): ReceiptTreeBatchAccounts {
+  const plan = planReceiptTreeBatch(decodeReceiptTreesData(registryData), quantity);
+  const merkleTree = new PublicKey(plan.active.merkleTree);
+  const nextMerkleTree = plan.next ? new PublicKey(plan.next.merkleTree) : null;
+  return {
+    merkleTree,
+    treeConfig: deriveTreeConfig(merkleTree),
+    nextMerkleTree,
+    nextTreeConfig: nextMerkleTree ? deriveTreeConfig(nextMerkleTree) : null,
+    assetIdBumps: [...receiptAssetIdBumps(plan.active), ...(plan.next ? receiptAssetIdBumps(plan.next) : [])],
+  };
+}
+
+// Drops without a receipt tree registry mint into the cosigner's own tree, from its `num_minted` and without rollover.
+export function resolveLegacyReceiptTreeBatch(
+  merkleTree: PublicKey,
+  treeConfigData: Uint8Array,
+  quantity: number,
+): ReceiptTreeBatchAccounts {
+  if (treeConfigData.length < TREE_CONFIG_NUM_MINTED_OFFSET + 8) {
+    throw new Error('Receipts tree config is too short');
+  }
+  const segment = {
+    merkleTree: merkleTree.toBytes(),
+    firstLeafIndex: readU64LE(treeConfigData, TREE_CONFIG_NUM_MINTED_OFFSET),
+    leaves: quantity,
+  };
+  return {
+    merkleTree,
+    treeConfig: deriveTreeConfig(merkleTree),
+    nextMerkleTree: null,
+    nextTreeConfig: null,
+    assetIdBumps: receiptAssetIdBumps(segment),
+  };
+}
+
+// Trailing optional `next_merkle_tree` / `next_tree_config` accounts; the program id stands in for both when the
+// batch fits the active tree.
+export function receiptTreeRolloverAccountMetas(batch: ReceiptTreeBatchAccounts, programId: PublicKey): AccountMeta[] {
+  if (!batch.nextMerkleTree || !batch.nextTreeConfig) {
+    return [
+      { pubkey: programId, isSigner: false, isWritable: false },
+      { pubkey: programId, isSigner: false, isWritable: false },
+    ];
+  }
+  return [
+    { pubkey: batch.nextMerkleTree, isSigner: false, isWritable: true },
+    { pubkey: batch.nextTreeConfig, isSigner: false, isWritable: true },
+  ];
+}
@@ -1,5 +1,12 @@
 export const BOX_MINTER_CONFIG_SEED = 'config';
 export const BOX_MINTER_PENDING_OPEN_SEED = 'open';
+export const BOX_MINTER_VARIANT_TABLE_SEED = 'variants';
+export const BOX_MINTER_ROYALTIES_SEED = 'royalties';
+export const BOX_MINTER_RECEIPT_TREES_SEED = 'receipt_trees';
+export const BOX_MINTER_RECEIPT_LEDGER_SEED = 'receipt_ledger';
+export const BOX_MINTER_DELIVERY_FEES_SEED = 'delivery_fees';
+export const BOX_MINTER_DELIVERY_FEE_ROUTING_SEED = 'delivery_fee_routing';
+export const BOX_MINTER_CUSTODY_VAULT_SEED = 'custody_vault';
 
 export const BOX_MINTER_MIN_CONFIGURED_ITEMS_PER_BOX = 0;
 export const BOX_MINTER_MIN_OPENABLE_ITEMS_PER_BOX = 1;
@@ -10,11 +17,13 @@ export const BOX_MINTER_MAX_DISCOUNT_MINTS_PER_WALLET = 3;
 
 export const BOX_MINTER_MINT_VARIANT_KIND_NONE = 0;
 export const BOX_MINTER_MINT_VARIANT_KIND_SIZE = 1;
+export const BOX_MINTER_MINT_VARIANT_KIND_TABLE = 2;
 export const BOX_MINTER_MINT_VARIANT_OPTION_COUNT = 3;
 
 export type BoxMinterMintVariantKind =
   | typeof BOX_MINTER_MINT_VARIANT_KIND_NONE
-  | typeof BOX_MINTER_MINT_VARIANT_KIND_SIZE;
+  | typeof BOX_MINTER_MINT_VARIANT_KIND_SIZE
+  | typeof BOX_MINTER_MINT_VARIANT_KIND_TABLE;
 
 export type BoxMinterMintVariantTuple = [number, number, number];
 
@@ -53,6 +62,7 @@ export function isBoxMinterMintVariantKind(
 ): value is BoxMinterMintVariantKind {
   return (
     value === BOX_MINTER_MINT_VARIANT_KIND_NONE ||
-    value === BOX_MINTER_MINT_VARIANT_KIND_SIZE
+    value === BOX_MINTER_MINT_VARIANT_KIND_SIZE ||
+    value === BOX_MINTER_MINT_VARIANT_KIND_TABLE
   );
 }
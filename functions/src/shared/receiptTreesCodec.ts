@@ -0,0 +1,125 @@
+import { bytesEqual, readU32LE, readU64LE } from './byteCodec.js';
+
+export const RECEIPT_TREES_DISCRIMINATOR = Uint8Array.from([
+  0xf2, 0xe0, 0xf9, 0xd9, 0xf2, 0xa4, 0x69, 0x1e,
+]);
+
+type ReceiptTreesCodecErrorReason =
+  | 'too-short'
+  | 'invalid-discriminator'
+  | 'truncated-vector'
+  | 'invalid-quantity'
+  | 'trees-full'
+  | 'capacity-exceeded';
+
+export class ReceiptTreesCodecError extends Error {
+  constructor(readonly reason: ReceiptTreesCodecErrorReason) {
+    super(reason);
+    this.name = 'ReceiptTreesCodecError';
+  }
+}
+
+export type DecodedReceiptTree = {
+  merkleTree: Uint8Array;
+  capacity: bigint;
+  minted: bigint;
+};
+
+export type DecodedReceiptTrees = {
+  config: Uint8Array;
+  activeIndex: number;
+  trees: DecodedReceiptTree[];
+  bump: number;
+};
+
+// A run of consecutive leaves in one receipts tree. Registry trees are only minted into by the
+// program, so `minted` is also the Bubblegum `num_minted` of the tree.
+export type ReceiptTreeSegment = {
+  merkleTree: Uint8Array;
+  firstLeafIndex: bigint;
+  leaves: number;
+};
+
+export type ReceiptTreeBatchPlan = {
+  active: ReceiptTreeSegment;
+  next: ReceiptTreeSegment | null;
+};
+
+const HEADER_LEN = 8 + 32 + 1 + 4;
+const TREE_LEN = 32 + 8 + 8;
+
+export function decodeReceiptTreesData(data: Uint8Array): DecodedReceiptTrees {
+  if (data.length < HEADER_LEN + 1) {
+    throw new ReceiptTreesCodecError('too-short');
+  }
+  if (!bytesEqual(data.subarray(0, 8), RECEIPT_TREES_DISCRIMINATOR)) {
+    throw new ReceiptTreesCodecError('invalid-discriminator');
+  }
+
+  let offset = 8;
+  const config = data.subarray(offset, offset + 32);
+  offset += 32;
+  const activeIndex = data[offset] ?? 0;
+  offset += 1;
+  const treeCount = readU32LE(data, offset);
+  offset += 4;
+  if (data.length < HEADER_LEN + TREE_LEN * treeCount + 1) {
+    throw new ReceiptTreesCodecError('truncated-vector');
+  }
+
+  const trees: DecodedReceiptTree[] = [];
+  for (let index = 0; index < treeCount; index += 1) {
+    const merkleTree = data.subarray(offset, offset + 32);
+    offset += 32;
+    const capacity = readU64LE(data, offset);
+    offset += 8;
+    const minted = readU64LE(data, offset);
+    offset += 8;
+    trees.push({ merkleTree, capacity, minted });
+  }
+  const bump = data[offset] ?? 0;
+  return { config, activeIndex, trees, bump };
+}
+
+function remainingLeaves(tree: DecodedReceiptTree): bigint {
+  return tree.capacity > tree.minted ? tree.capacity - tree.minted : 0n;
+}
+
+// Mirrors the program's reservation: fill the active tree, then roll the rest of the batch over
+// to the next registered tree. A batch spans at most two trees.
+export function planReceiptTreeBatch(
+  receiptTrees: DecodedReceiptTrees,
+  quantity: number,
+): ReceiptTreeBatchPlan {
+  if (!Number.isSafeInteger(quantity) || quantity < 0) {
+    throw new ReceiptTreesCodecError('invalid-quantity');
+  }
+  let activeIndex = receiptTrees.activeIndex;
+  while (activeIndex < receiptTrees.trees.length && remainingLeaves(receiptTrees.trees[activeIndex]) === 0n) {
+    activeIndex += 1;
+  }
+  const active = receiptTrees.trees[activeIndex];
+  if (!active) {
+    throw new ReceiptTreesCodecError('trees-full');
+  }
+
+  const activeRemaining = remainingLeaves(active);
+  const activeLeaves = BigInt(quantity) < activeRemaining ? quantity : Number(activeRemaining);
+  const rollover = quantity - activeLeaves;
+  let next: ReceiptTreeSegment | null = null;
+  if (rollover > 0) {
+    const nextTree = receiptTrees.trees[activeIndex + 1];
+    if (!nextTree) {
+      throw new ReceiptTreesCodecError('trees-full');
+    }
+    if (BigInt(rollover) > remainingLeaves(nextTree)) {
+      throw new ReceiptTreesCodecError('capacity-exceeded');
+    }
+    next = { merkleTree: nextTree.merkleTree, firstLeafIndex: nextTree.minted, leaves: rollover };
+  }
+
+  return {
+    active: { merkleTree: active.merkleTree, firstLeafIndex: active.minted, leaves: activeLeaves },
+    next,
+  };
+}
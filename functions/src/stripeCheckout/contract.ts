@@ -20,6 +20,7 @@ import {
 import {
   isStripeOffchainFulfillmentSession,
 } from '../shared/stripeWebhook.js';
+import { BOX_MINTER_ROYALTIES_SEED, BOX_MINTER_VARIANT_TABLE_SEED } from '../shared/boxMinterProtocol.js';
 export {
   generateStripeReceiptClaimCode,
   generateUniqueStripeReceiptClaimCodes,
@@ -369,10 +370,20 @@ export function deriveAdminOrderPda(
   return PublicKey.findProgramAddressSync([Buffer.from(ADMIN_ORDER_SEED), configPda.toBuffer(), hash], programId);
 }
 
+export function deriveRoyaltiesPda(programId: PublicKey, configPda: PublicKey): [PublicKey, number] {
+  return PublicKey.findProgramAddressSync([Buffer.from(BOX_MINTER_ROYALTIES_SEED), configPda.toBuffer()], programId);
+}
+
+export function deriveVariantTablePda(programId: PublicKey, configPda: PublicKey): [PublicKey, number] {
+  return PublicKey.findProgramAddressSync([Buffer.from(BOX_MINTER_VARIANT_TABLE_SEED), configPda.toBuffer()], programId);
+}
+
 export function encodeAdminDeliverVariantOrderArgs(args: {
   orderHash: Uint8Array;
   variantIndex: number;
   quantity: number;
+  withReceiptAssetData: boolean;
+  assetIdBumps: readonly number[];
 }): Buffer {
   const orderHash = Buffer.from(args.orderHash || []);
   const variantIndex = Number(args.variantIndex);
@@ -384,10 +395,14 @@ export function encodeAdminDeliverVariantOrderArgs(args: {
   if (!Number.isInteger(quantity) || quantity < 1 || quantity > 255) {
     throw new Error('quantity must be a u8');
   }
+  const assetIdBumpCount = Buffer.alloc(4);
+  assetIdBumpCount.writeUInt32LE(args.assetIdBumps.length, 0);
   return Buffer.concat([
     IX_ADMIN_DELIVER_VARIANT_ORDER,
     orderHash,
-    Buffer.from([variantIndex & 0xff, quantity & 0xff]),
+    Buffer.from([variantIndex & 0xff, quantity & 0xff, args.withReceiptAssetData ? 1 : 0]),
+    assetIdBumpCount,
+    Buffer.from(args.assetIdBumps),
   ]);
 }
 
@@ -13,14 +13,25 @@ import {
 import type Stripe from 'stripe';
 import type { MintSelectionConfig, SolanaCluster } from '../config/deployment.js';
 import type { DropFamily, DropSalesMode } from '../shared/deploymentCore.js';
+import { BOX_MINTER_MINT_VARIANT_KIND_TABLE } from '../shared/boxMinterProtocol.js';
 import { dropDeliveryOrderPath, dropRootPath } from '../dropPaths.js';
 import { countStripeIrlPackStatus, type PackStatusDropRuntime } from '../packStatus.js';
+import {
+  deriveReceiptLedgerPda,
+  deriveReceiptTreesPda,
+  receiptTreeRolloverAccountMetas,
+  resolveLegacyReceiptTreeBatch,
+  resolveReceiptTreeBatch,
+  type ReceiptTreeBatchAccounts,
+} from '../receiptTrees.js';
 import {
   buildStripeOffchainAddressSnapshot,
   buildStripeOffchainDeliveryOrderDocument,
   buildStripeOffchainOrderMarkerDocument,
   decodeAdminDeliveryOrderRecord,
   deriveAdminOrderPda,
+  deriveRoyaltiesPda,
+  deriveVariantTablePda,
   encodeAdminDeliverVariantOrderArgs,
   generateUniqueStripeReceiptClaimCodes,
   isStripeOffchainFulfillmentSession,
@@ -140,6 +151,7 @@ export type StripeCheckoutDropRuntime = {
 export type StripeCheckoutOnchainConfig = {
   admin: PublicKey;
   coreCollection: PublicKey;
+  mintVariantKind: number;
 };
 
 type StripeCheckoutPrograms = {
@@ -1127,7 +1139,39 @@ async function fulfillStripeCheckoutSession<
   let receiptTx: string | null = null;
 
   if (!record) {
-    const treeConfig = PublicKey.findProgramAddressSync([dropRuntime.receiptsMerkleTree.toBuffer()], deps.programs.bubblegumProgramId)[0];
+    const receiptTreesPda = deriveReceiptTreesPda(dropRuntime.boxMinterProgramId, dropRuntime.boxMinterConfigPda);
+    const receiptTreesInfo = await deps.withTimeout(
+      conn.getAccountInfo(receiptTreesPda, { commitment: 'confirmed' }),
+      deps.rpcTimeoutMs,
+      'getAccountInfo:receiptTrees',
+    );
+    let receiptTrees: ReceiptTreeBatchAccounts;
+    try {
+      if (receiptTreesInfo && receiptTreesInfo.owner.equals(dropRuntime.boxMinterProgramId)) {
+        receiptTrees = resolveReceiptTreeBatch(receiptTreesInfo.data, checkout.quantity);
+      } else {
+        // No registry yet: the order's receipts go into the drop's configured tree, which the cosigner signs for.
+        const treeConfigPda = PublicKey.findProgramAddressSync(
+          [dropRuntime.receiptsMerkleTree.toBuffer()],
+          deps.programs.bubblegumProgramId,
+        )[0];
+        const treeConfigInfo = await deps.withTimeout(
+          conn.getAccountInfo(treeConfigPda, { commitment: 'confirmed' }),
+          deps.rpcTimeoutMs,
+          'getAccountInfo:receiptsTreeConfig',
+        );
+        if (!treeConfigInfo) {
+          throw new Error('Receipts tree is not initialized');
+        }
+        receiptTrees = resolveLegacyReceiptTreeBatch(dropRuntime.receiptsMerkleTree, treeConfigInfo.data, checkout.quantity);
+      }
+    } catch (err) {
+      throw new HttpsError('failed-precondition', 'Receipt trees cannot hold this order', {
+        dropId,
+        quantity: checkout.quantity,
+        reason: err instanceof Error ? err.message : String(err),
+      });
+    }
     const ix = new TransactionInstruction({
       programId: dropRuntime.boxMinterProgramId,
       keys: [
@@ -1135,8 +1179,8 @@ async function fulfillStripeCheckoutSession<
         { pubkey: signer.publicKey, isSigner: true, isWritable: true },
         { pubkey: receiptOwner, isSigner: false, isWritable: false },
         { pubkey: adminOrderPda, isSigner: false, isWritable: true },
-        { pubkey: dropRuntime.receiptsMerkleTree, isSigner: false, isWritable: true },
-        { pubkey: treeConfig, isSigner: false, isWritable: true },
+        { pubkey: receiptTrees.merkleTree, isSigner: false, isWritable: true },
+        { pubkey: receiptTrees.treeConfig, isSigner: false, isWritable: true },
         { pubkey: cfg.coreCollection, isSigner: false, isWritable: true },
         { pubkey: deps.programs.bubblegumProgramId, isSigner: false, isWritable: false },
         { pubkey: deps.programs.mplNoopProgramId, isSigner: false, isWritable: false },
@@ -1144,11 +1188,32 @@ async function fulfillStripeCheckoutSession<
         { pubkey: deps.programs.mplCoreProgramId, isSigner: false, isWritable: false },
         { pubkey: deps.programs.mplCoreCpiSigner, isSigner: false, isWritable: false },
         { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+        {
+          pubkey: deriveRoyaltiesPda(dropRuntime.boxMinterProgramId, dropRuntime.boxMinterConfigPda)[0],
+          isSigner: false,
+          isWritable: false,
+        },
+        {
+          pubkey: cfg.mintVariantKind === BOX_MINTER_MINT_VARIANT_KIND_TABLE
+            ? deriveVariantTablePda(dropRuntime.boxMinterProgramId, dropRuntime.boxMinterConfigPda)[0]
+            : dropRuntime.boxMinterProgramId,
+          isSigner: false,
+          isWritable: cfg.mintVariantKind === BOX_MINTER_MINT_VARIANT_KIND_TABLE,
+        },
+        { pubkey: receiptTreesPda, isSigner: false, isWritable: true },
+        {
+          pubkey: deriveReceiptLedgerPda(dropRuntime.boxMinterProgramId, dropRuntime.boxMinterConfigPda),
+          isSigner: false,
+          isWritable: true,
+        },
+        ...receiptTreeRolloverAccountMetas(receiptTrees, dropRuntime.boxMinterProgramId),
       ],
       data: encodeAdminDeliverVariantOrderArgs({
         orderHash,
         variantIndex,
         quantity: checkout.quantity,
+        withReceiptAssetData: true,
+        assetIdBumps: receiptTrees.assetIdBumps,
       }),
     });
     const { blockhash } = await deps.withTimeout(
@@ -1,7 +1,11 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::entrypoint::MAX_PERMITTED_DATA_INCREASE;
 use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
 use anchor_lang::solana_program::program::invoke;
 use anchor_lang::solana_program::program::invoke_signed;
+use anchor_lang::solana_program::sysvar::instructions::{
+    load_current_index_checked, load_instruction_at_checked,
+};
 use core::fmt::Write;
 use solana_sha256_hasher::hashv;
 
@@ -84,15 +88,47 @@ const EXPECTED_INITIALIZER: Pubkey = pubkey!("kPG2L5zuxqNkvWvJNptbkqnPhk4nGjnGp7
 const MAX_SAFE_MINTS_PER_TX: u8 = 15;
 // Delivery is mostly limited by tx size; keep this high enough to not be the limiting factor.
 const MAX_SAFE_DELIVERY_ITEMS_PER_TX: u8 = 32;
+// Each delivered item also mints a Bubblegum receipt, so keep well below the plain delivery cap.
+const MAX_SAFE_DELIVERY_WITH_RECEIPTS_ITEMS_PER_TX: u8 = 12;
+const MAX_CLOSE_DELIVERIES_PER_TX: usize = 16;
 const MIN_DISCOUNT_MINTS_PER_WALLET: u8 = 1;
 const MAX_DISCOUNT_MINTS_PER_WALLET: u8 = 3;
 
 const MIN_OPENABLE_ITEMS_PER_BOX: u8 = 1;
 // Keep this conservative: start_open_box + finalize_open_box do multiple MPL-Core CPIs per figure.
 const MAX_ITEMS_PER_BOX: u8 = 5;
+// Batched opens: each box costs one TransferV1 plus `items_per_box` CreateV1 CPIs.
+const MAX_SAFE_OPEN_CPIS_PER_TX: usize = 12;
+// Per-box remaining accounts in `start_open_boxes`: box asset + pending record.
+const OPEN_BOX_GROUP_FIXED_ACCOUNTS: usize = 2;
+// Batched finalizes: each box costs one BurnV1 plus `items_per_box` AddPluginV1 + UpdateV2 +
+// TransferV1 CPIs.
+const MAX_SAFE_FINALIZE_CPIS_PER_TX: usize = 16;
+// Per-box remaining accounts in `finalize_open_boxes`: box asset + pending record + user.
+const FINALIZE_BOX_GROUP_FIXED_ACCOUNTS: usize = 3;
+// Legacy size drops keep three fixed variant slots in `BoxMinterConfig`; new variant drops use a
+// `VariantTable`.
 const MINT_VARIANT_OPTION_COUNT: usize = 3;
+const MAX_ROYALTY_BASIS_POINTS: u16 = 10_000;
+const MAX_ROYALTY_CREATORS: usize = 5;
+const MAX_ROYALTY_RULE_SET_PROGRAMS: usize = 8;
+// Bubblegum's metadata URI limit; receipt URIs include the order/delivery query.
+const MAX_RECEIPT_URI_LEN: usize = 200;
+// MPL-Core `Plugin` variant indices.
+const MPL_CORE_PLUGIN_ROYALTIES: u8 = 0;
+const MPL_CORE_PLUGIN_FREEZE_DELEGATE: u8 = 1;
+const MPL_CORE_PLUGIN_BURN_DELEGATE: u8 = 2;
+const MPL_CORE_PLUGIN_ATTRIBUTES: u8 = 6;
+// MPL-Core `PluginAuthority::Address` variant index.
+const MPL_CORE_PLUGIN_AUTHORITY_ADDRESS: u8 = 3;
 const MINT_VARIANT_KIND_NONE: u8 = 0;
+// Legacy size drops; direct-delivery only (openable variant drops use a `VariantTable`).
 const MINT_VARIANT_KIND_SIZE: u8 = 1;
+// Variants live in a separate `VariantTable` PDA (labels, prices and open settings per variant).
+const MINT_VARIANT_KIND_TABLE: u8 = 2;
+const MAX_VARIANT_TABLE_OPTIONS: usize = 16;
+const MAX_RECEIPT_TREES: usize = 8;
+const MAX_DELIVERY_FEE_ZONES: usize = 16;
 const SPLIT_PAYMENTS_V1_MAGIC: [u8; 8] = *b"MONSPAY\0";
 const SPLIT_PAYMENTS_V1_VERSION: u8 = 1;
 const SPLIT_PAYMENTS_V1_MIN_RECIPIENTS: usize = 2;
@@ -107,11 +143,25 @@ const SPLIT_PAYMENTS_V1_RESERVED_OFFSET: usize = 109;
 // Asset PDA namespaces (owned by mpl-core; signed for via our program).
 const SEED_BOX_ASSET: &[u8] = b"box";
 const SEED_DELIVERY: &[u8] = b"delivery";
+const DELIVERY_QUOTE_DOMAIN: &[u8] = b"box_minter:delivery_quote:v1";
+// Longest a delivery quote may stay valid, about a blockhash lifetime. Cancelling closes the
+// delivery record, so this also bounds how long a quote for a freed delivery id can be replayed.
+const MAX_DELIVERY_QUOTE_SLOTS: u64 = 150;
+// Ed25519 program instruction: count + padding, then one 14-byte offsets entry.
+const ED25519_OFFSETS_START: usize = 2;
+const ED25519_OFFSETS_LEN: usize = 14;
 const SEED_ADMIN_ORDER: &[u8] = b"admin_order";
 // Pending (two-step) box open flow.
 const SEED_PENDING_OPEN: &[u8] = b"open";
 const SEED_PENDING_DUDE_ASSET: &[u8] = b"pdude";
 const SEED_DISCOUNT_MINT: &[u8] = b"discount";
+const SEED_VARIANT_TABLE: &[u8] = b"variants";
+const SEED_ROYALTIES: &[u8] = b"royalties";
+const SEED_RECEIPT_TREES: &[u8] = b"receipt_trees";
+const SEED_RECEIPT_LEDGER: &[u8] = b"receipt_ledger";
+const SEED_DELIVERY_FEES: &[u8] = b"delivery_fees";
+const SEED_DELIVERY_FEE_ROUTING: &[u8] = b"delivery_fee_routing";
+const SEED_CUSTODY_VAULT: &[u8] = b"custody_vault";
 
 // Metaplex Core program id.
 const MPL_CORE_PROGRAM_ID: Pubkey = Pubkey::new_from_array([
@@ -125,6 +175,12 @@ const SPL_NOOP_PROGRAM_ID: Pubkey = Pubkey::new_from_array([
     220, 23, 234, 203, 3, 205, 26, 35, 205, 126, 120, 124,
 ]);
 
+// Ed25519 signature verification native program id.
+const ED25519_PROGRAM_ID: Pubkey = Pubkey::new_from_array([
+    3, 125, 70, 214, 124, 147, 251, 190, 18, 249, 66, 143, 131, 141, 64, 255, 5, 112, 116, 73, 39,
+    244, 138, 100, 252, 202, 112, 68, 128, 0, 0, 0,
+]);
+
 // Metaplex Noop program id (Bubblegum v2 log wrapper).
 const MPL_NOOP_PROGRAM_ID: Pubkey = Pubkey::new_from_array([
     11, 121, 89, 138, 15, 175, 40, 176, 251, 210, 37, 99, 35, 51, 65, 75, 208, 58, 171, 36, 15,
@@ -151,6 +207,19 @@ const MPL_CORE_CPI_SIGNER: Pubkey = Pubkey::new_from_array([
 
 // Bubblegum v2 mint discriminator: [120, 121, 23, 146, 173, 110, 199, 205]
 const IX_BUBBLEGUM_MINT_V2: [u8; 8] = [120, 121, 23, 146, 173, 110, 199, 205];
+const IX_BUBBLEGUM_TRANSFER_V2: [u8; 8] = [119, 40, 6, 235, 234, 221, 248, 49];
+const IX_BUBBLEGUM_BURN_V2: [u8; 8] = [115, 210, 34, 240, 232, 143, 183, 16];
+/// `merkle_tree` and `tree_config` lead each delivered receipt's account group.
+const DELIVERED_RECEIPT_FIXED_ACCOUNTS: usize = 2;
+// Bubblegum TreeConfig: discriminator + tree_creator + tree_delegate + total_mint_capacity, then
+// num_minted (the next leaf index / asset nonce).
+const BUBBLEGUM_TREE_CONFIG_NUM_MINTED_OFFSET: usize = 8 + 32 + 32 + 8;
+const SEED_BUBBLEGUM_ASSET: &[u8] = b"asset";
+// Bubblegum v2 createTreeV2 discriminator: [55, 99, 95, 215, 142, 203, 227, 205]
+// Bubblegum TreeConfig: discriminator + tree_creator + tree_delegate, then total_mint_capacity
+// (2^max_depth of the tree Bubblegum created).
+const BUBBLEGUM_TREE_CONFIG_TOTAL_MINT_CAPACITY_OFFSET: usize = 8 + 32 + 32;
+const IX_BUBBLEGUM_CREATE_TREE_V2: [u8; 8] = [55, 99, 95, 215, 142, 203, 227, 205];
 
 // URI path prefixes appended to the configured DROP BASE (`config.uri_base`).
 // Kept as `&'static str` so we can avoid allocating derived base Strings on the SBF heap.
@@ -158,6 +227,9 @@ const URI_PREFIX_BOXES: &str = "/b";
 const URI_PREFIX_FIGURES: &str = "/f";
 const URI_PREFIX_RECEIPTS_FIGURES: &str = "/rf";
 const URI_PREFIX_RECEIPTS_BOXES: &str = "/rb";
+// Longest receipt URI after the drop base: `/rb<u32>.json?order=<64 hex>&delivery=<u32>`.
+const MAX_RECEIPT_URI_TAIL_LEN: usize =
+    URI_PREFIX_RECEIPTS_BOXES.len() + 10 + ".json?order=".len() + 64 + "&delivery=".len() + 10;
 const RECEIPT_NAME_PREFIX: &str = "receipt · ";
 const CARD_NFT_2_DROP_SEED: [u8; 32] = [
     0xa7, 0x9d, 0x76, 0xec, 0x11, 0x37, 0xba, 0xff, 0x9c, 0xbc, 0x2f, 0xa2, 0xd0, 0x57, 0xf0, 0xfd,
@@ -178,6 +250,20 @@ fn hash_sorted_pair(left: [u8; 32], right: [u8; 32]) -> [u8; 32] {
     hashv(&[a.as_ref(), b.as_ref()]).to_bytes()
 }
 
+/// Hash of the delivered asset keys in delivery order, chained one key at a time (sha256 of the
+/// previous hash and the key, starting from sha256 of nothing) so that a delivery session
+/// appended over several transactions hashes the same as a single delivery of its assets.
+fn delivery_assets_hash(asset_keys: &[Pubkey]) -> [u8; 32] {
+    extend_delivery_assets_hash(&hashv(&[]).to_bytes(), asset_keys)
+}
+
+/// `assets_hash` after more assets are delivered (see `delivery_assets_hash`).
+fn extend_delivery_assets_hash(assets_hash: &[u8; 32], asset_keys: &[Pubkey]) -> [u8; 32] {
+    asset_keys.iter().fold(*assets_hash, |hash, key| {
+        hashv(&[hash.as_ref(), key.as_ref()]).to_bytes()
+    })
+}
+
 fn has_any_non_zero_byte(data: &[u8]) -> bool {
     data.iter().any(|b| *b != 0)
 }
@@ -450,12 +536,40 @@ fn split_payment_amounts(total_lamports: u64, split: &SplitPaymentsV1) -> Result
     Ok(amounts)
 }
 
+fn split_payments_v1_from_args(args: &SplitPaymentsV1Args) -> SplitPaymentsV1 {
+    SplitPaymentsV1 {
+        recipient_count: args.recipient_count,
+        recipients: args.recipients,
+        percentages: args.percentages,
+    }
+}
+
+fn validate_delivery_fee_route(
+    config: &Account<BoxMinterConfig>,
+    route: &DeliveryFeeRoute,
+) -> Result<()> {
+    match route {
+        DeliveryFeeRoute::Treasury => Ok(()),
+        DeliveryFeeRoute::MintSplit => {
+            require!(
+                load_split_payments_v1(config)?.is_some(),
+                BoxMinterError::InvalidSplitPaymentsConfig
+            );
+            Ok(())
+        }
+        DeliveryFeeRoute::Split(args) => {
+            validate_split_payments_v1(&split_payments_v1_from_args(args))
+        }
+    }
+}
+
 struct MintBoxesInnerAccounts<'info> {
     payer: AccountInfo<'info>,
     treasury: AccountInfo<'info>,
     core_collection: AccountInfo<'info>,
     mpl_core_program: AccountInfo<'info>,
     system_program: AccountInfo<'info>,
+    royalties: Option<DropRoyalties>,
 }
 
 impl<'info> MintBoxesInnerAccounts<'info> {
@@ -465,6 +579,7 @@ impl<'info> MintBoxesInnerAccounts<'info> {
         core_collection: AccountInfo<'info>,
         mpl_core_program: AccountInfo<'info>,
         system_program: AccountInfo<'info>,
+        royalties: Option<DropRoyalties>,
     ) -> Self {
         Self {
             payer,
@@ -472,33 +587,193 @@ impl<'info> MintBoxesInnerAccounts<'info> {
             core_collection,
             mpl_core_program,
             system_program,
+            royalties,
         }
     }
 
-    fn from_mint_boxes(accounts: &MintBoxes<'info>) -> Self {
-        Self::new(
+    fn from_mint_boxes(accounts: &MintBoxes<'info>, program_id: &Pubkey) -> Result<Self> {
+        Ok(Self::new(
             accounts.payer.to_account_info(),
             accounts.treasury.to_account_info(),
             accounts.core_collection.to_account_info(),
             accounts.mpl_core_program.to_account_info(),
             accounts.system_program.to_account_info(),
-        )
+            load_drop_royalties(&accounts.royalties, program_id)?,
+        ))
     }
 
-    fn from_discounted_box(accounts: &MintDiscountedBox<'info>) -> Self {
-        Self::new(
+    fn from_mint_variant_box(
+        accounts: &MintVariantBox<'info>,
+        program_id: &Pubkey,
+    ) -> Result<Self> {
+        Ok(Self::new(
             accounts.payer.to_account_info(),
             accounts.treasury.to_account_info(),
             accounts.core_collection.to_account_info(),
             accounts.mpl_core_program.to_account_info(),
             accounts.system_program.to_account_info(),
-        )
+            load_drop_royalties(&accounts.royalties, program_id)?,
+        ))
+    }
+
+    fn from_discounted_box(
+        accounts: &MintDiscountedBox<'info>,
+        program_id: &Pubkey,
+    ) -> Result<Self> {
+        Ok(Self::new(
+            accounts.payer.to_account_info(),
+            accounts.treasury.to_account_info(),
+            accounts.core_collection.to_account_info(),
+            accounts.mpl_core_program.to_account_info(),
+            accounts.system_program.to_account_info(),
+            load_drop_royalties(&accounts.royalties, program_id)?,
+        ))
+    }
+}
+
+/// Loads the drop's royalty settings; `None` while the royalties PDA is uninitialized.
+///
+/// Callers must validate the PDA address (Anchor seeds constraint).
+fn load_drop_royalties(
+    royalties_ai: &AccountInfo,
+    program_id: &Pubkey,
+) -> Result<Option<DropRoyalties>> {
+    if royalties_ai.owner != program_id || royalties_ai.data_is_empty() {
+        return Ok(None);
+    }
+    let data = royalties_ai.try_borrow_data()?;
+    DropRoyalties::try_deserialize(&mut &data[..]).map(Some)
+}
+
+/// Borsh `CreateV1.plugins`: the drop's Royalties plugin (if configured) followed by an
+/// Attributes plugin (if any attributes), both with their default (update authority) plugin
+/// authority. `None` when there is nothing to write.
+fn create_plugins_data(
+    royalties: Option<&DropRoyalties>,
+    attributes: &[(&str, &str)],
+) -> Result<Vec<u8>> {
+    let plugin_count = u32::from(royalties.is_some()) + u32::from(!attributes.is_empty());
+    if plugin_count == 0 {
+        return Ok(vec![0]);
+    }
+    let mut data = Vec::with_capacity(DropRoyalties::SPACE + 128);
+    data.push(1); // Some
+    data.extend_from_slice(&plugin_count.to_le_bytes()); // PluginAuthorityPair count
+    if let Some(royalties) = royalties {
+        data.push(MPL_CORE_PLUGIN_ROYALTIES);
+        data.extend_from_slice(&royalties.basis_points.to_le_bytes());
+        royalties
+            .creators
+            .serialize(&mut data)
+            .map_err(|_| error!(BoxMinterError::SerializationFailed))?;
+        royalties
+            .rule_set
+            .serialize(&mut data)
+            .map_err(|_| error!(BoxMinterError::SerializationFailed))?;
+        data.push(0); // authority: None (plugin default)
+    }
+    if !attributes.is_empty() {
+        write_attributes_plugin(&mut data, attributes);
+        data.push(0); // authority: None (plugin default)
+    }
+    Ok(data)
+}
+
+/// Borsh `Plugin::Attributes { attribute_list }`.
+fn write_attributes_plugin(data: &mut Vec<u8>, attributes: &[(&str, &str)]) {
+    data.push(MPL_CORE_PLUGIN_ATTRIBUTES);
+    data.extend_from_slice(&(attributes.len() as u32).to_le_bytes());
+    for (key, value) in attributes {
+        data.extend_from_slice(&(key.len() as u32).to_le_bytes());
+        data.extend_from_slice(key.as_bytes());
+        data.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        data.extend_from_slice(value.as_bytes());
+    }
+}
+
+fn hex_bytes32(bytes: &[u8; 32]) -> String {
+    let mut out = String::with_capacity(64);
+    for byte in bytes {
+        // Writing to a String cannot fail.
+        let _ = write!(&mut out, "{:02x}", byte);
+    }
+    out
+}
+
+/// Value of a box's `variant` attribute: the table label, or the slot index for size drops.
+fn variant_attribute_value(
+    cfg: &BoxMinterConfig,
+    variant_table: Option<&VariantTable>,
+    variant_index: u8,
+) -> Result<Option<String>> {
+    if !cfg.requires_variant_selection() {
+        return Ok(None);
+    }
+    if cfg.uses_variant_table() {
+        let table = variant_table.ok_or(error!(BoxMinterError::VariantTableMissing))?;
+        return Ok(Some(table.option(variant_index)?.label.clone()));
+    }
+    Ok(Some(variant_index.to_string()))
+}
+
+fn validate_drop_royalties(args: &DropRoyaltiesArgs) -> Result<()> {
+    require!(
+        args.basis_points <= MAX_ROYALTY_BASIS_POINTS,
+        BoxMinterError::InvalidRoyalties
+    );
+    require!(
+        !args.creators.is_empty() && args.creators.len() <= MAX_ROYALTY_CREATORS,
+        BoxMinterError::InvalidRoyalties
+    );
+    let mut total_percentage = 0u16;
+    for (i, creator) in args.creators.iter().enumerate() {
+        require!(
+            creator.address != Pubkey::default()
+                && !args.creators[..i]
+                    .iter()
+                    .any(|other| other.address == creator.address),
+            BoxMinterError::InvalidRoyalties
+        );
+        total_percentage += u16::from(creator.percentage);
+    }
+    require!(total_percentage == 100, BoxMinterError::InvalidRoyalties);
+    let programs = match &args.rule_set {
+        RoyaltyRuleSet::None => &[][..],
+        RoyaltyRuleSet::ProgramAllowList(programs) | RoyaltyRuleSet::ProgramDenyList(programs) => {
+            programs.as_slice()
+        }
+    };
+    require!(
+        programs.len() <= MAX_ROYALTY_RULE_SET_PROGRAMS,
+        BoxMinterError::InvalidRoyalties
+    );
+    Ok(())
+}
+
+fn validate_delivery_fee_schedule(args: &DeliveryFeeScheduleArgs) -> Result<()> {
+    require!(
+        !args.zones.is_empty() && args.zones.len() <= MAX_DELIVERY_FEE_ZONES,
+        BoxMinterError::InvalidDeliveryFeeSchedule
+    );
+    for (i, zone) in args.zones.iter().enumerate() {
+        require!(
+            zone.base_fee_lamports <= zone.max_fee_lamports
+                && !args.zones[..i]
+                    .iter()
+                    .any(|other| other.zone_id == zone.zone_id),
+            BoxMinterError::InvalidDeliveryFeeSchedule
+        );
     }
+    Ok(())
 }
 
 struct MintBoxAssetBuffers {
     name_buf: String,
     uri_buf: String,
+    number_buf: String,
+    drop_seed_hex: String,
+    /// `variant` attribute for variant drops; set by the caller before minting.
+    variant_label: Option<String>,
     create_ix: Instruction,
 }
 
@@ -620,7 +895,7 @@ fn new_mint_box_asset_buffers<'info>(
                 + 1 // data_state
                 + 4 + (BoxMinterConfig::MAX_NAME_PREFIX + 12) // name
                 + 4 + max_uri_len // uri (dynamic based on derived prefix)
-                + 1, // plugins option
+                + DropRoyalties::SPACE + 256, // plugins (royalties + attributes)
         ),
     };
     create_ix
@@ -652,6 +927,9 @@ fn new_mint_box_asset_buffers<'info>(
     MintBoxAssetBuffers {
         name_buf: String::with_capacity(BoxMinterConfig::MAX_NAME_PREFIX + 12),
         uri_buf: String::with_capacity(max_uri_len),
+        number_buf: String::with_capacity(10),
+        drop_seed_hex: hex_bytes32(&cfg.drop_seed),
+        variant_label: None,
         create_ix,
     }
 }
@@ -768,7 +1046,17 @@ fn mint_one_box_asset<'info>(
         .create_ix
         .data
         .extend_from_slice(buffers.uri_buf.as_bytes());
-    buffers.create_ix.data.push(0); // plugins: None
+    buffers.number_buf.clear();
+    write!(&mut buffers.number_buf, "{}", metadata_id)
+        .map_err(|_| error!(BoxMinterError::SerializationFailed))?;
+    let mut attributes: Vec<(&str, &str)> = Vec::with_capacity(3);
+    if let Some(variant_label) = buffers.variant_label.as_deref() {
+        attributes.push(("variant", variant_label));
+    }
+    attributes.push(("box", buffers.number_buf.as_str()));
+    attributes.push(("drop", buffers.drop_seed_hex.as_str()));
+    let plugins_data = create_plugins_data(accounts.royalties.as_ref(), &attributes)?;
+    buffers.create_ix.data.extend_from_slice(&plugins_data);
 
     let cfg_ai = cfg.to_account_info();
     let cpi_infos = [
@@ -862,15 +1150,22 @@ fn mint_standard_boxes_inner<'info>(
     Ok(())
 }
 
+/// Variant chosen by the buyer plus where its ids and price come from.
+struct VariantMintSelection<'a> {
+    variant_index: u8,
+    discounted: bool,
+    /// Required for `MINT_VARIANT_KIND_TABLE` drops; ignored otherwise.
+    variant_table: Option<&'a mut VariantTable>,
+}
+
 fn mint_variant_box_inner<'info>(
     cfg: &mut Account<'info, BoxMinterConfig>,
     accounts: &MintBoxesInnerAccounts<'info>,
     remaining_accounts: &[AccountInfo<'info>],
-    variant_index: u8,
+    mut selection: VariantMintSelection,
     mint_id: u64,
     box_bump: u8,
     program_id: &Pubkey,
-    unit_price_lamports: u64,
 ) -> Result<()> {
     require!(cfg.started, BoxMinterError::MintNotStarted);
     require_keys_eq!(
@@ -892,7 +1187,23 @@ fn mint_variant_box_inner<'info>(
         &asset_bumps,
         program_id,
     )?;
-    let metadata_id = reserve_variant_metadata_ids(&mut *cfg, variant_index, 1)?;
+    let unit_price_lamports = variant_unit_price(
+        cfg,
+        selection.variant_table.as_deref(),
+        selection.variant_index,
+        selection.discounted,
+    )?;
+    let metadata_id = reserve_variant_selection_metadata_ids(
+        &mut *cfg,
+        selection.variant_table.as_deref_mut(),
+        selection.variant_index,
+        1,
+    )?;
+    let variant_label = variant_attribute_value(
+        cfg,
+        selection.variant_table.as_deref(),
+        selection.variant_index,
+    )?;
     charge_mint_payment(
         accounts,
         unit_price_lamports,
@@ -902,6 +1213,7 @@ fn mint_variant_box_inner<'info>(
     )?;
 
     let mut buffers = new_mint_box_asset_buffers(cfg, accounts);
+    buffers.variant_label = variant_label;
     mint_one_box_asset(
         cfg,
         accounts,
@@ -1049,2067 +1361,6935 @@ fn create_or_reclaim_empty_pda_account<'info>(
     Ok(())
 }
 
-struct ReceiptMintAccounts<'info> {
-    cosigner: AccountInfo<'info>,
-    leaf_recipient: AccountInfo<'info>,
-    merkle_tree: AccountInfo<'info>,
-    tree_config: AccountInfo<'info>,
-    core_collection: AccountInfo<'info>,
-    bubblegum_program: AccountInfo<'info>,
-    log_wrapper: AccountInfo<'info>,
-    compression_program: AccountInfo<'info>,
-    mpl_core_program: AccountInfo<'info>,
-    mpl_core_cpi_signer: AccountInfo<'info>,
-    system_program: AccountInfo<'info>,
+/// Loads a per-drop PDA; `None` until it is initialized (program-owned).
+///
+/// Callers must validate the PDA address (Anchor seeds constraint).
+fn load_drop_pda<T: AccountDeserialize>(
+    account: &AccountInfo,
+    program_id: &Pubkey,
+) -> Result<Option<T>> {
+    if account.owner != program_id || account.data_is_empty() {
+        return Ok(None);
+    }
+    let data = account.try_borrow_data()?;
+    T::try_deserialize(&mut &data[..]).map(Some)
 }
 
-struct ReceiptMintCpi<'info> {
-    ix: Instruction,
-    account_infos: [AccountInfo<'info>; 14],
+/// Holder of opened boxes and delivered items: the drop's custody vault PDA once
+/// `initialize_custody_vault` ran, `config.admin` before that.
+#[derive(Clone)]
+struct Custody<'info> {
+    owner: AccountInfo<'info>,
+    config_key: Pubkey,
+    /// Custody vault bump; `None` while the admin holds custody.
+    vault_bump: Option<u8>,
 }
 
-impl<'info> ReceiptMintAccounts<'info> {
-    fn from_admin_delivery(accounts: &AdminDeliverVariantOrder<'info>) -> Self {
+impl<'info> Custody<'info> {
+    fn admin(config_key: Pubkey, admin: &AccountInfo<'info>) -> Self {
         Self {
-            cosigner: accounts.cosigner.to_account_info(),
-            leaf_recipient: accounts.receipt_owner.to_account_info(),
-            merkle_tree: accounts.merkle_tree.to_account_info(),
-            tree_config: accounts.tree_config.to_account_info(),
+            owner: admin.clone(),
+            config_key,
+            vault_bump: None,
+        }
+    }
+
+    /// `admin` must already be checked against `config.admin`. The vault only takes over once it
+    /// is program-owned.
+    fn load(
+        config_key: Pubkey,
+        admin: &AccountInfo<'info>,
+        custody_vault: &AccountInfo<'info>,
+        program_id: &Pubkey,
+    ) -> Result<Self> {
+        let Some(vault) = load_drop_pda::<CustodyVault>(custody_vault, program_id)? else {
+            return Ok(Self::admin(config_key, admin));
+        };
+        Ok(Self {
+            owner: custody_vault.clone(),
+            config_key,
+            vault_bump: Some(vault.bump),
+        })
+    }
+
+    /// Assets moved into custody before the vault existed stay with the admin, who still signs
+    /// for them.
+    fn for_asset_owner(&self, owner: Pubkey, admin: &AccountInfo<'info>) -> Self {
+        if owner == admin.key() {
+            Self::admin(self.config_key, admin)
+        } else {
+            self.clone()
+        }
+    }
+
+    /// Invokes `ix` with the custody owner as a signer; the program signs for the vault PDA.
+    fn invoke(&self, ix: &Instruction, account_infos: &[AccountInfo<'info>]) -> Result<()> {
+        match self.vault_bump {
+            Some(bump) => invoke_signed(
+                ix,
+                account_infos,
+                &[&[SEED_CUSTODY_VAULT, self.config_key.as_ref(), &[bump]]],
+            ),
+            None => invoke(ix, account_infos),
+        }
+        .map_err(Into::into)
+    }
+}
+
+fn core_asset_owner(asset_ai: &AccountInfo) -> Result<Pubkey> {
+    require_keys_eq!(
+        *asset_ai.owner,
+        MPL_CORE_PROGRAM_ID,
+        BoxMinterError::InvalidAsset
+    );
+    let data = asset_ai.try_borrow_data()?;
+    Ok(parse_mpl_core_base_asset_v1(&data)?.owner)
+}
+
+struct OpenBoxInnerAccounts<'info> {
+    config: AccountInfo<'info>,
+    payer: AccountInfo<'info>,
+    vault: AccountInfo<'info>,
+    core_collection: AccountInfo<'info>,
+    mpl_core_program: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    log_wrapper: AccountInfo<'info>,
+    variant_table: Option<VariantTable>,
+    royalties: Option<DropRoyalties>,
+}
+
+impl<'info> OpenBoxInnerAccounts<'info> {
+    fn from_start_open_box(accounts: &StartOpenBox<'info>, program_id: &Pubkey) -> Result<Self> {
+        Ok(Self {
+            config: accounts.config.to_account_info(),
+            payer: accounts.payer.to_account_info(),
+            vault: Custody::load(
+                accounts.config.key(),
+                &accounts.vault,
+                &accounts.custody_vault,
+                program_id,
+            )?
+            .owner,
             core_collection: accounts.core_collection.to_account_info(),
-            bubblegum_program: accounts.bubblegum_program.to_account_info(),
-            log_wrapper: accounts.log_wrapper.to_account_info(),
-            compression_program: accounts.compression_program.to_account_info(),
             mpl_core_program: accounts.mpl_core_program.to_account_info(),
-            mpl_core_cpi_signer: accounts.mpl_core_cpi_signer.to_account_info(),
             system_program: accounts.system_program.to_account_info(),
-        }
+            log_wrapper: accounts.log_wrapper.to_account_info(),
+            variant_table: accounts.variant_table.as_deref().cloned(),
+            royalties: load_drop_royalties(&accounts.royalties, program_id)?,
+        })
     }
 
-    fn from_mint_receipts(accounts: &MintReceipts<'info>) -> Self {
-        Self {
-            cosigner: accounts.cosigner.to_account_info(),
-            leaf_recipient: accounts.user.to_account_info(),
-            merkle_tree: accounts.merkle_tree.to_account_info(),
-            tree_config: accounts.tree_config.to_account_info(),
+    fn from_start_open_boxes(
+        accounts: &StartOpenBoxes<'info>,
+        program_id: &Pubkey,
+    ) -> Result<Self> {
+        Ok(Self {
+            config: accounts.config.to_account_info(),
+            payer: accounts.payer.to_account_info(),
+            vault: Custody::load(
+                accounts.config.key(),
+                &accounts.vault,
+                &accounts.custody_vault,
+                program_id,
+            )?
+            .owner,
             core_collection: accounts.core_collection.to_account_info(),
-            bubblegum_program: accounts.bubblegum_program.to_account_info(),
-            log_wrapper: accounts.log_wrapper.to_account_info(),
-            compression_program: accounts.compression_program.to_account_info(),
             mpl_core_program: accounts.mpl_core_program.to_account_info(),
-            mpl_core_cpi_signer: accounts.mpl_core_cpi_signer.to_account_info(),
             system_program: accounts.system_program.to_account_info(),
-        }
+            log_wrapper: accounts.log_wrapper.to_account_info(),
+            variant_table: accounts.variant_table.as_deref().cloned(),
+            royalties: load_drop_royalties(&accounts.royalties, program_id)?,
+        })
     }
 }
 
-fn validate_receipt_mint_accounts(accounts: &ReceiptMintAccounts<'_>) -> Result<()> {
-    require_keys_eq!(
-        accounts.bubblegum_program.key(),
-        BUBBLEGUM_PROGRAM_ID,
-        BoxMinterError::InvalidBubblegumProgram
-    );
-    require_keys_eq!(
-        accounts.log_wrapper.key(),
-        MPL_NOOP_PROGRAM_ID,
-        BoxMinterError::InvalidMplNoopProgram
-    );
-    require_keys_eq!(
-        accounts.compression_program.key(),
-        MPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
-        BoxMinterError::InvalidCompressionProgram
-    );
-    require_keys_eq!(
-        accounts.mpl_core_program.key(),
-        MPL_CORE_PROGRAM_ID,
-        BoxMinterError::InvalidMplCoreProgram
-    );
-    require_keys_eq!(
-        accounts.mpl_core_cpi_signer.key(),
-        MPL_CORE_CPI_SIGNER,
-        BoxMinterError::InvalidMplCoreCpiSigner
-    );
-    require_keys_eq!(
-        *accounts.merkle_tree.owner,
-        MPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
-        BoxMinterError::InvalidReceiptsMerkleTree
-    );
-    let (expected_tree_config, _) = Pubkey::find_program_address(
-        &[accounts.merkle_tree.key().as_ref()],
-        &BUBBLEGUM_PROGRAM_ID,
-    );
-    require_keys_eq!(
-        accounts.tree_config.key(),
-        expected_tree_config,
-        BoxMinterError::InvalidReceiptsTreeConfig
-    );
+fn validate_pending_open_pda(
+    program_id: &Pubkey,
+    box_asset_key: Pubkey,
+    pending_key: Pubkey,
+    pending_bump: u8,
+) -> Result<()> {
+    let expected = Pubkey::create_program_address(
+        &[SEED_PENDING_OPEN, box_asset_key.as_ref(), &[pending_bump]],
+        program_id,
+    )
+    .map_err(|_| error!(BoxMinterError::InvalidPendingRecord))?;
+    require_keys_eq!(pending_key, expected, BoxMinterError::InvalidPendingRecord);
     Ok(())
 }
 
-fn new_receipt_mint_ix(accounts: &ReceiptMintAccounts<'_>) -> Instruction {
-    Instruction {
-        program_id: BUBBLEGUM_PROGRAM_ID,
-        accounts: vec![
-            // mintV2 accounts order (kinobi):
-            // 0 treeConfig (writable)
-            AccountMeta::new(accounts.tree_config.key(), false),
-            // 1 payer (writable signer)
-            AccountMeta::new(accounts.cosigner.key(), true),
-            // 2 treeCreatorOrDelegate (signer)
-            AccountMeta::new_readonly(accounts.cosigner.key(), true),
-            // 3 collectionAuthority (signer)
-            AccountMeta::new_readonly(accounts.cosigner.key(), true),
-            // 4 leafOwner
-            AccountMeta::new_readonly(accounts.leaf_recipient.key(), false),
-            // 5 leafDelegate
-            AccountMeta::new_readonly(accounts.leaf_recipient.key(), false),
-            // 6 merkleTree (writable)
-            AccountMeta::new(accounts.merkle_tree.key(), false),
-            // 7 coreCollection (writable)
-            AccountMeta::new(accounts.core_collection.key(), false),
-            // 8 mplCoreCpiSigner
-            AccountMeta::new_readonly(accounts.mpl_core_cpi_signer.key(), false),
-            // 9 logWrapper
-            AccountMeta::new_readonly(accounts.log_wrapper.key(), false),
-            // 10 compressionProgram
-            AccountMeta::new_readonly(accounts.compression_program.key(), false),
-            // 11 mplCoreProgram
-            AccountMeta::new_readonly(accounts.mpl_core_program.key(), false),
-            // 12 systemProgram
-            AccountMeta::new_readonly(accounts.system_program.key(), false),
-        ],
-        data: Vec::with_capacity(256),
-    }
+/// Per-box accounts for a start: the user's box, its pending record PDA and the placeholder
+/// figure asset PDAs to create.
+struct StartOpenBoxAccounts<'a, 'info> {
+    box_asset: &'a AccountInfo<'info>,
+    pending: &'a AccountInfo<'info>,
+    pending_bump: u8,
+    placeholders: &'a [AccountInfo<'info>],
 }
 
-fn new_receipt_mint_cpi<'info>(accounts: &ReceiptMintAccounts<'info>) -> ReceiptMintCpi<'info> {
-    ReceiptMintCpi {
-        ix: new_receipt_mint_ix(accounts),
-        // Matches the AccountMeta order in new_receipt_mint_ix; Bubblegum's program account is
-        // appended for CPI invocation.
-        account_infos: [
-            accounts.tree_config.clone(),
-            accounts.cosigner.clone(),
-            accounts.cosigner.clone(),
-            accounts.cosigner.clone(),
-            accounts.leaf_recipient.clone(),
-            accounts.leaf_recipient.clone(),
-            accounts.merkle_tree.clone(),
-            accounts.core_collection.clone(),
-            accounts.mpl_core_cpi_signer.clone(),
-            accounts.log_wrapper.clone(),
-            accounts.compression_program.clone(),
-            accounts.mpl_core_program.clone(),
-            accounts.system_program.clone(),
-            accounts.bubblegum_program.clone(),
-        ],
+/// How a specific box opens: how many figures it holds and which figure ids it may reveal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct BoxOpenSettings {
+    items_per_box: u8,
+    figure_start_id: u16,
+    figure_end_id: u16,
+}
+
+fn box_open_settings(
+    cfg: &BoxMinterConfig,
+    variant_table: Option<&VariantTable>,
+    box_id: u32,
+) -> Result<BoxOpenSettings> {
+    if !cfg.requires_variant_selection() {
+        return Ok(BoxOpenSettings {
+            items_per_box: cfg.items_per_box,
+            figure_start_id: 1,
+            figure_end_id: cfg.max_figure_id()?,
+        });
     }
+    require!(
+        cfg.uses_variant_table(),
+        BoxMinterError::MintVariantDirectDeliveryOnly
+    );
+    let table = variant_table.ok_or(error!(BoxMinterError::VariantTableMissing))?;
+    Ok(table.option_for_metadata_id(box_id)?.open_settings())
 }
 
-fn write_receipt_mint_data(
-    data: &mut Vec<u8>,
-    core_collection: Pubkey,
-    name: &str,
-    uri: &str,
-) -> Result<()> {
-    data.clear();
-    // MetadataArgsV2 (borsh):
-    // name, symbol, uri, sellerFeeBasisPoints(u16), primarySaleHappened(bool), isMutable(bool),
-    // tokenStandard: Option<TokenStandard> (Some(NonFungible=0)),
-    // creators: Vec<Creator> (empty),
-    // collection: Option<Pubkey> (Some(coreCollection))
-    data.extend_from_slice(&IX_BUBBLEGUM_MINT_V2);
-    borsh_push_string(data, name)?;
-    borsh_push_string(data, "")?;
-    borsh_push_string(data, uri)?;
-    data.extend_from_slice(&(0u16).to_le_bytes()); // sellerFeeBasisPoints
-    data.push(0u8); // primarySaleHappened=false
-    data.push(1u8); // isMutable=true
-    data.push(1u8); // tokenStandard: Some
-    data.push(0u8); // NonFungible enum index
-    data.extend_from_slice(&(0u32).to_le_bytes()); // creators vec len=0
-    data.push(1u8); // collection: Some
-    data.extend_from_slice(core_collection.as_ref());
-    data.push(0u8); // assetData: None
-    data.push(0u8); // assetDataSchema: None
-    Ok(())
-}
-
-fn invoke_receipt_mint_v2<'info>(
-    cpi: &mut ReceiptMintCpi<'info>,
-    core_collection: Pubkey,
-    name: &str,
-    uri: &str,
-) -> Result<()> {
-    write_receipt_mint_data(&mut cpi.ix.data, core_collection, name, uri)?;
-    // CPI: include the program account at the end (like SystemProgram CPIs).
-    invoke(&cpi.ix, &cpi.account_infos)?;
-    Ok(())
-}
-
-fn build_receipt_name_and_uri(
-    name_buf: &mut String,
-    uri_buf: &mut String,
-    drop_base: &str,
-    label: &str,
-    uri_prefix: &str,
-    id: impl core::fmt::Display + Copy,
-) -> Result<()> {
-    name_buf.clear();
-    name_buf.push_str(RECEIPT_NAME_PREFIX);
-    append_label_and_id(name_buf, label, id)?;
-
-    uri_buf.clear();
-    uri_buf.push_str(drop_base);
-    uri_buf.push_str(uri_prefix);
-    write!(uri_buf, "{}", id).map_err(|_| error!(BoxMinterError::SerializationFailed))?;
-    uri_buf.push_str(".json");
-    Ok(())
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct VariantRange {
+    start_id: u32,
+    end_id: u32,
+    next_id: u32,
 }
 
-fn mint_admin_order_receipt_cnfts<'info>(
+/// Checks how each variant opens against its box id range.
+///
+/// Direct-delivery drops carry no open settings. Openable drops need `1..=config.items_per_box`
+/// figures per box and disjoint figure pools large enough for every box in the variant's range.
+fn validate_variant_open_settings(
     cfg: &BoxMinterConfig,
-    accounts: &ReceiptMintAccounts<'info>,
-    first_metadata_id: u32,
-    quantity: u8,
+    ranges: &[VariantRange],
+    settings: &[BoxOpenSettings],
 ) -> Result<()> {
-    require!(quantity >= 1, BoxMinterError::InvalidQuantity);
-    let last_metadata_id = first_metadata_id
-        .checked_add(u32::from(quantity) - 1)
-        .ok_or(BoxMinterError::MathOverflow)?;
     require!(
-        first_metadata_id >= 1 && last_metadata_id <= cfg.max_supply,
-        BoxMinterError::InvalidAssetMetadata
+        ranges.len() == settings.len(),
+        BoxMinterError::InvalidBoxVariantConfig
     );
-
-    let drop_base = cfg.uri_base.as_str();
-    let mut name_buf = String::with_capacity(48);
-    let mut uri_buf = String::with_capacity(drop_base.len() + URI_PREFIX_RECEIPTS_BOXES.len() + 16);
-    let mut mint_cpi = new_receipt_mint_cpi(accounts);
-
-    for metadata_id in first_metadata_id..=last_metadata_id {
-        build_receipt_name_and_uri(
-            &mut name_buf,
-            &mut uri_buf,
-            drop_base,
-            &cfg.name_prefix,
-            URI_PREFIX_RECEIPTS_BOXES,
-            metadata_id,
-        )?;
-        invoke_receipt_mint_v2(&mut mint_cpi, cfg.core_collection, &name_buf, &uri_buf)?;
+    if cfg.items_per_box == 0 {
+        require!(
+            settings.iter().all(|setting| *setting
+                == BoxOpenSettings {
+                    items_per_box: 0,
+                    figure_start_id: 0,
+                    figure_end_id: 0,
+                }),
+            BoxMinterError::InvalidBoxVariantConfig
+        );
+        return Ok(());
     }
+    let max_figure_id = cfg.max_figure_id()?;
 
-    Ok(())
-}
+    for (i, (range, setting)) in ranges.iter().zip(settings).enumerate() {
+        let items = setting.items_per_box;
+        let figure_start_id = setting.figure_start_id;
+        let figure_end_id = setting.figure_end_id;
+        require!(
+            items >= MIN_OPENABLE_ITEMS_PER_BOX && items <= cfg.items_per_box,
+            BoxMinterError::InvalidBoxVariantConfig
+        );
+        require!(
+            figure_start_id >= 1
+                && figure_start_id <= figure_end_id
+                && figure_end_id <= max_figure_id,
+            BoxMinterError::InvalidBoxVariantConfig
+        );
 
-fn validate_admin_order_hash(order_hash: &[u8; 32]) -> Result<()> {
-    require!(
-        has_any_non_zero_byte(order_hash.as_ref()),
-        BoxMinterError::InvalidAdminOrder
-    );
+        // Every box in the variant's id range must be able to draw unique figures from its pool.
+        let box_count = u64::from(
+            range
+                .end_id
+                .checked_sub(range.start_id)
+                .ok_or(BoxMinterError::InvalidMintVariantConfig)?,
+        ) + 1;
+        let pool_size = u64::from(figure_end_id - figure_start_id) + 1;
+        require!(
+            pool_size >= box_count * u64::from(items),
+            BoxMinterError::InvalidBoxVariantConfig
+        );
+
+        for other in &settings[..i] {
+            require!(
+                figure_end_id < other.figure_start_id || figure_start_id > other.figure_end_id,
+                BoxMinterError::InvalidBoxVariantConfig
+            );
+        }
+    }
     Ok(())
 }
 
-fn admin_order_is_valid_retry(
-    record: &AdminDeliveryOrderRecord,
-    args: &AdminDeliverVariantOrderArgs,
-    effective_variant_index: u8,
-    receipt_owner: Pubkey,
-    order_bump: u8,
-) -> bool {
-    record.order_hash == args.order_hash
-        && record.variant_index == effective_variant_index
-        && record.quantity == args.quantity
-        && record.receipt_owner == receipt_owner
-        && record.bump == order_bump
-        && record.quantity >= 1
-        && record.first_metadata_id >= 1
+/// Verifies that `box_asset` is a Mons box owned by `owner`; returns its box number and how it
+/// opens.
+fn verify_openable_box(
+    cfg: &BoxMinterConfig,
+    variant_table: Option<&VariantTable>,
+    box_asset: &AccountInfo,
+    owner: Pubkey,
+) -> Result<(u32, BoxOpenSettings)> {
+    let box_id = verify_core_asset_owned_by_uri(
+        box_asset,
+        owner,
+        cfg.core_collection,
+        cfg.uri_base.as_str(),
+        legacy_box_uri_base(cfg),
+        URI_PREFIX_BOXES,
+        None,
+    )?;
+    Ok((box_id, box_open_settings(cfg, variant_table, box_id)?))
 }
 
-fn deserialize_admin_order_record(
-    account: &AccountInfo<'_>,
+/// Moves one box into the vault and creates its pending open record + placeholder figures.
+///
+/// Callers must validate the MPL-Core/log wrapper program ids, the pending PDA address and the
+/// box itself (see `verify_openable_box`).
+fn start_open_box_inner<'info>(
+    cfg: &BoxMinterConfig,
+    accounts: &OpenBoxInnerAccounts<'info>,
+    box_accounts: &StartOpenBoxAccounts<'_, 'info>,
+    settings: &BoxOpenSettings,
     program_id: &Pubkey,
-) -> Result<AdminDeliveryOrderRecord> {
+) -> Result<()> {
+    let box_asset = box_accounts.box_asset;
+    let pending_ai = box_accounts.pending;
+    let pending_bump = box_accounts.pending_bump;
+    let placeholder_accounts = box_accounts.placeholders;
+    let items_per_box = usize::from(settings.items_per_box);
+
+    // Pending open record PDA:
+    // - Do not rely on Anchor `init_if_needed` here; its reclaim behavior for pre-funded PDA stubs
+    //   (system-owned, data_len=0) has historically been version-sensitive.
+    // - Starting an open twice for the same box must fail.
+    //
+    // Note: a PDA can be "pre-funded", creating a system-owned stub account that makes
+    // `system_instruction::create_account` fail ("account already in use"). Since this is a PDA,
+    // we can sign for it and reclaim it via `allocate` + `assign`.
+    let pending_space: usize = PendingOpenBox::space(settings.items_per_box);
+    let box_asset_key = box_asset.key();
+    let pending_seeds: &[&[u8]] = &[SEED_PENDING_OPEN, box_asset_key.as_ref(), &[pending_bump]];
+    create_or_reclaim_empty_pda_account(
+        pending_ai,
+        &accounts.payer,
+        &accounts.system_program,
+        pending_space,
+        program_id,
+        pending_seeds,
+        BoxMinterError::InvalidPendingRecord,
+        BoxMinterError::PendingAlreadyExists,
+    )?;
+
+    // Post-conditions: at this point the pending PDA must be a properly sized, program-owned
+    // account ready for serialization.
     require_keys_eq!(
-        *account.owner,
+        *pending_ai.owner,
         *program_id,
-        BoxMinterError::InvalidAdminOrder
-    );
-    let data = account.try_borrow_data()?;
-    let mut data: &[u8] = &data;
-    AdminDeliveryOrderRecord::try_deserialize(&mut data)
-        .map_err(|_| error!(BoxMinterError::InvalidAdminOrder))
-}
-
-fn validate_admin_order_pda(
-    program_id: &Pubkey,
-    config_key: &Pubkey,
-    order_hash: &[u8; 32],
-    admin_order_key: Pubkey,
-) -> Result<u8> {
-    let (expected_order, canonical_bump) = Pubkey::find_program_address(
-        &[SEED_ADMIN_ORDER, config_key.as_ref(), order_hash.as_ref()],
-        program_id,
+        BoxMinterError::InvalidPendingRecord
     );
-    require_keys_eq!(
-        admin_order_key,
-        expected_order,
-        BoxMinterError::InvalidAdminOrderPda
+    require!(
+        pending_ai.data_len() == pending_space,
+        BoxMinterError::InvalidPendingRecord
     );
-    Ok(canonical_bump)
-}
-
-fn reserve_variant_metadata_ids(
-    cfg: &mut BoxMinterConfig,
-    variant_index: u8,
-    quantity: u8,
-) -> Result<u32> {
-    require!(quantity >= 1, BoxMinterError::InvalidQuantity);
-    let new_total = cfg
-        .minted
-        .checked_add(u32::from(quantity))
-        .ok_or(BoxMinterError::MathOverflow)?;
-    require!(new_total <= cfg.max_supply, BoxMinterError::SoldOut);
 
-    let variant_slot = cfg.variant_slot(variant_index)?;
-    let first_metadata_id = cfg.next_variant_metadata_id(variant_slot)?;
-    let last_metadata_id = first_metadata_id
-        .checked_add(u32::from(quantity) - 1)
-        .ok_or(BoxMinterError::MathOverflow)?;
+    // Exactly `items_per_box` (for this box's variant) new placeholder figure asset PDAs.
     require!(
-        last_metadata_id <= cfg.mint_variant_end_ids[variant_slot],
-        BoxMinterError::MintVariantUnavailable
+        placeholder_accounts.len() == items_per_box,
+        BoxMinterError::InvalidRemainingAccounts
     );
 
-    cfg.minted = new_total;
-    cfg.mint_variant_next_ids[variant_slot] = last_metadata_id
-        .checked_add(1)
-        .ok_or(BoxMinterError::MathOverflow)?;
-    Ok(first_metadata_id)
-}
+    // Transfer the box to the vault/admin via MPL-Core `TransferV1` inside this instruction.
+    // This makes the instruction robust against wallets that insert extra instructions into the tx.
+    let mpl_core_program = &accounts.mpl_core_program;
+    let core_collection = &accounts.core_collection;
+    let payer = &accounts.payer;
+    let vault = &accounts.vault;
+    let system_program = &accounts.system_program;
+    let log_wrapper = &accounts.log_wrapper;
+    let cfg_ai = &accounts.config;
+    let cfg_bump_bytes = [cfg.bump];
+    let cfg_signer_seeds: &[&[u8]] = &[
+        BoxMinterConfig::SEED,
+        cfg.drop_seed.as_ref(),
+        &cfg_bump_bytes,
+    ];
 
-fn reserve_standard_metadata_ids(cfg: &mut BoxMinterConfig, quantity: u8) -> Result<u32> {
-    require!(quantity >= 1, BoxMinterError::InvalidQuantity);
-    let first_metadata_id = cfg
-        .minted
-        .checked_add(1)
-        .ok_or(BoxMinterError::MathOverflow)?;
-    let new_total = cfg
-        .minted
-        .checked_add(u32::from(quantity))
-        .ok_or(BoxMinterError::MathOverflow)?;
-    require!(new_total <= cfg.max_supply, BoxMinterError::SoldOut);
+    let transfer_ix = Instruction {
+        program_id: MPL_CORE_PROGRAM_ID,
+        accounts: vec![
+            // asset, collection, payer, authority, new_owner, system_program, log_wrapper
+            AccountMeta::new(box_asset.key(), false),
+            AccountMeta::new_readonly(core_collection.key(), false),
+            AccountMeta::new(payer.key(), true),
+            AccountMeta::new_readonly(payer.key(), true),
+            AccountMeta::new_readonly(vault.key(), false),
+            AccountMeta::new_readonly(system_program.key(), false),
+            AccountMeta::new_readonly(log_wrapper.key(), false),
+        ],
+        // TransferV1 discriminator=14, compression_proof=None (0)
+        data: vec![14u8, 0u8],
+    };
+    invoke(
+        &transfer_ix,
+        &[
+            box_asset.clone(),
+            core_collection.clone(),
+            payer.clone(),
+            payer.clone(),
+            vault.clone(),
+            system_program.clone(),
+            log_wrapper.clone(),
+            mpl_core_program.clone(),
+        ],
+    )?;
 
-    cfg.minted = new_total;
-    Ok(first_metadata_id)
-}
+    // Create placeholder Core assets:
+    // - owner: the custody vault (config.admin until the drop's vault PDA exists)
+    // - update authority: config PDA (so only the program can later "reveal" by updating metadata + setting collection)
+    // - collection: None (placeholder) so the assets do NOT appear in the collection until reveal.
+    let pending_key = pending_ai.key();
+    let mut dudes: Vec<Pubkey> = Vec::with_capacity(items_per_box);
 
-fn admin_delivery_effective_variant_index(cfg: &BoxMinterConfig, variant_index: u8) -> u8 {
-    if cfg.requires_variant_selection() {
-        variant_index
-    } else {
-        0
-    }
-}
+    let plugins_data = create_plugins_data(accounts.royalties.as_ref(), &[])?;
+    let mut create_ix = Instruction {
+        program_id: MPL_CORE_PROGRAM_ID,
+        accounts: vec![
+            // 0 asset (placeholder)
+            AccountMeta::new(Pubkey::default(), true),
+            // 1 collection: None => placeholder = program id (must be readonly when absent)
+            AccountMeta::new_readonly(MPL_CORE_PROGRAM_ID, false),
+            // 2 authority (signer): config PDA
+            AccountMeta::new_readonly(cfg_ai.key(), true),
+            // 3 payer (signer)
+            AccountMeta::new(payer.key(), true),
+            // 4 owner: custody vault/admin (not signer)
+            AccountMeta::new_readonly(vault.key(), false),
+            // 5 update authority: config PDA (not signer account meta)
+            AccountMeta::new_readonly(cfg_ai.key(), false),
+            // 6 system program
+            AccountMeta::new_readonly(system_program.key(), false),
+            // 7 log wrapper: None => placeholder = program id
+            AccountMeta::new_readonly(MPL_CORE_PROGRAM_ID, false),
+        ],
+        data: Vec::with_capacity(10 + plugins_data.len()),
+    };
 
-fn reserve_admin_delivery_metadata_ids(
-    cfg: &mut BoxMinterConfig,
-    variant_index: u8,
-    quantity: u8,
-) -> Result<u32> {
-    require!(quantity >= 1, BoxMinterError::InvalidQuantity);
-    require!(cfg.started, BoxMinterError::MintNotStarted);
-    let max_qty = cfg.max_per_tx.min(MAX_SAFE_MINTS_PER_TX);
-    require!(quantity <= max_qty, BoxMinterError::InvalidQuantity);
+    for (i, asset_ai) in placeholder_accounts.iter().enumerate() {
+        let i_u8: u8 = i
+            .try_into()
+            .map_err(|_| error!(BoxMinterError::InvalidRemainingAccounts))?;
+        let i_seed = [i_u8];
+        let (expected, asset_bump) = Pubkey::find_program_address(
+            &[SEED_PENDING_DUDE_ASSET, pending_key.as_ref(), &i_seed],
+            program_id,
+        );
 
-    if cfg.requires_variant_selection() {
-        reserve_variant_metadata_ids(cfg, variant_index, quantity)
-    } else {
-        reserve_standard_metadata_ids(cfg, quantity)
+        require_keys_eq!(asset_ai.key(), expected, BoxMinterError::InvalidAssetPda);
+        // Ensure the account is uninitialized (otherwise Create will fail and waste compute).
+        require_keys_eq!(
+            *asset_ai.owner,
+            anchor_lang::solana_program::system_program::ID,
+            BoxMinterError::InvalidAssetPda
+        );
+
+        dudes.push(expected);
+
+        let asset_seeds: &[&[u8]] = &[
+            SEED_PENDING_DUDE_ASSET,
+            pending_key.as_ref(),
+            &i_seed,
+            &[asset_bump],
+        ];
+        let signer_seeds: &[&[&[u8]]] = &[cfg_signer_seeds, asset_seeds];
+
+        // Prevent PDA "squatting": if the placeholder PDA was pre-funded, MPL-Core Create would fail.
+        // Drain any prefunded lamports back to the payer before invoking MPL-Core.
+        let prefund_lamports = asset_ai.lamports();
+        if prefund_lamports > 0 {
+            let sweep_ix = anchor_lang::solana_program::system_instruction::transfer(
+                asset_ai.key,
+                payer.key,
+                prefund_lamports,
+            );
+            invoke_signed(
+                &sweep_ix,
+                &[asset_ai.clone(), payer.clone(), system_program.clone()],
+                &[asset_seeds],
+            )
+            .map_err(anchor_lang::error::Error::from)?;
+        }
+
+        create_ix.accounts[0].pubkey = asset_ai.key();
+        create_ix.data.clear();
+        // CreateV1 discriminator=0, DataState::AccountState=0
+        create_ix.data.push(0u8);
+        create_ix.data.push(0u8);
+        // name: empty string
+        create_ix.data.extend_from_slice(&(0u32).to_le_bytes());
+        // uri: empty string
+        create_ix.data.extend_from_slice(&(0u32).to_le_bytes());
+        // plugins: drop royalties, if configured (carried over to the revealed figure)
+        create_ix.data.extend_from_slice(&plugins_data);
+
+        let create_infos = [
+            mpl_core_program.clone(),
+            asset_ai.clone(),
+            cfg_ai.clone(),
+            payer.clone(),
+            vault.clone(),
+            cfg_ai.clone(),
+            system_program.clone(),
+        ];
+        invoke_signed(&create_ix, &create_infos, signer_seeds)
+            .map_err(anchor_lang::error::Error::from)?;
     }
+
+    // Persist the pending flow record so the admin can later finalize it.
+    let record = PendingOpenBox {
+        owner: payer.key(),
+        box_asset: box_asset_key,
+        dudes,
+        created_slot: Clock::get()?.slot,
+        bump: pending_bump,
+        config: cfg_ai.key(),
+    };
+    record.try_serialize(&mut &mut pending_ai.data.borrow_mut()[..])?;
+    Ok(())
 }
 
-fn initialize_box_minter_config(
-    cfg: &mut Account<BoxMinterConfig>,
-    admin: Pubkey,
-    treasury: Pubkey,
-    core_collection_ai: &AccountInfo,
-    bump: u8,
-    args: InitializeArgs,
-) -> Result<()> {
-    require_keys_eq!(
-        admin,
-        EXPECTED_INITIALIZER,
-        BoxMinterError::UnauthorizedInitializer
-    );
-    require!(args.max_supply > 0, BoxMinterError::InvalidMaxSupply);
-    require!(args.max_per_tx > 0, BoxMinterError::InvalidMaxPerTx);
-    require!(
-        args.max_per_tx <= MAX_SAFE_MINTS_PER_TX,
-        BoxMinterError::InvalidMaxPerTx
-    );
-    require!(
-        args.items_per_box <= MAX_ITEMS_PER_BOX,
-        BoxMinterError::InvalidItemsPerBox
-    );
-    let max_figure_id = (args.max_supply as u64)
-        .checked_mul(args.items_per_box as u64)
-        .ok_or(BoxMinterError::MathOverflow)?;
-    require!(
-        max_figure_id <= u16::MAX as u64,
-        BoxMinterError::InvalidItemsPerBox
-    );
-    validate_mint_prices(args.price_lamports, args.discount_price_lamports)?;
-    require!(
-        args.mint_variant_kind == MINT_VARIANT_KIND_NONE
-            || args.mint_variant_kind == MINT_VARIANT_KIND_SIZE,
-        BoxMinterError::InvalidMintVariantConfig
-    );
-    require!(
-        args.discount_merkle_root != [0u8; 32],
-        BoxMinterError::DiscountNotConfigured
-    );
-    require!(
-        args.discount_mints_per_wallet >= MIN_DISCOUNT_MINTS_PER_WALLET
-            && args.discount_mints_per_wallet <= MAX_DISCOUNT_MINTS_PER_WALLET,
-        BoxMinterError::InvalidDiscountMintsPerWallet
-    );
-    require!(
-        args.name_prefix.len() <= BoxMinterConfig::MAX_NAME_PREFIX,
-        BoxMinterError::NameTooLong
-    );
-    require!(
-        args.figure_name_prefix.len() <= BoxMinterConfig::MAX_FIGURE_NAME_PREFIX,
-        BoxMinterError::FigureNameTooLong
-    );
-    require!(
-        args.symbol.len() <= BoxMinterConfig::MAX_SYMBOL,
-        BoxMinterError::SymbolTooLong
+struct FinalizeOpenBoxInnerAccounts<'info> {
+    config: AccountInfo<'info>,
+    cosigner: AccountInfo<'info>,
+    core_collection: AccountInfo<'info>,
+    mpl_core_program: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    log_wrapper: AccountInfo<'info>,
+    variant_table: Option<VariantTable>,
+    custody: Custody<'info>,
+}
+
+impl<'info> FinalizeOpenBoxInnerAccounts<'info> {
+    fn from_finalize_open_box(
+        accounts: &FinalizeOpenBox<'info>,
+        program_id: &Pubkey,
+    ) -> Result<Self> {
+        Ok(Self {
+            config: accounts.config.to_account_info(),
+            cosigner: accounts.cosigner.to_account_info(),
+            core_collection: accounts.core_collection.to_account_info(),
+            mpl_core_program: accounts.mpl_core_program.to_account_info(),
+            system_program: accounts.system_program.to_account_info(),
+            log_wrapper: accounts.log_wrapper.to_account_info(),
+            variant_table: accounts.variant_table.as_deref().cloned(),
+            custody: Custody::load(
+                accounts.config.key(),
+                &accounts.cosigner,
+                &accounts.custody_vault,
+                program_id,
+            )?,
+        })
+    }
+
+    fn from_finalize_open_boxes(
+        accounts: &FinalizeOpenBoxes<'info>,
+        program_id: &Pubkey,
+    ) -> Result<Self> {
+        Ok(Self {
+            config: accounts.config.to_account_info(),
+            cosigner: accounts.cosigner.to_account_info(),
+            core_collection: accounts.core_collection.to_account_info(),
+            mpl_core_program: accounts.mpl_core_program.to_account_info(),
+            system_program: accounts.system_program.to_account_info(),
+            log_wrapper: accounts.log_wrapper.to_account_info(),
+            variant_table: accounts.variant_table.as_deref().cloned(),
+            custody: Custody::load(
+                accounts.config.key(),
+                &accounts.cosigner,
+                &accounts.custody_vault,
+                program_id,
+            )?,
+        })
+    }
+}
+
+/// Per-box accounts for a finalize: the vault-owned box, its pending record, the user receiving
+/// the figures and the placeholder figure assets in the order stored on-chain.
+struct PendingOpenBoxAccounts<'a, 'info> {
+    box_asset: &'a AccountInfo<'info>,
+    pending: &'a AccountInfo<'info>,
+    user: &'a AccountInfo<'info>,
+    placeholders: &'a [AccountInfo<'info>],
+}
+
+struct FinalizeOpenBoxBuffers {
+    name_buf: String,
+    uri_buf: String,
+    figure_buf: String,
+    box_buf: String,
+    burn_ix: Instruction,
+    add_plugin_ix: Instruction,
+    update_ix: Instruction,
+    transfer_ix: Instruction,
+}
+
+fn new_finalize_open_box_buffers(
+    cfg: &BoxMinterConfig,
+    accounts: &FinalizeOpenBoxInnerAccounts<'_>,
+) -> FinalizeOpenBoxBuffers {
+    let core_collection = accounts.core_collection.key();
+    let cosigner = accounts.cosigner.key();
+    let system_program = accounts.system_program.key();
+    let log_wrapper = accounts.log_wrapper.key();
+
+    // BurnV1 (reclaims box rent to the admin payer); the box's custody owner authorizes it.
+    let burn_ix = Instruction {
+        program_id: MPL_CORE_PROGRAM_ID,
+        accounts: vec![
+            // asset, collection, payer, authority, system_program, log_wrapper
+            AccountMeta::new(Pubkey::default(), false), // asset placeholder
+            AccountMeta::new(core_collection, false),
+            AccountMeta::new(cosigner, true),
+            AccountMeta::new_readonly(Pubkey::default(), true), // authority placeholder
+            AccountMeta::new_readonly(system_program, false),
+            AccountMeta::new_readonly(log_wrapper, false),
+        ],
+        // BurnV1 discriminator=12, compression_proof=None (0)
+        data: vec![12u8, 0u8],
+    };
+
+    // AddPluginV1 (Attributes) runs before `UpdateV2`, while the placeholder is still outside the
+    // collection and the config PDA is its direct update authority.
+    let add_plugin_ix = Instruction {
+        program_id: MPL_CORE_PROGRAM_ID,
+        accounts: vec![
+            // asset, collection (optional), payer, authority, system_program, log_wrapper
+            AccountMeta::new(Pubkey::default(), false), // asset placeholder
+            // collection: None (placeholder)
+            AccountMeta::new_readonly(MPL_CORE_PROGRAM_ID, false),
+            AccountMeta::new(cosigner, true),
+            AccountMeta::new_readonly(accounts.config.key(), true), // authority (config PDA)
+            AccountMeta::new_readonly(system_program, false),
+            AccountMeta::new_readonly(log_wrapper, false),
+        ],
+        data: Vec::with_capacity(64),
+    };
+
+    // IMPORTANT: MPL-Core only supports moving an asset into a collection via `UpdateV2`
+    // (UpdateV1 cannot add/remove/change collection).
+    let update_ix = Instruction {
+        program_id: MPL_CORE_PROGRAM_ID,
+        accounts: vec![
+            // UpdateV2 accounts:
+            //   asset, collection (optional), payer, authority, new_collection (optional), system_program, log_wrapper
+            AccountMeta::new(Pubkey::default(), false), // asset placeholder
+            // collection: None (placeholder)
+            AccountMeta::new_readonly(MPL_CORE_PROGRAM_ID, false),
+            AccountMeta::new(cosigner, true),
+            AccountMeta::new_readonly(accounts.config.key(), true), // authority (config PDA)
+            // new_collection: core collection (writable; mpl-core increments size)
+            AccountMeta::new(core_collection, false),
+            AccountMeta::new_readonly(system_program, false),
+            AccountMeta::new_readonly(log_wrapper, false),
+        ],
+        data: Vec::with_capacity(128),
+    };
+
+    let transfer_ix = Instruction {
+        program_id: MPL_CORE_PROGRAM_ID,
+        accounts: vec![
+            // asset, collection, payer, authority, new_owner, system_program, log_wrapper
+            AccountMeta::new(Pubkey::default(), false), // asset placeholder
+            AccountMeta::new_readonly(core_collection, false),
+            AccountMeta::new(cosigner, true),
+            AccountMeta::new_readonly(Pubkey::default(), true), // authority placeholder
+            AccountMeta::new_readonly(Pubkey::default(), false), // new_owner placeholder
+            AccountMeta::new_readonly(system_program, false),
+            AccountMeta::new_readonly(log_wrapper, false),
+        ],
+        // TransferV1 discriminator=14, compression_proof=None (0)
+        data: vec![14u8, 0u8],
+    };
+
+    FinalizeOpenBoxBuffers {
+        name_buf: String::with_capacity(32),
+        uri_buf: String::with_capacity(cfg.uri_base.len() + URI_PREFIX_FIGURES.len() + 16),
+        figure_buf: String::with_capacity(5),
+        box_buf: String::with_capacity(10),
+        burn_ix,
+        add_plugin_ix,
+        update_ix,
+        transfer_ix,
+    }
+}
+
+/// Burns one vault-owned box, reveals its placeholder figures and hands them to the user; the
+/// program signs as the custody vault for boxes it holds.
+///
+/// Callers must validate the cosigner, the MPL-Core/log wrapper program ids and the pending PDA
+/// address; this helper validates the pending record contents and figure ids.
+fn finalize_open_box_inner<'info>(
+    cfg: &BoxMinterConfig,
+    accounts: &FinalizeOpenBoxInnerAccounts<'info>,
+    buffers: &mut FinalizeOpenBoxBuffers,
+    box_accounts: &PendingOpenBoxAccounts<'_, 'info>,
+    dude_ids: &[u16],
+    program_id: &Pubkey,
+) -> Result<()> {
+    let pending_ai = box_accounts.pending;
+    require_keys_eq!(
+        *pending_ai.owner,
+        *program_id,
+        BoxMinterError::InvalidPendingRecord
     );
-    require!(
-        args.uri_base.len() <= BoxMinterConfig::MAX_URI_BASE,
-        BoxMinterError::UriTooLong
+    let pending = {
+        let pending_data = pending_ai.try_borrow_data()?;
+        decode_pending_open_box_account(&pending_data)?
+    };
+    // Opens started before the custody vault existed left the box and placeholders with the admin.
+    let custody = accounts.custody.for_asset_owner(
+        core_asset_owner(box_accounts.box_asset)?,
+        &accounts.cosigner,
     );
+    // Defensive: ensure the box is a Mons *box* now owned by the custody vault/admin, and resolve
+    // the item count + figure pool of its variant.
+    let (box_id, settings) = verify_openable_box(
+        cfg,
+        accounts.variant_table.as_ref(),
+        box_accounts.box_asset,
+        custody.owner.key(),
+    )?;
+    let items_per_box = usize::from(settings.items_per_box);
+
+    // Validate figure IDs.
     require!(
-        has_any_non_zero_byte(args.drop_seed.as_ref()),
-        BoxMinterError::InvalidDropSeed
+        dude_ids.len() == items_per_box,
+        BoxMinterError::InvalidDudeId
     );
-    let drop_base = normalized_metadata_base(&args.uri_base)?;
-    if args.mint_variant_kind == MINT_VARIANT_KIND_SIZE {
-        require!(
-            args.items_per_box == 0,
-            BoxMinterError::MintVariantDirectDeliveryOnly
-        );
+    for id in dude_ids.iter() {
         require!(
-            args.max_supply < u32::MAX,
-            BoxMinterError::InvalidMintVariantConfig
+            *id >= settings.figure_start_id && *id <= settings.figure_end_id,
+            BoxMinterError::InvalidDudeId
         );
-        for i in 0..MINT_VARIANT_OPTION_COUNT {
-            let start_id = args.mint_variant_start_ids[i];
-            let end_id = args.mint_variant_end_ids[i];
-            let next_id = args.mint_variant_next_ids[i];
-            require!(start_id >= 1, BoxMinterError::InvalidMintVariantConfig);
-            require!(end_id >= start_id, BoxMinterError::InvalidMintVariantConfig);
-            require!(
-                next_id == start_id,
-                BoxMinterError::InvalidMintVariantConfig
-            );
-            if i == 0 {
-                require!(start_id == 1, BoxMinterError::InvalidMintVariantConfig);
-            } else {
-                require!(
-                    start_id
-                        == args.mint_variant_end_ids[i - 1]
-                            .checked_add(1)
-                            .ok_or(BoxMinterError::MathOverflow)?,
-                    BoxMinterError::InvalidMintVariantConfig
-                );
-            }
+    }
+    for i in 0..dude_ids.len() {
+        for j in (i + 1)..dude_ids.len() {
+            require!(dude_ids[i] != dude_ids[j], BoxMinterError::DuplicateDudeId);
         }
-        require!(
-            args.mint_variant_end_ids[MINT_VARIANT_OPTION_COUNT - 1] == args.max_supply,
-            BoxMinterError::InvalidMintVariantConfig
-        );
-    } else {
-        require!(
-            args.mint_variant_start_ids == [0; MINT_VARIANT_OPTION_COUNT]
-                && args.mint_variant_end_ids == [0; MINT_VARIANT_OPTION_COUNT]
-                && args.mint_variant_next_ids == [0; MINT_VARIANT_OPTION_COUNT],
-            BoxMinterError::InvalidMintVariantConfig
+    }
+
+    // Pending record must belong to the provided user, and must correspond to this box.
+    require_keys_eq!(
+        pending.box_asset,
+        box_accounts.box_asset.key(),
+        BoxMinterError::InvalidPendingRecord
+    );
+    require_keys_eq!(
+        box_accounts.user.key(),
+        pending.owner,
+        BoxMinterError::InvalidPendingRecord
+    );
+    if let Some(pending_config) = pending.config {
+        require_keys_eq!(
+            pending_config,
+            accounts.config.key(),
+            BoxMinterError::InvalidPendingRecord
         );
     }
 
     require!(
-        core_collection_ai.key() != Pubkey::default(),
-        BoxMinterError::InvalidCoreCollection
+        pending.dudes.len() == items_per_box,
+        BoxMinterError::InvalidPendingRecord
     );
-    require_keys_eq!(
-        *core_collection_ai.owner,
-        MPL_CORE_PROGRAM_ID,
-        BoxMinterError::InvalidCoreCollection
+    // Exactly `items_per_box` placeholder figure assets, in the order stored on-chain.
+    require!(
+        box_accounts.placeholders.len() == items_per_box,
+        BoxMinterError::InvalidRemainingAccounts
     );
+    for i in 0..items_per_box {
+        require_keys_eq!(
+            box_accounts.placeholders[i].key(),
+            pending.dudes[i],
+            BoxMinterError::InvalidRemainingAccounts
+        );
+    }
 
-    cfg.admin = admin;
-    cfg.treasury = treasury;
-    cfg.core_collection = core_collection_ai.key();
-    cfg.price_lamports = args.price_lamports;
-    cfg.discount_price_lamports = args.discount_price_lamports;
-    cfg.discount_merkle_root = args.discount_merkle_root;
-    cfg.max_supply = args.max_supply;
-    cfg.max_per_tx = args.max_per_tx;
-    cfg.items_per_box = args.items_per_box;
-    cfg.discount_mints_per_wallet = args.discount_mints_per_wallet;
-    cfg.started = false;
-    cfg.minted = 0;
-    cfg.name_prefix = args.name_prefix;
-    cfg.symbol = args.symbol;
-    cfg.uri_base = drop_base.to_string();
-    cfg.bump = bump;
-    cfg.figure_name_prefix = args.figure_name_prefix;
-    cfg.mint_variant_kind = args.mint_variant_kind;
-    cfg.mint_variant_start_ids = args.mint_variant_start_ids;
-    cfg.mint_variant_end_ids = args.mint_variant_end_ids;
-    cfg.mint_variant_next_ids = args.mint_variant_next_ids;
-    cfg.drop_seed = args.drop_seed;
-    Ok(())
-}
+    let drop_base = cfg.uri_base.as_str();
 
-#[program]
-pub mod box_minter {
-    use super::*;
+    let mpl_core_program = &accounts.mpl_core_program;
+    let core_collection = &accounts.core_collection;
+    let cosigner = &accounts.cosigner;
+    let system_program = &accounts.system_program;
+    let log_wrapper = &accounts.log_wrapper;
+    let cfg_ai = &accounts.config;
+    let user_ai = box_accounts.user;
+    let cfg_bump_bytes = [cfg.bump];
+    let cfg_signer_seeds: &[&[u8]] = &[
+        BoxMinterConfig::SEED,
+        cfg.drop_seed.as_ref(),
+        &cfg_bump_bytes,
+    ];
 
-    pub fn initialize(ctx: Context<Initialize>, args: InitializeArgs) -> Result<()> {
-        let core_collection_ai = ctx.accounts.core_collection.to_account_info();
-        initialize_box_minter_config(
-            &mut ctx.accounts.config,
-            ctx.accounts.admin.key(),
-            ctx.accounts.treasury.key(),
-            &core_collection_ai,
-            ctx.bumps.config,
-            args,
+    // 1) Burn the box (reclaim rent to the admin payer).
+    buffers.burn_ix.accounts[0].pubkey = box_accounts.box_asset.key();
+    buffers.burn_ix.accounts[3].pubkey = custody.owner.key();
+    custody.invoke(
+        &buffers.burn_ix,
+        &[
+            box_accounts.box_asset.clone(),
+            core_collection.clone(),
+            cosigner.clone(),
+            custody.owner.clone(),
+            system_program.clone(),
+            log_wrapper.clone(),
+            mpl_core_program.clone(),
+        ],
+    )?;
+
+    // 2) Write figure/source-box attributes.
+    // 3) Update + "add to collection" by setting update authority to Collection(core_collection).
+    // 4) Transfer dudes to the user.
+    buffers.box_buf.clear();
+    write!(&mut buffers.box_buf, "{}", box_id)
+        .map_err(|_| error!(BoxMinterError::SerializationFailed))?;
+    buffers.transfer_ix.accounts[3].pubkey = custody.owner.key();
+    buffers.transfer_ix.accounts[4].pubkey = user_ai.key();
+    for (i, asset_ai) in box_accounts.placeholders.iter().enumerate() {
+        let dude_id = dude_ids[i];
+        buffers.figure_buf.clear();
+        write!(&mut buffers.figure_buf, "{}", dude_id)
+            .map_err(|_| error!(BoxMinterError::SerializationFailed))?;
+
+        // AddPluginV1: plugin = Attributes, init_authority = None (plugin default).
+        let add_plugin_ix = &mut buffers.add_plugin_ix;
+        add_plugin_ix.accounts[0].pubkey = asset_ai.key();
+        add_plugin_ix.data.clear();
+        add_plugin_ix.data.push(2u8); // AddPluginV1 discriminator
+        write_attributes_plugin(
+            &mut add_plugin_ix.data,
+            &[
+                ("figure", buffers.figure_buf.as_str()),
+                ("box", buffers.box_buf.as_str()),
+            ],
+        );
+        add_plugin_ix.data.push(0u8); // init_authority: None
+        invoke_signed(
+            add_plugin_ix,
+            &[
+                asset_ai.clone(),
+                cosigner.clone(),
+                cfg_ai.clone(),
+                system_program.clone(),
+                log_wrapper.clone(),
+                mpl_core_program.clone(),
+            ],
+            &[cfg_signer_seeds],
         )
-    }
+        .map_err(anchor_lang::error::Error::from)?;
 
-    pub fn initialize_split_payments_v1(
-        ctx: Context<Initialize>,
-        args: InitializeArgs,
-        split_args: SplitPaymentsV1Args,
-    ) -> Result<()> {
-        if ctx.accounts.treasury.key() == Pubkey::default() {
-            return Err(BoxMinterError::InvalidDeliveryReceiver.into());
-        }
-        let split = SplitPaymentsV1 {
-            recipient_count: split_args.recipient_count,
-            recipients: split_args.recipients,
-            percentages: split_args.percentages,
-        };
-        validate_split_payments_v1(&split)?;
-        let core_collection_ai = ctx.accounts.core_collection.to_account_info();
-        initialize_box_minter_config(
-            &mut ctx.accounts.config,
-            ctx.accounts.admin.key(),
-            ctx.accounts.treasury.key(),
-            &core_collection_ai,
-            ctx.bumps.config,
-            args,
+        buffers.name_buf.clear();
+        append_label_and_id(&mut buffers.name_buf, &cfg.figure_name_prefix, dude_id)?;
+
+        buffers.uri_buf.clear();
+        buffers.uri_buf.push_str(drop_base);
+        buffers.uri_buf.push_str(URI_PREFIX_FIGURES);
+        write!(&mut buffers.uri_buf, "{}", dude_id)
+            .map_err(|_| error!(BoxMinterError::SerializationFailed))?;
+        buffers.uri_buf.push_str(".json");
+
+        // UpdateV2:
+        // - newName: Some(name)
+        // - newUri: Some(uri)
+        // - newUpdateAuthority: Some(Collection(core_collection))
+        let update_ix = &mut buffers.update_ix;
+        update_ix.accounts[0].pubkey = asset_ai.key();
+        update_ix.data.clear();
+        // discriminator
+        update_ix.data.push(30u8);
+        // newName: Some(string)
+        update_ix.data.push(1u8);
+        update_ix
+            .data
+            .extend_from_slice(&(buffers.name_buf.len() as u32).to_le_bytes());
+        update_ix
+            .data
+            .extend_from_slice(buffers.name_buf.as_bytes());
+        // newUri: Some(string)
+        update_ix.data.push(1u8);
+        update_ix
+            .data
+            .extend_from_slice(&(buffers.uri_buf.len() as u32).to_le_bytes());
+        update_ix.data.extend_from_slice(buffers.uri_buf.as_bytes());
+        // newUpdateAuthority: Some(BaseUpdateAuthority::Collection(core_collection))
+        update_ix.data.push(1u8); // Option::Some
+        update_ix.data.push(2u8); // BaseUpdateAuthority::Collection enum index
+        update_ix
+            .data
+            .extend_from_slice(core_collection.key().as_ref());
+
+        invoke_signed(
+            update_ix,
+            &[
+                asset_ai.clone(),
+                core_collection.clone(),
+                cosigner.clone(),
+                cfg_ai.clone(),
+                system_program.clone(),
+                log_wrapper.clone(),
+                mpl_core_program.clone(),
+            ],
+            &[cfg_signer_seeds],
+        )
+        .map_err(anchor_lang::error::Error::from)?;
+
+        // TransferV1 to the user.
+        buffers.transfer_ix.accounts[0].pubkey = asset_ai.key();
+        custody.invoke(
+            &buffers.transfer_ix,
+            &[
+                asset_ai.clone(),
+                core_collection.clone(),
+                cosigner.clone(),
+                custody.owner.clone(),
+                user_ai.clone(),
+                system_program.clone(),
+                log_wrapper.clone(),
+                mpl_core_program.clone(),
+            ],
         )?;
-        resize_split_payments_v1_config(
-            &ctx.accounts.config,
-            &ctx.accounts.admin.to_account_info(),
-            &ctx.accounts.system_program.to_account_info(),
+    }
+
+    close_program_account(pending_ai, cosigner)
+}
+
+/// Accounts shared by the `deliver*` instructions and `cancel_delivery`; delivered Core assets go
+/// to `custody` (the drop's custody vault, or `cosigner` before it exists), receipt leaves to
+/// `cosigner`.
+struct DeliveryAccounts<'info> {
+    config_key: Pubkey,
+    cosigner: AccountInfo<'info>,
+    payer: AccountInfo<'info>,
+    treasury: AccountInfo<'info>,
+    core_collection: AccountInfo<'info>,
+    mpl_core_program: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    log_wrapper: AccountInfo<'info>,
+    delivery: AccountInfo<'info>,
+    custody: Custody<'info>,
+    /// Set when the instruction can deliver receipt cNFTs.
+    receipts: Option<ReceiptDeliveryAccounts<'info>>,
+}
+
+/// Bubblegum accounts for delivering (or returning) receipt cNFTs.
+struct ReceiptDeliveryAccounts<'info> {
+    /// Merkle trees of the drop's receipt tree registry.
+    registered_trees: Vec<Pubkey>,
+    bubblegum_program: AccountInfo<'info>,
+    log_wrapper: AccountInfo<'info>,
+    compression_program: AccountInfo<'info>,
+    /// Only needed to burn receipts.
+    mpl_core_cpi_signer: Option<AccountInfo<'info>>,
+}
+
+impl<'info> ReceiptDeliveryAccounts<'info> {
+    fn from_deliver(accounts: &Deliver<'info>, program_id: &Pubkey) -> Result<Option<Self>> {
+        // Until the registry is initialized no tree is registered, so no receipt can be delivered.
+        let registered_trees = load_drop_pda::<ReceiptTrees>(&accounts.receipt_trees, program_id)?
+            .map_or_else(Vec::new, |receipt_trees| {
+                receipt_trees
+                    .trees
+                    .iter()
+                    .map(|tree| tree.merkle_tree)
+                    .collect()
+            });
+        Ok(
+            match (
+                &accounts.bubblegum_program,
+                &accounts.receipts_log_wrapper,
+                &accounts.compression_program,
+                &accounts.mpl_core_cpi_signer,
+            ) {
+                (
+                    Some(bubblegum_program),
+                    Some(log_wrapper),
+                    Some(compression_program),
+                    Some(mpl_core_cpi_signer),
+                ) => Some(Self {
+                    registered_trees,
+                    bubblegum_program: bubblegum_program.to_account_info(),
+                    log_wrapper: log_wrapper.to_account_info(),
+                    compression_program: compression_program.to_account_info(),
+                    mpl_core_cpi_signer: Some(mpl_core_cpi_signer.to_account_info()),
+                }),
+                _ => None,
+            },
+        )
+    }
+
+    /// Receipt returns only transfer leaves whose ids the delivery record hashed, so no tree
+    /// registry is needed.
+    fn from_cancel_delivery(accounts: &CancelDelivery<'info>) -> Option<Self> {
+        match (
+            &accounts.bubblegum_program,
+            &accounts.receipts_log_wrapper,
+            &accounts.compression_program,
+        ) {
+            (Some(bubblegum_program), Some(log_wrapper), Some(compression_program)) => Some(Self {
+                registered_trees: Vec::new(),
+                bubblegum_program: bubblegum_program.to_account_info(),
+                log_wrapper: log_wrapper.to_account_info(),
+                compression_program: compression_program.to_account_info(),
+                mpl_core_cpi_signer: None,
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl<'info> DeliveryAccounts<'info> {
+    fn from_deliver(accounts: &Deliver<'info>, program_id: &Pubkey) -> Result<Self> {
+        Ok(Self {
+            config_key: accounts.config.key(),
+            cosigner: accounts.cosigner.to_account_info(),
+            payer: accounts.payer.to_account_info(),
+            treasury: accounts.treasury.to_account_info(),
+            core_collection: accounts.core_collection.to_account_info(),
+            mpl_core_program: accounts.mpl_core_program.to_account_info(),
+            system_program: accounts.system_program.to_account_info(),
+            log_wrapper: accounts.log_wrapper.to_account_info(),
+            delivery: accounts.delivery.to_account_info(),
+            custody: Custody::load(
+                accounts.config.key(),
+                &accounts.cosigner,
+                &accounts.custody_vault,
+                program_id,
+            )?,
+            receipts: ReceiptDeliveryAccounts::from_deliver(accounts, program_id)?,
+        })
+    }
+
+    fn from_cancel_delivery(accounts: &CancelDelivery<'info>, program_id: &Pubkey) -> Result<Self> {
+        Ok(Self {
+            config_key: accounts.config.key(),
+            cosigner: accounts.cosigner.to_account_info(),
+            payer: accounts.payer.to_account_info(),
+            treasury: accounts.treasury.to_account_info(),
+            core_collection: accounts.core_collection.to_account_info(),
+            mpl_core_program: accounts.mpl_core_program.to_account_info(),
+            system_program: accounts.system_program.to_account_info(),
+            log_wrapper: accounts.log_wrapper.to_account_info(),
+            delivery: accounts.delivery.to_account_info(),
+            custody: Custody::load(
+                accounts.config.key(),
+                &accounts.cosigner,
+                &accounts.custody_vault,
+                program_id,
+            )?,
+            receipts: ReceiptDeliveryAccounts::from_cancel_delivery(accounts),
+        })
+    }
+
+    fn from_deliver_with_quote(
+        accounts: &DeliverWithQuote<'info>,
+        program_id: &Pubkey,
+    ) -> Result<Self> {
+        Ok(Self {
+            config_key: accounts.config.key(),
+            cosigner: accounts.vault.to_account_info(),
+            payer: accounts.payer.to_account_info(),
+            treasury: accounts.treasury.to_account_info(),
+            core_collection: accounts.core_collection.to_account_info(),
+            mpl_core_program: accounts.mpl_core_program.to_account_info(),
+            system_program: accounts.system_program.to_account_info(),
+            log_wrapper: accounts.log_wrapper.to_account_info(),
+            delivery: accounts.delivery.to_account_info(),
+            custody: Custody::load(
+                accounts.config.key(),
+                &accounts.vault,
+                &accounts.custody_vault,
+                program_id,
+            )?,
+            receipts: None,
+        })
+    }
+
+    fn from_deliver_with_receipts(
+        accounts: &DeliverWithReceipts<'info>,
+        program_id: &Pubkey,
+    ) -> Result<Self> {
+        Ok(Self {
+            config_key: accounts.config.key(),
+            cosigner: accounts.cosigner.to_account_info(),
+            payer: accounts.payer.to_account_info(),
+            treasury: accounts.treasury.to_account_info(),
+            core_collection: accounts.core_collection.to_account_info(),
+            mpl_core_program: accounts.mpl_core_program.to_account_info(),
+            system_program: accounts.system_program.to_account_info(),
+            log_wrapper: accounts.log_wrapper.to_account_info(),
+            delivery: accounts.delivery.to_account_info(),
+            custody: Custody::load(
+                accounts.config.key(),
+                &accounts.cosigner,
+                &accounts.custody_vault,
+                program_id,
+            )?,
+            receipts: None,
+        })
+    }
+}
+
+/// How a delivery fee is priced and paid: the drop's fee schedule (if initialized), and either
+/// `config.treasury` or the route's split with its (validated) recipient accounts.
+struct DeliveryFees<'a, 'info> {
+    schedule: Option<DeliveryFeeSchedule>,
+    split: Option<SplitPaymentsV1>,
+    recipients: &'a [AccountInfo<'info>],
+}
+
+/// Resolves the drop's delivery fee schedule and route (each once initialized). Split recipients
+/// are appended to `remaining_accounts` after the delivered assets, in split order; returns the
+/// fees and the asset accounts.
+fn resolve_delivery_fees<'a, 'info>(
+    config: &Account<BoxMinterConfig>,
+    schedule: &AccountInfo,
+    routing: &AccountInfo,
+    remaining_accounts: &'a [AccountInfo<'info>],
+    program_id: &Pubkey,
+) -> Result<(DeliveryFees<'a, 'info>, &'a [AccountInfo<'info>])> {
+    let schedule = load_drop_pda::<DeliveryFeeSchedule>(schedule, program_id)?;
+    let routing = load_drop_pda::<DeliveryFeeRouting>(routing, program_id)?;
+    let split = match routing.as_ref().map(|routing| &routing.route) {
+        None | Some(DeliveryFeeRoute::Treasury) => None,
+        Some(DeliveryFeeRoute::MintSplit) => Some(
+            load_split_payments_v1(config)?
+                .ok_or(error!(BoxMinterError::InvalidSplitPaymentsConfig))?,
+        ),
+        Some(DeliveryFeeRoute::Split(args)) => Some(split_payments_v1_from_args(args)),
+    };
+    let recipient_count = split
+        .as_ref()
+        .map(|routing| usize::from(routing.recipient_count))
+        .unwrap_or(0);
+    let asset_count = remaining_accounts
+        .len()
+        .checked_sub(recipient_count)
+        .ok_or(BoxMinterError::InvalidRemainingAccounts)?;
+    let (asset_accounts, recipient_accounts) = remaining_accounts.split_at(asset_count);
+    if let Some(routing) = split.as_ref() {
+        for (index, recipient_account) in recipient_accounts.iter().enumerate() {
+            if !recipient_account.is_writable
+                || recipient_account.key() != routing.recipients[index]
+            {
+                return Err(BoxMinterError::InvalidSplitPaymentsRecipients.into());
+            }
+        }
+    }
+    Ok((
+        DeliveryFees {
+            schedule,
+            split,
+            recipients: recipient_accounts,
+        },
+        asset_accounts,
+    ))
+}
+
+/// Fee for delivering `item_count` items. With a fee schedule the fee comes from the zone in
+/// `args.delivery_zone` and `args.delivery_fee_lamports` must equal it; without one the cosigned
+/// `args.delivery_fee_lamports` is used as is.
+fn resolve_delivery_fee(
+    fee_schedule: Option<&DeliveryFeeSchedule>,
+    args: &DeliverArgs,
+    item_count: usize,
+) -> Result<u64> {
+    match (fee_schedule, args.delivery_zone) {
+        (None, None) => Ok(args.delivery_fee_lamports),
+        (Some(fee_schedule), Some(zone_id)) => {
+            let zone = fee_schedule
+                .zone(zone_id)
+                .ok_or(error!(BoxMinterError::InvalidDeliveryZone))?;
+            let fee = zone.fee_for(item_count as u64);
+            require!(
+                args.delivery_fee_lamports == fee,
+                BoxMinterError::DeliveryFeeMismatch
+            );
+            Ok(fee)
+        }
+        _ => err!(BoxMinterError::InvalidDeliveryZone),
+    }
+}
+
+/// Cosigner and CPI program checks shared by every instruction that moves assets into the vault.
+fn validate_delivery_accounts(accounts: &DeliveryAccounts, cfg: &BoxMinterConfig) -> Result<()> {
+    // Require a cloud-held signer (same admin as initialize) so users can't choose arbitrary fees.
+    // The delivery fee itself is determined off-chain and embedded in the cosigned transaction,
+    // unless the drop's fee schedule is initialized (then it is computed from the zone).
+    require_keys_eq!(
+        accounts.cosigner.key(),
+        cfg.admin,
+        BoxMinterError::InvalidCosigner
+    );
+    require_keys_eq!(
+        accounts.mpl_core_program.key(),
+        MPL_CORE_PROGRAM_ID,
+        BoxMinterError::InvalidMplCoreProgram
+    );
+    require_keys_eq!(
+        accounts.log_wrapper.key(),
+        SPL_NOOP_PROGRAM_ID,
+        BoxMinterError::InvalidLogWrapper
+    );
+    Ok(())
+}
+
+/// Delivery record PDA: `delivery` + config + delivery_id.
+fn validate_delivery_pda(
+    delivery_key: Pubkey,
+    config_key: &Pubkey,
+    delivery_id: u32,
+    delivery_bump: u8,
+    program_id: &Pubkey,
+) -> Result<()> {
+    let expected_delivery = Pubkey::create_program_address(
+        &[
+            SEED_DELIVERY,
+            config_key.as_ref(),
+            &delivery_id.to_le_bytes(),
+            &[delivery_bump],
+        ],
+        program_id,
+    )
+    .map_err(|_| error!(BoxMinterError::InvalidDeliveryPda))?;
+    require_keys_eq!(
+        delivery_key,
+        expected_delivery,
+        BoxMinterError::InvalidDeliveryPda
+    );
+    Ok(())
+}
+
+/// One transaction's worth of verified delivery items.
+struct DeliveredAssets {
+    /// Kind and ref id of each asset, in `assets` order.
+    items: Vec<(ReceiptKind, u32)>,
+    keys: Vec<Pubkey>,
+    box_count: u16,
+    figure_count: u16,
+}
+
+/// Only this drop's boxes and revealed figures can be delivered (no placeholders or foreign
+/// assets); reads their ref ids before the transfers move them into the vault.
+fn verify_delivered_assets(
+    cfg: &BoxMinterConfig,
+    assets: &[AccountInfo],
+    owner: Pubkey,
+) -> Result<DeliveredAssets> {
+    let mut delivered = DeliveredAssets {
+        items: Vec::with_capacity(assets.len()),
+        keys: Vec::with_capacity(assets.len()),
+        box_count: 0,
+        figure_count: 0,
+    };
+    for asset_ai in assets.iter() {
+        let (kind, ref_id) = verify_delivered_asset(cfg, asset_ai, owner)?;
+        match kind {
+            ReceiptKind::Box => delivered.box_count += 1,
+            ReceiptKind::Figure => delivered.figure_count += 1,
+        }
+        delivered.items.push((kind, ref_id));
+        delivered.keys.push(asset_ai.key());
+    }
+    Ok(delivered)
+}
+
+fn require_delivery_item_count(item_count: usize) -> Result<()> {
+    require!(
+        item_count > 0 && item_count <= usize::from(MAX_SAFE_DELIVERY_ITEMS_PER_TX),
+        BoxMinterError::InvalidQuantity
+    );
+    Ok(())
+}
+
+/// Verifies `assets` are this drop's boxes/figures owned by the payer, creates the delivery
+/// record, takes the delivery fee and transfers the assets into the vault. Returns each Core
+/// asset's kind and ref id, in `assets` order.
+///
+/// Receipt cNFTs in `args.receipts` have their account groups after the Core assets; they are
+/// transferred to `config.admin` (or burned) through Bubblegum and counted as items.
+///
+/// `session_item_count` opens a delivery session instead: the fee covers that many items, of
+/// which `assets` are the first, and the record stays `Open` until `seal_delivery`.
+///
+/// With `args.freeze_in_place` the Core assets stay with the payer, frozen in place (see
+/// `freeze_delivered_assets`).
+fn deliver_core_assets<'info>(
+    accounts: &DeliveryAccounts<'info>,
+    cfg: &BoxMinterConfig,
+    args: &DeliverArgs,
+    fees: &DeliveryFees<'_, 'info>,
+    assets: &[AccountInfo<'info>],
+    program_id: &Pubkey,
+    session_item_count: Option<u16>,
+) -> Result<Vec<(ReceiptKind, u32)>> {
+    validate_delivery_accounts(accounts, cfg)?;
+    // Frozen items are released against the record's asset hash, so it must cover exactly the
+    // Core assets of one transaction (no sessions, no receipt leaves).
+    require!(
+        !args.freeze_in_place || (session_item_count.is_none() && args.receipts.is_empty()),
+        BoxMinterError::InvalidDeliveryMode
+    );
+    let receipt_account_count: usize = args
+        .receipts
+        .iter()
+        .map(|receipt| DELIVERED_RECEIPT_FIXED_ACCOUNTS + usize::from(receipt.proof_len))
+        .sum();
+    let core_asset_count = assets
+        .len()
+        .checked_sub(receipt_account_count)
+        .ok_or(BoxMinterError::InvalidRemainingAccounts)?;
+    let (core_assets, receipt_accounts) = assets.split_at(core_asset_count);
+    require_delivery_item_count(core_asset_count + args.receipts.len())?;
+
+    let delivered = verify_delivered_assets(cfg, core_assets, accounts.payer.key())?;
+    let receipt_groups = if args.receipts.is_empty() {
+        Vec::new()
+    } else {
+        let receipt_delivery = accounts
+            .receipts
+            .as_ref()
+            .ok_or(BoxMinterError::InvalidDeliveredReceipt)?;
+        delivered_receipt_groups(receipt_delivery, &args.receipts, receipt_accounts)?
+    };
+    let mut asset_keys = delivered.keys;
+    asset_keys.extend(
+        receipt_groups
+            .iter()
+            .zip(args.receipts.iter())
+            .map(|(group, receipt)| receipt_asset_id(group[0].key, u64::from(receipt.leaf_index))),
+    );
+    let item_count = asset_keys.len() as u16;
+    let paid_item_count = session_item_count.unwrap_or(item_count);
+    require!(
+        paid_item_count >= item_count,
+        BoxMinterError::InvalidDeliveryItemCount
+    );
+    let delivery_fee_lamports =
+        resolve_delivery_fee(fees.schedule.as_ref(), args, usize::from(paid_item_count))?;
+
+    let config_key = accounts.config_key;
+    validate_delivery_pda(
+        accounts.delivery.key(),
+        &config_key,
+        args.delivery_id,
+        args.delivery_bump,
+        program_id,
+    )?;
+    let delivery_ai = accounts.delivery.clone();
+    // Create (or reclaim) the tiny on-chain delivery record (presence == paid order).
+    //
+    // Note: a PDA can be "pre-funded", creating a system-owned stub account that makes
+    // `system_instruction::create_account` fail ("account already in use"). Since this is a PDA,
+    // we can sign for it and reclaim it via `allocate` + `assign`.
+    let delivery_space: usize = DeliveryRecord::SPACE;
+    let delivery_id_bytes = args.delivery_id.to_le_bytes();
+    let delivery_bump_bytes = [args.delivery_bump];
+    let delivery_seeds: &[&[u8]] = &[
+        SEED_DELIVERY,
+        config_key.as_ref(),
+        &delivery_id_bytes,
+        &delivery_bump_bytes,
+    ];
+    create_or_reclaim_empty_pda_account(
+        &delivery_ai,
+        &accounts.payer,
+        &accounts.system_program,
+        delivery_space,
+        program_id,
+        delivery_seeds,
+        BoxMinterError::InvalidDeliveryPda,
+        BoxMinterError::DeliveryAlreadyExists,
+    )?;
+
+    let record = DeliveryRecord {
+        payer: accounts.payer.key(),
+        delivery_fee_lamports,
+        item_count,
+        box_count: delivered.box_count,
+        figure_count: delivered.figure_count,
+        assets_hash: delivery_assets_hash(&asset_keys),
+        address_cipher_hash: args.address_cipher_hash,
+        created_slot: Clock::get()?.slot,
+        status: if session_item_count.is_some() {
+            DeliveryStatus::Open
+        } else {
+            DeliveryStatus::Paid
+        },
+        tracking_hash: [0u8; 32],
+        paid_item_count,
+        receipt_count: args.receipts.len() as u16,
+        frozen_in_place: args.freeze_in_place,
+        fulfilled: false,
+        receipts_issued: false,
+        fee_split: fees.split.is_some(),
+    };
+    record.try_serialize(&mut &mut delivery_ai.data.borrow_mut()[..])?;
+
+    // Take delivery payment (enforced on-chain).
+    if let Some(split) = fees.split.as_ref() {
+        let amounts = split_payment_amounts(delivery_fee_lamports, split)?;
+        for (recipient, amount) in fees.recipients.iter().zip(amounts.iter().copied()) {
+            transfer_delivery_fee(accounts, recipient, amount)?;
+        }
+    } else {
+        transfer_delivery_fee(accounts, &accounts.treasury, delivery_fee_lamports)?;
+    }
+
+    // Transfer all delivered assets to the custody vault (or config.admin), or freeze them in the
+    // payer's wallet; payment receiver is `config.treasury`.
+    if args.freeze_in_place {
+        freeze_delivered_assets(accounts, core_assets)?;
+    } else {
+        transfer_core_assets(
+            accounts,
+            &accounts.payer,
+            &accounts.custody.owner,
+            core_assets,
         )?;
-        write_split_payments_v1_config(&ctx.accounts.config, &split)
     }
+    for (receipt, group) in args.receipts.iter().zip(receipt_groups) {
+        invoke_delivered_receipt_cpi(accounts, receipt, group)?;
+    }
+    Ok(delivered.items)
+}
 
-    pub fn split_payments_v1_capability(_ctx: Context<SplitPaymentsV1Capability>) -> Result<()> {
-        Ok(())
+/// Splits `receipt_accounts` into one `[merkle_tree, tree_config, proof..]` group per receipt.
+/// Only leaves of the drop's registered (program-owned) receipts trees are accepted, so every
+/// delivered leaf is a receipt this program minted; Bubblegum verifies the proof and owner.
+fn delivered_receipt_groups<'a, 'info>(
+    receipt_delivery: &ReceiptDeliveryAccounts<'info>,
+    receipts: &[DeliveredReceiptArgs],
+    receipt_accounts: &'a [AccountInfo<'info>],
+) -> Result<Vec<&'a [AccountInfo<'info>]>> {
+    let groups = receipt_leaf_groups(receipt_delivery, receipts, receipt_accounts)?;
+    for group in groups.iter() {
+        require!(
+            receipt_delivery.registered_trees.contains(group[0].key),
+            BoxMinterError::InvalidDeliveredReceipt
+        );
     }
+    Ok(groups)
+}
 
-    pub fn set_treasury(ctx: Context<SetTreasury>, treasury: Pubkey) -> Result<()> {
-        if treasury == Pubkey::default()
-            && ctx.accounts.config.to_account_info().data_len() == SPLIT_PAYMENTS_V1_CONFIG_SPACE
-        {
-            return Err(BoxMinterError::InvalidDeliveryReceiver.into());
+/// `delivered_receipt_groups` without the registry check, for leaves the caller already pins.
+fn receipt_leaf_groups<'a, 'info>(
+    receipt_delivery: &ReceiptDeliveryAccounts<'info>,
+    receipts: &[DeliveredReceiptArgs],
+    receipt_accounts: &'a [AccountInfo<'info>],
+) -> Result<Vec<&'a [AccountInfo<'info>]>> {
+    require_keys_eq!(
+        receipt_delivery.bubblegum_program.key(),
+        BUBBLEGUM_PROGRAM_ID,
+        BoxMinterError::InvalidBubblegumProgram
+    );
+    let mut groups = Vec::with_capacity(receipts.len());
+    let mut rest = receipt_accounts;
+    for receipt in receipts {
+        let (group, tail) =
+            rest.split_at(DELIVERED_RECEIPT_FIXED_ACCOUNTS + usize::from(receipt.proof_len));
+        let merkle_tree = group[0].key();
+        let (expected_tree_config, _) =
+            Pubkey::find_program_address(&[merkle_tree.as_ref()], &BUBBLEGUM_PROGRAM_ID);
+        require_keys_eq!(
+            group[1].key(),
+            expected_tree_config,
+            BoxMinterError::InvalidReceiptsTreeConfig
+        );
+        groups.push(group);
+        rest = tail;
+    }
+    Ok(groups)
+}
+
+/// Bubblegum v2 `transferV2` / `burnV2` args (identical layouts).
+fn delivered_receipt_ix_data(receipt: &DeliveredReceiptArgs) -> Vec<u8> {
+    let mut data = Vec::with_capacity(8 + 32 * 3 + 33 + 2 + 8 + 4);
+    data.extend_from_slice(if receipt.burn {
+        &IX_BUBBLEGUM_BURN_V2
+    } else {
+        &IX_BUBBLEGUM_TRANSFER_V2
+    });
+    data.extend_from_slice(&receipt.root);
+    data.extend_from_slice(&receipt.data_hash);
+    data.extend_from_slice(&receipt.creator_hash);
+    match receipt.asset_data_hash {
+        Some(asset_data_hash) => {
+            data.push(1);
+            data.extend_from_slice(&asset_data_hash);
         }
-        let cfg = &mut ctx.accounts.config;
-        cfg.treasury = treasury;
-        Ok(())
+        None => data.push(0),
+    }
+    match receipt.flags {
+        Some(flags) => data.extend_from_slice(&[1, flags]),
+        None => data.push(0),
     }
+    data.extend_from_slice(&u64::from(receipt.leaf_index).to_le_bytes()); // nonce
+    data.extend_from_slice(&receipt.leaf_index.to_le_bytes()); // index
+    data
+}
 
-    pub fn set_mint_prices(
-        ctx: Context<SetMintPrices>,
-        price_lamports: u64,
-        discount_price_lamports: u64,
-    ) -> Result<()> {
-        validate_mint_prices(price_lamports, discount_price_lamports)?;
+/// Moves a delivered receipt leaf out of the payer's hands: Bubblegum `transferV2` to
+/// `config.admin` (a wallet, so `cancel_delivery` can hand the leaf back), or `burnV2`.
+fn invoke_delivered_receipt_cpi<'info>(
+    accounts: &DeliveryAccounts<'info>,
+    receipt: &DeliveredReceiptArgs,
+    group: &[AccountInfo<'info>],
+) -> Result<()> {
+    invoke_receipt_leaf_cpi(
+        accounts,
+        receipt,
+        group,
+        &accounts.payer,
+        &accounts.cosigner,
+    )
+}
 
-        let cfg = &mut ctx.accounts.config;
-        cfg.price_lamports = price_lamports;
-        cfg.discount_price_lamports = discount_price_lamports;
-        Ok(())
+/// Bubblegum `transferV2` of a receipt leaf from `owner` to `new_owner` (or `burnV2`). `owner`
+/// signs as leaf owner (and is its own delegate) and pays.
+fn invoke_receipt_leaf_cpi<'info>(
+    accounts: &DeliveryAccounts<'info>,
+    receipt: &DeliveredReceiptArgs,
+    group: &[AccountInfo<'info>],
+    owner: &AccountInfo<'info>,
+    new_owner: &AccountInfo<'info>,
+) -> Result<()> {
+    let receipt_delivery = accounts
+        .receipts
+        .as_ref()
+        .ok_or(BoxMinterError::InvalidDeliveredReceipt)?;
+    let (merkle_tree, tree_config, proof) = (&group[0], &group[1], &group[2..]);
+    let mut metas = vec![
+        AccountMeta::new(tree_config.key(), false),
+        AccountMeta::new(owner.key(), true),           // payer
+        AccountMeta::new_readonly(owner.key(), true),  // authority
+        AccountMeta::new_readonly(owner.key(), false), // leafOwner
+        AccountMeta::new_readonly(owner.key(), false), // leafDelegate
+    ];
+    let mut account_infos = vec![
+        tree_config.clone(),
+        owner.clone(),
+        owner.clone(),
+        owner.clone(),
+        owner.clone(),
+    ];
+    if receipt.burn {
+        // Burns update the collection's Bubblegum plugin, so the collection must be writable.
+        require!(
+            accounts.core_collection.is_writable,
+            BoxMinterError::InvalidDeliveredReceipt
+        );
+        let mpl_core_cpi_signer = receipt_delivery
+            .mpl_core_cpi_signer
+            .as_ref()
+            .ok_or(BoxMinterError::InvalidDeliveredReceipt)?;
+        metas.extend([
+            AccountMeta::new(merkle_tree.key(), false),
+            AccountMeta::new(accounts.core_collection.key(), false),
+            AccountMeta::new_readonly(mpl_core_cpi_signer.key(), false),
+            AccountMeta::new_readonly(receipt_delivery.log_wrapper.key(), false),
+            AccountMeta::new_readonly(receipt_delivery.compression_program.key(), false),
+            AccountMeta::new_readonly(accounts.mpl_core_program.key(), false),
+            AccountMeta::new_readonly(accounts.system_program.key(), false),
+        ]);
+        account_infos.extend([
+            merkle_tree.clone(),
+            accounts.core_collection.clone(),
+            mpl_core_cpi_signer.clone(),
+            receipt_delivery.log_wrapper.clone(),
+            receipt_delivery.compression_program.clone(),
+            accounts.mpl_core_program.clone(),
+            accounts.system_program.clone(),
+        ]);
+    } else {
+        metas.extend([
+            AccountMeta::new_readonly(new_owner.key(), false), // newLeafOwner
+            AccountMeta::new(merkle_tree.key(), false),
+            AccountMeta::new_readonly(accounts.core_collection.key(), false),
+            AccountMeta::new_readonly(receipt_delivery.log_wrapper.key(), false),
+            AccountMeta::new_readonly(receipt_delivery.compression_program.key(), false),
+            AccountMeta::new_readonly(accounts.system_program.key(), false),
+        ]);
+        account_infos.extend([
+            new_owner.clone(),
+            merkle_tree.clone(),
+            accounts.core_collection.clone(),
+            receipt_delivery.log_wrapper.clone(),
+            receipt_delivery.compression_program.clone(),
+            accounts.system_program.clone(),
+        ]);
     }
+    metas.extend(
+        proof
+            .iter()
+            .map(|node| AccountMeta::new_readonly(node.key(), false)),
+    );
+    account_infos.extend(proof.iter().cloned());
+    account_infos.push(receipt_delivery.bubblegum_program.clone());
+
+    let ix = Instruction {
+        program_id: BUBBLEGUM_PROGRAM_ID,
+        accounts: metas,
+        data: delivered_receipt_ix_data(receipt),
+    };
+    invoke(&ix, &account_infos)?;
+    Ok(())
+}
+
+fn transfer_delivery_fee<'info>(
+    accounts: &DeliveryAccounts<'info>,
+    recipient: &AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    if amount > 0 {
+        let ix = anchor_lang::solana_program::system_instruction::transfer(
+            &accounts.payer.key(),
+            &recipient.key(),
+            amount,
+        );
+        invoke(
+            &ix,
+            &[
+                accounts.payer.clone(),
+                recipient.clone(),
+                accounts.system_program.clone(),
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+/// Transfers `assets` from `owner` (fee payer and authority) to `new_owner` via MPL-Core
+/// `TransferV1`.
+fn transfer_core_assets<'info>(
+    accounts: &DeliveryAccounts<'info>,
+    owner: &AccountInfo<'info>,
+    new_owner: &AccountInfo<'info>,
+    assets: &[AccountInfo<'info>],
+) -> Result<()> {
+    let mut transfer_ix = Instruction {
+        program_id: MPL_CORE_PROGRAM_ID,
+        accounts: vec![
+            // asset, collection, payer, authority, new_owner, system_program, log_wrapper
+            AccountMeta::new(Pubkey::default(), false), // asset placeholder
+            AccountMeta::new_readonly(accounts.core_collection.key(), false),
+            AccountMeta::new(owner.key(), true),
+            AccountMeta::new_readonly(owner.key(), true),
+            AccountMeta::new_readonly(new_owner.key(), false),
+            AccountMeta::new_readonly(accounts.system_program.key(), false),
+            AccountMeta::new_readonly(accounts.log_wrapper.key(), false),
+        ],
+        // TransferV1 discriminator=14, compression_proof=None (0)
+        data: vec![14u8, 0u8],
+    };
+
+    for asset_ai in assets.iter() {
+        transfer_ix.accounts[0].pubkey = asset_ai.key();
+        invoke(
+            &transfer_ix,
+            &[
+                asset_ai.clone(),
+                accounts.core_collection.clone(),
+                owner.clone(),
+                owner.clone(),
+                new_owner.clone(),
+                accounts.system_program.clone(),
+                accounts.log_wrapper.clone(),
+                accounts.mpl_core_program.clone(),
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+/// MPL-Core CPI accounts for freezing delivered assets in place and releasing them again.
+struct FreezeInPlaceAccounts<'info> {
+    /// Pays for the plugin changes: the delivering payer when freezing, the cosigner after.
+    payer: AccountInfo<'info>,
+    core_collection: AccountInfo<'info>,
+    mpl_core_program: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    log_wrapper: AccountInfo<'info>,
+}
+
+impl<'info> FreezeInPlaceAccounts<'info> {
+    fn from_delivery(accounts: &DeliveryAccounts<'info>, payer: &AccountInfo<'info>) -> Self {
+        Self {
+            payer: payer.clone(),
+            core_collection: accounts.core_collection.clone(),
+            mpl_core_program: accounts.mpl_core_program.clone(),
+            system_program: accounts.system_program.clone(),
+            log_wrapper: accounts.log_wrapper.clone(),
+        }
+    }
+
+    fn from_fulfill_frozen_delivery(accounts: &FulfillFrozenDelivery<'info>) -> Self {
+        Self {
+            payer: accounts.cosigner.to_account_info(),
+            core_collection: accounts.core_collection.to_account_info(),
+            mpl_core_program: accounts.mpl_core_program.to_account_info(),
+            system_program: accounts.system_program.to_account_info(),
+            log_wrapper: accounts.log_wrapper.to_account_info(),
+        }
+    }
+
+    /// Invokes an MPL-Core instruction taking `asset, collection, payer, authority,
+    /// system_program, log_wrapper` (`AddPluginV1`, `UpdatePluginV1`, `RevokePluginAuthorityV1`,
+    /// `BurnV1`).
+    fn invoke(
+        &self,
+        data: Vec<u8>,
+        asset: &AccountInfo<'info>,
+        authority: &AccountInfo<'info>,
+        signer_seeds: &[&[&[u8]]],
+    ) -> Result<()> {
+        let ix = Instruction {
+            program_id: MPL_CORE_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(asset.key(), false),
+                AccountMeta::new(self.core_collection.key(), false),
+                AccountMeta::new(self.payer.key(), true),
+                AccountMeta::new_readonly(authority.key(), true),
+                AccountMeta::new_readonly(self.system_program.key(), false),
+                AccountMeta::new_readonly(self.log_wrapper.key(), false),
+            ],
+            data,
+        };
+        invoke_signed(
+            &ix,
+            &[
+                asset.clone(),
+                self.core_collection.clone(),
+                self.payer.clone(),
+                authority.clone(),
+                self.system_program.clone(),
+                self.log_wrapper.clone(),
+                self.mpl_core_program.clone(),
+            ],
+            signer_seeds,
+        )
+        .map_err(Into::into)
+    }
+}
+
+/// `AddPluginV1` data for an owner-managed delegate `plugin` (borsh `Plugin`) whose authority is
+/// `authority`.
+fn add_delegate_plugin_ix_data(plugin: &[u8], authority: &Pubkey) -> Vec<u8> {
+    let mut data = Vec::with_capacity(3 + plugin.len() + 32);
+    data.push(2u8); // AddPluginV1 discriminator
+    data.extend_from_slice(plugin);
+    data.push(1u8); // init_authority: Some
+    data.push(MPL_CORE_PLUGIN_AUTHORITY_ADDRESS);
+    data.extend_from_slice(authority.as_ref());
+    data
+}
+
+/// `UpdatePluginV1` data setting the FreezeDelegate's `frozen` flag.
+fn set_frozen_ix_data(frozen: bool) -> Vec<u8> {
+    vec![6u8, MPL_CORE_PLUGIN_FREEZE_DELEGATE, u8::from(frozen)]
+}
+
+/// `RevokePluginAuthorityV1` data; the owner gets the plugin's authority back.
+fn revoke_plugin_authority_ix_data(plugin_type: u8) -> Vec<u8> {
+    vec![10u8, plugin_type]
+}
+
+/// Freeze-in-place delivery: each Core asset stays in the payer's wallet with a config-PDA
+/// BurnDelegate and a frozen config-PDA FreezeDelegate, so it shows as in transit but can't be
+/// moved (or delivered again) until `fulfill_frozen_delivery` burns it or `cancel_delivery` thaws
+/// it. The plugins change the collection, so it must be passed writable.
+fn freeze_delivered_assets<'info>(
+    accounts: &DeliveryAccounts<'info>,
+    assets: &[AccountInfo<'info>],
+) -> Result<()> {
+    require!(
+        accounts.core_collection.is_writable,
+        BoxMinterError::InvalidDeliveryMode
+    );
+    let cpi = FreezeInPlaceAccounts::from_delivery(accounts, &accounts.payer);
+    for asset_ai in assets.iter() {
+        // The burn delegate goes first so nothing is added to an already frozen asset.
+        cpi.invoke(
+            add_delegate_plugin_ix_data(&[MPL_CORE_PLUGIN_BURN_DELEGATE], &accounts.config_key),
+            asset_ai,
+            &accounts.payer,
+            &[],
+        )?;
+        cpi.invoke(
+            add_delegate_plugin_ix_data(
+                &[MPL_CORE_PLUGIN_FREEZE_DELEGATE, 1],
+                &accounts.config_key,
+            ),
+            asset_ai,
+            &accounts.payer,
+            &[],
+        )?;
+    }
+    Ok(())
+}
+
+/// Thaws frozen-in-place delivery assets, with the config PDA signing as their delegate, then
+/// burns them (fulfillment) or revokes both delegates so they are the payer's again
+/// (cancellation).
+fn release_frozen_assets<'info>(
+    cfg: &BoxMinterConfig,
+    config: &AccountInfo<'info>,
+    cpi: &FreezeInPlaceAccounts<'info>,
+    assets: &[AccountInfo<'info>],
+    burn: bool,
+) -> Result<()> {
+    require!(
+        cpi.core_collection.is_writable,
+        BoxMinterError::InvalidDeliveryMode
+    );
+    let cfg_bump_bytes = [cfg.bump];
+    let cfg_signer_seeds: &[&[u8]] = &[
+        BoxMinterConfig::SEED,
+        cfg.drop_seed.as_ref(),
+        &cfg_bump_bytes,
+    ];
+    for asset_ai in assets.iter() {
+        cpi.invoke(
+            set_frozen_ix_data(false),
+            asset_ai,
+            config,
+            &[cfg_signer_seeds],
+        )?;
+        if burn {
+            // BurnV1 discriminator=12, compression_proof=None (0)
+            cpi.invoke(vec![12u8, 0u8], asset_ai, config, &[cfg_signer_seeds])?;
+        } else {
+            for plugin_type in [
+                MPL_CORE_PLUGIN_FREEZE_DELEGATE,
+                MPL_CORE_PLUGIN_BURN_DELEGATE,
+            ] {
+                cpi.invoke(
+                    revoke_plugin_authority_ix_data(plugin_type),
+                    asset_ai,
+                    config,
+                    &[cfg_signer_seeds],
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Transfers `assets` out of `custody` to `new_owner` via MPL-Core `TransferV1`; the cosigner
+/// pays and the program signs when the custody vault owns them.
+fn transfer_custody_core_assets<'info>(
+    accounts: &DeliveryAccounts<'info>,
+    custody: &Custody<'info>,
+    new_owner: &AccountInfo<'info>,
+    assets: &[AccountInfo<'info>],
+) -> Result<()> {
+    let mut transfer_ix = Instruction {
+        program_id: MPL_CORE_PROGRAM_ID,
+        accounts: vec![
+            // asset, collection, payer, authority, new_owner, system_program, log_wrapper
+            AccountMeta::new(Pubkey::default(), false), // asset placeholder
+            AccountMeta::new_readonly(accounts.core_collection.key(), false),
+            AccountMeta::new(accounts.cosigner.key(), true),
+            AccountMeta::new_readonly(custody.owner.key(), true),
+            AccountMeta::new_readonly(new_owner.key(), false),
+            AccountMeta::new_readonly(accounts.system_program.key(), false),
+            AccountMeta::new_readonly(accounts.log_wrapper.key(), false),
+        ],
+        // TransferV1 discriminator=14, compression_proof=None (0)
+        data: vec![14u8, 0u8],
+    };
+
+    for asset_ai in assets.iter() {
+        transfer_ix.accounts[0].pubkey = asset_ai.key();
+        custody.invoke(
+            &transfer_ix,
+            &[
+                asset_ai.clone(),
+                accounts.core_collection.clone(),
+                accounts.cosigner.clone(),
+                custody.owner.clone(),
+                new_owner.clone(),
+                accounts.system_program.clone(),
+                accounts.log_wrapper.clone(),
+                accounts.mpl_core_program.clone(),
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+/// Verifies a delivered asset is one of this drop's boxes (`/b`) or figures (`/f`) owned by
+/// `owner` and returns its kind and ref id (the id of the `/rb` or `/rf` receipt it earns).
+fn verify_delivered_asset(
+    cfg: &BoxMinterConfig,
+    asset_ai: &AccountInfo,
+    owner: Pubkey,
+) -> Result<(ReceiptKind, u32)> {
+    if let Ok(box_id) = verify_core_asset_owned_by_uri(
+        asset_ai,
+        owner,
+        cfg.core_collection,
+        &cfg.uri_base,
+        legacy_box_uri_base(cfg),
+        URI_PREFIX_BOXES,
+        None,
+    ) {
+        require!(
+            box_id <= cfg.max_supply,
+            BoxMinterError::InvalidAssetMetadata
+        );
+        return Ok((ReceiptKind::Box, box_id));
+    }
+    let figure_id = verify_core_asset_owned_by_uri(
+        asset_ai,
+        owner,
+        cfg.core_collection,
+        &cfg.uri_base,
+        None,
+        URI_PREFIX_FIGURES,
+        None,
+    )?;
+    require!(
+        figure_id <= u32::from(cfg.max_figure_id()?),
+        BoxMinterError::InvalidDudeId
+    );
+    Ok((ReceiptKind::Figure, figure_id))
+}
+
+struct ReceiptMintAccounts<'info> {
+    /// Pays for the mints.
+    payer: AccountInfo<'info>,
+    leaf_recipient: AccountInfo<'info>,
+    merkle_tree: AccountInfo<'info>,
+    tree_config: AccountInfo<'info>,
+    core_collection: AccountInfo<'info>,
+    bubblegum_program: AccountInfo<'info>,
+    log_wrapper: AccountInfo<'info>,
+    compression_program: AccountInfo<'info>,
+    mpl_core_program: AccountInfo<'info>,
+    mpl_core_cpi_signer: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    /// The config PDA signs as tree delegate and collection authority of the program's trees;
+    /// `None` while the drop has no receipt tree registry, and `payer` (the cosigner) signs for
+    /// its own tree instead.
+    program_signer: Option<ReceiptProgramSigner<'info>>,
+    /// Receipts of the batch that fit in `merkle_tree`; the rest go to `next_tree`.
+    active_tree_leaves: u64,
+    next_tree: Option<ReceiptNextTree<'info>>,
+}
+
+/// Registered receipts tree a batch continues in once the active tree is full.
+#[derive(Clone)]
+struct ReceiptNextTree<'info> {
+    merkle_tree: AccountInfo<'info>,
+    tree_config: AccountInfo<'info>,
+}
+
+impl<'info> ReceiptNextTree<'info> {
+    fn new(
+        merkle_tree: &Option<UncheckedAccount<'info>>,
+        tree_config: &Option<UncheckedAccount<'info>>,
+    ) -> Option<Self> {
+        match (merkle_tree, tree_config) {
+            (Some(merkle_tree), Some(tree_config)) => Some(Self {
+                merkle_tree: merkle_tree.to_account_info(),
+                tree_config: tree_config.to_account_info(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+struct ReceiptProgramSigner<'info> {
+    config: AccountInfo<'info>,
+    drop_seed: [u8; 32],
+    bump: u8,
+}
+
+impl<'info> ReceiptProgramSigner<'info> {
+    fn new(config: &Account<'info, BoxMinterConfig>) -> Self {
+        Self {
+            config: config.to_account_info(),
+            drop_seed: config.drop_seed,
+            bump: config.bump,
+        }
+    }
+}
+
+impl<'info> ReceiptMintAccounts<'info> {
+    /// Tree delegate and collection authority of the mints.
+    fn authority(&self) -> &AccountInfo<'info> {
+        self.program_signer
+            .as_ref()
+            .map_or(&self.payer, |signer| &signer.config)
+    }
+}
+
+struct ReceiptMintCpi<'a, 'info> {
+    ix: Instruction,
+    account_infos: [AccountInfo<'info>; 14],
+    /// `(drop_seed, bump)` of the config PDA when it signs the mint.
+    config_signer: Option<([u8; 32], u8)>,
+    /// Mints left in `merkle_tree` before the batch moves on to `next_tree`.
+    active_tree_leaves: u64,
+    next_tree: Option<ReceiptNextTree<'info>>,
+    merkle_tree: Pubkey,
+    leaf_owner: Pubkey,
+    /// Leaf the next mint lands in: the tree's `num_minted`, read once and counted locally.
+    next_leaf_index: u64,
+    /// Client-derived Bubblegum asset id bumps still unused, one per leaf minted (see
+    /// `MintReceiptsArgs::asset_id_bumps`); each mint takes the first.
+    asset_id_bumps: &'a [u8],
+}
+
+impl<'info> ReceiptMintAccounts<'info> {
+    fn from_admin_delivery(
+        accounts: &AdminDeliverVariantOrder<'info>,
+        active_tree_leaves: Option<u64>,
+    ) -> Self {
+        Self {
+            payer: accounts.cosigner.to_account_info(),
+            leaf_recipient: accounts.receipt_owner.to_account_info(),
+            merkle_tree: accounts.merkle_tree.to_account_info(),
+            tree_config: accounts.tree_config.to_account_info(),
+            core_collection: accounts.core_collection.to_account_info(),
+            bubblegum_program: accounts.bubblegum_program.to_account_info(),
+            log_wrapper: accounts.log_wrapper.to_account_info(),
+            compression_program: accounts.compression_program.to_account_info(),
+            mpl_core_program: accounts.mpl_core_program.to_account_info(),
+            mpl_core_cpi_signer: accounts.mpl_core_cpi_signer.to_account_info(),
+            system_program: accounts.system_program.to_account_info(),
+            program_signer: active_tree_leaves.map(|_| ReceiptProgramSigner::new(&accounts.config)),
+            active_tree_leaves: active_tree_leaves.unwrap_or(u64::MAX),
+            next_tree: active_tree_leaves.and(ReceiptNextTree::new(
+                &accounts.next_merkle_tree,
+                &accounts.next_tree_config,
+            )),
+        }
+    }
+
+    fn from_deliver_with_receipts(
+        accounts: &DeliverWithReceipts<'info>,
+        active_tree_leaves: Option<u64>,
+    ) -> Self {
+        Self {
+            payer: accounts.cosigner.to_account_info(),
+            leaf_recipient: accounts.payer.to_account_info(),
+            merkle_tree: accounts.merkle_tree.to_account_info(),
+            tree_config: accounts.tree_config.to_account_info(),
+            core_collection: accounts.core_collection.to_account_info(),
+            bubblegum_program: accounts.bubblegum_program.to_account_info(),
+            log_wrapper: accounts.receipts_log_wrapper.to_account_info(),
+            compression_program: accounts.compression_program.to_account_info(),
+            mpl_core_program: accounts.mpl_core_program.to_account_info(),
+            mpl_core_cpi_signer: accounts.mpl_core_cpi_signer.to_account_info(),
+            system_program: accounts.system_program.to_account_info(),
+            program_signer: active_tree_leaves.map(|_| ReceiptProgramSigner::new(&accounts.config)),
+            active_tree_leaves: active_tree_leaves.unwrap_or(u64::MAX),
+            next_tree: active_tree_leaves.and(ReceiptNextTree::new(
+                &accounts.next_merkle_tree,
+                &accounts.next_tree_config,
+            )),
+        }
+    }
+
+    fn from_mint_delivery_receipts(
+        accounts: &MintDeliveryReceipts<'info>,
+        active_tree_leaves: Option<u64>,
+    ) -> Self {
+        Self {
+            payer: accounts.payer.to_account_info(),
+            leaf_recipient: accounts.recipient.to_account_info(),
+            merkle_tree: accounts.merkle_tree.to_account_info(),
+            tree_config: accounts.tree_config.to_account_info(),
+            core_collection: accounts.core_collection.to_account_info(),
+            bubblegum_program: accounts.bubblegum_program.to_account_info(),
+            log_wrapper: accounts.log_wrapper.to_account_info(),
+            compression_program: accounts.compression_program.to_account_info(),
+            mpl_core_program: accounts.mpl_core_program.to_account_info(),
+            mpl_core_cpi_signer: accounts.mpl_core_cpi_signer.to_account_info(),
+            system_program: accounts.system_program.to_account_info(),
+            program_signer: active_tree_leaves.map(|_| ReceiptProgramSigner::new(&accounts.config)),
+            active_tree_leaves: active_tree_leaves.unwrap_or(u64::MAX),
+            next_tree: active_tree_leaves.and(ReceiptNextTree::new(
+                &accounts.next_merkle_tree,
+                &accounts.next_tree_config,
+            )),
+        }
+    }
+
+    fn from_mint_receipts(accounts: &MintReceipts<'info>, active_tree_leaves: Option<u64>) -> Self {
+        Self {
+            payer: accounts.cosigner.to_account_info(),
+            leaf_recipient: accounts.user.to_account_info(),
+            merkle_tree: accounts.merkle_tree.to_account_info(),
+            tree_config: accounts.tree_config.to_account_info(),
+            core_collection: accounts.core_collection.to_account_info(),
+            bubblegum_program: accounts.bubblegum_program.to_account_info(),
+            log_wrapper: accounts.log_wrapper.to_account_info(),
+            compression_program: accounts.compression_program.to_account_info(),
+            mpl_core_program: accounts.mpl_core_program.to_account_info(),
+            mpl_core_cpi_signer: accounts.mpl_core_cpi_signer.to_account_info(),
+            system_program: accounts.system_program.to_account_info(),
+            program_signer: active_tree_leaves.map(|_| ReceiptProgramSigner::new(&accounts.config)),
+            active_tree_leaves: active_tree_leaves.unwrap_or(u64::MAX),
+            next_tree: active_tree_leaves.and(ReceiptNextTree::new(
+                &accounts.next_merkle_tree,
+                &accounts.next_tree_config,
+            )),
+        }
+    }
+}
+
+fn validate_receipt_mint_accounts(accounts: &ReceiptMintAccounts<'_>) -> Result<()> {
+    require_keys_eq!(
+        accounts.bubblegum_program.key(),
+        BUBBLEGUM_PROGRAM_ID,
+        BoxMinterError::InvalidBubblegumProgram
+    );
+    require_keys_eq!(
+        accounts.log_wrapper.key(),
+        MPL_NOOP_PROGRAM_ID,
+        BoxMinterError::InvalidMplNoopProgram
+    );
+    require_keys_eq!(
+        accounts.compression_program.key(),
+        MPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
+        BoxMinterError::InvalidCompressionProgram
+    );
+    require_keys_eq!(
+        accounts.mpl_core_program.key(),
+        MPL_CORE_PROGRAM_ID,
+        BoxMinterError::InvalidMplCoreProgram
+    );
+    require_keys_eq!(
+        accounts.mpl_core_cpi_signer.key(),
+        MPL_CORE_CPI_SIGNER,
+        BoxMinterError::InvalidMplCoreCpiSigner
+    );
+    validate_receipt_tree_accounts(&accounts.merkle_tree, &accounts.tree_config)?;
+    if let Some(next_tree) = accounts.next_tree.as_ref() {
+        validate_receipt_tree_accounts(&next_tree.merkle_tree, &next_tree.tree_config)?;
+    }
+    Ok(())
+}
+
+fn validate_receipt_tree_accounts(
+    merkle_tree: &AccountInfo<'_>,
+    tree_config: &AccountInfo<'_>,
+) -> Result<()> {
+    require_keys_eq!(
+        *merkle_tree.owner,
+        MPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
+        BoxMinterError::InvalidReceiptsMerkleTree
+    );
+    let (expected_tree_config, _) =
+        Pubkey::find_program_address(&[merkle_tree.key().as_ref()], &BUBBLEGUM_PROGRAM_ID);
+    require_keys_eq!(
+        tree_config.key(),
+        expected_tree_config,
+        BoxMinterError::InvalidReceiptsTreeConfig
+    );
+    Ok(())
+}
+
+/// Reserves the batch's leaves in the drop's receipt tree registry (see
+/// `reserve_receipt_tree_leaves`) and stores the updated registry. Returns `None` while the
+/// registry is uninitialized: receipts then go into the cosigner's own `merkle_tree`, without
+/// rollover.
+fn reserve_registered_receipt_leaves(
+    receipt_trees_ai: &AccountInfo,
+    program_id: &Pubkey,
+    merkle_tree: Pubkey,
+    next_merkle_tree: Option<Pubkey>,
+    quantity: u64,
+) -> Result<Option<u64>> {
+    let Some(mut receipt_trees) = load_drop_pda::<ReceiptTrees>(receipt_trees_ai, program_id)?
+    else {
+        return Ok(None);
+    };
+    let active_tree_leaves =
+        reserve_receipt_tree_leaves(&mut receipt_trees, merkle_tree, next_merkle_tree, quantity)?;
+    receipt_trees.try_serialize(&mut &mut receipt_trees_ai.try_borrow_mut_data()?[..])?;
+    Ok(Some(active_tree_leaves))
+}
+
+/// Reserves `quantity` leaves in the drop's receipts trees, skipping past full trees: leaves that
+/// don't fit in the active tree (`merkle_tree`) roll over into the registered tree after it,
+/// which must then be passed as `next_merkle_tree`. Returns the leaves left in `merkle_tree`.
+fn reserve_receipt_tree_leaves(
+    receipt_trees: &mut ReceiptTrees,
+    merkle_tree: Pubkey,
+    next_merkle_tree: Option<Pubkey>,
+    quantity: u64,
+) -> Result<u64> {
+    receipt_trees.skip_full_trees();
+    let active_index = usize::from(receipt_trees.active_index);
+    let active = receipt_trees
+        .trees
+        .get(active_index)
+        .ok_or(error!(BoxMinterError::ReceiptTreesFull))?;
+    require_keys_eq!(
+        merkle_tree,
+        active.merkle_tree,
+        BoxMinterError::ReceiptsTreeNotActive
+    );
+    let active_leaves = quantity.min(active.capacity.saturating_sub(active.minted));
+    let rollover = quantity - active_leaves;
+    if rollover > 0 {
+        let next = receipt_trees
+            .trees
+            .get(active_index + 1)
+            .ok_or(error!(BoxMinterError::ReceiptTreesFull))?;
+        require!(
+            next_merkle_tree == Some(next.merkle_tree),
+            BoxMinterError::ReceiptsTreeNotActive
+        );
+        // A batch spans at most two trees.
+        require!(
+            rollover <= next.capacity.saturating_sub(next.minted),
+            BoxMinterError::ReceiptsTreeCapacityExceeded
+        );
+        receipt_trees.trees[active_index + 1].minted += rollover;
+    }
+    receipt_trees.trees[active_index].minted += active_leaves;
+    receipt_trees.skip_full_trees();
+    Ok(active_leaves)
+}
+
+/// Marks receipt `id` (1-based) as issued in `bits`; `false` if it already was.
+fn mark_receipt_issued(bits: &mut [u8], id: u32) -> Result<bool> {
+    require!(id >= 1, BoxMinterError::InvalidAssetMetadata);
+    let index = (id - 1) as usize;
+    let byte = bits
+        .get_mut(index / 8)
+        .ok_or(error!(BoxMinterError::InvalidAssetMetadata))?;
+    let mask = 1u8 << (index % 8);
+    if *byte & mask != 0 {
+        return Ok(false);
+    }
+    *byte |= mask;
+    Ok(true)
+}
+
+/// Box and figure ids of delivered items, for their receipts.
+fn split_receipt_ids(items: &[(ReceiptKind, u32)]) -> (Vec<u32>, Vec<u16>) {
+    let mut box_ids = Vec::with_capacity(items.len());
+    let mut dude_ids = Vec::with_capacity(items.len());
+    for (kind, ref_id) in items {
+        match kind {
+            ReceiptKind::Box => box_ids.push(*ref_id),
+            // Figure ids are bounded by `max_figure_id() -> u16`.
+            ReceiptKind::Figure => dude_ids.push(*ref_id as u16),
+        }
+    }
+    (box_ids, dude_ids)
+}
+
+/// Drops box and figure ids the ledger already records as issued and marks the rest in place.
+/// Keeps every id while the drop has no ledger.
+fn retain_unissued_receipts(
+    ledger_ai: &AccountInfo,
+    program_id: &Pubkey,
+    box_ids: &mut Vec<u32>,
+    dude_ids: &mut Vec<u16>,
+) -> Result<()> {
+    let Some(receipt_ledger) = load_drop_pda::<ReceiptLedger>(ledger_ai, program_id)? else {
+        return Ok(());
+    };
+    let mut data = ledger_ai.try_borrow_mut_data()?;
+    let (box_bits, figure_bits) = receipt_ledger.bitmaps(&mut data)?;
+    retain_unissued_receipt_ids(box_bits, box_ids)?;
+    retain_unissued_receipt_ids(figure_bits, dude_ids)
+}
+
+/// Drops ids whose receipts were already issued and marks the rest, so retried
+/// `mint_receipts` calls only mint what is still missing.
+fn retain_unissued_receipt_ids<T: Copy + Into<u32>>(
+    bits: &mut [u8],
+    ids: &mut Vec<T>,
+) -> Result<()> {
+    let mut unissued = Vec::with_capacity(ids.len());
+    for id in ids.iter() {
+        if mark_receipt_issued(bits, (*id).into())? {
+            unissued.push(*id);
+        }
+    }
+    *ids = unissued;
+    Ok(())
+}
+
+fn create_receipts_tree_v2_data(args: &CreateReceiptsTreeArgs) -> Vec<u8> {
+    let mut data = Vec::with_capacity(8 + 4 + 4 + 2);
+    data.extend_from_slice(&IX_BUBBLEGUM_CREATE_TREE_V2);
+    data.extend_from_slice(&args.max_depth.to_le_bytes());
+    data.extend_from_slice(&args.max_buffer_size.to_le_bytes());
+    // public: Some(false) so only the tree creator/delegate (config PDA) may mint.
+    data.push(1u8);
+    data.push(0u8);
+    data
+}
+
+fn new_receipt_mint_ix(accounts: &ReceiptMintAccounts<'_>) -> Instruction {
+    let authority = accounts.authority().key();
+    Instruction {
+        program_id: BUBBLEGUM_PROGRAM_ID,
+        accounts: vec![
+            // mintV2 accounts order (kinobi):
+            // 0 treeConfig (writable)
+            AccountMeta::new(accounts.tree_config.key(), false),
+            // 1 payer (writable signer)
+            AccountMeta::new(accounts.payer.key(), true),
+            // 2 treeCreatorOrDelegate (signer)
+            AccountMeta::new_readonly(authority, true),
+            // 3 collectionAuthority (signer)
+            AccountMeta::new_readonly(authority, true),
+            // 4 leafOwner
+            AccountMeta::new_readonly(accounts.leaf_recipient.key(), false),
+            // 5 leafDelegate
+            AccountMeta::new_readonly(accounts.leaf_recipient.key(), false),
+            // 6 merkleTree (writable)
+            AccountMeta::new(accounts.merkle_tree.key(), false),
+            // 7 coreCollection (writable)
+            AccountMeta::new(accounts.core_collection.key(), false),
+            // 8 mplCoreCpiSigner
+            AccountMeta::new_readonly(accounts.mpl_core_cpi_signer.key(), false),
+            // 9 logWrapper
+            AccountMeta::new_readonly(accounts.log_wrapper.key(), false),
+            // 10 compressionProgram
+            AccountMeta::new_readonly(accounts.compression_program.key(), false),
+            // 11 mplCoreProgram
+            AccountMeta::new_readonly(accounts.mpl_core_program.key(), false),
+            // 12 systemProgram
+            AccountMeta::new_readonly(accounts.system_program.key(), false),
+        ],
+        data: Vec::with_capacity(256),
+    }
+}
+
+fn new_receipt_mint_cpi<'a, 'info>(
+    accounts: &ReceiptMintAccounts<'info>,
+    asset_id_bumps: &'a [u8],
+) -> Result<ReceiptMintCpi<'a, 'info>> {
+    let authority = accounts.authority();
+    Ok(ReceiptMintCpi {
+        ix: new_receipt_mint_ix(accounts),
+        // Matches the AccountMeta order in new_receipt_mint_ix; Bubblegum's program account is
+        // appended for CPI invocation.
+        account_infos: [
+            accounts.tree_config.clone(),
+            accounts.payer.clone(),
+            authority.clone(),
+            authority.clone(),
+            accounts.leaf_recipient.clone(),
+            accounts.leaf_recipient.clone(),
+            accounts.merkle_tree.clone(),
+            accounts.core_collection.clone(),
+            accounts.mpl_core_cpi_signer.clone(),
+            accounts.log_wrapper.clone(),
+            accounts.compression_program.clone(),
+            accounts.mpl_core_program.clone(),
+            accounts.system_program.clone(),
+            accounts.bubblegum_program.clone(),
+        ],
+        config_signer: accounts
+            .program_signer
+            .as_ref()
+            .map(|signer| (signer.drop_seed, signer.bump)),
+        active_tree_leaves: accounts.active_tree_leaves,
+        next_tree: accounts.next_tree.clone(),
+        merkle_tree: accounts.merkle_tree.key(),
+        leaf_owner: accounts.leaf_recipient.key(),
+        next_leaf_index: read_tree_num_minted(&accounts.tree_config)?,
+        asset_id_bumps,
+    })
+}
+
+/// Per-instruction receipt metadata shared by every receipt minted in the call.
+struct ReceiptMetadata<'a> {
+    core_collection: Pubkey,
+    symbol: &'a str,
+    /// Seller fee + creators come from the drop's royalties, when configured.
+    seller_fee_basis_points: u16,
+    creators: &'a [RoyaltyCreator],
+    /// `?order=<hex order hash>&delivery=<id>` appended to every receipt URI. Bubblegum v2 does
+    /// not accept `assetData` yet, so the receipt's ties travel in its metadata URI instead.
+    uri_query: Option<String>,
+}
+
+fn new_receipt_metadata<'a>(
+    cfg: &'a BoxMinterConfig,
+    royalties: Option<&'a DropRoyalties>,
+    asset_data: Option<&ReceiptAssetData>,
+) -> ReceiptMetadata<'a> {
+    ReceiptMetadata {
+        core_collection: cfg.core_collection,
+        symbol: cfg.symbol.as_str(),
+        seller_fee_basis_points: royalties.map_or(0, |royalties| royalties.basis_points),
+        creators: royalties.map_or(&[][..], |royalties| royalties.creators.as_slice()),
+        uri_query: asset_data.map(|asset_data| {
+            format!(
+                "?order={}&delivery={}",
+                hex_bytes32(&asset_data.order_hash),
+                asset_data.delivery_id
+            )
+        }),
+    }
+}
+
+fn write_receipt_mint_data(
+    data: &mut Vec<u8>,
+    metadata: &ReceiptMetadata,
+    name: &str,
+    uri: &str,
+) -> Result<()> {
+    data.clear();
+    // MetadataArgsV2 (borsh):
+    // name, symbol, uri, sellerFeeBasisPoints(u16), primarySaleHappened(bool), isMutable(bool),
+    // tokenStandard: Option<TokenStandard> (Some(NonFungible=0)),
+    // creators: Vec<Creator> (unverified),
+    // collection: Option<Pubkey> (Some(coreCollection))
+    data.extend_from_slice(&IX_BUBBLEGUM_MINT_V2);
+    borsh_push_string(data, name)?;
+    borsh_push_string(data, metadata.symbol)?;
+    let query = metadata.uri_query.as_deref().unwrap_or_default();
+    let uri_len = uri.len() + query.len();
+    require!(
+        uri_len <= MAX_RECEIPT_URI_LEN,
+        BoxMinterError::InvalidReceiptUriBase
+    );
+    data.extend_from_slice(&(uri_len as u32).to_le_bytes());
+    data.extend_from_slice(uri.as_bytes());
+    data.extend_from_slice(query.as_bytes());
+    data.extend_from_slice(&metadata.seller_fee_basis_points.to_le_bytes());
+    data.push(0u8); // primarySaleHappened=false
+    data.push(1u8); // isMutable=true
+    data.push(1u8); // tokenStandard: Some
+    data.push(0u8); // NonFungible enum index
+    data.extend_from_slice(&(metadata.creators.len() as u32).to_le_bytes());
+    for creator in metadata.creators {
+        data.extend_from_slice(creator.address.as_ref());
+        data.push(0u8); // verified=false
+        data.push(creator.percentage); // share
+    }
+    data.push(1u8); // collection: Some
+    data.extend_from_slice(metadata.core_collection.as_ref());
+    data.push(0u8); // assetData: None
+    data.push(0u8); // assetDataSchema: None
+    Ok(())
+}
+
+/// Leaf a receipt was minted into, with its Bubblegum asset id.
+struct ReceiptLeaf {
+    leaf_index: u64,
+    asset_id: Pubkey,
+}
+
+fn read_tree_num_minted(tree_config: &AccountInfo<'_>) -> Result<u64> {
+    read_tree_config_u64(tree_config, BUBBLEGUM_TREE_CONFIG_NUM_MINTED_OFFSET)
+}
+
+fn receipt_asset_id(merkle_tree: &Pubkey, leaf_index: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            SEED_BUBBLEGUM_ASSET,
+            merkle_tree.as_ref(),
+            &leaf_index.to_le_bytes(),
+        ],
+        &BUBBLEGUM_PROGRAM_ID,
+    )
+    .0
+}
+
+/// Bubblegum asset id of leaf `leaf_index` from its client-derived bump.
+fn receipt_asset_id_with_bump(merkle_tree: &Pubkey, leaf_index: u64, bump: u8) -> Result<Pubkey> {
+    Pubkey::create_program_address(
+        &[
+            SEED_BUBBLEGUM_ASSET,
+            merkle_tree.as_ref(),
+            &leaf_index.to_le_bytes(),
+            &[bump],
+        ],
+        &BUBBLEGUM_PROGRAM_ID,
+    )
+    .map_err(|_| error!(BoxMinterError::InvalidReceiptAssetBump))
+}
+
+fn emit_receipt_minted(
+    config: Pubkey,
+    cpi: &ReceiptMintCpi<'_, '_>,
+    leaf: ReceiptLeaf,
+    kind: ReceiptKind,
+    id: u32,
+) {
+    emit!(ReceiptMinted {
+        config,
+        merkle_tree: cpi.merkle_tree,
+        leaf_owner: cpi.leaf_owner,
+        leaf_index: leaf.leaf_index,
+        asset_id: leaf.asset_id,
+        kind,
+        id,
+    });
+}
+
+fn invoke_receipt_mint_v2(
+    cpi: &mut ReceiptMintCpi<'_, '_>,
+    metadata: &ReceiptMetadata,
+    name: &str,
+    uri: &str,
+) -> Result<ReceiptLeaf> {
+    if cpi.active_tree_leaves == 0 {
+        roll_over_receipt_tree(cpi)?;
+    }
+    // The leaf index Bubblegum assigns is the tree's `num_minted` before the mint.
+    let leaf_index = cpi.next_leaf_index;
+    let (&bump, rest) = cpi
+        .asset_id_bumps
+        .split_first()
+        .ok_or(error!(BoxMinterError::InvalidReceiptAssetBump))?;
+    let asset_id = receipt_asset_id_with_bump(&cpi.merkle_tree, leaf_index, bump)?;
+    write_receipt_mint_data(&mut cpi.ix.data, metadata, name, uri)?;
+    // CPI: include the program account at the end (like SystemProgram CPIs).
+    match cpi.config_signer {
+        Some((drop_seed, cfg_bump)) => {
+            let bump_bytes = [cfg_bump];
+            let cfg_signer_seeds: &[&[u8]] =
+                &[BoxMinterConfig::SEED, drop_seed.as_ref(), &bump_bytes];
+            invoke_signed(&cpi.ix, &cpi.account_infos, &[cfg_signer_seeds])?;
+        }
+        None => invoke(&cpi.ix, &cpi.account_infos)?,
+    }
+    cpi.active_tree_leaves -= 1;
+    cpi.next_leaf_index = leaf_index
+        .checked_add(1)
+        .ok_or(BoxMinterError::MathOverflow)?;
+    cpi.asset_id_bumps = rest;
+    Ok(ReceiptLeaf {
+        leaf_index,
+        asset_id,
+    })
+}
+
+fn read_tree_config_u64(tree_config: &AccountInfo<'_>, offset: usize) -> Result<u64> {
+    require_keys_eq!(
+        *tree_config.owner,
+        BUBBLEGUM_PROGRAM_ID,
+        BoxMinterError::InvalidReceiptsTreeConfig
+    );
+    let data = tree_config.try_borrow_data()?;
+    let bytes = data
+        .get(offset..offset + 8)
+        .ok_or(error!(BoxMinterError::InvalidReceiptsTreeConfig))?;
+    let mut value = [0u8; 8];
+    value.copy_from_slice(bytes);
+    Ok(u64::from_le_bytes(value))
+}
+
+/// Points the mint CPI at the next registered tree once the batch has filled the active one.
+fn roll_over_receipt_tree(cpi: &mut ReceiptMintCpi<'_, '_>) -> Result<()> {
+    let next_tree = cpi
+        .next_tree
+        .take()
+        .ok_or(error!(BoxMinterError::ReceiptsTreeCapacityExceeded))?;
+    // mintV2 accounts 0 (treeConfig) and 6 (merkleTree), see new_receipt_mint_ix.
+    cpi.ix.accounts[0].pubkey = next_tree.tree_config.key();
+    cpi.ix.accounts[6].pubkey = next_tree.merkle_tree.key();
+    cpi.next_leaf_index = read_tree_num_minted(&next_tree.tree_config)?;
+    cpi.merkle_tree = next_tree.merkle_tree.key();
+    cpi.active_tree_leaves = u64::MAX;
+    cpi.account_infos[0] = next_tree.tree_config;
+    cpi.account_infos[6] = next_tree.merkle_tree;
+    Ok(())
+}
+
+fn build_receipt_name_and_uri(
+    name_buf: &mut String,
+    uri_buf: &mut String,
+    drop_base: &str,
+    label: &str,
+    uri_prefix: &str,
+    id: impl core::fmt::Display + Copy,
+) -> Result<()> {
+    name_buf.clear();
+    name_buf.push_str(RECEIPT_NAME_PREFIX);
+    append_label_and_id(name_buf, label, id)?;
+
+    uri_buf.clear();
+    uri_buf.push_str(drop_base);
+    uri_buf.push_str(uri_prefix);
+    write!(uri_buf, "{}", id).map_err(|_| error!(BoxMinterError::SerializationFailed))?;
+    uri_buf.push_str(".json");
+    Ok(())
+}
+
+/// The drop's base for receipt URIs; bases stored before `MAX_URI_BASE` was lowered can be too
+/// long for the receipt path and query and must be shortened with `set_uri_base`.
+fn receipt_uri_base(cfg: &BoxMinterConfig) -> Result<&str> {
+    require!(
+        cfg.uri_base.len() <= BoxMinterConfig::MAX_URI_BASE,
+        BoxMinterError::ReceiptUriBaseTooLong
+    );
+    Ok(&cfg.uri_base)
+}
+
+fn mint_box_and_figure_receipts<'info>(
+    config_key: Pubkey,
+    cfg: &BoxMinterConfig,
+    accounts: &ReceiptMintAccounts<'info>,
+    metadata: &ReceiptMetadata,
+    box_ids: &[u32],
+    dude_ids: &[u16],
+    asset_id_bumps: &[u8],
+) -> Result<()> {
+    let drop_base = receipt_uri_base(cfg)?;
+    let mut mint_cpi = new_receipt_mint_cpi(accounts, asset_id_bumps)?;
+
+    let mut name_buf = String::with_capacity(48);
+    let mut uri_buf = String::with_capacity(
+        drop_base.len()
+            + URI_PREFIX_RECEIPTS_BOXES
+                .len()
+                .max(URI_PREFIX_RECEIPTS_FIGURES.len())
+            + 16,
+    );
+
+    for box_id in box_ids.iter() {
+        build_receipt_name_and_uri(
+            &mut name_buf,
+            &mut uri_buf,
+            drop_base,
+            &cfg.name_prefix,
+            URI_PREFIX_RECEIPTS_BOXES,
+            *box_id,
+        )?;
+        let leaf = invoke_receipt_mint_v2(&mut mint_cpi, metadata, &name_buf, &uri_buf)?;
+        emit_receipt_minted(config_key, &mint_cpi, leaf, ReceiptKind::Box, *box_id);
+    }
+
+    for dude_id in dude_ids.iter() {
+        build_receipt_name_and_uri(
+            &mut name_buf,
+            &mut uri_buf,
+            drop_base,
+            &cfg.figure_name_prefix,
+            URI_PREFIX_RECEIPTS_FIGURES,
+            *dude_id,
+        )?;
+        let leaf = invoke_receipt_mint_v2(&mut mint_cpi, metadata, &name_buf, &uri_buf)?;
+        emit_receipt_minted(
+            config_key,
+            &mint_cpi,
+            leaf,
+            ReceiptKind::Figure,
+            u32::from(*dude_id),
+        );
+    }
+
+    Ok(())
+}
+
+fn mint_admin_order_receipt_cnfts<'info>(
+    config_key: Pubkey,
+    cfg: &BoxMinterConfig,
+    accounts: &ReceiptMintAccounts<'info>,
+    metadata: &ReceiptMetadata,
+    first_metadata_id: u32,
+    quantity: u8,
+    asset_id_bumps: &[u8],
+) -> Result<()> {
+    require!(quantity >= 1, BoxMinterError::InvalidQuantity);
+    let last_metadata_id = first_metadata_id
+        .checked_add(u32::from(quantity) - 1)
+        .ok_or(BoxMinterError::MathOverflow)?;
+    require!(
+        first_metadata_id >= 1 && last_metadata_id <= cfg.max_supply,
+        BoxMinterError::InvalidAssetMetadata
+    );
+
+    let drop_base = receipt_uri_base(cfg)?;
+    let mut name_buf = String::with_capacity(48);
+    let mut uri_buf = String::with_capacity(drop_base.len() + URI_PREFIX_RECEIPTS_BOXES.len() + 16);
+    let mut mint_cpi = new_receipt_mint_cpi(accounts, asset_id_bumps)?;
+
+    for metadata_id in first_metadata_id..=last_metadata_id {
+        build_receipt_name_and_uri(
+            &mut name_buf,
+            &mut uri_buf,
+            drop_base,
+            &cfg.name_prefix,
+            URI_PREFIX_RECEIPTS_BOXES,
+            metadata_id,
+        )?;
+        let leaf = invoke_receipt_mint_v2(&mut mint_cpi, metadata, &name_buf, &uri_buf)?;
+        emit_receipt_minted(config_key, &mint_cpi, leaf, ReceiptKind::Box, metadata_id);
+    }
+
+    Ok(())
+}
+
+fn validate_admin_order_hash(order_hash: &[u8; 32]) -> Result<()> {
+    require!(
+        has_any_non_zero_byte(order_hash.as_ref()),
+        BoxMinterError::InvalidAdminOrder
+    );
+    Ok(())
+}
+
+fn admin_order_is_valid_retry(
+    record: &AdminDeliveryOrderRecord,
+    args: &AdminDeliverVariantOrderArgs,
+    effective_variant_index: u8,
+    receipt_owner: Pubkey,
+    order_bump: u8,
+) -> bool {
+    record.order_hash == args.order_hash
+        && record.variant_index == effective_variant_index
+        && record.quantity == args.quantity
+        && record.receipt_owner == receipt_owner
+        && record.bump == order_bump
+        && record.quantity >= 1
+        && record.first_metadata_id >= 1
+}
+
+fn deserialize_admin_order_record(
+    account: &AccountInfo<'_>,
+    program_id: &Pubkey,
+) -> Result<AdminDeliveryOrderRecord> {
+    require_keys_eq!(
+        *account.owner,
+        *program_id,
+        BoxMinterError::InvalidAdminOrder
+    );
+    let data = account.try_borrow_data()?;
+    decode_admin_order_record(&data)
+}
+
+/// Closes a delivery record (either layout), returning its rent to the payer stored in it.
+fn close_delivery_record<'info>(
+    delivery_ai: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    program_id: &Pubkey,
+) -> Result<()> {
+    require_keys_eq!(
+        *delivery_ai.owner,
+        *program_id,
+        BoxMinterError::InvalidDeliveryPda
+    );
+    let (record, data_len) = {
+        let data = delivery_ai.try_borrow_data()?;
+        (decode_delivery_record(&data)?, data.len())
+    };
+    require_keys_eq!(
+        payer.key(),
+        record.payer,
+        BoxMinterError::InvalidDeliveryPayer
+    );
+    require!(
+        delivery_record_is_closable(&record, data_len),
+        BoxMinterError::DeliveryNotClosable
+    );
+    close_program_account(delivery_ai, payer)
+}
+
+/// Records only close once delivered or once their receipts were issued, and freeze-in-place
+/// records only after fulfillment burned their assets; legacy records have no status and stay
+/// closable.
+fn delivery_record_is_closable(record: &DeliveryRecord, data_len: usize) -> bool {
+    data_len == DeliveryRecord::LEGACY_SPACE
+        || ((record.status == DeliveryStatus::Delivered || record.receipts_issued)
+            && (!record.frozen_in_place || record.fulfilled))
+}
+
+/// Marks the delivery record `mint_receipts` names as `receipts_issued`: the backend burns the
+/// delivered assets in the same transaction, so the record can no longer be cancelled and may be
+/// closed. Like `mint_delivery_receipts`, the delivery must be packed first (see
+/// `DeliveryStatus::allows_receipts`). Repeated batches for the same delivery leave it marked.
+fn mark_delivery_receipts_issued<'info>(
+    accounts: &MintReceipts<'info>,
+    delivery: &CloseDeliveryArgs,
+    program_id: &Pubkey,
+) -> Result<()> {
+    let delivery_ai = accounts
+        .delivery
+        .as_ref()
+        .ok_or(BoxMinterError::InvalidDeliveryPda)?
+        .to_account_info();
+    let expected = Pubkey::create_program_address(
+        &[
+            SEED_DELIVERY,
+            accounts.config.key().as_ref(),
+            &delivery.delivery_id.to_le_bytes(),
+            &[delivery.delivery_bump],
+        ],
+        program_id,
+    )
+    .map_err(|_| error!(BoxMinterError::InvalidDeliveryPda))?;
+    require_keys_eq!(
+        delivery_ai.key(),
+        expected,
+        BoxMinterError::InvalidDeliveryPda
+    );
+    require_keys_eq!(
+        *delivery_ai.owner,
+        *program_id,
+        BoxMinterError::InvalidDeliveryPda
+    );
+    let mut record = decode_delivery_record(&delivery_ai.try_borrow_data()?)?;
+    require_keys_eq!(
+        accounts.user.key(),
+        record.payer,
+        BoxMinterError::InvalidDeliveryPayer
+    );
+    require!(
+        record.status.allows_receipts(),
+        BoxMinterError::InvalidDeliveryStatusTransition
+    );
+    if record.receipts_issued {
+        return Ok(());
+    }
+    record.receipts_issued = true;
+    resize_delivery_record(
+        &delivery_ai,
+        &accounts.cosigner.to_account_info(),
+        &accounts.system_program.to_account_info(),
+    )?;
+    record.try_serialize(&mut &mut delivery_ai.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+/// Message the cosigner signs to quote a delivery: domain, config, delivery id, payer, fee,
+/// `delivery_assets_hash` of the items, address cipher hash, zone (borsh `Option<u16>`), sha256
+/// of the borsh-encoded receipts, freeze flag and the last slot the quote is valid for.
+fn delivery_quote_message(
+    config: &Pubkey,
+    payer: &Pubkey,
+    delivery: &DeliverArgs,
+    assets_hash: &[u8; 32],
+    expiry_slot: u64,
+) -> Result<Vec<u8>> {
+    let mut receipts = Vec::new();
+    delivery
+        .receipts
+        .serialize(&mut receipts)
+        .map_err(|_| error!(BoxMinterError::SerializationFailed))?;
+    let mut message = Vec::with_capacity(
+        DELIVERY_QUOTE_DOMAIN.len() + 32 + 4 + 32 + 8 + 32 + 32 + 3 + 32 + 1 + 8,
+    );
+    message.extend_from_slice(DELIVERY_QUOTE_DOMAIN);
+    message.extend_from_slice(config.as_ref());
+    message.extend_from_slice(&delivery.delivery_id.to_le_bytes());
+    message.extend_from_slice(payer.as_ref());
+    message.extend_from_slice(&delivery.delivery_fee_lamports.to_le_bytes());
+    message.extend_from_slice(assets_hash);
+    message.extend_from_slice(&delivery.address_cipher_hash);
+    match delivery.delivery_zone {
+        Some(zone) => {
+            message.push(1);
+            message.extend_from_slice(&zone.to_le_bytes());
+        }
+        None => message.push(0),
+    }
+    message.extend_from_slice(&hashv(&[&receipts]).to_bytes());
+    message.push(u8::from(delivery.freeze_in_place));
+    message.extend_from_slice(&expiry_slot.to_le_bytes());
+    Ok(message)
+}
+
+/// Checks that an Ed25519 program instruction verifies exactly one signature by `signer` over
+/// `message`, with the signature, key and message all inline in that instruction.
+fn verify_ed25519_instruction(ix: &Instruction, signer: &Pubkey, message: &[u8]) -> Result<()> {
+    require_keys_eq!(
+        ix.program_id,
+        ED25519_PROGRAM_ID,
+        BoxMinterError::InvalidDeliveryQuote
+    );
+    let data = ix.data.as_slice();
+    require!(
+        data.len() >= ED25519_OFFSETS_START + ED25519_OFFSETS_LEN && data[0] == 1,
+        BoxMinterError::InvalidDeliveryQuote
+    );
+    let read_u16 = |index: usize| -> usize {
+        let o = ED25519_OFFSETS_START + index * 2;
+        usize::from(u16::from_le_bytes([data[o], data[o + 1]]))
+    };
+    let signature_ix = read_u16(1);
+    let public_key_offset = read_u16(2);
+    let public_key_ix = read_u16(3);
+    let message_offset = read_u16(4);
+    let message_size = read_u16(5);
+    let message_ix = read_u16(6);
+    let current = usize::from(u16::MAX);
+    require!(
+        signature_ix == current && public_key_ix == current && message_ix == current,
+        BoxMinterError::InvalidDeliveryQuote
+    );
+    let public_key = data
+        .get(public_key_offset..public_key_offset + 32)
+        .ok_or(error!(BoxMinterError::InvalidDeliveryQuote))?;
+    require!(
+        public_key == signer.as_ref(),
+        BoxMinterError::InvalidDeliveryQuote
+    );
+    let signed_message = data
+        .get(message_offset..message_offset + message_size)
+        .ok_or(error!(BoxMinterError::InvalidDeliveryQuote))?;
+    require!(
+        signed_message == message,
+        BoxMinterError::InvalidDeliveryQuote
+    );
+    Ok(())
+}
+
+/// Grows a legacy delivery record to `DeliveryRecord::SPACE`, topping up rent from `payer`.
+fn resize_delivery_record<'info>(
+    delivery_ai: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    if delivery_ai.data_len() >= DeliveryRecord::SPACE {
+        return Ok(());
+    }
+    grow_program_account(delivery_ai, DeliveryRecord::SPACE, payer, system_program)
+}
+
+/// Legacy records (payer, fee and item count only) decode with every later field zeroed
+/// (status `Paid`); anything else must be the current layout.
+fn decode_delivery_record(data: &[u8]) -> Result<DeliveryRecord> {
+    let mut padded;
+    let mut data = data;
+    if data.len() == DeliveryRecord::LEGACY_SPACE {
+        padded = data.to_vec();
+        padded.resize(DeliveryRecord::SPACE, 0);
+        data = &padded;
+    }
+    DeliveryRecord::try_deserialize(&mut data)
+        .map_err(|_| error!(BoxMinterError::InvalidDeliveryPda))
+}
+
+/// Records created before receipt leaf tracking lack the trailing tree/leaf fields; those decode
+/// as the default tree and leaf 0 with nothing rolled over.
+fn decode_admin_order_record(data: &[u8]) -> Result<AdminDeliveryOrderRecord> {
+    let mut padded;
+    let mut data = data;
+    if data.len() == AdminDeliveryOrderRecord::LEGACY_SPACE {
+        padded = data.to_vec();
+        padded.resize(AdminDeliveryOrderRecord::SPACE, 0);
+        data = &padded;
+    }
+    AdminDeliveryOrderRecord::try_deserialize(&mut data)
+        .map_err(|_| error!(BoxMinterError::InvalidAdminOrder))
+}
+
+/// Receipts of an admin order that land in the tree after the active one (`active_tree_leaves`
+/// fit in the active tree), with that tree and its first leaf (its `num_minted` before minting).
+fn admin_order_receipt_rollover(
+    quantity: u8,
+    active_tree_leaves: u64,
+    next_tree: Option<(Pubkey, u64)>,
+) -> Result<(u8, Pubkey, u64)> {
+    let rollover = u64::from(quantity).saturating_sub(active_tree_leaves);
+    if rollover == 0 {
+        return Ok((0, Pubkey::default(), 0));
+    }
+    let (merkle_tree, first_leaf_index) =
+        next_tree.ok_or(error!(BoxMinterError::ReceiptsTreeNotActive))?;
+    Ok((rollover as u8, merkle_tree, first_leaf_index))
+}
+
+fn validate_admin_order_pda(
+    program_id: &Pubkey,
+    config_key: &Pubkey,
+    order_hash: &[u8; 32],
+    admin_order_key: Pubkey,
+) -> Result<u8> {
+    let (expected_order, canonical_bump) = Pubkey::find_program_address(
+        &[SEED_ADMIN_ORDER, config_key.as_ref(), order_hash.as_ref()],
+        program_id,
+    );
+    require_keys_eq!(
+        admin_order_key,
+        expected_order,
+        BoxMinterError::InvalidAdminOrderPda
+    );
+    Ok(canonical_bump)
+}
+
+fn reserve_variant_metadata_ids(
+    cfg: &mut BoxMinterConfig,
+    variant_index: u8,
+    quantity: u8,
+) -> Result<u32> {
+    require!(quantity >= 1, BoxMinterError::InvalidQuantity);
+    let new_total = cfg
+        .minted
+        .checked_add(u32::from(quantity))
+        .ok_or(BoxMinterError::MathOverflow)?;
+    require!(new_total <= cfg.max_supply, BoxMinterError::SoldOut);
+
+    let variant_slot = cfg.variant_slot(variant_index)?;
+    let first_metadata_id = cfg.next_variant_metadata_id(variant_slot)?;
+    let last_metadata_id = first_metadata_id
+        .checked_add(u32::from(quantity) - 1)
+        .ok_or(BoxMinterError::MathOverflow)?;
+    require!(
+        last_metadata_id <= cfg.mint_variant_end_ids[variant_slot],
+        BoxMinterError::MintVariantUnavailable
+    );
+
+    cfg.minted = new_total;
+    cfg.mint_variant_next_ids[variant_slot] = last_metadata_id
+        .checked_add(1)
+        .ok_or(BoxMinterError::MathOverflow)?;
+    Ok(first_metadata_id)
+}
+
+fn reserve_variant_table_metadata_ids(
+    cfg: &mut BoxMinterConfig,
+    table: &mut VariantTable,
+    variant_index: u8,
+    quantity: u8,
+) -> Result<u32> {
+    require!(quantity >= 1, BoxMinterError::InvalidQuantity);
+    let new_total = cfg
+        .minted
+        .checked_add(u32::from(quantity))
+        .ok_or(BoxMinterError::MathOverflow)?;
+    require!(new_total <= cfg.max_supply, BoxMinterError::SoldOut);
+
+    let option = table.option_mut(variant_index)?;
+    require!(!option.sold_out, BoxMinterError::MintVariantUnavailable);
+    require!(
+        option.next_id >= option.start_id,
+        BoxMinterError::InvalidMintVariantConfig
+    );
+    let first_metadata_id = option.next_id;
+    let last_metadata_id = first_metadata_id
+        .checked_add(u32::from(quantity) - 1)
+        .ok_or(BoxMinterError::MathOverflow)?;
+    require!(
+        last_metadata_id <= option.end_id,
+        BoxMinterError::MintVariantUnavailable
+    );
+
+    cfg.minted = new_total;
+    option.next_id = last_metadata_id
+        .checked_add(1)
+        .ok_or(BoxMinterError::MathOverflow)?;
+    // Flip the flag once the range is exhausted so clients can read availability directly.
+    if option.next_id > option.end_id {
+        option.sold_out = true;
+    }
+    Ok(first_metadata_id)
+}
+
+fn reserve_variant_selection_metadata_ids(
+    cfg: &mut BoxMinterConfig,
+    variant_table: Option<&mut VariantTable>,
+    variant_index: u8,
+    quantity: u8,
+) -> Result<u32> {
+    if cfg.uses_variant_table() {
+        let table = variant_table.ok_or(error!(BoxMinterError::VariantTableMissing))?;
+        reserve_variant_table_metadata_ids(cfg, table, variant_index, quantity)
+    } else {
+        reserve_variant_metadata_ids(cfg, variant_index, quantity)
+    }
+}
+
+fn variant_unit_price(
+    cfg: &BoxMinterConfig,
+    variant_table: Option<&VariantTable>,
+    variant_index: u8,
+    discounted: bool,
+) -> Result<u64> {
+    if !cfg.uses_variant_table() {
+        return Ok(if discounted {
+            cfg.discount_price_lamports
+        } else {
+            cfg.price_lamports
+        });
+    }
+    let table = variant_table.ok_or(error!(BoxMinterError::VariantTableMissing))?;
+    let option = table.option(variant_index)?;
+    Ok(if discounted {
+        option.discount_price_lamports
+    } else {
+        option.price_lamports
+    })
+}
+
+/// Moves variant boundaries to `new_end_ids`, touching only unminted tails.
+///
+/// Ranges stay contiguous from `1` to `max_supply`. A variant that has issued ids keeps its start
+/// and must still cover everything it issued; an untouched variant may also move its start.
+fn rebalanced_variant_ranges(
+    ranges: &[VariantRange],
+    new_end_ids: &[u32],
+    max_supply: u32,
+) -> Result<Vec<VariantRange>> {
+    require!(
+        !ranges.is_empty() && new_end_ids.len() == ranges.len(),
+        BoxMinterError::InvalidMintVariantConfig
+    );
+    require!(
+        new_end_ids[new_end_ids.len() - 1] == max_supply,
+        BoxMinterError::InvalidMintVariantConfig
+    );
+
+    let mut rebalanced = Vec::with_capacity(ranges.len());
+    let mut start_id = 1u32;
+    for (range, &end_id) in ranges.iter().zip(new_end_ids) {
+        require!(end_id >= start_id, BoxMinterError::InvalidMintVariantConfig);
+        let issued = range.next_id > range.start_id;
+        let next_id = if issued {
+            require!(
+                start_id == range.start_id && end_id >= range.next_id - 1,
+                BoxMinterError::VariantRangeOverlapsIssuedIds
+            );
+            range.next_id
+        } else {
+            start_id
+        };
+        rebalanced.push(VariantRange {
+            start_id,
+            end_id,
+            next_id,
+        });
+        start_id = end_id.checked_add(1).ok_or(BoxMinterError::MathOverflow)?;
+    }
+    Ok(rebalanced)
+}
+
+/// Sold-out flag for `option` once moved to `range`: exhaustion is re-derived from the issued
+/// ids against the new bounds, while an admin pause on a range that still had ids stays.
+fn rebalanced_sold_out(option: &VariantOption, range: &VariantRange) -> bool {
+    let paused = option.sold_out && option.next_id <= option.end_id;
+    paused || range.next_id > range.end_id
+}
+
+fn validate_variant_table_options(
+    cfg: &BoxMinterConfig,
+    options: &[VariantOptionArgs],
+) -> Result<()> {
+    require!(
+        cfg.uses_variant_table(),
+        BoxMinterError::MintVariantSelectionRequired
+    );
+    require!(
+        !options.is_empty() && options.len() <= MAX_VARIANT_TABLE_OPTIONS,
+        BoxMinterError::InvalidMintVariantConfig
+    );
+    let mut expected_start_id = 1u32;
+    for option in options {
+        require!(
+            !option.label.is_empty() && option.label.len() <= VariantOption::MAX_LABEL,
+            BoxMinterError::InvalidMintVariantConfig
+        );
+        require!(
+            option.start_id == expected_start_id && option.end_id >= option.start_id,
+            BoxMinterError::InvalidMintVariantConfig
+        );
+        validate_mint_prices(option.price_lamports, option.discount_price_lamports)?;
+        expected_start_id = option
+            .end_id
+            .checked_add(1)
+            .ok_or(BoxMinterError::MathOverflow)?;
+    }
+    require!(
+        expected_start_id - 1 == cfg.max_supply,
+        BoxMinterError::InvalidMintVariantConfig
+    );
+
+    let ranges: Vec<VariantRange> = options
+        .iter()
+        .map(|option| VariantRange {
+            start_id: option.start_id,
+            end_id: option.end_id,
+            next_id: option.start_id,
+        })
+        .collect();
+    let settings: Vec<BoxOpenSettings> = options
+        .iter()
+        .map(|option| BoxOpenSettings {
+            items_per_box: option.items_per_box,
+            figure_start_id: option.figure_start_id,
+            figure_end_id: option.figure_end_id,
+        })
+        .collect();
+    validate_variant_open_settings(cfg, &ranges, &settings)
+}
+
+fn reserve_standard_metadata_ids(cfg: &mut BoxMinterConfig, quantity: u8) -> Result<u32> {
+    require!(quantity >= 1, BoxMinterError::InvalidQuantity);
+    let first_metadata_id = cfg
+        .minted
+        .checked_add(1)
+        .ok_or(BoxMinterError::MathOverflow)?;
+    let new_total = cfg
+        .minted
+        .checked_add(u32::from(quantity))
+        .ok_or(BoxMinterError::MathOverflow)?;
+    require!(new_total <= cfg.max_supply, BoxMinterError::SoldOut);
+
+    cfg.minted = new_total;
+    Ok(first_metadata_id)
+}
+
+fn admin_delivery_effective_variant_index(cfg: &BoxMinterConfig, variant_index: u8) -> u8 {
+    if cfg.requires_variant_selection() {
+        variant_index
+    } else {
+        0
+    }
+}
+
+fn reserve_admin_delivery_metadata_ids(
+    cfg: &mut BoxMinterConfig,
+    variant_table: Option<&mut VariantTable>,
+    variant_index: u8,
+    quantity: u8,
+) -> Result<u32> {
+    require!(quantity >= 1, BoxMinterError::InvalidQuantity);
+    require!(cfg.started, BoxMinterError::MintNotStarted);
+    let max_qty = cfg.max_per_tx.min(MAX_SAFE_MINTS_PER_TX);
+    require!(quantity <= max_qty, BoxMinterError::InvalidQuantity);
+
+    if cfg.requires_variant_selection() {
+        reserve_variant_selection_metadata_ids(cfg, variant_table, variant_index, quantity)
+    } else {
+        reserve_standard_metadata_ids(cfg, quantity)
+    }
+}
+
+fn initialize_box_minter_config(
+    cfg: &mut Account<BoxMinterConfig>,
+    admin: Pubkey,
+    treasury: Pubkey,
+    core_collection_ai: &AccountInfo,
+    bump: u8,
+    args: InitializeArgs,
+) -> Result<()> {
+    require_keys_eq!(
+        admin,
+        EXPECTED_INITIALIZER,
+        BoxMinterError::UnauthorizedInitializer
+    );
+    require!(args.max_supply > 0, BoxMinterError::InvalidMaxSupply);
+    require!(args.max_per_tx > 0, BoxMinterError::InvalidMaxPerTx);
+    require!(
+        args.max_per_tx <= MAX_SAFE_MINTS_PER_TX,
+        BoxMinterError::InvalidMaxPerTx
+    );
+    require!(
+        args.items_per_box <= MAX_ITEMS_PER_BOX,
+        BoxMinterError::InvalidItemsPerBox
+    );
+    let max_figure_id = (args.max_supply as u64)
+        .checked_mul(args.items_per_box as u64)
+        .ok_or(BoxMinterError::MathOverflow)?;
+    require!(
+        max_figure_id <= u16::MAX as u64,
+        BoxMinterError::InvalidItemsPerBox
+    );
+    validate_mint_prices(args.price_lamports, args.discount_price_lamports)?;
+    require!(
+        args.mint_variant_kind == MINT_VARIANT_KIND_NONE
+            || args.mint_variant_kind == MINT_VARIANT_KIND_SIZE
+            || args.mint_variant_kind == MINT_VARIANT_KIND_TABLE,
+        BoxMinterError::InvalidMintVariantConfig
+    );
+    require!(
+        args.discount_merkle_root != [0u8; 32],
+        BoxMinterError::DiscountNotConfigured
+    );
+    require!(
+        args.discount_mints_per_wallet >= MIN_DISCOUNT_MINTS_PER_WALLET
+            && args.discount_mints_per_wallet <= MAX_DISCOUNT_MINTS_PER_WALLET,
+        BoxMinterError::InvalidDiscountMintsPerWallet
+    );
+    require!(
+        args.name_prefix.len() <= BoxMinterConfig::MAX_NAME_PREFIX,
+        BoxMinterError::NameTooLong
+    );
+    require!(
+        args.figure_name_prefix.len() <= BoxMinterConfig::MAX_FIGURE_NAME_PREFIX,
+        BoxMinterError::FigureNameTooLong
+    );
+    require!(
+        args.symbol.len() <= BoxMinterConfig::MAX_SYMBOL,
+        BoxMinterError::SymbolTooLong
+    );
+    require!(
+        args.uri_base.len() <= BoxMinterConfig::MAX_URI_BASE,
+        BoxMinterError::UriTooLong
+    );
+    require!(
+        has_any_non_zero_byte(args.drop_seed.as_ref()),
+        BoxMinterError::InvalidDropSeed
+    );
+    let drop_base = normalized_metadata_base(&args.uri_base)?;
+    if args.mint_variant_kind == MINT_VARIANT_KIND_SIZE {
+        // Size drops are direct-delivery only; openable variant drops use a `VariantTable`.
+        require!(
+            args.items_per_box == 0,
+            BoxMinterError::MintVariantDirectDeliveryOnly
+        );
+        require!(
+            args.max_supply < u32::MAX,
+            BoxMinterError::InvalidMintVariantConfig
+        );
+        for i in 0..MINT_VARIANT_OPTION_COUNT {
+            let start_id = args.mint_variant_start_ids[i];
+            let end_id = args.mint_variant_end_ids[i];
+            let next_id = args.mint_variant_next_ids[i];
+            require!(start_id >= 1, BoxMinterError::InvalidMintVariantConfig);
+            require!(end_id >= start_id, BoxMinterError::InvalidMintVariantConfig);
+            require!(
+                next_id == start_id,
+                BoxMinterError::InvalidMintVariantConfig
+            );
+            if i == 0 {
+                require!(start_id == 1, BoxMinterError::InvalidMintVariantConfig);
+            } else {
+                require!(
+                    start_id
+                        == args.mint_variant_end_ids[i - 1]
+                            .checked_add(1)
+                            .ok_or(BoxMinterError::MathOverflow)?,
+                    BoxMinterError::InvalidMintVariantConfig
+                );
+            }
+        }
+        require!(
+            args.mint_variant_end_ids[MINT_VARIANT_OPTION_COUNT - 1] == args.max_supply,
+            BoxMinterError::InvalidMintVariantConfig
+        );
+    } else {
+        if args.mint_variant_kind == MINT_VARIANT_KIND_TABLE {
+            require!(
+                args.max_supply < u32::MAX,
+                BoxMinterError::InvalidMintVariantConfig
+            );
+        }
+        // Table variant ranges live in the `VariantTable`; the legacy config arrays stay empty.
+        require!(
+            args.mint_variant_start_ids == [0; MINT_VARIANT_OPTION_COUNT]
+                && args.mint_variant_end_ids == [0; MINT_VARIANT_OPTION_COUNT]
+                && args.mint_variant_next_ids == [0; MINT_VARIANT_OPTION_COUNT],
+            BoxMinterError::InvalidMintVariantConfig
+        );
+    }
+
+    require!(
+        core_collection_ai.key() != Pubkey::default(),
+        BoxMinterError::InvalidCoreCollection
+    );
+    require_keys_eq!(
+        *core_collection_ai.owner,
+        MPL_CORE_PROGRAM_ID,
+        BoxMinterError::InvalidCoreCollection
+    );
+
+    cfg.admin = admin;
+    cfg.treasury = treasury;
+    cfg.core_collection = core_collection_ai.key();
+    cfg.price_lamports = args.price_lamports;
+    cfg.discount_price_lamports = args.discount_price_lamports;
+    cfg.discount_merkle_root = args.discount_merkle_root;
+    cfg.max_supply = args.max_supply;
+    cfg.max_per_tx = args.max_per_tx;
+    cfg.items_per_box = args.items_per_box;
+    cfg.discount_mints_per_wallet = args.discount_mints_per_wallet;
+    cfg.started = false;
+    cfg.minted = 0;
+    cfg.name_prefix = args.name_prefix;
+    cfg.symbol = args.symbol;
+    cfg.uri_base = drop_base.to_string();
+    cfg.bump = bump;
+    cfg.figure_name_prefix = args.figure_name_prefix;
+    cfg.mint_variant_kind = args.mint_variant_kind;
+    cfg.mint_variant_start_ids = args.mint_variant_start_ids;
+    cfg.mint_variant_end_ids = args.mint_variant_end_ids;
+    cfg.mint_variant_next_ids = args.mint_variant_next_ids;
+    cfg.drop_seed = args.drop_seed;
+    Ok(())
+}
+
+#[program]
+pub mod box_minter {
+    use super::*;
+
+    pub fn initialize(ctx: Context<Initialize>, args: InitializeArgs) -> Result<()> {
+        let core_collection_ai = ctx.accounts.core_collection.to_account_info();
+        initialize_box_minter_config(
+            &mut ctx.accounts.config,
+            ctx.accounts.admin.key(),
+            ctx.accounts.treasury.key(),
+            &core_collection_ai,
+            ctx.bumps.config,
+            args,
+        )
+    }
+
+    pub fn initialize_split_payments_v1(
+        ctx: Context<Initialize>,
+        args: InitializeArgs,
+        split_args: SplitPaymentsV1Args,
+    ) -> Result<()> {
+        if ctx.accounts.treasury.key() == Pubkey::default() {
+            return Err(BoxMinterError::InvalidDeliveryReceiver.into());
+        }
+        let split = split_payments_v1_from_args(&split_args);
+        validate_split_payments_v1(&split)?;
+        let core_collection_ai = ctx.accounts.core_collection.to_account_info();
+        initialize_box_minter_config(
+            &mut ctx.accounts.config,
+            ctx.accounts.admin.key(),
+            ctx.accounts.treasury.key(),
+            &core_collection_ai,
+            ctx.bumps.config,
+            args,
+        )?;
+        resize_split_payments_v1_config(
+            &ctx.accounts.config,
+            &ctx.accounts.admin.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+        write_split_payments_v1_config(&ctx.accounts.config, &split)
+    }
+
+    pub fn split_payments_v1_capability(_ctx: Context<SplitPaymentsV1Capability>) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn set_treasury(ctx: Context<SetTreasury>, treasury: Pubkey) -> Result<()> {
+        if treasury == Pubkey::default()
+            && ctx.accounts.config.to_account_info().data_len() == SPLIT_PAYMENTS_V1_CONFIG_SPACE
+        {
+            return Err(BoxMinterError::InvalidDeliveryReceiver.into());
+        }
+        let cfg = &mut ctx.accounts.config;
+        cfg.treasury = treasury;
+        Ok(())
+    }
+
+    pub fn set_mint_prices(
+        ctx: Context<SetMintPrices>,
+        price_lamports: u64,
+        discount_price_lamports: u64,
+    ) -> Result<()> {
+        validate_mint_prices(price_lamports, discount_price_lamports)?;
+
+        let cfg = &mut ctx.accounts.config;
+        cfg.price_lamports = price_lamports;
+        cfg.discount_price_lamports = discount_price_lamports;
+        Ok(())
+    }
+
+    pub fn set_uri_base(ctx: Context<SetUriBase>, uri_base: String) -> Result<()> {
+        let normalized = normalized_metadata_base(&uri_base)?;
+        ctx.accounts.config.uri_base = normalized.to_string();
+        Ok(())
+    }
+
+    /// Creates the variant table for a `MINT_VARIANT_KIND_TABLE` drop.
+    ///
+    /// Options must tile `1..=max_supply` contiguously. On openable drops each option also sets
+    /// how its boxes open (figures per box and figure id pool). Prices and the sold-out flag stay
+    /// editable via `set_variant_option`; ranges move only through `rebalance_variant_ranges`, and
+    /// open settings are fixed once minting starts.
+    pub fn initialize_variant_table(
+        ctx: Context<InitializeVariantTable>,
+        args: InitializeVariantTableArgs,
+    ) -> Result<()> {
+        let cfg = &ctx.accounts.config;
+        require!(!cfg.started, BoxMinterError::MintAlreadyStarted);
+        validate_variant_table_options(cfg, &args.options)?;
+
+        let variant_table = &mut ctx.accounts.variant_table;
+        variant_table.config = cfg.key();
+        variant_table.options = args
+            .options
+            .into_iter()
+            .map(|option| VariantOption {
+                label: option.label,
+                start_id: option.start_id,
+                end_id: option.end_id,
+                next_id: option.start_id,
+                price_lamports: option.price_lamports,
+                discount_price_lamports: option.discount_price_lamports,
+                sold_out: false,
+                items_per_box: option.items_per_box,
+                figure_start_id: option.figure_start_id,
+                figure_end_id: option.figure_end_id,
+            })
+            .collect();
+        variant_table.bump = ctx.bumps.variant_table;
+        Ok(())
+    }
+
+    /// Moves unsold capacity between neighbouring variants by setting new inclusive end ids.
+    ///
+    /// Legacy size drops update the config arrays; table drops update `variant_table`, re-checking
+    /// figure pools against the new ranges on openable drops.
+    pub fn rebalance_variant_ranges(
+        ctx: Context<RebalanceVariantRanges>,
+        end_ids: Vec<u32>,
+    ) -> Result<()> {
+        let cfg = &mut ctx.accounts.config;
+        require!(
+            cfg.requires_variant_selection(),
+            BoxMinterError::MintVariantSelectionRequired
+        );
+
+        if cfg.uses_variant_table() {
+            let table = ctx
+                .accounts
+                .variant_table
+                .as_deref_mut()
+                .ok_or(error!(BoxMinterError::VariantTableMissing))?;
+            let ranges: Vec<VariantRange> = table
+                .options
+                .iter()
+                .map(|option| VariantRange {
+                    start_id: option.start_id,
+                    end_id: option.end_id,
+                    next_id: option.next_id,
+                })
+                .collect();
+            let rebalanced = rebalanced_variant_ranges(&ranges, &end_ids, cfg.max_supply)?;
+            let settings: Vec<BoxOpenSettings> = table
+                .options
+                .iter()
+                .map(VariantOption::open_settings)
+                .collect();
+            validate_variant_open_settings(cfg, &rebalanced, &settings)?;
+            for (option, range) in table.options.iter_mut().zip(rebalanced) {
+                option.sold_out = rebalanced_sold_out(option, &range);
+                option.start_id = range.start_id;
+                option.end_id = range.end_id;
+                option.next_id = range.next_id;
+            }
+            return Ok(());
+        }
+
+        let ranges: Vec<VariantRange> = (0..MINT_VARIANT_OPTION_COUNT)
+            .map(|slot| VariantRange {
+                start_id: cfg.mint_variant_start_ids[slot],
+                end_id: cfg.mint_variant_end_ids[slot],
+                next_id: cfg.mint_variant_next_ids[slot],
+            })
+            .collect();
+        let rebalanced = rebalanced_variant_ranges(&ranges, &end_ids, cfg.max_supply)?;
+        for (slot, range) in rebalanced.into_iter().enumerate() {
+            cfg.mint_variant_start_ids[slot] = range.start_id;
+            cfg.mint_variant_end_ids[slot] = range.end_id;
+            cfg.mint_variant_next_ids[slot] = range.next_id;
+        }
+        Ok(())
+    }
+
+    /// Updates one variant's prices and sold-out flag.
+    pub fn set_variant_option(
+        ctx: Context<SetVariantOption>,
+        args: SetVariantOptionArgs,
+    ) -> Result<()> {
+        validate_mint_prices(args.price_lamports, args.discount_price_lamports)?;
+
+        let option = ctx.accounts.variant_table.option_mut(args.variant_index)?;
+        option.price_lamports = args.price_lamports;
+        option.discount_price_lamports = args.discount_price_lamports;
+        option.sold_out = args.sold_out;
+        Ok(())
+    }
+
+    /// Creates the drop's royalty settings, written as an MPL-Core Royalties plugin on every box
+    /// minted and every figure placeholder created afterwards.
+    pub fn initialize_drop_royalties(
+        ctx: Context<InitializeDropRoyalties>,
+        args: DropRoyaltiesArgs,
+    ) -> Result<()> {
+        validate_drop_royalties(&args)?;
+
+        let royalties = &mut ctx.accounts.royalties;
+        royalties.config = ctx.accounts.config.key();
+        royalties.basis_points = args.basis_points;
+        royalties.creators = args.creators;
+        royalties.rule_set = args.rule_set;
+        royalties.bump = ctx.bumps.royalties;
+        Ok(())
+    }
+
+    /// Replaces the drop's royalty settings. Assets that already exist keep the plugin they were
+    /// created with.
+    pub fn set_drop_royalties(
+        ctx: Context<SetDropRoyalties>,
+        args: DropRoyaltiesArgs,
+    ) -> Result<()> {
+        validate_drop_royalties(&args)?;
+
+        let royalties = &mut ctx.accounts.royalties;
+        royalties.basis_points = args.basis_points;
+        royalties.creators = args.creators;
+        royalties.rule_set = args.rule_set;
+        Ok(())
+    }
+
+    /// Creates the drop's receipt ledger: one bit per box id and per figure id, set once a receipt
+    /// for that id has been minted. Only the header is allocated here; `grow_receipt_ledger`
+    /// allocates the bitmaps, and receipts can be minted once it has allocated all of them.
+    pub fn initialize_receipt_ledger(ctx: Context<InitializeReceiptLedger>) -> Result<()> {
+        let cfg = &ctx.accounts.config;
+        let max_figure_id = cfg.max_figure_id()?;
+        let receipt_ledger = &mut ctx.accounts.receipt_ledger;
+        receipt_ledger.config = cfg.key();
+        receipt_ledger.box_bits_len = ReceiptLedger::bitmap_len(cfg.max_supply) as u32;
+        receipt_ledger.figure_bits_len = ReceiptLedger::bitmap_len(u32::from(max_figure_id)) as u32;
+        receipt_ledger.bump = ctx.bumps.receipt_ledger;
+        Ok(())
+    }
+
+    /// Allocates up to another 10KB of the receipt ledger's bitmaps (the most an account can
+    /// grow by in one instruction); repeat until the ledger reaches `ReceiptLedger::space`.
+    pub fn grow_receipt_ledger(ctx: Context<GrowReceiptLedger>) -> Result<()> {
+        let ledger_ai = ctx.accounts.receipt_ledger.to_account_info();
+        let space = ctx.accounts.receipt_ledger.space();
+        let len = ledger_ai.data_len();
+        if len >= space {
+            return Ok(());
+        }
+        grow_program_account(
+            &ledger_ai,
+            space.min(len + MAX_PERMITTED_DATA_INCREASE),
+            &ctx.accounts.admin.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )
+    }
+
+    /// Creates the drop's (empty) receipt tree registry; trees are appended by
+    /// `create_receipts_tree`.
+    pub fn initialize_receipt_trees(ctx: Context<InitializeReceiptTrees>) -> Result<()> {
+        let receipt_trees = &mut ctx.accounts.receipt_trees;
+        receipt_trees.config = ctx.accounts.config.key();
+        receipt_trees.active_index = 0;
+        receipt_trees.trees = Vec::new();
+        receipt_trees.bump = ctx.bumps.receipt_trees;
+        Ok(())
+    }
+
+    /// Creates a Bubblegum v2 receipts tree owned by the program: the config PDA is tree creator
+    /// (and therefore tree delegate), so receipt mints into it are signed by the program. The
+    /// tree is appended to the drop's registry and used once the trees before it are full.
+    ///
+    /// `merkle_tree` must already be allocated by the client (owned by the account compression
+    /// program and sized for `max_depth`/`max_buffer_size`).
+    pub fn create_receipts_tree(
+        ctx: Context<CreateReceiptsTree>,
+        args: CreateReceiptsTreeArgs,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.receipt_trees.trees.len() < MAX_RECEIPT_TREES,
+            BoxMinterError::ReceiptTreesFull
+        );
+        require!(
+            ctx.accounts
+                .receipt_trees
+                .trees
+                .iter()
+                .all(|tree| tree.merkle_tree != ctx.accounts.merkle_tree.key()),
+            BoxMinterError::InvalidReceiptsMerkleTree
+        );
+        require_keys_eq!(
+            *ctx.accounts.merkle_tree.owner,
+            MPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
+            BoxMinterError::InvalidReceiptsMerkleTree
+        );
+        let (expected_tree_config, _) = Pubkey::find_program_address(
+            &[ctx.accounts.merkle_tree.key().as_ref()],
+            &BUBBLEGUM_PROGRAM_ID,
+        );
+        require_keys_eq!(
+            ctx.accounts.tree_config.key(),
+            expected_tree_config,
+            BoxMinterError::InvalidReceiptsTreeConfig
+        );
+
+        let cfg = &ctx.accounts.config;
+        let data = create_receipts_tree_v2_data(&args);
+        let ix = Instruction {
+            program_id: BUBBLEGUM_PROGRAM_ID,
+            accounts: vec![
+                // createTreeV2 accounts order (kinobi):
+                AccountMeta::new(ctx.accounts.tree_config.key(), false),
+                AccountMeta::new(ctx.accounts.merkle_tree.key(), false),
+                AccountMeta::new(ctx.accounts.admin.key(), true), // payer
+                AccountMeta::new_readonly(cfg.key(), true),       // treeCreator: config PDA
+                AccountMeta::new_readonly(MPL_NOOP_PROGRAM_ID, false),
+                AccountMeta::new_readonly(MPL_ACCOUNT_COMPRESSION_PROGRAM_ID, false),
+                AccountMeta::new_readonly(ctx.accounts.system_program.key(), false),
+            ],
+            data,
+        };
+        let cfg_bump_bytes = [cfg.bump];
+        let cfg_signer_seeds: &[&[u8]] = &[
+            BoxMinterConfig::SEED,
+            cfg.drop_seed.as_ref(),
+            &cfg_bump_bytes,
+        ];
+        invoke_signed(
+            &ix,
+            &[
+                ctx.accounts.tree_config.to_account_info(),
+                ctx.accounts.merkle_tree.to_account_info(),
+                ctx.accounts.admin.to_account_info(),
+                cfg.to_account_info(),
+                ctx.accounts.log_wrapper.to_account_info(),
+                ctx.accounts.compression_program.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                ctx.accounts.bubblegum_program.to_account_info(),
+            ],
+            &[cfg_signer_seeds],
+        )?;
+
+        // Bubblegum sizes the tree config from the tree it actually initialized.
+        let capacity = read_tree_config_u64(
+            &ctx.accounts.tree_config,
+            BUBBLEGUM_TREE_CONFIG_TOTAL_MINT_CAPACITY_OFFSET,
+        )?;
+        require!(capacity > 0, BoxMinterError::InvalidReceiptsTreeConfig);
+        let merkle_tree = ctx.accounts.merkle_tree.key();
+        ctx.accounts.receipt_trees.trees.push(ReceiptTree {
+            merkle_tree,
+            capacity,
+            minted: 0,
+        });
+        Ok(())
+    }
+
+    /// Creates the drop's delivery fee schedule. Deliveries that pass it (with a zone) are charged
+    /// the zone's fee for their item count instead of a cosigner-chosen fee.
+    pub fn initialize_delivery_fee_schedule(
+        ctx: Context<InitializeDeliveryFeeSchedule>,
+        args: DeliveryFeeScheduleArgs,
+    ) -> Result<()> {
+        validate_delivery_fee_schedule(&args)?;
+
+        let delivery_fees = &mut ctx.accounts.delivery_fees;
+        delivery_fees.config = ctx.accounts.config.key();
+        delivery_fees.zones = args.zones;
+        delivery_fees.bump = ctx.bumps.delivery_fees;
+        Ok(())
+    }
+
+    /// Replaces the drop's delivery fee zones. Existing delivery records keep the fee they paid.
+    pub fn set_delivery_fee_schedule(
+        ctx: Context<SetDeliveryFeeSchedule>,
+        args: DeliveryFeeScheduleArgs,
+    ) -> Result<()> {
+        validate_delivery_fee_schedule(&args)?;
+
+        ctx.accounts.delivery_fees.zones = args.zones;
+        Ok(())
+    }
+
+    /// Creates the drop's delivery fee route. Without it (or with `Treasury`) delivery fees go to
+    /// `config.treasury`.
+    pub fn initialize_delivery_fee_routing(
+        ctx: Context<InitializeDeliveryFeeRouting>,
+        route: DeliveryFeeRoute,
+    ) -> Result<()> {
+        validate_delivery_fee_route(&ctx.accounts.config, &route)?;
+
+        let routing = &mut ctx.accounts.delivery_fee_routing;
+        routing.config = ctx.accounts.config.key();
+        routing.route = route;
+        routing.bump = ctx.bumps.delivery_fee_routing;
+        Ok(())
+    }
+
+    pub fn set_delivery_fee_routing(
+        ctx: Context<SetDeliveryFeeRouting>,
+        route: DeliveryFeeRoute,
+    ) -> Result<()> {
+        validate_delivery_fee_route(&ctx.accounts.config, &route)?;
+
+        ctx.accounts.delivery_fee_routing.route = route;
+        Ok(())
+    }
+
+    /// Creates the drop's custody vault. From then on opened boxes and delivered items go to the
+    /// vault PDA instead of `config.admin`, and the program signs their transfers and burns; assets
+    /// already held by the admin stay there.
+    pub fn initialize_custody_vault(ctx: Context<InitializeCustodyVault>) -> Result<()> {
+        let custody_vault = &mut ctx.accounts.custody_vault;
+        custody_vault.config = ctx.accounts.config.key();
+        custody_vault.bump = ctx.bumps.custody_vault;
+        Ok(())
+    }
+
+    pub fn start_mint(ctx: Context<StartMint>) -> Result<()> {
+        let cfg = &mut ctx.accounts.config;
+        cfg.started = true;
+        Ok(())
+    }
+
+    pub fn mint_boxes<'a, 'b, 'c, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, MintBoxes<'info>>,
+        quantity: u8,
+        // Client-chosen mint id used only for PDA derivation (prevents stale-PDA failures under high concurrency).
+        // Must be random/unique per attempted mint transaction.
+        mint_id: u64,
+        // PDA bumps for each box asset PDA, in the same order as `remaining_accounts`.
+        // Passed in from the client to avoid `find_program_address` compute inside the program.
+        box_bumps: Vec<u8>,
+    ) -> Result<()> {
+        let accounts = MintBoxesInnerAccounts::from_mint_boxes(ctx.accounts, ctx.program_id)?;
+        let unit_price_lamports = ctx.accounts.config.price_lamports;
+        mint_standard_boxes_inner(
+            &mut ctx.accounts.config,
+            &accounts,
+            ctx.remaining_accounts,
+            quantity,
+            mint_id,
+            box_bumps,
+            ctx.program_id,
+            unit_price_lamports,
+        )
+    }
+
+    pub fn mint_variant_box<'a, 'b, 'c, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, MintVariantBox<'info>>,
+        variant_index: u8,
+        mint_id: u64,
+        box_bump: u8,
+    ) -> Result<()> {
+        let accounts = MintBoxesInnerAccounts::from_mint_variant_box(ctx.accounts, ctx.program_id)?;
+        let selection = VariantMintSelection {
+            variant_index,
+            discounted: false,
+            variant_table: ctx.accounts.variant_table.as_deref_mut(),
+        };
+        mint_variant_box_inner(
+            &mut ctx.accounts.config,
+            &accounts,
+            ctx.remaining_accounts,
+            selection,
+            mint_id,
+            box_bump,
+            ctx.program_id,
+        )
+    }
+
+    pub fn mint_discounted_box<'a, 'b, 'c, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, MintDiscountedBox<'info>>,
+        mint_id: u64,
+        box_bumps: Vec<u8>,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        let discount_price = ctx.accounts.config.discount_price_lamports;
+        require!(discount_price > 0, BoxMinterError::InvalidDiscountPrice);
+        let discount_root = ctx.accounts.config.discount_merkle_root;
+        require!(
+            discount_root != [0u8; 32],
+            BoxMinterError::DiscountNotConfigured
+        );
+
+        let payer_key = ctx.accounts.payer.key();
+        require!(
+            verify_merkle_proof(payer_key.as_ref(), &proof, discount_root),
+            BoxMinterError::InvalidDiscountProof
+        );
+        let quantity =
+            u8::try_from(box_bumps.len()).map_err(|_| error!(BoxMinterError::InvalidQuantity))?;
+        let discount_limit = ctx.accounts.config.discount_mints_per_wallet;
+        resolve_and_validate_mint_remaining_accounts(
+            &ctx.accounts.config,
+            payer_key,
+            ctx.remaining_accounts,
+            mint_id,
+            &box_bumps,
+            ctx.program_id,
+        )?;
+        let discount_ai = ctx.accounts.discount_record.to_account_info();
+        let (mut discount_record, discount_bump) = load_or_create_discount_record(
+            ctx.accounts.config.key(),
+            payer_key,
+            ctx.accounts.payer.to_account_info(),
+            discount_ai.clone(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.program_id,
+        )?;
+        let new_discount_total = discount_record
+            .minted
+            .checked_add(quantity)
+            .ok_or(BoxMinterError::MathOverflow)?;
+        require!(
+            new_discount_total <= discount_limit,
+            BoxMinterError::DiscountAllowanceExceeded
+        );
+
+        let accounts = MintBoxesInnerAccounts::from_discounted_box(ctx.accounts, ctx.program_id)?;
+        mint_standard_boxes_inner(
+            &mut ctx.accounts.config,
+            &accounts,
+            ctx.remaining_accounts,
+            quantity,
+            mint_id,
+            box_bumps,
+            ctx.program_id,
+            discount_price,
+        )?;
+        discount_record.minted = new_discount_total;
+        discount_record.bump = discount_bump;
+        discount_record.try_serialize(&mut &mut discount_ai.data.borrow_mut()[..])?;
+        Ok(())
+    }
+
+    pub fn mint_discounted_variant_box<'a, 'b, 'c, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, MintDiscountedBox<'info>>,
+        variant_index: u8,
+        mint_id: u64,
+        box_bump: u8,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        let discount_price = ctx.accounts.config.discount_price_lamports;
+        require!(discount_price > 0, BoxMinterError::InvalidDiscountPrice);
+        let discount_root = ctx.accounts.config.discount_merkle_root;
+        require!(
+            discount_root != [0u8; 32],
+            BoxMinterError::DiscountNotConfigured
+        );
+
+        let payer_key = ctx.accounts.payer.key();
+        require!(
+            verify_merkle_proof(payer_key.as_ref(), &proof, discount_root),
+            BoxMinterError::InvalidDiscountProof
+        );
+        let discount_limit = ctx.accounts.config.discount_mints_per_wallet;
+        let asset_bumps = [box_bump];
+        resolve_and_validate_mint_remaining_accounts(
+            &ctx.accounts.config,
+            payer_key,
+            ctx.remaining_accounts,
+            mint_id,
+            &asset_bumps,
+            ctx.program_id,
+        )?;
+        let discount_ai = ctx.accounts.discount_record.to_account_info();
+        let (mut discount_record, discount_bump) = load_or_create_discount_record(
+            ctx.accounts.config.key(),
+            payer_key,
+            ctx.accounts.payer.to_account_info(),
+            discount_ai.clone(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.program_id,
+        )?;
+        let new_discount_total = discount_record
+            .minted
+            .checked_add(1)
+            .ok_or(BoxMinterError::MathOverflow)?;
+        require!(
+            new_discount_total <= discount_limit,
+            BoxMinterError::DiscountAllowanceExceeded
+        );
+
+        let accounts = MintBoxesInnerAccounts::from_discounted_box(ctx.accounts, ctx.program_id)?;
+        let selection = VariantMintSelection {
+            variant_index,
+            discounted: true,
+            variant_table: ctx.accounts.variant_table.as_deref_mut(),
+        };
+        mint_variant_box_inner(
+            &mut ctx.accounts.config,
+            &accounts,
+            ctx.remaining_accounts,
+            selection,
+            mint_id,
+            box_bump,
+            ctx.program_id,
+        )?;
+        discount_record.minted = new_discount_total;
+        discount_record.bump = discount_bump;
+        discount_record.try_serialize(&mut &mut discount_ai.data.borrow_mut()[..])?;
+        Ok(())
+    }
+
+    /// Starts a two-step box open flow.
+    ///
+    /// This instruction performs an MPL-Core `TransferV1` CPI that transfers `box_asset` from the
+    /// user to the drop's custody vault PDA (`config.admin` until it is initialized). This avoids
+    /// brittle reliance on instruction ordering (some wallets inject extra instructions like
+    /// Compute Budget).
+    ///
+    /// Side effects (all in this one transaction):
+    /// - creates a `PendingOpenBox` PDA keyed by the box asset pubkey
+    /// - mints `config.items_per_box` placeholder Core assets (empty metadata, no collection)
+    ///   owned by the same custody holder; openable variant drops use the box variant's item count
+    ///   instead
+    pub fn start_open_box<'a, 'b, 'c, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, StartOpenBox<'info>>,
+    ) -> Result<()> {
+        let cfg = &ctx.accounts.config;
+        cfg.require_openable()?;
+
+        require_keys_eq!(
+            ctx.accounts.mpl_core_program.key(),
+            MPL_CORE_PROGRAM_ID,
+            BoxMinterError::InvalidMplCoreProgram
+        );
+        require_keys_eq!(
+            ctx.accounts.log_wrapper.key(),
+            SPL_NOOP_PROGRAM_ID,
+            BoxMinterError::InvalidLogWrapper
+        );
+
+        let accounts = OpenBoxInnerAccounts::from_start_open_box(ctx.accounts, ctx.program_id)?;
+        let box_asset = ctx.accounts.box_asset.to_account_info();
+        // Defensive: ensure the provided asset is a Mons *box* owned by payer.
+        let (_, settings) = verify_openable_box(
+            cfg,
+            accounts.variant_table.as_ref(),
+            &box_asset,
+            accounts.payer.key(),
+        )?;
+        let box_accounts = StartOpenBoxAccounts {
+            box_asset: &box_asset,
+            pending: &ctx.accounts.pending.to_account_info(),
+            pending_bump: ctx.bumps.pending,
+            placeholders: ctx.remaining_accounts,
+        };
+        start_open_box_inner(cfg, &accounts, &box_accounts, &settings, ctx.program_id)
+    }
+
+    /// Batched variant of `start_open_box` for collectors opening several boxes at once.
+    ///
+    /// Remaining accounts are grouped per box, in the same order as `pending_bumps`:
+    /// `[box_asset, pending, placeholder_0, .., placeholder_{n - 1}]`, where `n` is the item count
+    /// of that box's variant (`config.items_per_box` for non-variant drops).
+    /// The number of boxes per transaction is capped by `BoxMinterConfig::max_open_boxes_per_tx`.
+    pub fn start_open_boxes<'a, 'b, 'c, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, StartOpenBoxes<'info>>,
+        // PDA bumps for each pending open record, in the same order as the box groups.
+        // Passed in from the client to avoid `find_program_address` compute inside the program.
+        pending_bumps: Vec<u8>,
+    ) -> Result<()> {
+        let cfg = &ctx.accounts.config;
+        cfg.require_openable()?;
+
+        let box_count = pending_bumps.len();
+        require!(
+            box_count >= 1 && box_count <= cfg.max_open_boxes_per_tx(),
+            BoxMinterError::InvalidQuantity
+        );
+
+        require_keys_eq!(
+            ctx.accounts.mpl_core_program.key(),
+            MPL_CORE_PROGRAM_ID,
+            BoxMinterError::InvalidMplCoreProgram
+        );
+        require_keys_eq!(
+            ctx.accounts.log_wrapper.key(),
+            SPL_NOOP_PROGRAM_ID,
+            BoxMinterError::InvalidLogWrapper
+        );
+
+        let accounts = OpenBoxInnerAccounts::from_start_open_boxes(ctx.accounts, ctx.program_id)?;
+        let mut remaining = ctx.remaining_accounts;
+        for pending_bump in pending_bumps.iter().copied() {
+            require!(
+                remaining.len() >= OPEN_BOX_GROUP_FIXED_ACCOUNTS,
+                BoxMinterError::InvalidRemainingAccounts
+            );
+            let box_asset = &remaining[0];
+            let pending = &remaining[1];
+            require!(
+                box_asset.is_writable && pending.is_writable,
+                BoxMinterError::InvalidRemainingAccounts
+            );
+            validate_pending_open_pda(
+                ctx.program_id,
+                box_asset.key(),
+                pending.key(),
+                pending_bump,
+            )?;
+            let (_, settings) = verify_openable_box(
+                cfg,
+                accounts.variant_table.as_ref(),
+                box_asset,
+                accounts.payer.key(),
+            )?;
+            let group_len = OPEN_BOX_GROUP_FIXED_ACCOUNTS + usize::from(settings.items_per_box);
+            require!(
+                remaining.len() >= group_len,
+                BoxMinterError::InvalidRemainingAccounts
+            );
+            let (group, rest) = remaining.split_at(group_len);
+            let box_accounts = StartOpenBoxAccounts {
+                box_asset,
+                pending,
+                pending_bump,
+                placeholders: &group[OPEN_BOX_GROUP_FIXED_ACCOUNTS..],
+            };
+            start_open_box_inner(cfg, &accounts, &box_accounts, &settings, ctx.program_id)?;
+            remaining = rest;
+        }
+        require!(
+            remaining.is_empty(),
+            BoxMinterError::InvalidRemainingAccounts
+        );
+        Ok(())
+    }
+
+    /// Finalizes a pending box open, admin-only.
+    ///
+    /// Performs in one transaction:
+    /// 1) burns the vault-owned box (reclaims rent)
+    /// 2) updates placeholder dudes with real IDs + moves them into the core collection
+    /// 3) transfers dudes to the user
+    /// 4) closes the pending record PDA
+    pub fn finalize_open_box<'a, 'b, 'c, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, FinalizeOpenBox<'info>>,
+        args: FinalizeOpenBoxArgs,
+    ) -> Result<()> {
+        let cfg = &ctx.accounts.config;
+        cfg.require_openable()?;
+
+        // Admin-only. The admin pays for the reveal and holds custody until the drop has a vault.
+        require_keys_eq!(
+            ctx.accounts.cosigner.key(),
+            cfg.admin,
+            BoxMinterError::InvalidCosigner
+        );
+
+        require_keys_eq!(
+            ctx.accounts.mpl_core_program.key(),
+            MPL_CORE_PROGRAM_ID,
+            BoxMinterError::InvalidMplCoreProgram
+        );
+        require_keys_eq!(
+            ctx.accounts.log_wrapper.key(),
+            SPL_NOOP_PROGRAM_ID,
+            BoxMinterError::InvalidLogWrapper
+        );
+
+        let accounts =
+            FinalizeOpenBoxInnerAccounts::from_finalize_open_box(ctx.accounts, ctx.program_id)?;
+        let mut buffers = new_finalize_open_box_buffers(cfg, &accounts);
+        let box_accounts = PendingOpenBoxAccounts {
+            box_asset: &ctx.accounts.box_asset.to_account_info(),
+            pending: &ctx.accounts.pending.to_account_info(),
+            user: &ctx.accounts.user.to_account_info(),
+            placeholders: ctx.remaining_accounts,
+        };
+        finalize_open_box_inner(
+            cfg,
+            &accounts,
+            &mut buffers,
+            &box_accounts,
+            &args.dude_ids,
+            ctx.program_id,
+        )
+    }
+
+    /// Batched variant of `finalize_open_box` for the reveal worker, admin-only.
+    ///
+    /// Remaining accounts are grouped per box, in the same order as `args.boxes`:
+    /// `[box_asset, pending, user, placeholder_0, .., placeholder_{n - 1}]`, where `n` is the
+    /// number of `dude_ids` in that entry.
+    /// All boxes must belong to this drop; the MPL-Core instruction buffers are shared across boxes.
+    pub fn finalize_open_boxes<'a, 'b, 'c, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, FinalizeOpenBoxes<'info>>,
+        args: FinalizeOpenBoxesArgs,
+    ) -> Result<()> {
+        let cfg = &ctx.accounts.config;
+        cfg.require_openable()?;
+
+        let box_count = args.boxes.len();
+        require!(
+            box_count >= 1 && box_count <= cfg.max_finalize_boxes_per_tx(),
+            BoxMinterError::InvalidQuantity
+        );
+
+        require_keys_eq!(
+            ctx.accounts.mpl_core_program.key(),
+            MPL_CORE_PROGRAM_ID,
+            BoxMinterError::InvalidMplCoreProgram
+        );
+        require_keys_eq!(
+            ctx.accounts.log_wrapper.key(),
+            SPL_NOOP_PROGRAM_ID,
+            BoxMinterError::InvalidLogWrapper
+        );
+
+        // The same figure id must never be revealed twice, even across boxes in one batch.
+        for i in 0..box_count {
+            for j in (i + 1)..box_count {
+                for id in args.boxes[i].dude_ids.iter() {
+                    require!(
+                        !args.boxes[j].dude_ids.contains(id),
+                        BoxMinterError::DuplicateDudeId
+                    );
+                }
+            }
+        }
+
+        let expected_count = args.boxes.iter().try_fold(0usize, |total, entry| {
+            total
+                .checked_add(FINALIZE_BOX_GROUP_FIXED_ACCOUNTS + entry.dude_ids.len())
+                .ok_or(error!(BoxMinterError::MathOverflow))
+        })?;
+        require!(
+            ctx.remaining_accounts.len() == expected_count,
+            BoxMinterError::InvalidRemainingAccounts
+        );
+
+        let accounts =
+            FinalizeOpenBoxInnerAccounts::from_finalize_open_boxes(ctx.accounts, ctx.program_id)?;
+        let mut buffers = new_finalize_open_box_buffers(cfg, &accounts);
+        let mut remaining = ctx.remaining_accounts;
+        for entry in args.boxes.iter() {
+            let (group, rest) =
+                remaining.split_at(FINALIZE_BOX_GROUP_FIXED_ACCOUNTS + entry.dude_ids.len());
+            remaining = rest;
+            require!(
+                group[0].is_writable && group[1].is_writable,
+                BoxMinterError::InvalidRemainingAccounts
+            );
+            validate_pending_open_pda(
+                ctx.program_id,
+                group[0].key(),
+                group[1].key(),
+                entry.pending_bump,
+            )?;
+            let box_accounts = PendingOpenBoxAccounts {
+                box_asset: &group[0],
+                pending: &group[1],
+                user: &group[2],
+                placeholders: &group[FINALIZE_BOX_GROUP_FIXED_ACCOUNTS..],
+            };
+            finalize_open_box_inner(
+                cfg,
+                &accounts,
+                &mut buffers,
+                &box_accounts,
+                &entry.dude_ids,
+                ctx.program_id,
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn deliver<'a, 'b, 'c, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, Deliver<'info>>,
+        args: DeliverArgs,
+    ) -> Result<()> {
+        let (fees, assets) = resolve_delivery_fees(
+            &ctx.accounts.config,
+            &ctx.accounts.delivery_fees,
+            &ctx.accounts.delivery_fee_routing,
+            ctx.remaining_accounts,
+            ctx.program_id,
+        )?;
+        let accounts = DeliveryAccounts::from_deliver(ctx.accounts, ctx.program_id)?;
+        deliver_core_assets(
+            &accounts,
+            &ctx.accounts.config,
+            &args,
+            &fees,
+            assets,
+            ctx.program_id,
+            None,
+        )?;
+        Ok(())
+    }
+
+    /// Starts a delivery session for shipments too big for one transaction (same accounts and fee
+    /// rules as `deliver`): creates the record, takes the fee for all `item_count` items and moves
+    /// the first assets into the vault. The rest follow with `append_delivery_items` under the same
+    /// `delivery_id`, and `seal_delivery` closes the session.
+    pub fn begin_delivery<'a, 'b, 'c, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, Deliver<'info>>,
+        args: BeginDeliveryArgs,
+    ) -> Result<()> {
+        let (fees, assets) = resolve_delivery_fees(
+            &ctx.accounts.config,
+            &ctx.accounts.delivery_fees,
+            &ctx.accounts.delivery_fee_routing,
+            ctx.remaining_accounts,
+            ctx.program_id,
+        )?;
+        let accounts = DeliveryAccounts::from_deliver(ctx.accounts, ctx.program_id)?;
+        deliver_core_assets(
+            &accounts,
+            &ctx.accounts.config,
+            &args.delivery,
+            &fees,
+            assets,
+            ctx.program_id,
+            Some(args.item_count),
+        )?;
+        Ok(())
+    }
+
+    /// Moves more of an open session's assets (`remaining_accounts`, fee recipients not included)
+    /// into the vault (sessions never freeze in place). Takes the `deliver` accounts; the fee
+    /// schedule and route are not used.
+    pub fn append_delivery_items<'a, 'b, 'c, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, Deliver<'info>>,
+        args: AppendDeliveryItemsArgs,
+    ) -> Result<()> {
+        let accounts = DeliveryAccounts::from_deliver(ctx.accounts, ctx.program_id)?;
+        let cfg = &ctx.accounts.config;
+        validate_delivery_accounts(&accounts, cfg)?;
+        validate_delivery_pda(
+            accounts.delivery.key(),
+            &accounts.config_key,
+            args.delivery_id,
+            args.delivery_bump,
+            ctx.program_id,
+        )?;
+        let delivery_ai = accounts.delivery.clone();
+        require_keys_eq!(
+            *delivery_ai.owner,
+            *ctx.program_id,
+            BoxMinterError::InvalidDeliveryPda
+        );
+        let mut record = decode_delivery_record(&delivery_ai.try_borrow_data()?)?;
+        require_keys_eq!(
+            accounts.payer.key(),
+            record.payer,
+            BoxMinterError::InvalidDeliveryPayer
+        );
+        require!(
+            record.status == DeliveryStatus::Open,
+            BoxMinterError::DeliveryNotOpen
+        );
+
+        require_delivery_item_count(ctx.remaining_accounts.len())?;
+        let delivered = verify_delivered_assets(cfg, ctx.remaining_accounts, record.payer)?;
+        let item_count = record
+            .item_count
+            .checked_add(delivered.keys.len() as u16)
+            .ok_or(BoxMinterError::MathOverflow)?;
+        require!(
+            item_count <= record.paid_item_count,
+            BoxMinterError::InvalidDeliveryItemCount
+        );
+        record.item_count = item_count;
+        record.box_count = record
+            .box_count
+            .checked_add(delivered.box_count)
+            .ok_or(BoxMinterError::MathOverflow)?;
+        record.figure_count = record
+            .figure_count
+            .checked_add(delivered.figure_count)
+            .ok_or(BoxMinterError::MathOverflow)?;
+        record.assets_hash = extend_delivery_assets_hash(&record.assets_hash, &delivered.keys);
+        record.try_serialize(&mut &mut delivery_ai.data.borrow_mut()[..])?;
+
+        transfer_core_assets(
+            &accounts,
+            &accounts.payer,
+            &accounts.custody.owner,
+            ctx.remaining_accounts,
+        )
+    }
+
+    /// Ends a delivery session once every paid-for item is in the vault; the delivery then moves
+    /// through the usual statuses from `Paid`.
+    pub fn seal_delivery(ctx: Context<SealDelivery>, args: SealDeliveryArgs) -> Result<()> {
+        let delivery_ai = ctx.accounts.delivery.to_account_info();
+        require_keys_eq!(
+            *delivery_ai.owner,
+            *ctx.program_id,
+            BoxMinterError::InvalidDeliveryPda
+        );
+        let mut record = decode_delivery_record(&delivery_ai.try_borrow_data()?)?;
+        require!(
+            record.status == DeliveryStatus::Open,
+            BoxMinterError::DeliveryNotOpen
+        );
+        require!(
+            record.item_count == record.paid_item_count,
+            BoxMinterError::InvalidDeliveryItemCount
+        );
+        record.status = DeliveryStatus::Paid;
+        record.try_serialize(&mut &mut delivery_ai.data.borrow_mut()[..])?;
+
+        emit!(DeliveryStatusChanged {
+            config: ctx.accounts.config.key(),
+            delivery: delivery_ai.key(),
+            delivery_id: args.delivery_id,
+            from: DeliveryStatus::Open,
+            to: DeliveryStatus::Paid,
+            tracking_hash: record.tracking_hash,
+            slot: Clock::get()?.slot,
+        });
+        Ok(())
+    }
+
+    /// `deliver` without the cosigner in the transaction: the fee comes from a quote the cosigner
+    /// signed off-chain, verified through an Ed25519 program instruction placed immediately
+    /// before this one. The quote binds the config, delivery id, payer, fee, the ordered asset
+    /// keys, the address, zone, receipts and freeze mode, and an expiry slot at most
+    /// `MAX_DELIVERY_QUOTE_SLOTS` ahead; a delivery id can't be reused while its record exists.
+    /// Once the drop's fee schedule is initialized the quoted fee must also match the zone fee.
+    pub fn deliver_with_quote<'a, 'b, 'c, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, DeliverWithQuote<'info>>,
+        args: DeliverWithQuoteArgs,
+    ) -> Result<()> {
+        let slot = Clock::get()?.slot;
+        require!(
+            slot <= args.quote_expiry_slot,
+            BoxMinterError::DeliveryQuoteExpired
+        );
+        require!(
+            args.quote_expiry_slot - slot <= MAX_DELIVERY_QUOTE_SLOTS,
+            BoxMinterError::InvalidDeliveryQuote
+        );
+        let instructions = ctx.accounts.instructions.to_account_info();
+        let current_index = load_current_index_checked(&instructions)?;
+        require!(current_index > 0, BoxMinterError::InvalidDeliveryQuote);
+        let ed25519_ix =
+            load_instruction_at_checked(usize::from(current_index - 1), &instructions)?;
+        let (fees, assets) = resolve_delivery_fees(
+            &ctx.accounts.config,
+            &ctx.accounts.delivery_fees,
+            &ctx.accounts.delivery_fee_routing,
+            ctx.remaining_accounts,
+            ctx.program_id,
+        )?;
+        let asset_keys: Vec<Pubkey> = assets.iter().map(|ai| ai.key()).collect();
+        let message = delivery_quote_message(
+            &ctx.accounts.config.key(),
+            &ctx.accounts.payer.key(),
+            &args.delivery,
+            &delivery_assets_hash(&asset_keys),
+            args.quote_expiry_slot,
+        )?;
+        verify_ed25519_instruction(&ed25519_ix, &ctx.accounts.config.admin, &message)?;
+
+        let accounts = DeliveryAccounts::from_deliver_with_quote(ctx.accounts, ctx.program_id)?;
+        deliver_core_assets(
+            &accounts,
+            &ctx.accounts.config,
+            &args.delivery,
+            &fees,
+            assets,
+            ctx.program_id,
+            None,
+        )?;
+        Ok(())
+    }
+
+    /// `deliver` plus the matching receipts in one transaction: every delivered box (`/b`) or
+    /// figure (`/f`) asset earns a `/rb` or `/rf` receipt cNFT minted to `payer`, so a paid
+    /// delivery can never end up without receipts.
+    pub fn deliver_with_receipts<'a, 'b, 'c, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, DeliverWithReceipts<'info>>,
+        args: DeliverArgs,
+    ) -> Result<()> {
+        let (fees, assets) = resolve_delivery_fees(
+            &ctx.accounts.config,
+            &ctx.accounts.delivery_fees,
+            &ctx.accounts.delivery_fee_routing,
+            ctx.remaining_accounts,
+            ctx.program_id,
+        )?;
+        require!(
+            assets.len() <= usize::from(MAX_SAFE_DELIVERY_WITH_RECEIPTS_ITEMS_PER_TX),
+            BoxMinterError::InvalidQuantity
+        );
+
+        let delivery_accounts =
+            DeliveryAccounts::from_deliver_with_receipts(ctx.accounts, ctx.program_id)?;
+        let delivered = deliver_core_assets(
+            &delivery_accounts,
+            &ctx.accounts.config,
+            &args,
+            &fees,
+            assets,
+            ctx.program_id,
+            None,
+        )?;
+        let delivery_ai = ctx.accounts.delivery.to_account_info();
+        let mut record = decode_delivery_record(&delivery_ai.try_borrow_data()?)?;
+        record.receipts_issued = true;
+        record.try_serialize(&mut &mut delivery_ai.data.borrow_mut()[..])?;
+
+        let (mut box_ids, mut dude_ids) = split_receipt_ids(&delivered);
+
+        retain_unissued_receipts(
+            &ctx.accounts.receipt_ledger,
+            ctx.program_id,
+            &mut box_ids,
+            &mut dude_ids,
+        )?;
+        if box_ids.is_empty() && dude_ids.is_empty() {
+            return Ok(());
+        }
+        let active_tree_leaves = reserve_registered_receipt_leaves(
+            &ctx.accounts.receipt_trees,
+            ctx.program_id,
+            ctx.accounts.merkle_tree.key(),
+            ctx.accounts
+                .next_merkle_tree
+                .as_ref()
+                .map(|tree| tree.key()),
+            (box_ids.len() + dude_ids.len()) as u64,
+        )?;
+        let receipt_accounts =
+            ReceiptMintAccounts::from_deliver_with_receipts(ctx.accounts, active_tree_leaves);
+        validate_receipt_mint_accounts(&receipt_accounts)?;
+
+        let cfg = &ctx.accounts.config;
+        let royalties = load_drop_royalties(&ctx.accounts.royalties, ctx.program_id)?;
+        let metadata = new_receipt_metadata(cfg, royalties.as_ref(), None);
+        mint_box_and_figure_receipts(
+            cfg.key(),
+            cfg,
+            &receipt_accounts,
+            &metadata,
+            &box_ids,
+            &dude_ids,
+            &args.asset_id_bumps,
+        )
+    }
+
+    /// Cancels a delivery that has not shipped (or came back): every recorded Core asset (passed
+    /// in `remaining_accounts`, in delivery order) goes back from the vault or the admin to the
+    /// payer (or, frozen in place, is thawed and freed of its delegates; the collection must then
+    /// be writable), the receipt cNFTs in `args.receipts` go back from the admin through
+    /// Bubblegum, the treasury refunds the fee, and the record is closed with its rent returned
+    /// to the payer.
+    ///
+    /// An `Open` session can be aborted the same way: the assets appended so far go back and the
+    /// whole session fee is refunded.
+    ///
+    /// The assets must hash to the record's `assets_hash`, so records created before it was
+    /// stored and deliveries whose receipt cNFTs were burned cannot be cancelled on-chain;
+    /// neither can deliveries whose receipts were already minted to the payer, nor those whose
+    /// fee the route split between recipients (the treasury only refunds fees it received).
+    pub fn cancel_delivery<'a, 'b, 'c, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, CancelDelivery<'info>>,
+        args: CancelDeliveryArgs,
+    ) -> Result<()> {
+        require_keys_eq!(
+            ctx.accounts.mpl_core_program.key(),
+            MPL_CORE_PROGRAM_ID,
+            BoxMinterError::InvalidMplCoreProgram
+        );
+        let delivery_ai = ctx.accounts.delivery.to_account_info();
+        require_keys_eq!(
+            *delivery_ai.owner,
+            *ctx.program_id,
+            BoxMinterError::InvalidDeliveryPda
+        );
+        let record = decode_delivery_record(&delivery_ai.try_borrow_data()?)?;
+        require_keys_eq!(
+            ctx.accounts.payer.key(),
+            record.payer,
+            BoxMinterError::InvalidDeliveryPayer
+        );
+        require!(
+            record.status.is_cancellable() && !record.fulfilled,
+            BoxMinterError::InvalidDeliveryStatusTransition
+        );
+        require!(
+            !record.receipts_issued,
+            BoxMinterError::DeliveryReceiptsIssued
+        );
+        require!(!record.fee_split, BoxMinterError::DeliveryFeeNotRefundable);
+        require!(
+            args.receipts.len() == usize::from(record.receipt_count)
+                && args.receipts.iter().all(|receipt| !receipt.burn),
+            BoxMinterError::InvalidDeliveredReceipt
+        );
+
+        let accounts = DeliveryAccounts::from_cancel_delivery(ctx.accounts, ctx.program_id)?;
+        let receipt_account_count: usize = args
+            .receipts
+            .iter()
+            .map(|receipt| DELIVERED_RECEIPT_FIXED_ACCOUNTS + usize::from(receipt.proof_len))
+            .sum();
+        let core_asset_count = ctx
+            .remaining_accounts
+            .len()
+            .checked_sub(receipt_account_count)
+            .ok_or(BoxMinterError::InvalidRemainingAccounts)?;
+        let (core_assets, receipt_accounts) = ctx.remaining_accounts.split_at(core_asset_count);
+        let receipt_groups = if args.receipts.is_empty() {
+            Vec::new()
+        } else {
+            let receipt_delivery = accounts
+                .receipts
+                .as_ref()
+                .ok_or(BoxMinterError::InvalidDeliveredReceipt)?;
+            receipt_leaf_groups(receipt_delivery, &args.receipts, receipt_accounts)?
+        };
+        // The hash pins the receipt leaves (tree and index) the delivery took from the payer.
+        let mut asset_keys: Vec<Pubkey> = core_assets.iter().map(|ai| ai.key()).collect();
+        asset_keys.extend(
+            receipt_groups
+                .iter()
+                .zip(args.receipts.iter())
+                .map(|(group, receipt)| {
+                    receipt_asset_id(group[0].key, u64::from(receipt.leaf_index))
+                }),
+        );
+        require!(
+            asset_keys.len() == usize::from(record.item_count)
+                && delivery_assets_hash(&asset_keys) == record.assets_hash,
+            BoxMinterError::InvalidDeliveryAssets
+        );
+
+        let freeze_accounts = FreezeInPlaceAccounts::from_delivery(&accounts, &accounts.cosigner);
+        // Custody is decided per asset: a session can span the custody vault's initialization
+        // (earlier items stay with the admin), and frozen items never left the payer's wallet.
+        for asset_ai in core_assets.iter() {
+            let owner = core_asset_owner(asset_ai)?;
+            let asset = core::slice::from_ref(asset_ai);
+            if owner == record.payer {
+                release_frozen_assets(
+                    &ctx.accounts.config,
+                    &ctx.accounts.config.to_account_info(),
+                    &freeze_accounts,
+                    asset,
+                    false,
+                )?;
+            } else {
+                let custody = accounts.custody.for_asset_owner(owner, &accounts.cosigner);
+                transfer_custody_core_assets(&accounts, &custody, &accounts.payer, asset)?;
+            }
+        }
+        for (receipt, group) in args.receipts.iter().zip(receipt_groups) {
+            invoke_receipt_leaf_cpi(
+                &accounts,
+                receipt,
+                group,
+                &accounts.cosigner,
+                &accounts.payer,
+            )?;
+        }
+
+        if record.delivery_fee_lamports > 0 {
+            let ix = anchor_lang::solana_program::system_instruction::transfer(
+                &accounts.treasury.key(),
+                &accounts.payer.key(),
+                record.delivery_fee_lamports,
+            );
+            invoke(
+                &ix,
+                &[
+                    accounts.treasury.clone(),
+                    accounts.payer.clone(),
+                    accounts.system_program.clone(),
+                ],
+            )?;
+        }
+
+        close_program_account(&delivery_ai, &accounts.payer)?;
+        emit!(DeliveryCancelled {
+            config: accounts.config_key,
+            delivery: delivery_ai.key(),
+            delivery_id: args.delivery_id,
+            payer: record.payer,
+            refunded_lamports: record.delivery_fee_lamports,
+            item_count: record.item_count,
+        });
+        Ok(())
+    }
+
+    /// Cosigner-only fulfillment of a freeze-in-place delivery once it has shipped: the recorded
+    /// assets (`remaining_accounts`, in delivery order) are thawed and burned by the config PDA
+    /// as their delegate, and the record is marked `fulfilled` so it can be closed once delivered.
+    pub fn fulfill_frozen_delivery<'a, 'b, 'c, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, FulfillFrozenDelivery<'info>>,
+        _args: FulfillFrozenDeliveryArgs,
+    ) -> Result<()> {
+        require_keys_eq!(
+            ctx.accounts.mpl_core_program.key(),
+            MPL_CORE_PROGRAM_ID,
+            BoxMinterError::InvalidMplCoreProgram
+        );
+        let delivery_ai = ctx.accounts.delivery.to_account_info();
+        require_keys_eq!(
+            *delivery_ai.owner,
+            *ctx.program_id,
+            BoxMinterError::InvalidDeliveryPda
+        );
+        let mut record = decode_delivery_record(&delivery_ai.try_borrow_data()?)?;
+        require!(
+            record.frozen_in_place && !record.fulfilled,
+            BoxMinterError::InvalidDeliveryMode
+        );
+        require!(
+            matches!(
+                record.status,
+                DeliveryStatus::Shipped | DeliveryStatus::Delivered
+            ),
+            BoxMinterError::InvalidDeliveryStatusTransition
+        );
+        let asset_keys: Vec<Pubkey> = ctx.remaining_accounts.iter().map(|ai| ai.key()).collect();
+        require!(
+            asset_keys.len() == usize::from(record.item_count)
+                && delivery_assets_hash(&asset_keys) == record.assets_hash,
+            BoxMinterError::InvalidDeliveryAssets
+        );
+
+        release_frozen_assets(
+            &ctx.accounts.config,
+            &ctx.accounts.config.to_account_info(),
+            &FreezeInPlaceAccounts::from_fulfill_frozen_delivery(ctx.accounts),
+            ctx.remaining_accounts,
+            true,
+        )?;
+
+        record.fulfilled = true;
+        resize_delivery_record(
+            &delivery_ai,
+            &ctx.accounts.cosigner.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+        record.try_serialize(&mut &mut delivery_ai.data.borrow_mut()[..])?;
+        Ok(())
+    }
+
+    /// Cosigner-only: moves a delivery's Core assets (`remaining_accounts`, in delivery order) out
+    /// of the drop's custody vault, either to `recipient` or, with `args.burn`, burning them. Only
+    /// deliveries that can no longer be cancelled (shipped, delivered or with receipts issued)
+    /// release their assets, so boxes held for pending opens and cancellable deliveries stay put.
+    pub fn release_custody_assets<'a, 'b, 'c, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, ReleaseCustodyAssets<'info>>,
+        args: ReleaseCustodyAssetsArgs,
+    ) -> Result<()> {
+        require_delivery_item_count(ctx.remaining_accounts.len())?;
+        require_keys_eq!(
+            ctx.accounts.mpl_core_program.key(),
+            MPL_CORE_PROGRAM_ID,
+            BoxMinterError::InvalidMplCoreProgram
+        );
+        let custody = Custody::load(
+            ctx.accounts.config.key(),
+            &ctx.accounts.cosigner,
+            &ctx.accounts.custody_vault,
+            ctx.program_id,
+        )?;
+        require!(
+            custody.vault_bump.is_some(),
+            BoxMinterError::InvalidCustodyVault
+        );
+        let delivery_ai = ctx.accounts.delivery.to_account_info();
+        require_keys_eq!(
+            *delivery_ai.owner,
+            *ctx.program_id,
+            BoxMinterError::InvalidDeliveryPda
+        );
+        let record = decode_delivery_record(&delivery_ai.try_borrow_data()?)?;
+        require!(
+            (!record.status.is_cancellable() || record.receipts_issued) && !record.frozen_in_place,
+            BoxMinterError::InvalidDeliveryStatusTransition
+        );
+        let mut asset_keys: Vec<Pubkey> =
+            ctx.remaining_accounts.iter().map(|ai| ai.key()).collect();
+        asset_keys.extend(args.receipt_asset_ids.iter().copied());
+        require!(
+            asset_keys.len() == usize::from(record.item_count)
+                && delivery_assets_hash(&asset_keys) == record.assets_hash,
+            BoxMinterError::InvalidDeliveryAssets
+        );
+
+        let cosigner = ctx.accounts.cosigner.to_account_info();
+        let core_collection = ctx.accounts.core_collection.to_account_info();
+        let recipient = ctx.accounts.recipient.to_account_info();
+        let system_program = ctx.accounts.system_program.to_account_info();
+        let log_wrapper = ctx.accounts.log_wrapper.to_account_info();
+        let mpl_core_program = ctx.accounts.mpl_core_program.to_account_info();
+        let mut ix = if args.burn {
+            Instruction {
+                program_id: MPL_CORE_PROGRAM_ID,
+                accounts: vec![
+                    // asset, collection, payer, authority, system_program, log_wrapper
+                    AccountMeta::new(Pubkey::default(), false), // asset placeholder
+                    AccountMeta::new(core_collection.key(), false),
+                    AccountMeta::new(cosigner.key(), true),
+                    AccountMeta::new_readonly(custody.owner.key(), true),
+                    AccountMeta::new_readonly(system_program.key(), false),
+                    AccountMeta::new_readonly(log_wrapper.key(), false),
+                ],
+                // BurnV1 discriminator=12, compression_proof=None (0)
+                data: vec![12u8, 0u8],
+            }
+        } else {
+            Instruction {
+                program_id: MPL_CORE_PROGRAM_ID,
+                accounts: vec![
+                    // asset, collection, payer, authority, new_owner, system_program, log_wrapper
+                    AccountMeta::new(Pubkey::default(), false), // asset placeholder
+                    AccountMeta::new_readonly(core_collection.key(), false),
+                    AccountMeta::new(cosigner.key(), true),
+                    AccountMeta::new_readonly(custody.owner.key(), true),
+                    AccountMeta::new_readonly(recipient.key(), false),
+                    AccountMeta::new_readonly(system_program.key(), false),
+                    AccountMeta::new_readonly(log_wrapper.key(), false),
+                ],
+                // TransferV1 discriminator=14, compression_proof=None (0)
+                data: vec![14u8, 0u8],
+            }
+        };
+        for asset_ai in ctx.remaining_accounts.iter() {
+            ix.accounts[0].pubkey = asset_ai.key();
+            let mut account_infos = vec![
+                asset_ai.clone(),
+                core_collection.clone(),
+                cosigner.clone(),
+                custody.owner.clone(),
+            ];
+            if !args.burn {
+                account_infos.push(recipient.clone());
+            }
+            account_infos.extend([
+                system_program.clone(),
+                log_wrapper.clone(),
+                mpl_core_program.clone(),
+            ]);
+            custody.invoke(&ix, &account_infos)?;
+        }
+        Ok(())
+    }
+
+    pub fn close_delivery(ctx: Context<CloseDelivery>, _args: CloseDeliveryArgs) -> Result<()> {
+        // The `CloseDelivery` account constraints enforce:
+        // - `cosigner` == `config.admin`
+        // - `delivery` is the expected PDA
+        // Both legacy and current record layouts are accepted; current records must be
+        // `Delivered`. Rent goes back to `record.payer`.
+        close_delivery_record(
+            &ctx.accounts.delivery.to_account_info(),
+            &ctx.accounts.payer.to_account_info(),
+            ctx.program_id,
+        )
+    }
+
+    /// Batch `close_delivery`: `remaining_accounts` holds a `(delivery, payer)` pair per entry of
+    /// `args.deliveries`, in the same order.
+    pub fn close_deliveries<'a, 'b, 'c, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, CloseDeliveries<'info>>,
+        args: CloseDeliveriesArgs,
+    ) -> Result<()> {
+        require!(
+            !args.deliveries.is_empty() && args.deliveries.len() <= MAX_CLOSE_DELIVERIES_PER_TX,
+            BoxMinterError::InvalidQuantity
+        );
+        require!(
+            ctx.remaining_accounts.len() == args.deliveries.len() * 2,
+            BoxMinterError::InvalidRemainingAccounts
+        );
+        let config_key = ctx.accounts.config.key();
+        for (delivery, accounts) in args
+            .deliveries
+            .iter()
+            .zip(ctx.remaining_accounts.chunks_exact(2))
+        {
+            let expected_delivery = Pubkey::create_program_address(
+                &[
+                    SEED_DELIVERY,
+                    config_key.as_ref(),
+                    &delivery.delivery_id.to_le_bytes(),
+                    &[delivery.delivery_bump],
+                ],
+                ctx.program_id,
+            )
+            .map_err(|_| error!(BoxMinterError::InvalidDeliveryPda))?;
+            require_keys_eq!(
+                accounts[0].key(),
+                expected_delivery,
+                BoxMinterError::InvalidDeliveryPda
+            );
+            close_delivery_record(&accounts[0], &accounts[1], ctx.program_id)?;
+        }
+        Ok(())
+    }
+
+    /// Cosigner-only shipping progress update; only the transitions allowed by
+    /// `DeliveryStatus::can_transition_to` are accepted. Shipping requires a tracking hash.
+    pub fn set_delivery_status(
+        ctx: Context<SetDeliveryStatus>,
+        args: SetDeliveryStatusArgs,
+    ) -> Result<()> {
+        let delivery_ai = ctx.accounts.delivery.to_account_info();
+        require_keys_eq!(
+            *delivery_ai.owner,
+            *ctx.program_id,
+            BoxMinterError::InvalidDeliveryPda
+        );
+        let mut record = decode_delivery_record(&delivery_ai.try_borrow_data()?)?;
+        let from = record.status;
+        require!(
+            from.can_transition_to(args.status),
+            BoxMinterError::InvalidDeliveryStatusTransition
+        );
+        if let Some(tracking_hash) = args.tracking_hash {
+            record.tracking_hash = tracking_hash;
+        }
+        if args.status == DeliveryStatus::Shipped {
+            require!(
+                has_any_non_zero_byte(&record.tracking_hash),
+                BoxMinterError::MissingTrackingHash
+            );
+        }
+        record.status = args.status;
+
+        resize_delivery_record(
+            &delivery_ai,
+            &ctx.accounts.cosigner.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+        record.try_serialize(&mut &mut delivery_ai.data.borrow_mut()[..])?;
+
+        emit!(DeliveryStatusChanged {
+            config: ctx.accounts.config.key(),
+            delivery: delivery_ai.key(),
+            delivery_id: args.delivery_id,
+            from,
+            to: args.status,
+            tracking_hash: record.tracking_hash,
+            slot: Clock::get()?.slot,
+        });
+        Ok(())
+    }
+
+    /// Admin-only off-chain order fulfillment for drops.
+    ///
+    /// This reserves a contiguous run of variant metadata ids, creates an idempotency PDA keyed by
+    /// the off-chain order hash, and mints the matching receipt cNFTs to the recipient wallet.
+    /// For non-variant drops, `variant_index` is ignored and the order record stores `0`.
+    /// Exact retries of a fulfilled order are accepted as no-ops.
+    pub fn admin_deliver_variant_order(
+        ctx: Context<AdminDeliverVariantOrder>,
+        args: AdminDeliverVariantOrderArgs,
+    ) -> Result<()> {
+        validate_admin_order_hash(&args.order_hash)?;
+        require!(args.quantity >= 1, BoxMinterError::InvalidQuantity);
+
+        let config_key = ctx.accounts.config.key();
+        let order_bump = validate_admin_order_pda(
+            ctx.program_id,
+            &config_key,
+            &args.order_hash,
+            ctx.accounts.admin_order.key(),
+        )?;
+        let order_bump_bytes = [order_bump];
+        let order_seeds: &[&[u8]] = &[
+            SEED_ADMIN_ORDER,
+            config_key.as_ref(),
+            args.order_hash.as_ref(),
+            &order_bump_bytes,
+        ];
+
+        let order_ai = ctx.accounts.admin_order.to_account_info();
+        let receipt_owner = ctx.accounts.receipt_owner.key();
+        let effective_variant_index =
+            admin_delivery_effective_variant_index(&ctx.accounts.config, args.variant_index);
+        require!(
+            receipt_owner != Pubkey::default(),
+            BoxMinterError::InvalidReceiptOwner
+        );
+        if !order_ai.data_is_empty() {
+            let existing = deserialize_admin_order_record(&order_ai, ctx.program_id)?;
+            require!(
+                admin_order_is_valid_retry(
+                    &existing,
+                    &args,
+                    effective_variant_index,
+                    receipt_owner,
+                    order_bump,
+                ),
+                BoxMinterError::AdminOrderAlreadyExists
+            );
+            return Ok(());
+        }
+        let active_tree_leaves = reserve_registered_receipt_leaves(
+            &ctx.accounts.receipt_trees,
+            ctx.program_id,
+            ctx.accounts.merkle_tree.key(),
+            ctx.accounts
+                .next_merkle_tree
+                .as_ref()
+                .map(|tree| tree.key()),
+            u64::from(args.quantity),
+        )?;
+        let receipt_accounts =
+            ReceiptMintAccounts::from_admin_delivery(&ctx.accounts, active_tree_leaves);
+        validate_receipt_mint_accounts(&receipt_accounts)?;
+
+        let first_metadata_id = reserve_admin_delivery_metadata_ids(
+            &mut *ctx.accounts.config,
+            ctx.accounts.variant_table.as_deref_mut(),
+            effective_variant_index,
+            args.quantity,
+        )?;
+        if let Some(receipt_ledger) =
+            load_drop_pda::<ReceiptLedger>(&ctx.accounts.receipt_ledger, ctx.program_id)?
+        {
+            let mut ledger_data = ctx.accounts.receipt_ledger.try_borrow_mut_data()?;
+            let (box_bits, _) = receipt_ledger.bitmaps(&mut ledger_data)?;
+            for metadata_id in first_metadata_id..first_metadata_id + u32::from(args.quantity) {
+                require!(
+                    mark_receipt_issued(box_bits, metadata_id)?,
+                    BoxMinterError::ReceiptAlreadyIssued
+                );
+            }
+        }
+
+        create_or_reclaim_empty_pda_account(
+            &order_ai,
+            &ctx.accounts.cosigner.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            AdminDeliveryOrderRecord::SPACE,
+            ctx.program_id,
+            order_seeds,
+            BoxMinterError::InvalidAdminOrderPda,
+            BoxMinterError::AdminOrderAlreadyExists,
+        )?;
+
+        let next_tree = match (
+            ctx.accounts.next_merkle_tree.as_ref(),
+            ctx.accounts.next_tree_config.as_ref(),
+        ) {
+            (Some(merkle_tree), Some(tree_config)) => {
+                Some((merkle_tree.key(), read_tree_num_minted(tree_config)?))
+            }
+            _ => None,
+        };
+        let (rollover_receipt_count, rollover_merkle_tree, rollover_first_leaf_index) =
+            admin_order_receipt_rollover(
+                args.quantity,
+                receipt_accounts.active_tree_leaves,
+                next_tree,
+            )?;
+        let record = AdminDeliveryOrderRecord {
+            order_hash: args.order_hash,
+            variant_index: effective_variant_index,
+            quantity: args.quantity,
+            first_metadata_id,
+            receipt_owner,
+            created_slot: Clock::get()?.slot,
+            bump: order_bump,
+            receipt_merkle_tree: ctx.accounts.merkle_tree.key(),
+            first_receipt_leaf_index: read_tree_num_minted(&ctx.accounts.tree_config)?,
+            rollover_receipt_count,
+            rollover_merkle_tree,
+            rollover_first_leaf_index,
+        };
+        record.try_serialize(&mut &mut order_ai.data.borrow_mut()[..])?;
+        let royalties = load_drop_royalties(&ctx.accounts.royalties, ctx.program_id)?;
+        let asset_data = args.with_receipt_asset_data.then_some(ReceiptAssetData {
+            order_hash: args.order_hash,
+            delivery_id: 0,
+        });
+        let metadata = new_receipt_metadata(
+            &ctx.accounts.config,
+            royalties.as_ref(),
+            asset_data.as_ref(),
+        );
+        mint_admin_order_receipt_cnfts(
+            ctx.accounts.config.key(),
+            &ctx.accounts.config,
+            &receipt_accounts,
+            &metadata,
+            first_metadata_id,
+            args.quantity,
+            &args.asset_id_bumps,
+        )
+    }
+
+    /// Mints a delivery's receipts without the cosigner: the program signs the mints once the
+    /// delivered assets (`remaining_accounts`, in delivery order) hash to the record's
+    /// `assets_hash`. Receipts go to the delivery payer, and either the cosigner or the delivery
+    /// payer must sign (only the cosigner until the drop has a receipt tree registry); ids the
+    /// ledger already records are skipped, and the record is marked `receipts_issued` (so it can
+    /// no longer be cancelled). Only packed, shipped or delivered deliveries without receipt cNFTs
+    /// are eligible.
+    pub fn mint_delivery_receipts<'a, 'b, 'c, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, MintDeliveryReceipts<'info>>,
+        args: MintDeliveryReceiptsArgs,
+    ) -> Result<()> {
+        let delivery_ai = ctx.accounts.delivery.to_account_info();
+        require_keys_eq!(
+            *delivery_ai.owner,
+            *ctx.program_id,
+            BoxMinterError::InvalidDeliveryPda
+        );
+        let mut record = decode_delivery_record(&delivery_ai.try_borrow_data()?)?;
+        require_keys_eq!(
+            ctx.accounts.recipient.key(),
+            record.payer,
+            BoxMinterError::InvalidDeliveryPayer
+        );
+        let payer = ctx.accounts.payer.key();
+        require!(
+            payer == ctx.accounts.config.admin || payer == record.payer,
+            BoxMinterError::InvalidDeliveryPayer
+        );
+        require!(
+            record.status.allows_receipts(),
+            BoxMinterError::InvalidDeliveryStatusTransition
+        );
+        require!(
+            !record.receipts_issued,
+            BoxMinterError::DeliveryReceiptsIssued
+        );
+        let asset_keys: Vec<Pubkey> = ctx.remaining_accounts.iter().map(|ai| ai.key()).collect();
+        require!(
+            record.receipt_count == 0
+                && asset_keys.len() == usize::from(record.item_count)
+                && delivery_assets_hash(&asset_keys) == record.assets_hash,
+            BoxMinterError::InvalidDeliveryAssets
+        );
+
+        let cfg = &ctx.accounts.config;
+        let mut items = Vec::with_capacity(ctx.remaining_accounts.len());
+        for asset_ai in ctx.remaining_accounts.iter() {
+            // The hash pins the assets; wherever they are held now, their ids are what counts.
+            items.push(verify_delivered_asset(
+                cfg,
+                asset_ai,
+                core_asset_owner(asset_ai)?,
+            )?);
+        }
+        let (mut box_ids, mut dude_ids) = split_receipt_ids(&items);
+
+        record.receipts_issued = true;
+        resize_delivery_record(
+            &delivery_ai,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+        record.try_serialize(&mut &mut delivery_ai.data.borrow_mut()[..])?;
+
+        retain_unissued_receipts(
+            &ctx.accounts.receipt_ledger,
+            ctx.program_id,
+            &mut box_ids,
+            &mut dude_ids,
+        )?;
+        if box_ids.is_empty() && dude_ids.is_empty() {
+            return Ok(());
+        }
+        let active_tree_leaves = reserve_registered_receipt_leaves(
+            &ctx.accounts.receipt_trees,
+            ctx.program_id,
+            ctx.accounts.merkle_tree.key(),
+            ctx.accounts
+                .next_merkle_tree
+                .as_ref()
+                .map(|tree| tree.key()),
+            (box_ids.len() + dude_ids.len()) as u64,
+        )?;
+        // Without the registry only the cosigner can sign for its own tree.
+        if active_tree_leaves.is_none() {
+            require_keys_eq!(
+                ctx.accounts.payer.key(),
+                ctx.accounts.config.admin,
+                BoxMinterError::InvalidCosigner
+            );
+        }
+        let receipt_accounts =
+            ReceiptMintAccounts::from_mint_delivery_receipts(ctx.accounts, active_tree_leaves);
+        validate_receipt_mint_accounts(&receipt_accounts)?;
+
+        let cfg = &ctx.accounts.config;
+        let royalties = load_drop_royalties(&ctx.accounts.royalties, ctx.program_id)?;
+        let metadata = new_receipt_metadata(cfg, royalties.as_ref(), None);
+        mint_box_and_figure_receipts(
+            cfg.key(),
+            cfg,
+            &receipt_accounts,
+            &metadata,
+            &box_ids,
+            &dude_ids,
+            &args.asset_id_bumps,
+        )
+    }
+
+    /// Mint compressed (Bubblegum v2) receipt cNFTs into the receipts tree, admin/cosigner-only.
+    ///
+    /// This is used by:
+    /// - delivery receipt issuance (boxes + figures)
+    /// - IRL claim flow (figures)
+    ///
+    /// Receipt metadata is derived on-chain from the configured `config.uri_base` so the backend
+    /// does not duplicate receipt URI/name logic.
+    ///
+    /// Receipts go into the drop's active registered tree; a batch that fills it continues in the
+    /// next registered tree (`next_merkle_tree`). Drops without a receipt tree registry keep
+    /// minting into the cosigner's own tree, and without a receipt ledger ids are not deduplicated
+    /// across calls. Naming a delivery (`args.delivery`) marks its record `receipts_issued`; the
+    /// delivery must be packed first.
+    pub fn mint_receipts(ctx: Context<MintReceipts>, args: MintReceiptsArgs) -> Result<()> {
+        let cfg = &ctx.accounts.config;
+
+        // Admin-only (server cosigner).
+        require_keys_eq!(
+            ctx.accounts.cosigner.key(),
+            cfg.admin,
+            BoxMinterError::InvalidCosigner
+        );
+
+        let mut box_ids = args.box_ids;
+        let mut dude_ids = args.dude_ids;
+        let max_dude_id = cfg.max_figure_id()?;
+
+        // Defensive caps (Bubblegum mints are compute-heavy).
+        let total = box_ids
+            .len()
+            .checked_add(dude_ids.len())
+            .ok_or(BoxMinterError::MathOverflow)?;
+        require!(total > 0, BoxMinterError::InvalidQuantity);
+        // Conservative cap; if the backend wants more, it should batch.
+        require!(total <= 24, BoxMinterError::InvalidQuantity);
+
+        // Validate box IDs (must correspond to configured box supply).
+        for id in box_ids.iter() {
+            require!(
+                *id >= 1 && *id <= cfg.max_supply,
+                BoxMinterError::InvalidAssetMetadata
+            );
+        }
+        // Validate dude IDs.
+        for id in dude_ids.iter() {
+            require!(
+                *id >= 1 && *id <= max_dude_id,
+                BoxMinterError::InvalidDudeId
+            );
+        }
+        // Ensure there are no duplicates (cheap O(n^2) since n is tiny).
+        for i in 0..box_ids.len() {
+            for j in (i + 1)..box_ids.len() {
+                require!(
+                    box_ids[i] != box_ids[j],
+                    BoxMinterError::InvalidAssetMetadata
+                );
+            }
+        }
+        for i in 0..dude_ids.len() {
+            for j in (i + 1)..dude_ids.len() {
+                require!(dude_ids[i] != dude_ids[j], BoxMinterError::DuplicateDudeId);
+            }
+        }
+
+        if let Some(delivery) = args.delivery.as_ref() {
+            mark_delivery_receipts_issued(ctx.accounts, delivery, ctx.program_id)?;
+        }
+
+        retain_unissued_receipts(
+            &ctx.accounts.receipt_ledger,
+            ctx.program_id,
+            &mut box_ids,
+            &mut dude_ids,
+        )?;
+        if box_ids.is_empty() && dude_ids.is_empty() {
+            // Retry of an already-completed call.
+            return Ok(());
+        }
+        let active_tree_leaves = reserve_registered_receipt_leaves(
+            &ctx.accounts.receipt_trees,
+            ctx.program_id,
+            ctx.accounts.merkle_tree.key(),
+            ctx.accounts
+                .next_merkle_tree
+                .as_ref()
+                .map(|tree| tree.key()),
+            (box_ids.len() + dude_ids.len()) as u64,
+        )?;
+        let receipt_accounts =
+            ReceiptMintAccounts::from_mint_receipts(&ctx.accounts, active_tree_leaves);
+        validate_receipt_mint_accounts(&receipt_accounts)?;
+
+        let royalties = load_drop_royalties(&ctx.accounts.royalties, ctx.program_id)?;
+        let metadata = new_receipt_metadata(cfg, royalties.as_ref(), args.asset_data.as_ref());
+        mint_box_and_figure_receipts(
+            cfg.key(),
+            cfg,
+            &receipt_accounts,
+            &metadata,
+            &box_ids,
+            &dude_ids,
+            &args.asset_id_bumps,
+        )
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct InitializeArgs {
+    pub price_lamports: u64,
+    pub discount_price_lamports: u64,
+    pub discount_merkle_root: [u8; 32],
+    pub max_supply: u32,
+    pub max_per_tx: u8,
+    pub items_per_box: u8,
+    pub name_prefix: String,
+    pub symbol: String,
+    pub uri_base: String,
+    pub discount_mints_per_wallet: u8,
+    pub figure_name_prefix: String,
+    pub mint_variant_kind: u8,
+    pub mint_variant_start_ids: [u32; MINT_VARIANT_OPTION_COUNT],
+    pub mint_variant_end_ids: [u32; MINT_VARIANT_OPTION_COUNT],
+    pub mint_variant_next_ids: [u32; MINT_VARIANT_OPTION_COUNT],
+    pub drop_seed: [u8; 32],
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SplitPaymentsV1Args {
+    pub recipient_count: u8,
+    pub recipients: [Pubkey; SPLIT_PAYMENTS_V1_MAX_RECIPIENTS],
+    pub percentages: [u8; SPLIT_PAYMENTS_V1_MAX_RECIPIENTS],
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct VariantOptionArgs {
+    pub label: String,
+    pub start_id: u32,
+    pub end_id: u32,
+    pub price_lamports: u64,
+    pub discount_price_lamports: u64,
+    /// Open settings; all zero on direct-delivery drops.
+    pub items_per_box: u8,
+    pub figure_start_id: u16,
+    pub figure_end_id: u16,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct InitializeVariantTableArgs {
+    pub options: Vec<VariantOptionArgs>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct SetVariantOptionArgs {
+    pub variant_index: u8,
+    pub price_lamports: u64,
+    pub discount_price_lamports: u64,
+    pub sold_out: bool,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct DropRoyaltiesArgs {
+    pub basis_points: u16,
+    pub creators: Vec<RoyaltyCreator>,
+    pub rule_set: RoyaltyRuleSet,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct CreateReceiptsTreeArgs {
+    pub max_depth: u32,
+    pub max_buffer_size: u32,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct DeliveryFeeScheduleArgs {
+    pub zones: Vec<DeliveryFeeZone>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct FinalizeOpenBoxArgs {
+    pub dude_ids: Vec<u16>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct FinalizeOpenBoxesEntry {
+    /// PDA bump for this box's pending open record.
+    pub pending_bump: u8,
+    pub dude_ids: Vec<u16>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct FinalizeOpenBoxesArgs {
+    /// One entry per box group in `remaining_accounts`, in the same order.
+    pub boxes: Vec<FinalizeOpenBoxesEntry>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct DeliverArgs {
+    pub delivery_id: u32,
+    /// Must equal the zone's fee when `delivery_zone` is set.
+    pub delivery_fee_lamports: u64,
+    /// PDA bump for `delivery` record (passed from client to avoid find_program_address compute).
+    pub delivery_bump: u8,
+    /// sha256 of the encrypted shipping address payload (`addressCipher` output).
+    pub address_cipher_hash: [u8; 32],
+    /// Zone in the drop's `delivery_fees` schedule; required exactly when it is initialized.
+    pub delivery_zone: Option<u16>,
+    /// Receipt cNFTs delivered with (or instead of) Core assets; their account groups follow the
+    /// Core assets in `remaining_accounts`, in this order.
+    pub receipts: Vec<DeliveredReceiptArgs>,
+    /// Keep the Core assets in the payer's wallet, frozen under config-PDA delegates, instead of
+    /// moving them to the vault; only for `deliver`-style deliveries without receipts.
+    pub freeze_in_place: bool,
+    /// `deliver_with_receipts` only: Bubblegum asset id bumps of the receipt leaves it mints; same
+    /// contract as `MintReceiptsArgs::asset_id_bumps`.
+    pub asset_id_bumps: Vec<u8>,
+}
+
+/// A receipt leaf owned by the payer, as Bubblegum v2 `transferV2`/`burnV2` expect it. Its
+/// `remaining_accounts` group is `merkle_tree`, `tree_config`, then `proof_len` proof nodes.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct DeliveredReceiptArgs {
+    pub root: [u8; 32],
+    pub data_hash: [u8; 32],
+    pub creator_hash: [u8; 32],
+    pub asset_data_hash: Option<[u8; 32]>,
+    pub flags: Option<u8>,
+    /// Leaf nonce/index (Bubblegum assigns both from the tree's mint count).
+    pub leaf_index: u32,
+    pub proof_len: u8,
+    /// Burn the leaf instead of transferring it to `config.admin`; burned receipts cannot be
+    /// returned, so the delivery can no longer be cancelled.
+    pub burn: bool,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct BeginDeliveryArgs {
+    pub delivery: DeliverArgs,
+    /// Items the session is paid for, including the assets passed to `begin_delivery`.
+    pub item_count: u16,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct AppendDeliveryItemsArgs {
+    pub delivery_id: u32,
+    pub delivery_bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct SealDeliveryArgs {
+    pub delivery_id: u32,
+    pub delivery_bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct SetDeliveryStatusArgs {
+    pub delivery_id: u32,
+    pub delivery_bump: u8,
+    pub status: DeliveryStatus,
+    /// sha256 of the carrier tracking number; keeps the stored hash when `None`.
+    pub tracking_hash: Option<[u8; 32]>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct DeliverWithQuoteArgs {
+    pub delivery: DeliverArgs,
+    /// Last slot the cosigner's quote is valid for; at most `MAX_DELIVERY_QUOTE_SLOTS` ahead.
+    pub quote_expiry_slot: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct CancelDeliveryArgs {
+    pub delivery_id: u32,
+    pub delivery_bump: u8,
+    /// Receipt leaves the delivery moved to `config.admin`, in delivery order, to hand back to the
+    /// payer (`burn` must be false); their account groups follow the Core assets.
+    pub receipts: Vec<DeliveredReceiptArgs>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct FulfillFrozenDeliveryArgs {
+    pub delivery_id: u32,
+    pub delivery_bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ReleaseCustodyAssetsArgs {
+    pub delivery_id: u32,
+    /// Delivery record PDA bump (passed from client to avoid find_program_address compute).
+    pub delivery_bump: u8,
+    /// Asset ids of the receipt cNFTs the delivery took, hashed after its Core assets.
+    pub receipt_asset_ids: Vec<Pubkey>,
+    /// Burn the assets instead of transferring them to `recipient`.
+    pub burn: bool,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct CloseDeliveryArgs {
+    pub delivery_id: u32,
+    pub delivery_bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct CloseDeliveriesArgs {
+    pub deliveries: Vec<CloseDeliveryArgs>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct MintDeliveryReceiptsArgs {
+    pub delivery_id: u32,
+    /// Delivery record PDA bump (passed from client to avoid find_program_address compute).
+    pub delivery_bump: u8,
+    /// Bubblegum asset id bumps of the receipt leaves, one per leaf minted; same contract as
+    /// `MintReceiptsArgs::asset_id_bumps`.
+    pub asset_id_bumps: Vec<u8>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct MintReceiptsArgs {
+    pub box_ids: Vec<u32>,
+    pub dude_ids: Vec<u16>,
+    /// Optional order hash and delivery id written into the URI of every receipt minted by this
+    /// call.
+    pub asset_data: Option<ReceiptAssetData>,
+    /// Bubblegum asset id bumps, one per leaf minted (passed from client to avoid
+    /// find_program_address compute). Entry `i` is for leaf `num_minted + i` of `merkle_tree`; once
+    /// its remaining capacity is used up, the entries continue at `next_merkle_tree`'s
+    /// `num_minted`. They are indexed by leaf, not by receipt id: ids the ledger skips take no
+    /// leaf, so bumps derived for the whole requested batch stay valid and unused trailing entries
+    /// are ignored.
+    pub asset_id_bumps: Vec<u8>,
+    /// Delivery these receipts fulfil, packed or further along; its record (`delivery` account) is
+    /// marked `receipts_issued`, so it can no longer be cancelled and the cosigner can close it.
+    pub delivery: Option<CloseDeliveryArgs>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ReceiptAssetData {
+    pub order_hash: [u8; 32],
+    /// Delivery id the receipts belong to; `0` when not tied to a delivery.
+    pub delivery_id: u32,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct AdminDeliverVariantOrderArgs {
+    pub order_hash: [u8; 32],
+    /// Ignored for non-variant drops.
+    pub variant_index: u8,
+    pub quantity: u8,
+    /// Attach the order hash to each receipt's URI.
+    pub with_receipt_asset_data: bool,
+    /// Bubblegum asset id bumps of the `quantity` receipt leaves (admin orders never skip ids);
+    /// same contract as `MintReceiptsArgs::asset_id_bumps`.
+    pub asset_id_bumps: Vec<u8>,
+}
+
+#[account]
+pub struct BoxMinterConfig {
+    pub admin: Pubkey,
+    pub treasury: Pubkey,
+    pub core_collection: Pubkey,
+    pub price_lamports: u64,
+    pub discount_price_lamports: u64,
+    pub discount_merkle_root: [u8; 32],
+    pub max_supply: u32,
+    pub max_per_tx: u8,
+    pub items_per_box: u8,
+    pub minted: u32,
+    pub name_prefix: String,
+    pub symbol: String,
+    pub uri_base: String,
+    /// If false, `mint_boxes` is paused.
+    pub started: bool,
+    pub bump: u8,
+    pub discount_mints_per_wallet: u8,
+    pub figure_name_prefix: String,
+    pub mint_variant_kind: u8,
+    pub mint_variant_start_ids: [u32; MINT_VARIANT_OPTION_COUNT],
+    pub mint_variant_end_ids: [u32; MINT_VARIANT_OPTION_COUNT],
+    pub mint_variant_next_ids: [u32; MINT_VARIANT_OPTION_COUNT],
+    pub drop_seed: [u8; 32],
+}
+
+#[account]
+pub struct DiscountMintRecord {
+    pub payer: Pubkey,
+    pub minted: u8,
+    pub bump: u8,
+}
+
+impl DiscountMintRecord {
+    pub const SPACE: usize = 8 // anchor account discriminator
+        + 32 // payer
+        + 1 // minted
+        + 1; // bump
+}
 
-    pub fn set_uri_base(ctx: Context<SetUriBase>, uri_base: String) -> Result<()> {
-        let normalized = normalized_metadata_base(&uri_base)?;
-        ctx.accounts.config.uri_base = normalized.to_string();
-        Ok(())
-    }
+#[account]
+pub struct DeliveryRecord {
+    pub payer: Pubkey,
+    pub delivery_fee_lamports: u64,
+    pub item_count: u16,
+    /// Boxes/figures among the delivered items; zero for records created before they were tracked.
+    pub box_count: u16,
+    pub figure_count: u16,
+    /// Chained sha256 of the ordered delivered asset keys (see `delivery_assets_hash`).
+    pub assets_hash: [u8; 32],
+    /// sha256 of the encrypted shipping address payload the delivery was paid for.
+    pub address_cipher_hash: [u8; 32],
+    pub created_slot: u64,
+    pub status: DeliveryStatus,
+    /// sha256 of the carrier tracking number; set when the delivery ships.
+    pub tracking_hash: [u8; 32],
+    /// Items the fee covers; a session is sealed once `item_count` reaches it. Zero for records
+    /// created before sessions existed.
+    pub paid_item_count: u16,
+    /// Receipt cNFTs among the delivered items (hashed into `assets_hash` by asset id).
+    pub receipt_count: u16,
+    /// The Core assets stayed in the payer's wallet, frozen until fulfillment burns them or a
+    /// cancellation thaws them.
+    pub frozen_in_place: bool,
+    /// Set by `fulfill_frozen_delivery` once the frozen assets are burned.
+    pub fulfilled: bool,
+    /// Receipt cNFTs were minted to the payer for this delivery, so it can no longer be cancelled.
+    pub receipts_issued: bool,
+    /// The fee route split the fee between several recipients, so the treasury cannot refund it.
+    pub fee_split: bool,
+}
 
-    pub fn start_mint(ctx: Context<StartMint>) -> Result<()> {
-        let cfg = &mut ctx.accounts.config;
-        cfg.started = true;
-        Ok(())
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeliveryStatus {
+    Paid,
+    Packed,
+    Shipped,
+    Delivered,
+    Returned,
+    /// Session started by `begin_delivery`; becomes `Paid` when sealed.
+    Open,
+}
+
+impl DeliveryStatus {
+    /// Paid -> Packed -> Shipped -> Delivered, with returns allowed once shipped.
+    pub fn can_transition_to(self, next: DeliveryStatus) -> bool {
+        matches!(
+            (self, next),
+            (DeliveryStatus::Paid, DeliveryStatus::Packed)
+                | (DeliveryStatus::Packed, DeliveryStatus::Shipped)
+                | (DeliveryStatus::Shipped, DeliveryStatus::Delivered)
+                | (DeliveryStatus::Shipped, DeliveryStatus::Returned)
+                | (DeliveryStatus::Delivered, DeliveryStatus::Returned)
+        )
     }
 
-    pub fn mint_boxes<'a, 'b, 'c, 'info>(
-        ctx: Context<'a, 'b, 'c, 'info, MintBoxes<'info>>,
-        quantity: u8,
-        // Client-chosen mint id used only for PDA derivation (prevents stale-PDA failures under high concurrency).
-        // Must be random/unique per attempted mint transaction.
-        mint_id: u64,
-        // PDA bumps for each box asset PDA, in the same order as `remaining_accounts`.
-        // Passed in from the client to avoid `find_program_address` compute inside the program.
-        box_bumps: Vec<u8>,
-    ) -> Result<()> {
-        let accounts = MintBoxesInnerAccounts::from_mint_boxes(&ctx.accounts);
-        let unit_price_lamports = ctx.accounts.config.price_lamports;
-        mint_standard_boxes_inner(
-            &mut ctx.accounts.config,
-            &accounts,
-            ctx.remaining_accounts,
-            quantity,
-            mint_id,
-            box_bumps,
-            ctx.program_id,
-            unit_price_lamports,
+    /// Packed or further along the shipping path; paid, open and returned deliveries get no
+    /// receipts.
+    pub fn allows_receipts(self) -> bool {
+        matches!(
+            self,
+            DeliveryStatus::Packed | DeliveryStatus::Shipped | DeliveryStatus::Delivered
         )
     }
 
-    pub fn mint_variant_box<'a, 'b, 'c, 'info>(
-        ctx: Context<'a, 'b, 'c, 'info, MintBoxes<'info>>,
-        variant_index: u8,
-        mint_id: u64,
-        box_bump: u8,
-    ) -> Result<()> {
-        let accounts = MintBoxesInnerAccounts::from_mint_boxes(&ctx.accounts);
-        let unit_price_lamports = ctx.accounts.config.price_lamports;
-        mint_variant_box_inner(
-            &mut ctx.accounts.config,
-            &accounts,
-            ctx.remaining_accounts,
-            variant_index,
-            mint_id,
-            box_bump,
-            ctx.program_id,
-            unit_price_lamports,
+    /// Not yet shipped (including sessions still open), or returned to sender (e.g. undeliverable
+    /// address).
+    pub fn is_cancellable(self) -> bool {
+        matches!(
+            self,
+            DeliveryStatus::Paid
+                | DeliveryStatus::Packed
+                | DeliveryStatus::Returned
+                | DeliveryStatus::Open
         )
     }
+}
 
-    pub fn mint_discounted_box<'a, 'b, 'c, 'info>(
-        ctx: Context<'a, 'b, 'c, 'info, MintDiscountedBox<'info>>,
-        mint_id: u64,
-        box_bumps: Vec<u8>,
-        proof: Vec<[u8; 32]>,
-    ) -> Result<()> {
-        let discount_price = ctx.accounts.config.discount_price_lamports;
-        require!(discount_price > 0, BoxMinterError::InvalidDiscountPrice);
-        let discount_root = ctx.accounts.config.discount_merkle_root;
-        require!(
-            discount_root != [0u8; 32],
-            BoxMinterError::DiscountNotConfigured
-        );
+#[event]
+pub struct DeliveryCancelled {
+    pub config: Pubkey,
+    pub delivery: Pubkey,
+    pub delivery_id: u32,
+    pub payer: Pubkey,
+    pub refunded_lamports: u64,
+    pub item_count: u16,
+}
 
-        let payer_key = ctx.accounts.payer.key();
-        require!(
-            verify_merkle_proof(payer_key.as_ref(), &proof, discount_root),
-            BoxMinterError::InvalidDiscountProof
-        );
-        let quantity =
-            u8::try_from(box_bumps.len()).map_err(|_| error!(BoxMinterError::InvalidQuantity))?;
-        let discount_limit = ctx.accounts.config.discount_mints_per_wallet;
-        resolve_and_validate_mint_remaining_accounts(
-            &ctx.accounts.config,
-            payer_key,
-            ctx.remaining_accounts,
-            mint_id,
-            &box_bumps,
-            ctx.program_id,
-        )?;
-        let discount_ai = ctx.accounts.discount_record.to_account_info();
-        let (mut discount_record, discount_bump) = load_or_create_discount_record(
-            ctx.accounts.config.key(),
-            payer_key,
-            ctx.accounts.payer.to_account_info(),
-            discount_ai.clone(),
-            ctx.accounts.system_program.to_account_info(),
-            ctx.program_id,
-        )?;
-        let new_discount_total = discount_record
-            .minted
-            .checked_add(quantity)
-            .ok_or(BoxMinterError::MathOverflow)?;
-        require!(
-            new_discount_total <= discount_limit,
-            BoxMinterError::DiscountAllowanceExceeded
-        );
+#[event]
+pub struct DeliveryStatusChanged {
+    pub config: Pubkey,
+    pub delivery: Pubkey,
+    pub delivery_id: u32,
+    pub from: DeliveryStatus,
+    pub to: DeliveryStatus,
+    pub tracking_hash: [u8; 32],
+    pub slot: u64,
+}
 
-        let accounts = MintBoxesInnerAccounts::from_discounted_box(&ctx.accounts);
-        mint_standard_boxes_inner(
-            &mut ctx.accounts.config,
-            &accounts,
-            ctx.remaining_accounts,
-            quantity,
-            mint_id,
-            box_bumps,
-            ctx.program_id,
-            discount_price,
-        )?;
-        discount_record.minted = new_discount_total;
-        discount_record.bump = discount_bump;
-        discount_record.try_serialize(&mut &mut discount_ai.data.borrow_mut()[..])?;
-        Ok(())
+impl DeliveryRecord {
+    pub const LEGACY_SPACE: usize = 8 // anchor account discriminator
+        + 32 // payer
+        + 8 // delivery_fee_lamports
+        + 2; // item_count
+    pub const SPACE: usize = Self::LEGACY_SPACE
+        + 2 // box_count
+        + 2 // figure_count
+        + 32 // assets_hash
+        + 32 // address_cipher_hash
+        + 8 // created_slot
+        + 1 // status
+        + 32 // tracking_hash
+        + 2 // paid_item_count
+        + 2 // receipt_count
+        + 1 // frozen_in_place
+        + 1 // fulfilled
+        + 1 // receipts_issued
+        + 1; // fee_split
+}
+
+#[account]
+pub struct AdminDeliveryOrderRecord {
+    pub order_hash: [u8; 32],
+    pub variant_index: u8,
+    pub quantity: u8,
+    pub first_metadata_id: u32,
+    pub receipt_owner: Pubkey,
+    pub created_slot: u64,
+    pub bump: u8,
+    /// Tree the order's receipts were minted into; default for records created before it was
+    /// tracked.
+    pub receipt_merkle_tree: Pubkey,
+    /// Leaf index of the first receipt; receipt `i` is leaf `first_receipt_leaf_index + i` and its
+    /// asset id is Bubblegum's `["asset", receipt_merkle_tree, leaf_index]` PDA.
+    pub first_receipt_leaf_index: u64,
+    /// Trailing receipts that rolled over into `rollover_merkle_tree` once `receipt_merkle_tree`
+    /// filled up; `0` when the order fit in one tree.
+    pub rollover_receipt_count: u8,
+    /// Registered tree after `receipt_merkle_tree`; default when nothing rolled over.
+    pub rollover_merkle_tree: Pubkey,
+    /// Leaf index of the first rolled-over receipt in `rollover_merkle_tree`.
+    pub rollover_first_leaf_index: u64,
+}
+
+impl AdminDeliveryOrderRecord {
+    pub const LEGACY_SPACE: usize = 8 // anchor account discriminator
+        + 32 // order_hash
+        + 1 // variant_index
+        + 1 // quantity
+        + 4 // first_metadata_id
+        + 32 // receipt_owner
+        + 8 // created_slot
+        + 1; // bump
+    pub const SPACE: usize = Self::LEGACY_SPACE
+        + 32 // receipt_merkle_tree
+        + 8 // first_receipt_leaf_index
+        + 1 // rollover_receipt_count
+        + 32 // rollover_merkle_tree
+        + 8; // rollover_first_leaf_index
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReceiptKind {
+    Box,
+    Figure,
+}
+
+/// Emitted for every receipt cNFT minted, so indexers can map ids to Bubblegum assets without
+/// replaying the mint transaction.
+#[event]
+pub struct ReceiptMinted {
+    pub config: Pubkey,
+    pub merkle_tree: Pubkey,
+    pub leaf_owner: Pubkey,
+    pub leaf_index: u64,
+    pub asset_id: Pubkey,
+    pub kind: ReceiptKind,
+    pub id: u32,
+}
+
+impl BoxMinterConfig {
+    pub const SEED: &'static [u8] = b"config";
+
+    // Keep these tiny by design; uncompressed Core mints are compute heavy.
+    pub const MAX_NAME_PREFIX: usize = 8;
+    pub const MAX_FIGURE_NAME_PREFIX: usize = 12;
+    pub const MAX_SYMBOL: usize = 10;
+    /// Leaves room for the longest receipt URI tail within `MAX_RECEIPT_URI_LEN` (91 bytes).
+    pub const MAX_URI_BASE: usize = MAX_RECEIPT_URI_LEN - MAX_RECEIPT_URI_TAIL_LEN;
+    /// Space kept for `uri_base`: configs created under the earlier 96-byte limit may use it all.
+    pub const URI_BASE_SPACE: usize = 96;
+
+    pub const SPACE: usize = 8 // anchor account discriminator
+        + 32 * 3 // pubkeys
+        + 8 // price_lamports
+        + 8 // discount_price_lamports
+        + 32 // discount_merkle_root
+        + 4 // max_supply
+        + 1 // max_per_tx
+        + 1 // items_per_box
+        + 4 // minted
+        + 4 + Self::MAX_NAME_PREFIX // name_prefix
+        + 4 + Self::MAX_SYMBOL // symbol
+        + 4 + Self::URI_BASE_SPACE // uri_base
+        + 1 // started (bool)
+        + 1 // bump
+        + 1 // discount_mints_per_wallet
+        + 4 + Self::MAX_FIGURE_NAME_PREFIX // figure_name_prefix
+        + 1 // mint_variant_kind
+        + 4 * MINT_VARIANT_OPTION_COUNT // mint_variant_start_ids
+        + 4 * MINT_VARIANT_OPTION_COUNT // mint_variant_end_ids
+        + 4 * MINT_VARIANT_OPTION_COUNT // mint_variant_next_ids
+        + 32; // drop_seed
+
+    pub fn items_per_box_len(&self) -> usize {
+        self.items_per_box as usize
     }
 
-    pub fn mint_discounted_variant_box<'a, 'b, 'c, 'info>(
-        ctx: Context<'a, 'b, 'c, 'info, MintDiscountedBox<'info>>,
-        variant_index: u8,
-        mint_id: u64,
-        box_bump: u8,
-        proof: Vec<[u8; 32]>,
-    ) -> Result<()> {
-        let discount_price = ctx.accounts.config.discount_price_lamports;
-        require!(discount_price > 0, BoxMinterError::InvalidDiscountPrice);
-        let discount_root = ctx.accounts.config.discount_merkle_root;
+    pub fn require_openable(&self) -> Result<()> {
         require!(
-            discount_root != [0u8; 32],
-            BoxMinterError::DiscountNotConfigured
+            self.items_per_box >= MIN_OPENABLE_ITEMS_PER_BOX,
+            BoxMinterError::OpeningDisabled
         );
+        Ok(())
+    }
 
-        let payer_key = ctx.accounts.payer.key();
-        require!(
-            verify_merkle_proof(payer_key.as_ref(), &proof, discount_root),
-            BoxMinterError::InvalidDiscountProof
-        );
-        let discount_limit = ctx.accounts.config.discount_mints_per_wallet;
-        let asset_bumps = [box_bump];
-        resolve_and_validate_mint_remaining_accounts(
-            &ctx.accounts.config,
-            payer_key,
-            ctx.remaining_accounts,
-            mint_id,
-            &asset_bumps,
-            ctx.program_id,
-        )?;
-        let discount_ai = ctx.accounts.discount_record.to_account_info();
-        let (mut discount_record, discount_bump) = load_or_create_discount_record(
-            ctx.accounts.config.key(),
-            payer_key,
-            ctx.accounts.payer.to_account_info(),
-            discount_ai.clone(),
-            ctx.accounts.system_program.to_account_info(),
-            ctx.program_id,
-        )?;
-        let new_discount_total = discount_record
-            .minted
-            .checked_add(1)
-            .ok_or(BoxMinterError::MathOverflow)?;
-        require!(
-            new_discount_total <= discount_limit,
-            BoxMinterError::DiscountAllowanceExceeded
-        );
+    /// Compute-safe number of boxes `start_open_boxes` may open in one transaction.
+    pub fn max_open_boxes_per_tx(&self) -> usize {
+        (MAX_SAFE_OPEN_CPIS_PER_TX / (1 + self.items_per_box_len())).max(1)
+    }
 
-        let accounts = MintBoxesInnerAccounts::from_discounted_box(&ctx.accounts);
-        mint_variant_box_inner(
-            &mut ctx.accounts.config,
-            &accounts,
-            ctx.remaining_accounts,
-            variant_index,
-            mint_id,
-            box_bump,
-            ctx.program_id,
-            discount_price,
-        )?;
-        discount_record.minted = new_discount_total;
-        discount_record.bump = discount_bump;
-        discount_record.try_serialize(&mut &mut discount_ai.data.borrow_mut()[..])?;
-        Ok(())
+    /// Compute-safe number of boxes `finalize_open_boxes` may finalize in one transaction.
+    pub fn max_finalize_boxes_per_tx(&self) -> usize {
+        (MAX_SAFE_FINALIZE_CPIS_PER_TX / (1 + 3 * self.items_per_box_len())).max(1)
+    }
+
+    pub fn max_figure_id(&self) -> Result<u16> {
+        let total = (self.max_supply as u64)
+            .checked_mul(self.items_per_box as u64)
+            .ok_or(BoxMinterError::MathOverflow)?;
+        require!(total <= u16::MAX as u64, BoxMinterError::InvalidItemsPerBox);
+        Ok(total as u16)
     }
 
-    /// Starts a two-step box open flow.
-    ///
-    /// This instruction performs an MPL-Core `TransferV1` CPI that transfers `box_asset` from the
-    /// user to `config.admin` (vault). This avoids brittle reliance on instruction ordering (some
-    /// wallets inject extra instructions like Compute Budget).
-    ///
-    /// Side effects (all in this one transaction):
-    /// - creates a `PendingOpenBox` PDA keyed by the box asset pubkey
-    /// - mints `config.items_per_box` placeholder Core assets (empty metadata, no collection)
-    ///   owned by `config.admin`
-    pub fn start_open_box<'a, 'b, 'c, 'info>(
-        ctx: Context<'a, 'b, 'c, 'info, StartOpenBox<'info>>,
-    ) -> Result<()> {
-        let cfg = &ctx.accounts.config;
-        cfg.require_openable()?;
-        let items_per_box = cfg.items_per_box_len();
+    pub fn requires_variant_selection(&self) -> bool {
+        self.mint_variant_kind == MINT_VARIANT_KIND_SIZE
+            || self.mint_variant_kind == MINT_VARIANT_KIND_TABLE
+    }
 
-        require_keys_eq!(
-            ctx.accounts.mpl_core_program.key(),
-            MPL_CORE_PROGRAM_ID,
-            BoxMinterError::InvalidMplCoreProgram
+    pub fn uses_variant_table(&self) -> bool {
+        self.mint_variant_kind == MINT_VARIANT_KIND_TABLE
+    }
+
+    pub fn variant_slot(&self, variant_index: u8) -> Result<usize> {
+        require!(
+            self.mint_variant_kind == MINT_VARIANT_KIND_SIZE,
+            BoxMinterError::MintVariantSelectionRequired
         );
-        require_keys_eq!(
-            ctx.accounts.log_wrapper.key(),
-            SPL_NOOP_PROGRAM_ID,
-            BoxMinterError::InvalidLogWrapper
+        let slot = usize::from(variant_index);
+        require!(
+            slot < MINT_VARIANT_OPTION_COUNT,
+            BoxMinterError::InvalidMintVariant
         );
+        Ok(slot)
+    }
 
-        // Pending open record PDA:
-        // - Do not rely on Anchor `init_if_needed` here; its reclaim behavior for pre-funded PDA stubs
-        //   (system-owned, data_len=0) has historically been version-sensitive.
-        // - Starting an open twice for the same box must fail.
-        let pending_ai = ctx.accounts.pending.to_account_info();
-
-        // Create (or reclaim) the pending record PDA.
-        //
-        // Note: a PDA can be "pre-funded", creating a system-owned stub account that makes
-        // `system_instruction::create_account` fail ("account already in use"). Since this is a PDA,
-        // we can sign for it and reclaim it via `allocate` + `assign`.
-        let pending_space: usize = PendingOpenBox::space(cfg.items_per_box);
-        let pending_bump: u8 = ctx.bumps.pending;
-        let box_asset_key = ctx.accounts.box_asset.key();
-        let pending_seeds: &[&[u8]] = &[SEED_PENDING_OPEN, box_asset_key.as_ref(), &[pending_bump]];
-        create_or_reclaim_empty_pda_account(
-            &pending_ai,
-            &ctx.accounts.payer.to_account_info(),
-            &ctx.accounts.system_program.to_account_info(),
-            pending_space,
-            ctx.program_id,
-            pending_seeds,
-            BoxMinterError::InvalidPendingRecord,
-            BoxMinterError::PendingAlreadyExists,
-        )?;
-
-        // Post-conditions: at this point the pending PDA must be a properly sized, program-owned
-        // account ready for serialization.
-        require_keys_eq!(
-            *pending_ai.owner,
-            *ctx.program_id,
-            BoxMinterError::InvalidPendingRecord
+    pub fn next_variant_metadata_id(&self, slot: usize) -> Result<u32> {
+        require!(
+            slot < MINT_VARIANT_OPTION_COUNT,
+            BoxMinterError::InvalidMintVariant
         );
+        let next_id = self.mint_variant_next_ids[slot];
+        let start_id = self.mint_variant_start_ids[slot];
+        let end_id = self.mint_variant_end_ids[slot];
         require!(
-            pending_ai.data_len() == pending_space,
-            BoxMinterError::InvalidPendingRecord
+            next_id >= start_id,
+            BoxMinterError::InvalidMintVariantConfig
         );
+        require!(next_id <= end_id, BoxMinterError::MintVariantUnavailable);
+        Ok(next_id)
+    }
+}
 
-        // Defensive: ensure the provided asset is a Mons *box* owned by payer.
-        let drop_base = cfg.uri_base.as_str();
-        verify_core_asset_owned_by_uri(
-            &ctx.accounts.box_asset.to_account_info(),
-            ctx.accounts.payer.key(),
-            cfg.core_collection,
-            drop_base,
-            legacy_box_uri_base(cfg),
-            URI_PREFIX_BOXES,
-            None,
-        )?;
+/// Variant options for `MINT_VARIANT_KIND_TABLE` drops, each with its own id range, prices and
+/// open settings.
+///
+/// A box's variant is the option whose range contains its box number, so the box URI
+/// (`/b{id}.json`) already identifies how it opens.
+#[account]
+pub struct VariantTable {
+    pub config: Pubkey,
+    /// Contiguous id ranges tiling `1..=config.max_supply`, in `variant_index` order.
+    pub options: Vec<VariantOption>,
+    pub bump: u8,
+}
 
-        // Remaining accounts: exactly `items_per_box` new placeholder figure asset PDAs.
-        require!(
-            ctx.remaining_accounts.len() == items_per_box,
-            BoxMinterError::InvalidRemainingAccounts
-        );
+impl VariantTable {
+    pub const SPACE: usize = 8 // anchor account discriminator
+        + 32 // config
+        + 4 + MAX_VARIANT_TABLE_OPTIONS * VariantOption::SPACE // options
+        + 1; // bump
 
-        // Transfer the box to the vault/admin via MPL-Core `TransferV1` inside this instruction.
-        // This makes the instruction robust against wallets that insert extra instructions into the tx.
-        let mpl_core_program = ctx.accounts.mpl_core_program.to_account_info();
-        let box_asset = ctx.accounts.box_asset.to_account_info();
-        let core_collection = ctx.accounts.core_collection.to_account_info();
-        let payer = ctx.accounts.payer.to_account_info();
-        let vault = ctx.accounts.vault.to_account_info();
-        let system_program = ctx.accounts.system_program.to_account_info();
-        let log_wrapper = ctx.accounts.log_wrapper.to_account_info();
-        let cfg_ai = ctx.accounts.config.to_account_info();
-        let cfg_bump_bytes = [cfg.bump];
-        let cfg_signer_seeds: &[&[u8]] = &[
-            BoxMinterConfig::SEED,
-            cfg.drop_seed.as_ref(),
-            &cfg_bump_bytes,
-        ];
+    pub fn option(&self, variant_index: u8) -> Result<&VariantOption> {
+        self.options
+            .get(usize::from(variant_index))
+            .ok_or(error!(BoxMinterError::InvalidMintVariant))
+    }
 
-        let transfer_ix = Instruction {
-            program_id: MPL_CORE_PROGRAM_ID,
-            accounts: vec![
-                // asset, collection, payer, authority, new_owner, system_program, log_wrapper
-                AccountMeta::new(box_asset.key(), false),
-                AccountMeta::new_readonly(core_collection.key(), false),
-                AccountMeta::new(payer.key(), true),
-                AccountMeta::new_readonly(payer.key(), true),
-                AccountMeta::new_readonly(vault.key(), false),
-                AccountMeta::new_readonly(system_program.key(), false),
-                AccountMeta::new_readonly(log_wrapper.key(), false),
-            ],
-            // TransferV1 discriminator=14, compression_proof=None (0)
-            data: vec![14u8, 0u8],
-        };
-        invoke(
-            &transfer_ix,
-            &[
-                box_asset.clone(),
-                core_collection.clone(),
-                payer.clone(),
-                payer.clone(),
-                vault.clone(),
-                system_program.clone(),
-                log_wrapper.clone(),
-                mpl_core_program.clone(),
-            ],
-        )?;
+    pub fn option_mut(&mut self, variant_index: u8) -> Result<&mut VariantOption> {
+        self.options
+            .get_mut(usize::from(variant_index))
+            .ok_or(error!(BoxMinterError::InvalidMintVariant))
+    }
 
-        // Create placeholder Core assets:
-        // - owner: config.admin (vault/admin)
-        // - update authority: config PDA (so only the program can later "reveal" by updating metadata + setting collection)
-        // - collection: None (placeholder) so the assets do NOT appear in the collection until reveal.
-        let pending_key = ctx.accounts.pending.key();
-        let mut dudes: Vec<Pubkey> = Vec::with_capacity(items_per_box);
+    /// Option whose id range contains `metadata_id`.
+    pub fn option_for_metadata_id(&self, metadata_id: u32) -> Result<&VariantOption> {
+        self.options
+            .iter()
+            .find(|option| metadata_id >= option.start_id && metadata_id <= option.end_id)
+            .ok_or(error!(BoxMinterError::InvalidMintVariant))
+    }
+}
 
-        let mut create_ix = Instruction {
-            program_id: MPL_CORE_PROGRAM_ID,
-            accounts: vec![
-                // 0 asset (placeholder)
-                AccountMeta::new(Pubkey::default(), true),
-                // 1 collection: None => placeholder = program id (must be readonly when absent)
-                AccountMeta::new_readonly(MPL_CORE_PROGRAM_ID, false),
-                // 2 authority (signer): config PDA
-                AccountMeta::new_readonly(cfg_ai.key(), true),
-                // 3 payer (signer)
-                AccountMeta::new(payer.key(), true),
-                // 4 owner: vault/admin (not signer)
-                AccountMeta::new_readonly(vault.key(), false),
-                // 5 update authority: config PDA (not signer account meta)
-                AccountMeta::new_readonly(cfg_ai.key(), false),
-                // 6 system program
-                AccountMeta::new_readonly(system_program.key(), false),
-                // 7 log wrapper: None => placeholder = program id
-                AccountMeta::new_readonly(MPL_CORE_PROGRAM_ID, false),
-            ],
-            data: Vec::with_capacity(32),
-        };
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct VariantOption {
+    pub label: String,
+    pub start_id: u32,
+    pub end_id: u32,
+    pub next_id: u32,
+    pub price_lamports: u64,
+    pub discount_price_lamports: u64,
+    /// Set by the admin to pause a variant, and automatically once its range is exhausted.
+    pub sold_out: bool,
+    /// Figures per box (`<= config.items_per_box`); zero on direct-delivery drops.
+    pub items_per_box: u8,
+    /// Inclusive figure id pool this variant reveals from; pools never overlap.
+    pub figure_start_id: u16,
+    pub figure_end_id: u16,
+}
 
-        for (i, asset_ai) in ctx.remaining_accounts.iter().enumerate() {
-            let i_u8: u8 = i
-                .try_into()
-                .map_err(|_| error!(BoxMinterError::InvalidRemainingAccounts))?;
-            let i_seed = [i_u8];
-            let (expected, asset_bump) = Pubkey::find_program_address(
-                &[SEED_PENDING_DUDE_ASSET, pending_key.as_ref(), &i_seed],
-                ctx.program_id,
-            );
+impl VariantOption {
+    pub const MAX_LABEL: usize = 16;
+    pub const SPACE: usize = 4 + Self::MAX_LABEL // label
+        + 4 // start_id
+        + 4 // end_id
+        + 4 // next_id
+        + 8 // price_lamports
+        + 8 // discount_price_lamports
+        + 1 // sold_out
+        + 1 // items_per_box
+        + 2 // figure_start_id
+        + 2; // figure_end_id
+
+    fn open_settings(&self) -> BoxOpenSettings {
+        BoxOpenSettings {
+            items_per_box: self.items_per_box,
+            figure_start_id: self.figure_start_id,
+            figure_end_id: self.figure_end_id,
+        }
+    }
+}
 
-            require_keys_eq!(asset_ai.key(), expected, BoxMinterError::InvalidAssetPda);
-            // Ensure the account is uninitialized (otherwise Create will fail and waste compute).
-            require_keys_eq!(
-                *asset_ai.owner,
-                anchor_lang::solana_program::system_program::ID,
-                BoxMinterError::InvalidAssetPda
-            );
+/// Secondary-sale royalties for a drop, mirrored into MPL-Core's Royalties plugin.
+#[account]
+pub struct DropRoyalties {
+    pub config: Pubkey,
+    pub basis_points: u16,
+    /// Creator shares; percentages sum to 100.
+    pub creators: Vec<RoyaltyCreator>,
+    pub rule_set: RoyaltyRuleSet,
+    pub bump: u8,
+}
 
-            dudes.push(expected);
+impl DropRoyalties {
+    pub const SPACE: usize = 8 // anchor account discriminator
+        + 32 // config
+        + 2 // basis_points
+        + 4 + MAX_ROYALTY_CREATORS * (32 + 1) // creators
+        + 1 + 4 + MAX_ROYALTY_RULE_SET_PROGRAMS * 32 // rule_set
+        + 1; // bump
+}
 
-            let asset_seeds: &[&[u8]] = &[
-                SEED_PENDING_DUDE_ASSET,
-                pending_key.as_ref(),
-                &i_seed,
-                &[asset_bump],
-            ];
-            let signer_seeds: &[&[&[u8]]] = &[cfg_signer_seeds, asset_seeds];
-
-            // Prevent PDA "squatting": if the placeholder PDA was pre-funded, MPL-Core Create would fail.
-            // Drain any prefunded lamports back to the payer before invoking MPL-Core.
-            let prefund_lamports = asset_ai.lamports();
-            if prefund_lamports > 0 {
-                let sweep_ix = anchor_lang::solana_program::system_instruction::transfer(
-                    asset_ai.key,
-                    payer.key,
-                    prefund_lamports,
-                );
-                invoke_signed(
-                    &sweep_ix,
-                    &[asset_ai.clone(), payer.clone(), system_program.clone()],
-                    &[asset_seeds],
-                )
-                .map_err(anchor_lang::error::Error::from)?;
-            }
+/// Same Borsh layout as MPL-Core's `Creator`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct RoyaltyCreator {
+    pub address: Pubkey,
+    pub percentage: u8,
+}
 
-            create_ix.accounts[0].pubkey = asset_ai.key();
-            create_ix.data.clear();
-            // CreateV1 discriminator=0, DataState::AccountState=0
-            create_ix.data.push(0u8);
-            create_ix.data.push(0u8);
-            // name: empty string
-            create_ix.data.extend_from_slice(&(0u32).to_le_bytes());
-            // uri: empty string
-            create_ix.data.extend_from_slice(&(0u32).to_le_bytes());
-            // plugins: None
-            create_ix.data.push(0u8);
-
-            let create_infos = [
-                mpl_core_program.clone(),
-                asset_ai.clone(),
-                cfg_ai.clone(),
-                payer.clone(),
-                vault.clone(),
-                cfg_ai.clone(),
-                system_program.clone(),
-            ];
-            invoke_signed(&create_ix, &create_infos, signer_seeds)
-                .map_err(anchor_lang::error::Error::from)?;
+/// Same Borsh layout as MPL-Core's `RuleSet`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub enum RoyaltyRuleSet {
+    None,
+    ProgramAllowList(Vec<Pubkey>),
+    ProgramDenyList(Vec<Pubkey>),
+}
+
+/// Per-drop bitmaps of issued box and figure receipts (bit `id - 1`). The bitmaps follow this
+/// header in the account data (box bits, then figure bits) and are updated in place, so a large
+/// drop's ledger is neither deserialized onto the heap nor allocated in one instruction.
+#[account]
+pub struct ReceiptLedger {
+    pub config: Pubkey,
+    pub box_bits_len: u32,
+    pub figure_bits_len: u32,
+    pub bump: u8,
+}
+
+impl ReceiptLedger {
+    pub const HEADER_SPACE: usize = 8 // anchor account discriminator
+        + 32 // config
+        + 4 // box_bits_len
+        + 4 // figure_bits_len
+        + 1; // bump
+
+    pub fn bitmap_len(max_id: u32) -> usize {
+        (max_id as usize).div_ceil(8)
+    }
+
+    /// Account size once both bitmaps are allocated.
+    pub fn space(&self) -> usize {
+        Self::HEADER_SPACE + self.box_bits_len as usize + self.figure_bits_len as usize
+    }
+
+    /// Box and figure bitmaps within the ledger account's `data`.
+    fn bitmaps<'a>(&self, data: &'a mut [u8]) -> Result<(&'a mut [u8], &'a mut [u8])> {
+        let bits = data
+            .get_mut(Self::HEADER_SPACE..self.space())
+            .ok_or(error!(BoxMinterError::ReceiptLedgerNotAllocated))?;
+        Ok(bits.split_at_mut(self.box_bits_len as usize))
+    }
+}
+
+/// Ordered program-owned Bubblegum v2 receipts trees (config PDA is tree creator/delegate).
+#[account]
+pub struct ReceiptTrees {
+    pub config: Pubkey,
+    /// Index into `trees` of the tree receipts are currently minted into.
+    pub active_index: u8,
+    pub trees: Vec<ReceiptTree>,
+    pub bump: u8,
+}
+
+impl ReceiptTrees {
+    pub const SPACE: usize = 8 // anchor account discriminator
+        + 32 // config
+        + 1 // active_index
+        + 4 + MAX_RECEIPT_TREES * ReceiptTree::SPACE // trees
+        + 1; // bump
+
+    fn skip_full_trees(&mut self) {
+        while let Some(tree) = self.trees.get(usize::from(self.active_index)) {
+            if tree.minted < tree.capacity {
+                break;
+            }
+            self.active_index += 1;
         }
+    }
+}
 
-        // Persist the pending flow record so the admin can later finalize it.
-        let record = PendingOpenBox {
-            owner: ctx.accounts.payer.key(),
-            box_asset: ctx.accounts.box_asset.key(),
-            dudes,
-            created_slot: Clock::get()?.slot,
-            bump: pending_bump,
-            config: ctx.accounts.config.key(),
-        };
-        record.try_serialize(&mut &mut pending_ai.data.borrow_mut()[..])?;
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ReceiptTree {
+    pub merkle_tree: Pubkey,
+    /// Leaf capacity (`2^max_depth`), read from the Bubblegum tree config.
+    pub capacity: u64,
+    /// Receipts minted into this tree by the program.
+    pub minted: u64,
+}
+
+impl ReceiptTree {
+    pub const SPACE: usize = 32 // merkle_tree
+        + 8 // capacity
+        + 8; // minted
+}
+
+/// Per-drop shipping fees by zone.
+#[account]
+pub struct DeliveryFeeSchedule {
+    pub config: Pubkey,
+    pub zones: Vec<DeliveryFeeZone>,
+    pub bump: u8,
+}
+
+impl DeliveryFeeSchedule {
+    pub const SPACE: usize = 8 // anchor account discriminator
+        + 32 // config
+        + 4 + MAX_DELIVERY_FEE_ZONES * DeliveryFeeZone::SPACE // zones
+        + 1; // bump
+
+    pub fn zone(&self, zone_id: u16) -> Option<&DeliveryFeeZone> {
+        self.zones.iter().find(|zone| zone.zone_id == zone_id)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct DeliveryFeeZone {
+    pub zone_id: u16,
+    pub base_fee_lamports: u64,
+    pub per_item_fee_lamports: u64,
+    /// Upper bound on the fee of a single delivery to this zone.
+    pub max_fee_lamports: u64,
+}
+
+impl DeliveryFeeZone {
+    pub const SPACE: usize = 2 // zone_id
+        + 8 // base_fee_lamports
+        + 8 // per_item_fee_lamports
+        + 8; // max_fee_lamports
+
+    /// `base + per_item * item_count`, capped at `max_fee_lamports`.
+    pub fn fee_for(&self, item_count: u64) -> u64 {
+        self.per_item_fee_lamports
+            .saturating_mul(item_count)
+            .saturating_add(self.base_fee_lamports)
+            .min(self.max_fee_lamports)
+    }
+}
+
+/// Per-drop destination of delivery fees.
+#[account]
+pub struct DeliveryFeeRouting {
+    pub config: Pubkey,
+    pub route: DeliveryFeeRoute,
+    pub bump: u8,
+}
+
+impl DeliveryFeeRouting {
+    pub const SPACE: usize = 8 // anchor account discriminator
+        + 32 // config
+        + 1 + 1 + SPLIT_PAYMENTS_V1_MAX_RECIPIENTS * (32 + 1) // route
+        + 1; // bump
+}
+
+/// Program-owned custody for a drop: once it exists, opened boxes and delivered items are owned
+/// by this PDA instead of `config.admin`, and the program signs their transfers and burns.
+#[account]
+pub struct CustodyVault {
+    pub config: Pubkey,
+    pub bump: u8,
+}
+
+impl CustodyVault {
+    pub const SPACE: usize = 8 // anchor account discriminator
+        + 32 // config
+        + 1; // bump
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub enum DeliveryFeeRoute {
+    /// Everything to `config.treasury`.
+    Treasury,
+    /// Same recipients and percentages as the drop's `SplitPaymentsV1` mint revenue split.
+    MintSplit,
+    /// A split of its own (e.g. fulfillment partners).
+    Split(SplitPaymentsV1Args),
+}
+
+#[account]
+pub struct PendingOpenBox {
+    /// User who started the open.
+    pub owner: Pubkey,
+    /// The box asset being opened (now owned by the vault).
+    pub box_asset: Pubkey,
+    /// Placeholder dude asset accounts to be updated + transferred on finalize.
+    pub dudes: Vec<Pubkey>,
+    /// Slot when the pending record was created (for UX ordering).
+    pub created_slot: u64,
+    /// PDA bump for this record.
+    pub bump: u8,
+    /// Config PDA that created this pending record. Shared-program drops use this to
+    /// disambiguate pending reveals without relying on external indexers.
+    pub config: Pubkey,
+}
+
+impl PendingOpenBox {
+    pub fn space(items_per_box: u8) -> usize {
+        8 // anchor discriminator
+        + 32 // owner
+        + 32 // box_asset
+        + 4 // dudes vec len
+        + 32 * items_per_box as usize // dudes
+        + 8 // created_slot
+        + 1 // bump
+        + 32 // config
+    }
+}
+
+#[cfg_attr(not(test), allow(dead_code))]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+struct PendingOpenBoxLegacyLayout {
+    pub owner: Pubkey,
+    pub box_asset: Pubkey,
+    pub dudes: Vec<Pubkey>,
+    pub created_slot: u64,
+    pub bump: u8,
+}
 
-        Ok(())
-    }
+#[cfg_attr(not(test), allow(dead_code))]
+struct PendingOpenBoxDecoded {
+    pub owner: Pubkey,
+    pub box_asset: Pubkey,
+    pub dudes: Vec<Pubkey>,
+    pub created_slot: u64,
+    pub bump: u8,
+    pub config: Option<Pubkey>,
+}
 
-    /// Finalizes a pending box open, admin-only.
-    ///
-    /// Performs in one transaction:
-    /// 1) burns the vault-owned box (reclaims rent)
-    /// 2) updates placeholder dudes with real IDs + moves them into the core collection
-    /// 3) transfers dudes to the user
-    /// 4) closes the pending record PDA
-    pub fn finalize_open_box<'a, 'b, 'c, 'info>(
-        ctx: Context<'a, 'b, 'c, 'info, FinalizeOpenBox<'info>>,
-        args: FinalizeOpenBoxArgs,
-    ) -> Result<()> {
-        let cfg = &ctx.accounts.config;
-        let pending_ai = ctx.accounts.pending.to_account_info();
-        require_keys_eq!(
-            *pending_ai.owner,
-            *ctx.program_id,
-            BoxMinterError::InvalidPendingRecord
-        );
-        let pending = {
-            let pending_data = pending_ai.try_borrow_data()?;
-            decode_pending_open_box_account(&pending_data)?
-        };
-        cfg.require_openable()?;
-        let items_per_box = cfg.items_per_box_len();
-        let max_dude_id = cfg.max_figure_id()?;
-        let dude_ids = &args.dude_ids;
+fn decode_pending_open_box_account(data: &[u8]) -> Result<PendingOpenBoxDecoded> {
+    const MIN_PENDING_OPEN_BOX_LEN: usize = 8 + 32 + 32 + 4 + 8 + 1;
 
-        // Admin-only. The admin key is the custody vault for delivered/opened assets.
-        require_keys_eq!(
-            ctx.accounts.cosigner.key(),
-            cfg.admin,
-            BoxMinterError::InvalidCosigner
-        );
+    require!(
+        data.len() >= MIN_PENDING_OPEN_BOX_LEN,
+        BoxMinterError::InvalidPendingRecord
+    );
+    require!(
+        data.get(..8) == Some(PendingOpenBox::DISCRIMINATOR.as_ref()),
+        BoxMinterError::InvalidPendingRecord
+    );
 
-        require_keys_eq!(
-            ctx.accounts.mpl_core_program.key(),
-            MPL_CORE_PROGRAM_ID,
-            BoxMinterError::InvalidMplCoreProgram
-        );
-        require_keys_eq!(
-            ctx.accounts.log_wrapper.key(),
-            SPL_NOOP_PROGRAM_ID,
-            BoxMinterError::InvalidLogWrapper
-        );
+    let mut o = 8usize;
+    let owner = read_pubkey(data, o)?;
+    o += 32;
+    let box_asset = read_pubkey(data, o)?;
+    o += 32;
+    let dude_count = read_u32_le_pending(data, o)? as usize;
+    o += 4;
 
-        // Validate figure IDs.
-        require!(
-            dude_ids.len() == items_per_box,
-            BoxMinterError::InvalidDudeId
-        );
-        for id in dude_ids.iter() {
-            require!(
-                *id >= 1 && *id <= max_dude_id,
-                BoxMinterError::InvalidDudeId
-            );
-        }
-        for i in 0..dude_ids.len() {
-            for j in (i + 1)..dude_ids.len() {
-                require!(dude_ids[i] != dude_ids[j], BoxMinterError::DuplicateDudeId);
-            }
-        }
+    let dude_bytes = dude_count
+        .checked_mul(32)
+        .ok_or(error!(BoxMinterError::InvalidPendingRecord))?;
+    let dudes_end = o
+        .checked_add(dude_bytes)
+        .ok_or(error!(BoxMinterError::InvalidPendingRecord))?;
+    require!(
+        dudes_end <= data.len(),
+        BoxMinterError::InvalidPendingRecord
+    );
 
-        // Pending record must belong to the provided user, and must correspond to this box.
-        require_keys_eq!(
-            pending.box_asset,
-            ctx.accounts.box_asset.key(),
-            BoxMinterError::InvalidPendingRecord
-        );
-        require_keys_eq!(
-            ctx.accounts.user.key(),
-            pending.owner,
-            BoxMinterError::InvalidPendingRecord
-        );
-        if let Some(pending_config) = pending.config {
-            require_keys_eq!(
-                pending_config,
-                ctx.accounts.config.key(),
-                BoxMinterError::InvalidPendingRecord
-            );
-        }
+    let mut dudes = Vec::with_capacity(dude_count);
+    for _ in 0..dude_count {
+        dudes.push(read_pubkey(data, o)?);
+        o += 32;
+    }
+
+    let created_slot = read_u64_le(data, o)?;
+    o += 8;
+    let bump = *data
+        .get(o)
+        .ok_or(error!(BoxMinterError::InvalidPendingRecord))?;
+    o += 1;
 
+    let config = if o == data.len() {
+        None
+    } else {
+        let config_end = o
+            .checked_add(32)
+            .ok_or(error!(BoxMinterError::InvalidPendingRecord))?;
         require!(
-            pending.dudes.len() == items_per_box,
+            config_end == data.len(),
             BoxMinterError::InvalidPendingRecord
         );
-        // Remaining accounts: exactly `items_per_box` placeholder figure assets, in the order stored on-chain.
-        require!(
-            ctx.remaining_accounts.len() == items_per_box,
-            BoxMinterError::InvalidRemainingAccounts
-        );
-        for i in 0..items_per_box {
-            require_keys_eq!(
-                ctx.remaining_accounts[i].key(),
-                pending.dudes[i],
-                BoxMinterError::InvalidRemainingAccounts
-            );
-        }
-
-        // Defensive: ensure the box is a Mons *box* now owned by the vault/admin.
-        let drop_base = cfg.uri_base.as_str();
-        verify_core_asset_owned_by_uri(
-            &ctx.accounts.box_asset.to_account_info(),
-            cfg.admin,
-            cfg.core_collection,
-            drop_base,
-            legacy_box_uri_base(cfg),
-            URI_PREFIX_BOXES,
-            None,
-        )?;
+        Some(read_pubkey(data, o)?)
+    };
 
-        let mpl_core_program = ctx.accounts.mpl_core_program.to_account_info();
-        let core_collection = ctx.accounts.core_collection.to_account_info();
-        let cosigner = ctx.accounts.cosigner.to_account_info();
-        let system_program = ctx.accounts.system_program.to_account_info();
-        let log_wrapper = ctx.accounts.log_wrapper.to_account_info();
-        let cfg_ai = ctx.accounts.config.to_account_info();
-        let cfg_bump_bytes = [cfg.bump];
-        let cfg_signer_seeds: &[&[u8]] = &[
-            BoxMinterConfig::SEED,
-            cfg.drop_seed.as_ref(),
-            &cfg_bump_bytes,
-        ];
+    Ok(PendingOpenBoxDecoded {
+        owner,
+        box_asset,
+        dudes,
+        created_slot,
+        bump,
+        config,
+    })
+}
 
-        // 1) Burn the box (reclaim rent to the admin payer).
-        let burn_ix = Instruction {
-            program_id: MPL_CORE_PROGRAM_ID,
-            accounts: vec![
-                // asset, collection, payer, authority, system_program, log_wrapper
-                AccountMeta::new(ctx.accounts.box_asset.key(), false),
-                AccountMeta::new(core_collection.key(), false),
-                AccountMeta::new(cosigner.key(), true),
-                AccountMeta::new_readonly(cosigner.key(), true),
-                AccountMeta::new_readonly(system_program.key(), false),
-                AccountMeta::new_readonly(log_wrapper.key(), false),
-            ],
-            // BurnV1 discriminator=12, compression_proof=None (0)
-            data: vec![12u8, 0u8],
-        };
+/// Resizes a program-owned account to `len`, topping up rent from `payer`.
+fn grow_program_account<'info>(
+    account: &AccountInfo<'info>,
+    len: usize,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    let minimum_balance = Rent::get()?.minimum_balance(len);
+    let funding = minimum_balance.saturating_sub(account.lamports());
+    if funding > 0 {
+        let ix = anchor_lang::solana_program::system_instruction::transfer(
+            &payer.key(),
+            &account.key(),
+            funding,
+        );
         invoke(
-            &burn_ix,
-            &[
-                ctx.accounts.box_asset.to_account_info(),
-                core_collection.clone(),
-                cosigner.clone(),
-                cosigner.clone(),
-                system_program.clone(),
-                log_wrapper.clone(),
-                mpl_core_program.clone(),
-            ],
+            &ix,
+            &[payer.clone(), account.clone(), system_program.clone()],
         )?;
+    }
+    account.resize(len)?;
+    Ok(())
+}
 
-        // 2) Update + "add to collection" by setting update authority to Collection(core_collection).
-        //
-        // IMPORTANT: MPL-Core only supports moving an asset into a collection via `UpdateV2`
-        // (UpdateV1 cannot add/remove/change collection).
-        let mut name_buf = String::with_capacity(32);
-        let mut uri_buf = String::with_capacity(drop_base.len() + URI_PREFIX_FIGURES.len() + 16);
+fn close_program_account<'info>(
+    account: &AccountInfo<'info>,
+    destination: &AccountInfo<'info>,
+) -> Result<()> {
+    let account_lamports = account.lamports();
+    let destination_lamports = destination.lamports();
+    let updated_destination_lamports = destination_lamports
+        .checked_add(account_lamports)
+        .ok_or(error!(BoxMinterError::MathOverflow))?;
 
-        let mut update_ix = Instruction {
-            program_id: MPL_CORE_PROGRAM_ID,
-            accounts: vec![
-                // UpdateV2 accounts:
-                //   asset, collection (optional), payer, authority, new_collection (optional), system_program, log_wrapper
-                AccountMeta::new(Pubkey::default(), false), // asset placeholder
-                // collection: None (placeholder)
-                AccountMeta::new_readonly(MPL_CORE_PROGRAM_ID, false),
-                AccountMeta::new(cosigner.key(), true),
-                AccountMeta::new_readonly(cfg_ai.key(), true), // authority (config PDA)
-                // new_collection: core collection (writable; mpl-core increments size)
-                AccountMeta::new(core_collection.key(), false),
-                AccountMeta::new_readonly(system_program.key(), false),
-                AccountMeta::new_readonly(log_wrapper.key(), false),
-            ],
-            data: Vec::with_capacity(128),
-        };
+    **destination.try_borrow_mut_lamports()? = updated_destination_lamports;
+    **account.try_borrow_mut_lamports()? = 0;
+    account.assign(&anchor_lang::solana_program::system_program::ID);
+    account.resize(0)?;
+    Ok(())
+}
 
-        // 3) Transfer dudes to the user.
-        let user_ai = ctx.accounts.user.to_account_info();
-        let mut transfer_ix = Instruction {
-            program_id: MPL_CORE_PROGRAM_ID,
-            accounts: vec![
-                // asset, collection, payer, authority, new_owner, system_program, log_wrapper
-                AccountMeta::new(Pubkey::default(), false), // asset placeholder
-                AccountMeta::new_readonly(core_collection.key(), false),
-                AccountMeta::new(cosigner.key(), true),
-                AccountMeta::new_readonly(cosigner.key(), true),
-                AccountMeta::new_readonly(user_ai.key(), false),
-                AccountMeta::new_readonly(system_program.key(), false),
-                AccountMeta::new_readonly(log_wrapper.key(), false),
-            ],
-            // TransferV1 discriminator=14, compression_proof=None (0)
-            data: vec![14u8, 0u8],
-        };
+#[derive(Accounts)]
+#[instruction(args: InitializeArgs)]
+pub struct Initialize<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = BoxMinterConfig::SPACE,
+        seeds = [BoxMinterConfig::SEED, args.drop_seed.as_ref()],
+        bump,
+    )]
+    pub config: Account<'info, BoxMinterConfig>,
 
-        for (i, asset_ai) in ctx.remaining_accounts.iter().enumerate() {
-            let dude_id = dude_ids[i];
-            name_buf.clear();
-            append_label_and_id(&mut name_buf, &cfg.figure_name_prefix, dude_id)?;
-
-            uri_buf.clear();
-            uri_buf.push_str(drop_base);
-            uri_buf.push_str(URI_PREFIX_FIGURES);
-            write!(&mut uri_buf, "{}", dude_id)
-                .map_err(|_| error!(BoxMinterError::SerializationFailed))?;
-            uri_buf.push_str(".json");
-
-            // UpdateV2:
-            // - newName: Some(name)
-            // - newUri: Some(uri)
-            // - newUpdateAuthority: Some(Collection(core_collection))
-            update_ix.accounts[0].pubkey = asset_ai.key();
-            update_ix.data.clear();
-            // discriminator
-            update_ix.data.push(30u8);
-            // newName: Some(string)
-            update_ix.data.push(1u8);
-            update_ix
-                .data
-                .extend_from_slice(&(name_buf.len() as u32).to_le_bytes());
-            update_ix.data.extend_from_slice(name_buf.as_bytes());
-            // newUri: Some(string)
-            update_ix.data.push(1u8);
-            update_ix
-                .data
-                .extend_from_slice(&(uri_buf.len() as u32).to_le_bytes());
-            update_ix.data.extend_from_slice(uri_buf.as_bytes());
-            // newUpdateAuthority: Some(BaseUpdateAuthority::Collection(core_collection))
-            update_ix.data.push(1u8); // Option::Some
-            update_ix.data.push(2u8); // BaseUpdateAuthority::Collection enum index
-            update_ix
-                .data
-                .extend_from_slice(core_collection.key().as_ref());
+    #[account(
+        mut,
+        constraint = admin.key() == EXPECTED_INITIALIZER @ BoxMinterError::UnauthorizedInitializer
+    )]
+    pub admin: Signer<'info>,
 
-            invoke_signed(
-                &update_ix,
-                &[
-                    asset_ai.clone(),
-                    core_collection.clone(),
-                    cosigner.clone(),
-                    cfg_ai.clone(),
-                    system_program.clone(),
-                    log_wrapper.clone(),
-                    mpl_core_program.clone(),
-                ],
-                &[cfg_signer_seeds],
-            )
-            .map_err(anchor_lang::error::Error::from)?;
+    /// CHECK: Any SOL receiver is fine; stored in config.
+    pub treasury: UncheckedAccount<'info>,
 
-            // TransferV1 to the user.
-            transfer_ix.accounts[0].pubkey = asset_ai.key();
-            invoke(
-                &transfer_ix,
-                &[
-                    asset_ai.clone(),
-                    core_collection.clone(),
-                    cosigner.clone(),
-                    cosigner.clone(),
-                    user_ai.clone(),
-                    system_program.clone(),
-                    log_wrapper.clone(),
-                    mpl_core_program.clone(),
-                ],
-            )?;
-        }
+    /// CHECK: MPL-Core collection address; stored in config and validated (owner == mpl-core program).
+    pub core_collection: UncheckedAccount<'info>,
 
-        close_program_account(&pending_ai, &cosigner)?;
-        Ok(())
-    }
+    pub system_program: Program<'info, System>,
+}
 
-    pub fn deliver<'a, 'b, 'c, 'info>(
-        ctx: Context<'a, 'b, 'c, 'info, Deliver<'info>>,
-        args: DeliverArgs,
-    ) -> Result<()> {
-        let cfg = &ctx.accounts.config;
+#[derive(Accounts)]
+pub struct SplitPaymentsV1Capability {}
 
-        // Require a cloud-held signer (same admin as initialize) so users can't choose arbitrary fees.
-        // The delivery fee itself is determined off-chain and embedded in the cosigned transaction.
-        require_keys_eq!(
-            ctx.accounts.cosigner.key(),
-            cfg.admin,
-            BoxMinterError::InvalidCosigner
-        );
+#[derive(Accounts)]
+pub struct SetTreasury<'info> {
+    #[account(mut, seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, BoxMinterConfig>,
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetMintPrices<'info> {
+    #[account(mut, seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, BoxMinterConfig>,
+    pub admin: Signer<'info>,
+}
 
-        require!(
-            !ctx.remaining_accounts.is_empty(),
-            BoxMinterError::InvalidQuantity
-        );
-        require!(
-            (ctx.remaining_accounts.len() as u8) <= MAX_SAFE_DELIVERY_ITEMS_PER_TX,
-            BoxMinterError::InvalidQuantity
-        );
+#[derive(Accounts)]
+pub struct SetUriBase<'info> {
+    #[account(mut, seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, BoxMinterConfig>,
+    pub admin: Signer<'info>,
+}
 
-        require_keys_eq!(
-            ctx.accounts.mpl_core_program.key(),
-            MPL_CORE_PROGRAM_ID,
-            BoxMinterError::InvalidMplCoreProgram
-        );
-        require_keys_eq!(
-            ctx.accounts.log_wrapper.key(),
-            SPL_NOOP_PROGRAM_ID,
-            BoxMinterError::InvalidLogWrapper
-        );
+#[derive(Accounts)]
+pub struct InitializeVariantTable<'info> {
+    #[account(seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, BoxMinterConfig>,
 
-        // Delivery record PDA: `delivery` + config + delivery_id.
-        let config_key = ctx.accounts.config.key();
-        let delivery_id_bytes = args.delivery_id.to_le_bytes();
-        let expected_delivery = Pubkey::create_program_address(
-            &[
-                SEED_DELIVERY,
-                config_key.as_ref(),
-                &delivery_id_bytes,
-                &[args.delivery_bump],
-            ],
-            ctx.program_id,
-        )
-        .map_err(|_| error!(BoxMinterError::InvalidDeliveryPda))?;
-        require_keys_eq!(
-            ctx.accounts.delivery.key(),
-            expected_delivery,
-            BoxMinterError::InvalidDeliveryPda
-        );
-        let delivery_ai = ctx.accounts.delivery.to_account_info();
-        // Create (or reclaim) the tiny on-chain delivery record (presence == paid order).
-        //
-        // Note: a PDA can be "pre-funded", creating a system-owned stub account that makes
-        // `system_instruction::create_account` fail ("account already in use"). Since this is a PDA,
-        // we can sign for it and reclaim it via `allocate` + `assign`.
-        let delivery_space: usize = DeliveryRecord::SPACE;
-        let delivery_bump_bytes = [args.delivery_bump];
-        let delivery_seeds: &[&[u8]] = &[
-            SEED_DELIVERY,
-            config_key.as_ref(),
-            &delivery_id_bytes,
-            &delivery_bump_bytes,
-        ];
-        create_or_reclaim_empty_pda_account(
-            &delivery_ai,
-            &ctx.accounts.payer.to_account_info(),
-            &ctx.accounts.system_program.to_account_info(),
-            delivery_space,
-            ctx.program_id,
-            delivery_seeds,
-            BoxMinterError::InvalidDeliveryPda,
-            BoxMinterError::DeliveryAlreadyExists,
-        )?;
+    #[account(mut)]
+    pub admin: Signer<'info>,
 
-        let record = DeliveryRecord {
-            payer: ctx.accounts.payer.key(),
-            delivery_fee_lamports: args.delivery_fee_lamports,
-            item_count: ctx.remaining_accounts.len() as u16,
-        };
-        record.try_serialize(&mut &mut delivery_ai.data.borrow_mut()[..])?;
+    #[account(
+        init,
+        payer = admin,
+        space = VariantTable::SPACE,
+        seeds = [SEED_VARIANT_TABLE, config.key().as_ref()],
+        bump,
+    )]
+    pub variant_table: Account<'info, VariantTable>,
 
-        // Take delivery payment (enforced on-chain).
-        if args.delivery_fee_lamports > 0 {
-            let ix = anchor_lang::solana_program::system_instruction::transfer(
-                &ctx.accounts.payer.key(),
-                &ctx.accounts.treasury.key(),
-                args.delivery_fee_lamports,
-            );
-            invoke(
-                &ix,
-                &[
-                    ctx.accounts.payer.to_account_info(),
-                    ctx.accounts.treasury.to_account_info(),
-                    ctx.accounts.system_program.to_account_info(),
-                ],
-            )?;
-        }
+    pub system_program: Program<'info, System>,
+}
 
-        // Transfer all delivered assets to the vault (config.admin) via MPL-Core `TransferV1`.
-        let mpl_core_program = ctx.accounts.mpl_core_program.to_account_info();
-        let core_collection = ctx.accounts.core_collection.to_account_info();
-        let payer = ctx.accounts.payer.to_account_info();
-        // Vault is the admin/cosigner key (custody); payment receiver is `config.treasury`.
-        let vault = ctx.accounts.cosigner.to_account_info();
-        let system_program = ctx.accounts.system_program.to_account_info();
-        let log_wrapper = ctx.accounts.log_wrapper.to_account_info();
+#[derive(Accounts)]
+pub struct SetVariantOption<'info> {
+    #[account(seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, BoxMinterConfig>,
+    pub admin: Signer<'info>,
 
-        let mut transfer_ix = Instruction {
-            program_id: MPL_CORE_PROGRAM_ID,
-            accounts: vec![
-                // asset, collection, payer, authority, new_owner, system_program, log_wrapper
-                AccountMeta::new(Pubkey::default(), false), // asset placeholder
-                AccountMeta::new_readonly(core_collection.key(), false),
-                AccountMeta::new(payer.key(), true),
-                AccountMeta::new_readonly(payer.key(), true),
-                AccountMeta::new_readonly(vault.key(), false),
-                AccountMeta::new_readonly(system_program.key(), false),
-                AccountMeta::new_readonly(log_wrapper.key(), false),
-            ],
-            // TransferV1 discriminator=14, compression_proof=None (0)
-            data: vec![14u8, 0u8],
-        };
+    #[account(mut, seeds = [SEED_VARIANT_TABLE, config.key().as_ref()], bump = variant_table.bump)]
+    pub variant_table: Account<'info, VariantTable>,
+}
 
-        for asset_ai in ctx.remaining_accounts.iter() {
-            transfer_ix.accounts[0].pubkey = asset_ai.key();
-            invoke(
-                &transfer_ix,
-                &[
-                    asset_ai.clone(),
-                    core_collection.clone(),
-                    payer.clone(),
-                    payer.clone(),
-                    vault.clone(),
-                    system_program.clone(),
-                    log_wrapper.clone(),
-                    mpl_core_program.clone(),
-                ],
-            )?;
-        }
-        Ok(())
-    }
+#[derive(Accounts)]
+pub struct RebalanceVariantRanges<'info> {
+    #[account(mut, seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, BoxMinterConfig>,
+    pub admin: Signer<'info>,
 
-    pub fn close_delivery(_ctx: Context<CloseDelivery>, _args: CloseDeliveryArgs) -> Result<()> {
-        // The `CloseDelivery` account constraints enforce:
-        // - `cosigner` == `config.admin`
-        // - `delivery` is the expected PDA
-        // - `delivery` is closed to `cosigner` (rent reclaimed) via Anchor's canonical close path
-        Ok(())
-    }
+    /// Required for `MINT_VARIANT_KIND_TABLE` drops.
+    #[account(mut, seeds = [SEED_VARIANT_TABLE, config.key().as_ref()], bump = variant_table.bump)]
+    pub variant_table: Option<Account<'info, VariantTable>>,
+}
 
-    /// Admin-only off-chain order fulfillment for drops.
-    ///
-    /// This reserves a contiguous run of variant metadata ids, creates an idempotency PDA keyed by
-    /// the off-chain order hash, and mints the matching receipt cNFTs to the recipient wallet.
-    /// For non-variant drops, `variant_index` is ignored and the order record stores `0`.
-    /// Exact retries of a fulfilled order are accepted as no-ops.
-    pub fn admin_deliver_variant_order(
-        ctx: Context<AdminDeliverVariantOrder>,
-        args: AdminDeliverVariantOrderArgs,
-    ) -> Result<()> {
-        validate_admin_order_hash(&args.order_hash)?;
-        require!(args.quantity >= 1, BoxMinterError::InvalidQuantity);
+#[derive(Accounts)]
+pub struct InitializeDropRoyalties<'info> {
+    #[account(seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, BoxMinterConfig>,
 
-        let config_key = ctx.accounts.config.key();
-        let order_bump = validate_admin_order_pda(
-            ctx.program_id,
-            &config_key,
-            &args.order_hash,
-            ctx.accounts.admin_order.key(),
-        )?;
-        let order_bump_bytes = [order_bump];
-        let order_seeds: &[&[u8]] = &[
-            SEED_ADMIN_ORDER,
-            config_key.as_ref(),
-            args.order_hash.as_ref(),
-            &order_bump_bytes,
-        ];
+    #[account(mut)]
+    pub admin: Signer<'info>,
 
-        let order_ai = ctx.accounts.admin_order.to_account_info();
-        let receipt_owner = ctx.accounts.receipt_owner.key();
-        let effective_variant_index =
-            admin_delivery_effective_variant_index(&ctx.accounts.config, args.variant_index);
-        require!(
-            receipt_owner != Pubkey::default(),
-            BoxMinterError::InvalidReceiptOwner
-        );
-        if !order_ai.data_is_empty() {
-            let existing = deserialize_admin_order_record(&order_ai, ctx.program_id)?;
-            require!(
-                admin_order_is_valid_retry(
-                    &existing,
-                    &args,
-                    effective_variant_index,
-                    receipt_owner,
-                    order_bump,
-                ),
-                BoxMinterError::AdminOrderAlreadyExists
-            );
-            return Ok(());
-        }
-        let receipt_accounts = ReceiptMintAccounts::from_admin_delivery(&ctx.accounts);
-        validate_receipt_mint_accounts(&receipt_accounts)?;
+    #[account(
+        init,
+        payer = admin,
+        space = DropRoyalties::SPACE,
+        seeds = [SEED_ROYALTIES, config.key().as_ref()],
+        bump,
+    )]
+    pub royalties: Account<'info, DropRoyalties>,
 
-        let first_metadata_id = reserve_admin_delivery_metadata_ids(
-            &mut *ctx.accounts.config,
-            effective_variant_index,
-            args.quantity,
-        )?;
+    pub system_program: Program<'info, System>,
+}
 
-        create_or_reclaim_empty_pda_account(
-            &order_ai,
-            &ctx.accounts.cosigner.to_account_info(),
-            &ctx.accounts.system_program.to_account_info(),
-            AdminDeliveryOrderRecord::SPACE,
-            ctx.program_id,
-            order_seeds,
-            BoxMinterError::InvalidAdminOrderPda,
-            BoxMinterError::AdminOrderAlreadyExists,
-        )?;
+#[derive(Accounts)]
+pub struct SetDropRoyalties<'info> {
+    #[account(seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, BoxMinterConfig>,
+    pub admin: Signer<'info>,
 
-        let record = AdminDeliveryOrderRecord {
-            order_hash: args.order_hash,
-            variant_index: effective_variant_index,
-            quantity: args.quantity,
-            first_metadata_id,
-            receipt_owner,
-            created_slot: Clock::get()?.slot,
-            bump: order_bump,
-        };
-        record.try_serialize(&mut &mut order_ai.data.borrow_mut()[..])?;
-        mint_admin_order_receipt_cnfts(
-            &ctx.accounts.config,
-            &receipt_accounts,
-            first_metadata_id,
-            args.quantity,
-        )
-    }
+    #[account(mut, seeds = [SEED_ROYALTIES, config.key().as_ref()], bump = royalties.bump)]
+    pub royalties: Account<'info, DropRoyalties>,
+}
 
-    /// Mint compressed (Bubblegum v2) receipt cNFTs into the receipts tree, admin/cosigner-only.
-    ///
-    /// This is used by:
-    /// - delivery receipt issuance (boxes + figures)
-    /// - IRL claim flow (figures)
-    ///
-    /// Receipt metadata is derived on-chain from the configured `config.uri_base` so the backend
-    /// does not duplicate receipt URI/name logic.
-    pub fn mint_receipts(ctx: Context<MintReceipts>, args: MintReceiptsArgs) -> Result<()> {
-        let cfg = &ctx.accounts.config;
+#[derive(Accounts)]
+pub struct InitializeReceiptLedger<'info> {
+    #[account(seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, BoxMinterConfig>,
 
-        // Admin-only (server cosigner).
-        require_keys_eq!(
-            ctx.accounts.cosigner.key(),
-            cfg.admin,
-            BoxMinterError::InvalidCosigner
-        );
+    #[account(mut)]
+    pub admin: Signer<'info>,
 
-        let box_ids = args.box_ids;
-        let dude_ids = args.dude_ids;
-        let max_dude_id = cfg.max_figure_id()?;
+    #[account(
+        init,
+        payer = admin,
+        space = ReceiptLedger::HEADER_SPACE,
+        seeds = [SEED_RECEIPT_LEDGER, config.key().as_ref()],
+        bump,
+    )]
+    pub receipt_ledger: Account<'info, ReceiptLedger>,
 
-        // Defensive caps (Bubblegum mints are compute-heavy).
-        let total = box_ids
-            .len()
-            .checked_add(dude_ids.len())
-            .ok_or(BoxMinterError::MathOverflow)?;
-        require!(total > 0, BoxMinterError::InvalidQuantity);
-        // Conservative cap; if the backend wants more, it should batch.
-        require!(total <= 24, BoxMinterError::InvalidQuantity);
+    pub system_program: Program<'info, System>,
+}
 
-        // Validate box IDs (must correspond to configured box supply).
-        for id in box_ids.iter() {
-            require!(
-                *id >= 1 && *id <= cfg.max_supply,
-                BoxMinterError::InvalidAssetMetadata
-            );
-        }
-        // Validate dude IDs.
-        for id in dude_ids.iter() {
-            require!(
-                *id >= 1 && *id <= max_dude_id,
-                BoxMinterError::InvalidDudeId
-            );
-        }
-        // Ensure there are no duplicates (cheap O(n^2) since n is tiny).
-        for i in 0..box_ids.len() {
-            for j in (i + 1)..box_ids.len() {
-                require!(
-                    box_ids[i] != box_ids[j],
-                    BoxMinterError::InvalidAssetMetadata
-                );
-            }
-        }
-        for i in 0..dude_ids.len() {
-            for j in (i + 1)..dude_ids.len() {
-                require!(dude_ids[i] != dude_ids[j], BoxMinterError::DuplicateDudeId);
-            }
-        }
+#[derive(Accounts)]
+pub struct GrowReceiptLedger<'info> {
+    #[account(seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, BoxMinterConfig>,
 
-        let receipt_accounts = ReceiptMintAccounts::from_mint_receipts(&ctx.accounts);
-        validate_receipt_mint_accounts(&receipt_accounts)?;
+    #[account(mut)]
+    pub admin: Signer<'info>,
 
-        let drop_base = cfg.uri_base.as_str();
-        let mut mint_cpi = new_receipt_mint_cpi(&receipt_accounts);
+    #[account(mut, seeds = [SEED_RECEIPT_LEDGER, config.key().as_ref()], bump = receipt_ledger.bump)]
+    pub receipt_ledger: Account<'info, ReceiptLedger>,
 
-        let mut name_buf = String::with_capacity(48);
-        let mut uri_buf = String::with_capacity(
-            drop_base.len()
-                + URI_PREFIX_RECEIPTS_BOXES
-                    .len()
-                    .max(URI_PREFIX_RECEIPTS_FIGURES.len())
-                + 16,
-        );
+    pub system_program: Program<'info, System>,
+}
 
-        for box_id in box_ids.iter() {
-            build_receipt_name_and_uri(
-                &mut name_buf,
-                &mut uri_buf,
-                drop_base,
-                &cfg.name_prefix,
-                URI_PREFIX_RECEIPTS_BOXES,
-                *box_id,
-            )?;
-            invoke_receipt_mint_v2(&mut mint_cpi, cfg.core_collection, &name_buf, &uri_buf)?;
-        }
+#[derive(Accounts)]
+pub struct InitializeReceiptTrees<'info> {
+    #[account(seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, BoxMinterConfig>,
 
-        for dude_id in dude_ids.iter() {
-            build_receipt_name_and_uri(
-                &mut name_buf,
-                &mut uri_buf,
-                drop_base,
-                &cfg.figure_name_prefix,
-                URI_PREFIX_RECEIPTS_FIGURES,
-                *dude_id,
-            )?;
-            invoke_receipt_mint_v2(&mut mint_cpi, cfg.core_collection, &name_buf, &uri_buf)?;
-        }
+    #[account(mut)]
+    pub admin: Signer<'info>,
 
-        Ok(())
-    }
-}
+    #[account(
+        init,
+        payer = admin,
+        space = ReceiptTrees::SPACE,
+        seeds = [SEED_RECEIPT_TREES, config.key().as_ref()],
+        bump,
+    )]
+    pub receipt_trees: Account<'info, ReceiptTrees>,
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
-pub struct InitializeArgs {
-    pub price_lamports: u64,
-    pub discount_price_lamports: u64,
-    pub discount_merkle_root: [u8; 32],
-    pub max_supply: u32,
-    pub max_per_tx: u8,
-    pub items_per_box: u8,
-    pub name_prefix: String,
-    pub symbol: String,
-    pub uri_base: String,
-    pub discount_mints_per_wallet: u8,
-    pub figure_name_prefix: String,
-    pub mint_variant_kind: u8,
-    pub mint_variant_start_ids: [u32; MINT_VARIANT_OPTION_COUNT],
-    pub mint_variant_end_ids: [u32; MINT_VARIANT_OPTION_COUNT],
-    pub mint_variant_next_ids: [u32; MINT_VARIANT_OPTION_COUNT],
-    pub drop_seed: [u8; 32],
+    pub system_program: Program<'info, System>,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
-pub struct SplitPaymentsV1Args {
-    pub recipient_count: u8,
-    pub recipients: [Pubkey; SPLIT_PAYMENTS_V1_MAX_RECIPIENTS],
-    pub percentages: [u8; SPLIT_PAYMENTS_V1_MAX_RECIPIENTS],
-}
+#[derive(Accounts)]
+pub struct CreateReceiptsTree<'info> {
+    #[account(seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, BoxMinterConfig>,
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
-pub struct FinalizeOpenBoxArgs {
-    pub dude_ids: Vec<u16>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(mut, seeds = [SEED_RECEIPT_TREES, config.key().as_ref()], bump = receipt_trees.bump)]
+    pub receipt_trees: Account<'info, ReceiptTrees>,
+
+    /// CHECK: Pre-allocated receipt cNFT Merkle tree (owned by MPL account compression program).
+    #[account(mut)]
+    pub merkle_tree: UncheckedAccount<'info>,
+
+    /// CHECK: Bubblegum tree config PDA for `merkle_tree` (validated in the handler).
+    #[account(mut)]
+    pub tree_config: UncheckedAccount<'info>,
+
+    /// CHECK: Metaplex Bubblegum program.
+    #[account(address = BUBBLEGUM_PROGRAM_ID @ BoxMinterError::InvalidBubblegumProgram)]
+    pub bubblegum_program: UncheckedAccount<'info>,
+
+    /// CHECK: Metaplex Noop program (Bubblegum v2 log wrapper).
+    #[account(address = MPL_NOOP_PROGRAM_ID)]
+    pub log_wrapper: UncheckedAccount<'info>,
+
+    /// CHECK: Metaplex Account Compression program.
+    #[account(address = MPL_ACCOUNT_COMPRESSION_PROGRAM_ID)]
+    pub compression_program: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
-pub struct DeliverArgs {
-    pub delivery_id: u32,
-    pub delivery_fee_lamports: u64,
-    /// PDA bump for `delivery` record (passed from client to avoid find_program_address compute).
-    pub delivery_bump: u8,
+#[derive(Accounts)]
+pub struct InitializeDeliveryFeeSchedule<'info> {
+    #[account(seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, BoxMinterConfig>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = DeliveryFeeSchedule::SPACE,
+        seeds = [SEED_DELIVERY_FEES, config.key().as_ref()],
+        bump,
+    )]
+    pub delivery_fees: Account<'info, DeliveryFeeSchedule>,
+
+    pub system_program: Program<'info, System>,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
-pub struct CloseDeliveryArgs {
-    pub delivery_id: u32,
-    pub delivery_bump: u8,
+#[derive(Accounts)]
+pub struct SetDeliveryFeeSchedule<'info> {
+    #[account(seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, BoxMinterConfig>,
+    pub admin: Signer<'info>,
+
+    #[account(mut, seeds = [SEED_DELIVERY_FEES, config.key().as_ref()], bump = delivery_fees.bump)]
+    pub delivery_fees: Account<'info, DeliveryFeeSchedule>,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
-pub struct MintReceiptsArgs {
-    pub box_ids: Vec<u32>,
-    pub dude_ids: Vec<u16>,
+#[derive(Accounts)]
+pub struct InitializeDeliveryFeeRouting<'info> {
+    #[account(seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, BoxMinterConfig>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = DeliveryFeeRouting::SPACE,
+        seeds = [SEED_DELIVERY_FEE_ROUTING, config.key().as_ref()],
+        bump,
+    )]
+    pub delivery_fee_routing: Account<'info, DeliveryFeeRouting>,
+
+    pub system_program: Program<'info, System>,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
-pub struct AdminDeliverVariantOrderArgs {
-    pub order_hash: [u8; 32],
-    /// Ignored for non-variant drops.
-    pub variant_index: u8,
-    pub quantity: u8,
+#[derive(Accounts)]
+pub struct SetDeliveryFeeRouting<'info> {
+    #[account(seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, BoxMinterConfig>,
+    pub admin: Signer<'info>,
+
+    #[account(mut, seeds = [SEED_DELIVERY_FEE_ROUTING, config.key().as_ref()], bump = delivery_fee_routing.bump)]
+    pub delivery_fee_routing: Account<'info, DeliveryFeeRouting>,
 }
 
-#[account]
-pub struct BoxMinterConfig {
-    pub admin: Pubkey,
-    pub treasury: Pubkey,
-    pub core_collection: Pubkey,
-    pub price_lamports: u64,
-    pub discount_price_lamports: u64,
-    pub discount_merkle_root: [u8; 32],
-    pub max_supply: u32,
-    pub max_per_tx: u8,
-    pub items_per_box: u8,
-    pub minted: u32,
-    pub name_prefix: String,
-    pub symbol: String,
-    pub uri_base: String,
-    /// If false, `mint_boxes` is paused.
-    pub started: bool,
-    pub bump: u8,
-    pub discount_mints_per_wallet: u8,
-    pub figure_name_prefix: String,
-    pub mint_variant_kind: u8,
-    pub mint_variant_start_ids: [u32; MINT_VARIANT_OPTION_COUNT],
-    pub mint_variant_end_ids: [u32; MINT_VARIANT_OPTION_COUNT],
-    pub mint_variant_next_ids: [u32; MINT_VARIANT_OPTION_COUNT],
-    pub drop_seed: [u8; 32],
+#[derive(Accounts)]
+pub struct InitializeCustodyVault<'info> {
+    #[account(seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, BoxMinterConfig>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = CustodyVault::SPACE,
+        seeds = [SEED_CUSTODY_VAULT, config.key().as_ref()],
+        bump,
+    )]
+    pub custody_vault: Account<'info, CustodyVault>,
+
+    pub system_program: Program<'info, System>,
 }
 
-#[account]
-pub struct DiscountMintRecord {
-    pub payer: Pubkey,
-    pub minted: u8,
-    pub bump: u8,
+#[derive(Accounts)]
+pub struct StartMint<'info> {
+    #[account(mut, seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, BoxMinterConfig>,
+    pub admin: Signer<'info>,
 }
 
-impl DiscountMintRecord {
-    pub const SPACE: usize = 8 // anchor account discriminator
-        + 32 // payer
-        + 1 // minted
-        + 1; // bump
+#[derive(Accounts)]
+pub struct MintBoxes<'info> {
+    #[account(mut, seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump)]
+    pub config: Account<'info, BoxMinterConfig>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Must match config.treasury
+    #[account(mut, address = config.treasury)]
+    pub treasury: UncheckedAccount<'info>,
+
+    /// CHECK: MPL-Core collection. Must match config.core_collection.
+    #[account(mut, address = config.core_collection)]
+    pub core_collection: UncheckedAccount<'info>,
+
+    /// CHECK: Metaplex Core program
+    pub mpl_core_program: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: Drop royalties PDA. Uninitialized until the admin configures royalties; the handler
+    /// only reads it when program-owned.
+    #[account(seeds = [SEED_ROYALTIES, config.key().as_ref()], bump)]
+    pub royalties: UncheckedAccount<'info>,
 }
 
-#[account]
-pub struct DeliveryRecord {
-    pub payer: Pubkey,
-    pub delivery_fee_lamports: u64,
-    pub item_count: u16,
+#[derive(Accounts)]
+pub struct MintVariantBox<'info> {
+    #[account(mut, seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump)]
+    pub config: Account<'info, BoxMinterConfig>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Must match config.treasury
+    #[account(mut, address = config.treasury)]
+    pub treasury: UncheckedAccount<'info>,
+
+    /// CHECK: MPL-Core collection. Must match config.core_collection.
+    #[account(mut, address = config.core_collection)]
+    pub core_collection: UncheckedAccount<'info>,
+
+    /// CHECK: Metaplex Core program
+    pub mpl_core_program: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: Drop royalties PDA. Uninitialized until the admin configures royalties; the handler
+    /// only reads it when program-owned.
+    #[account(seeds = [SEED_ROYALTIES, config.key().as_ref()], bump)]
+    pub royalties: UncheckedAccount<'info>,
+
+    /// Variant table; required for `MINT_VARIANT_KIND_TABLE` drops.
+    #[account(mut, seeds = [SEED_VARIANT_TABLE, config.key().as_ref()], bump = variant_table.bump)]
+    pub variant_table: Option<Account<'info, VariantTable>>,
 }
 
-impl DeliveryRecord {
-    pub const SPACE: usize = 8 // anchor account discriminator
-        + 32 // payer
-        + 8 // delivery_fee_lamports
-        + 2; // item_count
-}
+#[derive(Accounts)]
+pub struct MintDiscountedBox<'info> {
+    #[account(mut, seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump)]
+    pub config: Account<'info, BoxMinterConfig>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Discount mint record PDA. The handler creates it on demand or loads the existing
+    /// program-owned record after validating the PDA seeds above.
+    #[account(
+        mut,
+        seeds = [SEED_DISCOUNT_MINT, config.key().as_ref(), payer.key().as_ref()],
+        bump,
+    )]
+    pub discount_record: UncheckedAccount<'info>,
+
+    /// CHECK: Must match config.treasury
+    #[account(mut, address = config.treasury)]
+    pub treasury: UncheckedAccount<'info>,
+
+    /// CHECK: MPL-Core collection. Must match config.core_collection.
+    #[account(mut, address = config.core_collection)]
+    pub core_collection: UncheckedAccount<'info>,
+
+    /// CHECK: Metaplex Core program
+    pub mpl_core_program: UncheckedAccount<'info>,
 
-#[account]
-pub struct AdminDeliveryOrderRecord {
-    pub order_hash: [u8; 32],
-    pub variant_index: u8,
-    pub quantity: u8,
-    pub first_metadata_id: u32,
-    pub receipt_owner: Pubkey,
-    pub created_slot: u64,
-    pub bump: u8,
-}
+    pub system_program: Program<'info, System>,
 
-impl AdminDeliveryOrderRecord {
-    pub const SPACE: usize = 8 // anchor account discriminator
-        + 32 // order_hash
-        + 1 // variant_index
-        + 1 // quantity
-        + 4 // first_metadata_id
-        + 32 // receipt_owner
-        + 8 // created_slot
-        + 1; // bump
-}
+    /// CHECK: Drop royalties PDA. Uninitialized until the admin configures royalties; the handler
+    /// only reads it when program-owned.
+    #[account(seeds = [SEED_ROYALTIES, config.key().as_ref()], bump)]
+    pub royalties: UncheckedAccount<'info>,
 
-impl BoxMinterConfig {
-    pub const SEED: &'static [u8] = b"config";
+    /// Variant table; required for `MINT_VARIANT_KIND_TABLE` drops.
+    #[account(mut, seeds = [SEED_VARIANT_TABLE, config.key().as_ref()], bump = variant_table.bump)]
+    pub variant_table: Option<Account<'info, VariantTable>>,
+}
 
-    // Keep these tiny by design; uncompressed Core mints are compute heavy.
-    pub const MAX_NAME_PREFIX: usize = 8;
-    pub const MAX_FIGURE_NAME_PREFIX: usize = 12;
-    pub const MAX_SYMBOL: usize = 10;
-    pub const MAX_URI_BASE: usize = 96;
+#[derive(Accounts)]
+pub struct StartOpenBox<'info> {
+    #[account(seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump)]
+    pub config: Account<'info, BoxMinterConfig>,
 
-    pub const SPACE: usize = 8 // anchor account discriminator
-        + 32 * 3 // pubkeys
-        + 8 // price_lamports
-        + 8 // discount_price_lamports
-        + 32 // discount_merkle_root
-        + 4 // max_supply
-        + 1 // max_per_tx
-        + 1 // items_per_box
-        + 4 // minted
-        + 4 + Self::MAX_NAME_PREFIX // name_prefix
-        + 4 + Self::MAX_SYMBOL // symbol
-        + 4 + Self::MAX_URI_BASE // uri_base
-        + 1 // started (bool)
-        + 1 // bump
-        + 1 // discount_mints_per_wallet
-        + 4 + Self::MAX_FIGURE_NAME_PREFIX // figure_name_prefix
-        + 1 // mint_variant_kind
-        + 4 * MINT_VARIANT_OPTION_COUNT // mint_variant_start_ids
-        + 4 * MINT_VARIANT_OPTION_COUNT // mint_variant_end_ids
-        + 4 * MINT_VARIANT_OPTION_COUNT // mint_variant_next_ids
-        + 32; // drop_seed
+    #[account(mut)]
+    pub payer: Signer<'info>,
 
-    pub fn items_per_box_len(&self) -> usize {
-        self.items_per_box as usize
-    }
+    /// CHECK: Existing box Core asset account to transfer to the vault.
+    #[account(mut)]
+    pub box_asset: UncheckedAccount<'info>,
 
-    pub fn require_openable(&self) -> Result<()> {
-        require!(
-            self.items_per_box >= MIN_OPENABLE_ITEMS_PER_BOX,
-            BoxMinterError::OpeningDisabled
-        );
-        Ok(())
-    }
+    /// CHECK: Must match config.admin (vault that receives box transfers and temporarily owns placeholder dudes).
+    #[account(address = config.admin)]
+    pub vault: UncheckedAccount<'info>,
 
-    pub fn max_figure_id(&self) -> Result<u16> {
-        let total = (self.max_supply as u64)
-            .checked_mul(self.items_per_box as u64)
-            .ok_or(BoxMinterError::MathOverflow)?;
-        require!(total <= u16::MAX as u64, BoxMinterError::InvalidItemsPerBox);
-        Ok(total as u16)
-    }
+    /// CHECK: Drop custody vault PDA. Uninitialized until the admin creates it; once program-owned
+    /// it takes the boxes and placeholder dudes instead of `vault`.
+    #[account(seeds = [SEED_CUSTODY_VAULT, config.key().as_ref()], bump)]
+    pub custody_vault: UncheckedAccount<'info>,
 
-    pub fn requires_variant_selection(&self) -> bool {
-        self.mint_variant_kind == MINT_VARIANT_KIND_SIZE
-    }
+    /// CHECK: MPL-Core collection. Must match config.core_collection.
+    #[account(address = config.core_collection)]
+    pub core_collection: UncheckedAccount<'info>,
 
-    pub fn variant_slot(&self, variant_index: u8) -> Result<usize> {
-        require!(
-            self.requires_variant_selection(),
-            BoxMinterError::MintVariantSelectionRequired
-        );
-        let slot = usize::from(variant_index);
-        require!(
-            slot < MINT_VARIANT_OPTION_COUNT,
-            BoxMinterError::InvalidMintVariant
-        );
-        Ok(slot)
-    }
+    /// CHECK: Metaplex Core program
+    pub mpl_core_program: UncheckedAccount<'info>,
 
-    pub fn next_variant_metadata_id(&self, slot: usize) -> Result<u32> {
-        require!(
-            slot < MINT_VARIANT_OPTION_COUNT,
-            BoxMinterError::InvalidMintVariant
-        );
-        let next_id = self.mint_variant_next_ids[slot];
-        let start_id = self.mint_variant_start_ids[slot];
-        let end_id = self.mint_variant_end_ids[slot];
-        require!(
-            next_id >= start_id,
-            BoxMinterError::InvalidMintVariantConfig
-        );
-        require!(next_id <= end_id, BoxMinterError::MintVariantUnavailable);
-        Ok(next_id)
-    }
-}
+    pub system_program: Program<'info, System>,
 
-#[account]
-pub struct PendingOpenBox {
-    /// User who started the open.
-    pub owner: Pubkey,
-    /// The box asset being opened (now owned by the vault).
-    pub box_asset: Pubkey,
-    /// Placeholder dude asset accounts to be updated + transferred on finalize.
-    pub dudes: Vec<Pubkey>,
-    /// Slot when the pending record was created (for UX ordering).
-    pub created_slot: u64,
-    /// PDA bump for this record.
-    pub bump: u8,
-    /// Config PDA that created this pending record. Shared-program drops use this to
-    /// disambiguate pending reveals without relying on external indexers.
-    pub config: Pubkey,
-}
+    /// CHECK: SPL Noop program (MPL-Core log wrapper).
+    #[account(address = SPL_NOOP_PROGRAM_ID)]
+    pub log_wrapper: UncheckedAccount<'info>,
 
-impl PendingOpenBox {
-    pub fn space(items_per_box: u8) -> usize {
-        8 // anchor discriminator
-        + 32 // owner
-        + 32 // box_asset
-        + 4 // dudes vec len
-        + 32 * items_per_box as usize // dudes
-        + 8 // created_slot
-        + 1 // bump
-        + 32 // config
-    }
-}
+    /// CHECK: Pending open record PDA derived from `[SEED_PENDING_OPEN, box_asset]`.
+    ///
+    /// This is intentionally `UncheckedAccount` so the handler can create it or reclaim a
+    /// pre-funded PDA stub (PDA squatting). The handler checks that the account is either
+    /// uninitialized (`data_is_empty()` / system-owned stub) or, after creation, a properly-sized
+    /// program-owned account, and it will never overwrite an initialized record.
+    #[account(
+        mut,
+        seeds = [SEED_PENDING_OPEN, box_asset.key().as_ref()],
+        bump,
+    )]
+    pub pending: UncheckedAccount<'info>,
 
-#[cfg_attr(not(test), allow(dead_code))]
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
-struct PendingOpenBoxLegacyLayout {
-    pub owner: Pubkey,
-    pub box_asset: Pubkey,
-    pub dudes: Vec<Pubkey>,
-    pub created_slot: u64,
-    pub bump: u8,
-}
+    /// CHECK: Drop royalties PDA. Uninitialized until the admin configures royalties; the handler
+    /// only reads it when program-owned.
+    #[account(seeds = [SEED_ROYALTIES, config.key().as_ref()], bump)]
+    pub royalties: UncheckedAccount<'info>,
 
-#[cfg_attr(not(test), allow(dead_code))]
-struct PendingOpenBoxDecoded {
-    pub owner: Pubkey,
-    pub box_asset: Pubkey,
-    pub dudes: Vec<Pubkey>,
-    pub created_slot: u64,
-    pub bump: u8,
-    pub config: Option<Pubkey>,
+    /// Variant table (per-variant open settings); required for variant drops, ignored otherwise.
+    #[account(seeds = [SEED_VARIANT_TABLE, config.key().as_ref()], bump = variant_table.bump)]
+    pub variant_table: Option<Account<'info, VariantTable>>,
 }
 
-fn decode_pending_open_box_account(data: &[u8]) -> Result<PendingOpenBoxDecoded> {
-    const MIN_PENDING_OPEN_BOX_LEN: usize = 8 + 32 + 32 + 4 + 8 + 1;
+#[derive(Accounts)]
+pub struct StartOpenBoxes<'info> {
+    #[account(seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump)]
+    pub config: Account<'info, BoxMinterConfig>,
 
-    require!(
-        data.len() >= MIN_PENDING_OPEN_BOX_LEN,
-        BoxMinterError::InvalidPendingRecord
-    );
-    require!(
-        data.get(..8) == Some(PendingOpenBox::DISCRIMINATOR.as_ref()),
-        BoxMinterError::InvalidPendingRecord
-    );
+    #[account(mut)]
+    pub payer: Signer<'info>,
 
-    let mut o = 8usize;
-    let owner = read_pubkey(data, o)?;
-    o += 32;
-    let box_asset = read_pubkey(data, o)?;
-    o += 32;
-    let dude_count = read_u32_le_pending(data, o)? as usize;
-    o += 4;
+    /// CHECK: Must match config.admin (vault that receives box transfers and temporarily owns placeholder dudes).
+    #[account(address = config.admin)]
+    pub vault: UncheckedAccount<'info>,
 
-    let dude_bytes = dude_count
-        .checked_mul(32)
-        .ok_or(error!(BoxMinterError::InvalidPendingRecord))?;
-    let dudes_end = o
-        .checked_add(dude_bytes)
-        .ok_or(error!(BoxMinterError::InvalidPendingRecord))?;
-    require!(
-        dudes_end <= data.len(),
-        BoxMinterError::InvalidPendingRecord
-    );
+    /// CHECK: Drop custody vault PDA. Uninitialized until the admin creates it; once program-owned
+    /// it takes the boxes and placeholder dudes instead of `vault`.
+    #[account(seeds = [SEED_CUSTODY_VAULT, config.key().as_ref()], bump)]
+    pub custody_vault: UncheckedAccount<'info>,
 
-    let mut dudes = Vec::with_capacity(dude_count);
-    for _ in 0..dude_count {
-        dudes.push(read_pubkey(data, o)?);
-        o += 32;
-    }
+    /// CHECK: MPL-Core collection. Must match config.core_collection.
+    #[account(address = config.core_collection)]
+    pub core_collection: UncheckedAccount<'info>,
 
-    let created_slot = read_u64_le(data, o)?;
-    o += 8;
-    let bump = *data
-        .get(o)
-        .ok_or(error!(BoxMinterError::InvalidPendingRecord))?;
-    o += 1;
+    /// CHECK: Metaplex Core program
+    pub mpl_core_program: UncheckedAccount<'info>,
 
-    let config = if o == data.len() {
-        None
-    } else {
-        let config_end = o
-            .checked_add(32)
-            .ok_or(error!(BoxMinterError::InvalidPendingRecord))?;
-        require!(
-            config_end == data.len(),
-            BoxMinterError::InvalidPendingRecord
-        );
-        Some(read_pubkey(data, o)?)
-    };
+    pub system_program: Program<'info, System>,
 
-    Ok(PendingOpenBoxDecoded {
-        owner,
-        box_asset,
-        dudes,
-        created_slot,
-        bump,
-        config,
-    })
-}
+    /// CHECK: SPL Noop program (MPL-Core log wrapper).
+    #[account(address = SPL_NOOP_PROGRAM_ID)]
+    pub log_wrapper: UncheckedAccount<'info>,
 
-fn close_program_account<'info>(
-    account: &AccountInfo<'info>,
-    destination: &AccountInfo<'info>,
-) -> Result<()> {
-    let account_lamports = account.lamports();
-    let destination_lamports = destination.lamports();
-    let updated_destination_lamports = destination_lamports
-        .checked_add(account_lamports)
-        .ok_or(error!(BoxMinterError::MathOverflow))?;
+    /// CHECK: Drop royalties PDA. Uninitialized until the admin configures royalties; the handler
+    /// only reads it when program-owned.
+    #[account(seeds = [SEED_ROYALTIES, config.key().as_ref()], bump)]
+    pub royalties: UncheckedAccount<'info>,
 
-    **destination.try_borrow_mut_lamports()? = updated_destination_lamports;
-    **account.try_borrow_mut_lamports()? = 0;
-    account.assign(&anchor_lang::solana_program::system_program::ID);
-    account.resize(0)?;
-    Ok(())
+    /// Variant table (per-variant open settings); required for variant drops, ignored otherwise.
+    #[account(seeds = [SEED_VARIANT_TABLE, config.key().as_ref()], bump = variant_table.bump)]
+    pub variant_table: Option<Account<'info, VariantTable>>,
 }
 
 #[derive(Accounts)]
-#[instruction(args: InitializeArgs)]
-pub struct Initialize<'info> {
-    #[account(
-        init,
-        payer = admin,
-        space = BoxMinterConfig::SPACE,
-        seeds = [BoxMinterConfig::SEED, args.drop_seed.as_ref()],
-        bump,
-    )]
+pub struct FinalizeOpenBox<'info> {
+    #[account(seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump)]
     pub config: Account<'info, BoxMinterConfig>,
 
-    #[account(
-        mut,
-        constraint = admin.key() == EXPECTED_INITIALIZER @ BoxMinterError::UnauthorizedInitializer
-    )]
-    pub admin: Signer<'info>,
+    /// Cloud-held signer (must match config.admin).
+    #[account(mut)]
+    pub cosigner: Signer<'info>,
 
-    /// CHECK: Any SOL receiver is fine; stored in config.
-    pub treasury: UncheckedAccount<'info>,
+    /// CHECK: Vault-owned box Core asset to burn.
+    #[account(mut)]
+    pub box_asset: UncheckedAccount<'info>,
 
-    /// CHECK: MPL-Core collection address; stored in config and validated (owner == mpl-core program).
+    /// CHECK: MPL-Core collection. Must match config.core_collection.
+    #[account(mut, address = config.core_collection)]
     pub core_collection: UncheckedAccount<'info>,
 
+    /// CHECK: Metaplex Core program
+    pub mpl_core_program: UncheckedAccount<'info>,
+
     pub system_program: Program<'info, System>,
-}
 
-#[derive(Accounts)]
-pub struct SplitPaymentsV1Capability {}
+    /// CHECK: SPL Noop program (MPL-Core log wrapper).
+    #[account(address = SPL_NOOP_PROGRAM_ID)]
+    pub log_wrapper: UncheckedAccount<'info>,
 
-#[derive(Accounts)]
-pub struct SetTreasury<'info> {
-    #[account(mut, seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump, has_one = admin)]
-    pub config: Account<'info, BoxMinterConfig>,
-    pub admin: Signer<'info>,
-}
+    /// CHECK: Pending open record PDA. The handler manually decodes either the legacy or v2 layout,
+    /// then explicitly closes the account after a successful finalize to preserve backward
+    /// compatibility for in-flight opens created before the `config` field was added.
+    #[account(
+        mut,
+        seeds = [SEED_PENDING_OPEN, box_asset.key().as_ref()],
+        bump
+    )]
+    pub pending: UncheckedAccount<'info>,
 
-#[derive(Accounts)]
-pub struct SetMintPrices<'info> {
-    #[account(mut, seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump, has_one = admin)]
-    pub config: Account<'info, BoxMinterConfig>,
-    pub admin: Signer<'info>,
+    /// CHECK: User who will receive the dudes (must equal `pending.owner`).
+    pub user: UncheckedAccount<'info>,
+
+    /// Variant table (per-variant open settings); required for variant drops, ignored otherwise.
+    #[account(seeds = [SEED_VARIANT_TABLE, config.key().as_ref()], bump = variant_table.bump)]
+    pub variant_table: Option<Account<'info, VariantTable>>,
+
+    /// CHECK: Drop custody vault PDA; signs for the boxes and dudes it holds once initialized.
+    #[account(seeds = [SEED_CUSTODY_VAULT, config.key().as_ref()], bump)]
+    pub custody_vault: UncheckedAccount<'info>,
 }
 
 #[derive(Accounts)]
-pub struct SetUriBase<'info> {
-    #[account(mut, seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump, has_one = admin)]
+pub struct FinalizeOpenBoxes<'info> {
+    #[account(seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump)]
     pub config: Account<'info, BoxMinterConfig>,
-    pub admin: Signer<'info>,
+
+    /// Cloud-held signer (must match config.admin).
+    #[account(mut, address = config.admin @ BoxMinterError::InvalidCosigner)]
+    pub cosigner: Signer<'info>,
+
+    /// CHECK: MPL-Core collection. Must match config.core_collection.
+    #[account(mut, address = config.core_collection)]
+    pub core_collection: UncheckedAccount<'info>,
+
+    /// CHECK: Metaplex Core program
+    pub mpl_core_program: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: SPL Noop program (MPL-Core log wrapper).
+    #[account(address = SPL_NOOP_PROGRAM_ID)]
+    pub log_wrapper: UncheckedAccount<'info>,
+
+    /// Variant table (per-variant open settings); required for variant drops, ignored otherwise.
+    #[account(seeds = [SEED_VARIANT_TABLE, config.key().as_ref()], bump = variant_table.bump)]
+    pub variant_table: Option<Account<'info, VariantTable>>,
+
+    /// CHECK: Drop custody vault PDA; signs for the boxes and dudes it holds once initialized.
+    #[account(seeds = [SEED_CUSTODY_VAULT, config.key().as_ref()], bump)]
+    pub custody_vault: UncheckedAccount<'info>,
 }
 
 #[derive(Accounts)]
-pub struct StartMint<'info> {
-    #[account(mut, seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump, has_one = admin)]
+pub struct Deliver<'info> {
+    #[account(seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump)]
     pub config: Account<'info, BoxMinterConfig>,
-    pub admin: Signer<'info>,
+
+    /// Cloud-held signer (must match config.admin).
+    pub cosigner: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Must match config.treasury
+    #[account(mut, address = config.treasury)]
+    pub treasury: UncheckedAccount<'info>,
+
+    /// CHECK: MPL-Core collection. Must match config.core_collection.
+    #[account(address = config.core_collection)]
+    pub core_collection: UncheckedAccount<'info>,
+
+    /// CHECK: Metaplex Core program
+    pub mpl_core_program: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: SPL Noop program (MPL-Core log wrapper).
+    #[account(address = SPL_NOOP_PROGRAM_ID)]
+    pub log_wrapper: UncheckedAccount<'info>,
+
+    /// CHECK: Delivery record PDA (created by this instruction).
+    #[account(mut)]
+    pub delivery: UncheckedAccount<'info>,
+
+    /// CHECK: Drop custody vault PDA; once initialized it receives the delivered assets instead of
+    /// `config.admin`.
+    #[account(seeds = [SEED_CUSTODY_VAULT, config.key().as_ref()], bump)]
+    pub custody_vault: UncheckedAccount<'info>,
+
+    /// CHECK: Drop delivery fee schedule PDA; once initialized, the fee is computed from
+    /// `args.delivery_zone`.
+    #[account(seeds = [SEED_DELIVERY_FEES, config.key().as_ref()], bump)]
+    pub delivery_fees: UncheckedAccount<'info>,
+
+    /// CHECK: Drop delivery fee route PDA; once initialized and splitting, the recipients follow
+    /// the assets in `remaining_accounts`.
+    #[account(seeds = [SEED_DELIVERY_FEE_ROUTING, config.key().as_ref()], bump)]
+    pub delivery_fee_routing: UncheckedAccount<'info>,
+
+    /// CHECK: Receipt tree registry PDA; receipt cNFTs in `args.receipts` must come from a
+    /// registered tree.
+    #[account(seeds = [SEED_RECEIPT_TREES, config.key().as_ref()], bump)]
+    pub receipt_trees: UncheckedAccount<'info>,
+
+    /// CHECK: Metaplex Bubblegum program; required with `args.receipts`.
+    #[account(address = BUBBLEGUM_PROGRAM_ID)]
+    pub bubblegum_program: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Metaplex Noop program (Bubblegum v2 log wrapper).
+    #[account(address = MPL_NOOP_PROGRAM_ID)]
+    pub receipts_log_wrapper: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Metaplex Account Compression program.
+    #[account(address = MPL_ACCOUNT_COMPRESSION_PROGRAM_ID)]
+    pub compression_program: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Bubblegum -> MPL-Core CPI signer (receipt burns).
+    #[account(address = MPL_CORE_CPI_SIGNER)]
+    pub mpl_core_cpi_signer: Option<UncheckedAccount<'info>>,
 }
 
 #[derive(Accounts)]
-pub struct MintBoxes<'info> {
-    #[account(mut, seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump)]
+pub struct DeliverWithQuote<'info> {
+    #[account(seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump)]
     pub config: Account<'info, BoxMinterConfig>,
 
+    /// CHECK: config.admin, whose signature comes from the quote; receives the assets until the
+    /// drop has a custody vault.
+    #[account(address = config.admin @ BoxMinterError::InvalidCosigner)]
+    pub vault: UncheckedAccount<'info>,
+
     #[account(mut)]
     pub payer: Signer<'info>,
 
@@ -3118,32 +8298,55 @@ pub struct MintBoxes<'info> {
     pub treasury: UncheckedAccount<'info>,
 
     /// CHECK: MPL-Core collection. Must match config.core_collection.
-    #[account(mut, address = config.core_collection)]
+    #[account(address = config.core_collection)]
     pub core_collection: UncheckedAccount<'info>,
 
     /// CHECK: Metaplex Core program
     pub mpl_core_program: UncheckedAccount<'info>,
 
     pub system_program: Program<'info, System>,
+
+    /// CHECK: SPL Noop program (MPL-Core log wrapper).
+    #[account(address = SPL_NOOP_PROGRAM_ID)]
+    pub log_wrapper: UncheckedAccount<'info>,
+
+    /// CHECK: Delivery record PDA (created by this instruction).
+    #[account(mut)]
+    pub delivery: UncheckedAccount<'info>,
+
+    /// CHECK: Drop custody vault PDA; once initialized it receives the delivered assets instead of
+    /// `vault`.
+    #[account(seeds = [SEED_CUSTODY_VAULT, config.key().as_ref()], bump)]
+    pub custody_vault: UncheckedAccount<'info>,
+
+    /// CHECK: Instructions sysvar (Ed25519 quote verification).
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    /// CHECK: Drop delivery fee schedule PDA; once initialized, the quoted fee must match the
+    /// zone fee for `args.delivery.delivery_zone`.
+    #[account(seeds = [SEED_DELIVERY_FEES, config.key().as_ref()], bump)]
+    pub delivery_fees: UncheckedAccount<'info>,
+
+    /// CHECK: Drop delivery fee route PDA; once initialized and splitting, the recipients follow
+    /// the quoted assets in `remaining_accounts`.
+    #[account(seeds = [SEED_DELIVERY_FEE_ROUTING, config.key().as_ref()], bump)]
+    pub delivery_fee_routing: UncheckedAccount<'info>,
 }
 
 #[derive(Accounts)]
-pub struct MintDiscountedBox<'info> {
-    #[account(mut, seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump)]
+pub struct DeliverWithReceipts<'info> {
+    #[account(seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump)]
     pub config: Account<'info, BoxMinterConfig>,
 
+    /// Cloud-held signer (must match config.admin); also pays for the receipt mints.
+    #[account(mut, address = config.admin @ BoxMinterError::InvalidCosigner)]
+    pub cosigner: Signer<'info>,
+
+    /// Delivering wallet; receives the receipt cNFTs.
     #[account(mut)]
     pub payer: Signer<'info>,
 
-    /// CHECK: Discount mint record PDA. The handler creates it on demand or loads the existing
-    /// program-owned record after validating the PDA seeds above.
-    #[account(
-        mut,
-        seeds = [SEED_DISCOUNT_MINT, config.key().as_ref(), payer.key().as_ref()],
-        bump,
-    )]
-    pub discount_record: UncheckedAccount<'info>,
-
     /// CHECK: Must match config.treasury
     #[account(mut, address = config.treasury)]
     pub treasury: UncheckedAccount<'info>,
@@ -3156,23 +8359,135 @@ pub struct MintDiscountedBox<'info> {
     pub mpl_core_program: UncheckedAccount<'info>,
 
     pub system_program: Program<'info, System>,
+
+    /// CHECK: SPL Noop program (MPL-Core log wrapper).
+    #[account(address = SPL_NOOP_PROGRAM_ID)]
+    pub log_wrapper: UncheckedAccount<'info>,
+
+    /// CHECK: Delivery record PDA (created by this instruction).
+    #[account(mut)]
+    pub delivery: UncheckedAccount<'info>,
+
+    /// CHECK: Drop custody vault PDA; once initialized it receives the delivered assets instead of
+    /// `config.admin`.
+    #[account(seeds = [SEED_CUSTODY_VAULT, config.key().as_ref()], bump)]
+    pub custody_vault: UncheckedAccount<'info>,
+
+    /// CHECK: Receipt cNFT Merkle tree (owned by MPL account compression program).
+    #[account(mut)]
+    pub merkle_tree: UncheckedAccount<'info>,
+
+    /// CHECK: Bubblegum tree config PDA for `merkle_tree`.
+    #[account(mut)]
+    pub tree_config: UncheckedAccount<'info>,
+
+    /// CHECK: Metaplex Bubblegum program
+    pub bubblegum_program: UncheckedAccount<'info>,
+
+    /// CHECK: Metaplex Noop program (Bubblegum v2 log wrapper).
+    #[account(address = MPL_NOOP_PROGRAM_ID)]
+    pub receipts_log_wrapper: UncheckedAccount<'info>,
+
+    /// CHECK: Metaplex Account Compression program.
+    #[account(address = MPL_ACCOUNT_COMPRESSION_PROGRAM_ID)]
+    pub compression_program: UncheckedAccount<'info>,
+
+    /// CHECK: Bubblegum -> MPL-Core CPI signer.
+    #[account(address = MPL_CORE_CPI_SIGNER)]
+    pub mpl_core_cpi_signer: UncheckedAccount<'info>,
+
+    /// CHECK: Drop royalties PDA (seller fee + creators for receipts). May be uninitialized.
+    #[account(seeds = [SEED_ROYALTIES, config.key().as_ref()], bump)]
+    pub royalties: UncheckedAccount<'info>,
+
+    /// CHECK: Drop receipt tree registry PDA; once initialized, `merkle_tree` must be the active
+    /// registered tree and the config PDA signs the mints (before that the cosigner signs for its
+    /// own tree).
+    #[account(mut, seeds = [SEED_RECEIPT_TREES, config.key().as_ref()], bump)]
+    pub receipt_trees: UncheckedAccount<'info>,
+
+    /// CHECK: Drop receipt ledger PDA; once initialized, already-issued ids are skipped.
+    #[account(mut, seeds = [SEED_RECEIPT_LEDGER, config.key().as_ref()], bump)]
+    pub receipt_ledger: UncheckedAccount<'info>,
+
+    /// CHECK: Drop delivery fee schedule PDA; once initialized, the fee is computed from
+    /// `args.delivery_zone`.
+    #[account(seeds = [SEED_DELIVERY_FEES, config.key().as_ref()], bump)]
+    pub delivery_fees: UncheckedAccount<'info>,
+
+    /// CHECK: Drop delivery fee route PDA; once initialized and splitting, the recipients follow
+    /// the assets in `remaining_accounts`.
+    #[account(seeds = [SEED_DELIVERY_FEE_ROUTING, config.key().as_ref()], bump)]
+    pub delivery_fee_routing: UncheckedAccount<'info>,
+
+    /// CHECK: Registered receipts tree after `merkle_tree`; required when the receipts don't all
+    /// fit in `merkle_tree`.
+    #[account(mut)]
+    pub next_merkle_tree: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Bubblegum tree config PDA for `next_merkle_tree`.
+    #[account(mut)]
+    pub next_tree_config: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+#[instruction(args: SealDeliveryArgs)]
+pub struct SealDelivery<'info> {
+    #[account(seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump)]
+    pub config: Account<'info, BoxMinterConfig>,
+
+    /// Cloud-held signer (must match config.admin).
+    #[account(address = config.admin @ BoxMinterError::InvalidCosigner)]
+    pub cosigner: Signer<'info>,
+
+    /// CHECK: Delivery record PDA; decoded in the handler.
+    #[account(
+        mut,
+        seeds = [SEED_DELIVERY, config.key().as_ref(), &args.delivery_id.to_le_bytes()],
+        bump = args.delivery_bump,
+    )]
+    pub delivery: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(args: SetDeliveryStatusArgs)]
+pub struct SetDeliveryStatus<'info> {
+    #[account(seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump)]
+    pub config: Account<'info, BoxMinterConfig>,
+
+    /// Cloud-held signer (must match config.admin); funds the resize of legacy records.
+    #[account(mut, address = config.admin @ BoxMinterError::InvalidCosigner)]
+    pub cosigner: Signer<'info>,
+
+    /// CHECK: Delivery record PDA; decoded in the handler (legacy records are grown in place).
+    #[account(
+        mut,
+        seeds = [SEED_DELIVERY, config.key().as_ref(), &args.delivery_id.to_le_bytes()],
+        bump = args.delivery_bump,
+    )]
+    pub delivery: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct StartOpenBox<'info> {
+#[instruction(args: CancelDeliveryArgs)]
+pub struct CancelDelivery<'info> {
     #[account(seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump)]
     pub config: Account<'info, BoxMinterConfig>,
 
-    #[account(mut)]
-    pub payer: Signer<'info>,
+    /// Cloud-held signer (must match config.admin); pays for the returns and signs them for
+    /// assets it still holds.
+    #[account(mut, address = config.admin @ BoxMinterError::InvalidCosigner)]
+    pub cosigner: Signer<'info>,
 
-    /// CHECK: Existing box Core asset account to transfer to the vault.
-    #[account(mut)]
-    pub box_asset: UncheckedAccount<'info>,
+    /// Treasury co-signs the fee refund.
+    #[account(mut, address = config.treasury)]
+    pub treasury: Signer<'info>,
 
-    /// CHECK: Must match config.admin (vault that receives box transfers and temporarily owns placeholder dudes).
-    #[account(address = config.admin)]
-    pub vault: UncheckedAccount<'info>,
+    /// CHECK: Delivery payer (must match the record); receives the assets, fee and record rent.
+    #[account(mut)]
+    pub payer: UncheckedAccount<'info>,
 
     /// CHECK: MPL-Core collection. Must match config.core_collection.
     #[account(address = config.core_collection)]
@@ -3187,33 +8502,41 @@ pub struct StartOpenBox<'info> {
     #[account(address = SPL_NOOP_PROGRAM_ID)]
     pub log_wrapper: UncheckedAccount<'info>,
 
-    /// CHECK: Pending open record PDA derived from `[SEED_PENDING_OPEN, box_asset]`.
-    ///
-    /// This is intentionally `UncheckedAccount` so the handler can create it or reclaim a
-    /// pre-funded PDA stub (PDA squatting). The handler checks that the account is either
-    /// uninitialized (`data_is_empty()` / system-owned stub) or, after creation, a properly-sized
-    /// program-owned account, and it will never overwrite an initialized record.
+    /// CHECK: Delivery record PDA to cancel; decoded in the handler.
     #[account(
         mut,
-        seeds = [SEED_PENDING_OPEN, box_asset.key().as_ref()],
-        bump,
+        seeds = [SEED_DELIVERY, config.key().as_ref(), &args.delivery_id.to_le_bytes()],
+        bump = args.delivery_bump,
     )]
-    pub pending: UncheckedAccount<'info>,
+    pub delivery: UncheckedAccount<'info>,
+
+    /// CHECK: Drop custody vault PDA; the program signs the returns for assets it holds.
+    #[account(seeds = [SEED_CUSTODY_VAULT, config.key().as_ref()], bump)]
+    pub custody_vault: UncheckedAccount<'info>,
+
+    /// CHECK: Metaplex Bubblegum program; required with `args.receipts`.
+    #[account(address = BUBBLEGUM_PROGRAM_ID)]
+    pub bubblegum_program: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Metaplex Noop program (Bubblegum v2 log wrapper).
+    #[account(address = MPL_NOOP_PROGRAM_ID)]
+    pub receipts_log_wrapper: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Metaplex Account Compression program.
+    #[account(address = MPL_ACCOUNT_COMPRESSION_PROGRAM_ID)]
+    pub compression_program: Option<UncheckedAccount<'info>>,
 }
 
 #[derive(Accounts)]
-pub struct FinalizeOpenBox<'info> {
+#[instruction(args: FulfillFrozenDeliveryArgs)]
+pub struct FulfillFrozenDelivery<'info> {
     #[account(seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump)]
     pub config: Account<'info, BoxMinterConfig>,
 
-    /// Cloud-held signer (must match config.admin).
-    #[account(mut)]
+    /// Cloud-held signer (must match config.admin); pays for the MPL-Core CPIs.
+    #[account(mut, address = config.admin @ BoxMinterError::InvalidCosigner)]
     pub cosigner: Signer<'info>,
 
-    /// CHECK: Vault-owned box Core asset to burn.
-    #[account(mut)]
-    pub box_asset: UncheckedAccount<'info>,
-
     /// CHECK: MPL-Core collection. Must match config.core_collection.
     #[account(mut, address = config.core_collection)]
     pub core_collection: UncheckedAccount<'info>,
@@ -3227,37 +8550,34 @@ pub struct FinalizeOpenBox<'info> {
     #[account(address = SPL_NOOP_PROGRAM_ID)]
     pub log_wrapper: UncheckedAccount<'info>,
 
-    /// CHECK: Pending open record PDA. The handler manually decodes either the legacy or v2 layout,
-    /// then explicitly closes the account after a successful finalize to preserve backward
-    /// compatibility for in-flight opens created before the `config` field was added.
+    /// CHECK: Freeze-in-place delivery record PDA; decoded in the handler.
     #[account(
         mut,
-        seeds = [SEED_PENDING_OPEN, box_asset.key().as_ref()],
-        bump
+        seeds = [SEED_DELIVERY, config.key().as_ref(), &args.delivery_id.to_le_bytes()],
+        bump = args.delivery_bump,
     )]
-    pub pending: UncheckedAccount<'info>,
-
-    /// CHECK: User who will receive the dudes (must equal `pending.owner`).
-    pub user: UncheckedAccount<'info>,
+    pub delivery: UncheckedAccount<'info>,
 }
 
 #[derive(Accounts)]
-pub struct Deliver<'info> {
+#[instruction(args: ReleaseCustodyAssetsArgs)]
+pub struct ReleaseCustodyAssets<'info> {
     #[account(seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump)]
     pub config: Account<'info, BoxMinterConfig>,
 
-    /// Cloud-held signer (must match config.admin).
+    /// Cloud-held signer (must match config.admin); pays for the MPL-Core CPIs.
+    #[account(mut, address = config.admin @ BoxMinterError::InvalidCosigner)]
     pub cosigner: Signer<'info>,
 
-    #[account(mut)]
-    pub payer: Signer<'info>,
+    /// CHECK: Drop custody vault PDA holding the assets; must be initialized.
+    #[account(seeds = [SEED_CUSTODY_VAULT, config.key().as_ref()], bump)]
+    pub custody_vault: UncheckedAccount<'info>,
 
-    /// CHECK: Must match config.treasury
-    #[account(mut, address = config.treasury)]
-    pub treasury: UncheckedAccount<'info>,
+    /// CHECK: New owner of the released assets; ignored when burning.
+    pub recipient: UncheckedAccount<'info>,
 
     /// CHECK: MPL-Core collection. Must match config.core_collection.
-    #[account(address = config.core_collection)]
+    #[account(mut, address = config.core_collection)]
     pub core_collection: UncheckedAccount<'info>,
 
     /// CHECK: Metaplex Core program
@@ -3269,8 +8589,11 @@ pub struct Deliver<'info> {
     #[account(address = SPL_NOOP_PROGRAM_ID)]
     pub log_wrapper: UncheckedAccount<'info>,
 
-    /// CHECK: Delivery record PDA (created by this instruction).
-    #[account(mut)]
+    /// CHECK: Delivery record PDA the released assets belong to; decoded in the handler.
+    #[account(
+        seeds = [SEED_DELIVERY, config.key().as_ref(), &args.delivery_id.to_le_bytes()],
+        bump = args.delivery_bump,
+    )]
     pub delivery: UncheckedAccount<'info>,
 }
 
@@ -3284,16 +8607,30 @@ pub struct CloseDelivery<'info> {
     #[account(mut, address = config.admin)]
     pub cosigner: Signer<'info>,
 
-    /// Delivery record PDA to close (rent reclaimed to `cosigner`).
+    /// CHECK: Delivery record PDA to close; decoded in the handler so records created before
+    /// box/figure counts were tracked can still be closed.
     #[account(
         mut,
         seeds = [SEED_DELIVERY, config.key().as_ref(), &args.delivery_id.to_le_bytes()],
         bump = args.delivery_bump,
-        close = cosigner
     )]
-    pub delivery: Account<'info, DeliveryRecord>,
+    pub delivery: UncheckedAccount<'info>,
 
     pub system_program: Program<'info, System>,
+
+    /// CHECK: Must match `delivery.payer`; receives the record rent it paid for.
+    #[account(mut)]
+    pub payer: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseDeliveries<'info> {
+    #[account(seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump)]
+    pub config: Account<'info, BoxMinterConfig>,
+
+    /// Cloud-held signer (must match config.admin).
+    #[account(address = config.admin @ BoxMinterError::InvalidCosigner)]
+    pub cosigner: Signer<'info>,
 }
 
 #[derive(Accounts)]
@@ -3346,6 +8683,33 @@ pub struct AdminDeliverVariantOrder<'info> {
     pub mpl_core_cpi_signer: UncheckedAccount<'info>,
 
     pub system_program: Program<'info, System>,
+
+    /// CHECK: Drop royalties PDA (seller fee + creators for receipts). May be uninitialized.
+    #[account(seeds = [SEED_ROYALTIES, config.key().as_ref()], bump)]
+    pub royalties: UncheckedAccount<'info>,
+
+    /// Variant table; required for `MINT_VARIANT_KIND_TABLE` drops.
+    #[account(mut, seeds = [SEED_VARIANT_TABLE, config.key().as_ref()], bump = variant_table.bump)]
+    pub variant_table: Option<Account<'info, VariantTable>>,
+
+    /// CHECK: Drop receipt tree registry PDA; once initialized, `merkle_tree` must be the active
+    /// registered tree and the config PDA signs the mints (before that the cosigner signs for its
+    /// own tree).
+    #[account(mut, seeds = [SEED_RECEIPT_TREES, config.key().as_ref()], bump)]
+    pub receipt_trees: UncheckedAccount<'info>,
+
+    /// CHECK: Drop receipt ledger PDA; once initialized, already-issued ids are skipped (or rejected for admin orders).
+    #[account(mut, seeds = [SEED_RECEIPT_LEDGER, config.key().as_ref()], bump)]
+    pub receipt_ledger: UncheckedAccount<'info>,
+
+    /// CHECK: Registered receipts tree after `merkle_tree`; required when the receipts don't all
+    /// fit in `merkle_tree`.
+    #[account(mut)]
+    pub next_merkle_tree: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Bubblegum tree config PDA for `next_merkle_tree`.
+    #[account(mut)]
+    pub next_tree_config: Option<UncheckedAccount<'info>>,
 }
 
 #[derive(Accounts)]
@@ -3392,6 +8756,112 @@ pub struct MintReceipts<'info> {
     pub mpl_core_cpi_signer: UncheckedAccount<'info>,
 
     pub system_program: Program<'info, System>,
+
+    /// CHECK: Drop royalties PDA (seller fee + creators for receipts). May be uninitialized.
+    #[account(seeds = [SEED_ROYALTIES, config.key().as_ref()], bump)]
+    pub royalties: UncheckedAccount<'info>,
+
+    /// CHECK: Drop receipt tree registry PDA; once initialized, `merkle_tree` must be the active
+    /// registered tree and the config PDA signs the mints (before that the cosigner signs for its
+    /// own tree).
+    #[account(mut, seeds = [SEED_RECEIPT_TREES, config.key().as_ref()], bump)]
+    pub receipt_trees: UncheckedAccount<'info>,
+
+    /// CHECK: Drop receipt ledger PDA; once initialized, already-issued ids are skipped (or rejected for admin orders).
+    #[account(mut, seeds = [SEED_RECEIPT_LEDGER, config.key().as_ref()], bump)]
+    pub receipt_ledger: UncheckedAccount<'info>,
+
+    /// CHECK: Registered receipts tree after `merkle_tree`; required when the receipts don't all
+    /// fit in `merkle_tree`.
+    #[account(mut)]
+    pub next_merkle_tree: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Bubblegum tree config PDA for `next_merkle_tree`.
+    #[account(mut)]
+    pub next_tree_config: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Delivery record PDA named by `args.delivery`; checked and marked `receipts_issued`
+    /// in the handler.
+    #[account(mut)]
+    pub delivery: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+#[instruction(args: MintDeliveryReceiptsArgs)]
+pub struct MintDeliveryReceipts<'info> {
+    #[account(seeds = [BoxMinterConfig::SEED, config.drop_seed.as_ref()], bump = config.bump)]
+    pub config: Account<'info, BoxMinterConfig>,
+
+    /// Pays for the receipt mints (and any record resize); the cosigner or the delivery payer.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Delivery payer (must match the record); receives the receipt cNFTs.
+    pub recipient: UncheckedAccount<'info>,
+
+    /// CHECK: Delivery record PDA; decoded in the handler.
+    #[account(
+        mut,
+        seeds = [SEED_DELIVERY, config.key().as_ref(), &args.delivery_id.to_le_bytes()],
+        bump = args.delivery_bump,
+    )]
+    pub delivery: UncheckedAccount<'info>,
+
+    /// CHECK: Receipt cNFT Merkle tree (owned by MPL account compression program).
+    #[account(mut)]
+    pub merkle_tree: UncheckedAccount<'info>,
+
+    /// CHECK: Bubblegum tree config PDA for `merkle_tree`.
+    #[account(mut)]
+    pub tree_config: UncheckedAccount<'info>,
+
+    /// CHECK: MPL-Core collection. Must match config.core_collection.
+    #[account(mut, address = config.core_collection)]
+    pub core_collection: UncheckedAccount<'info>,
+
+    /// CHECK: Metaplex Bubblegum program
+    pub bubblegum_program: UncheckedAccount<'info>,
+
+    /// CHECK: Metaplex Noop program (Bubblegum v2 log wrapper).
+    #[account(address = MPL_NOOP_PROGRAM_ID)]
+    pub log_wrapper: UncheckedAccount<'info>,
+
+    /// CHECK: Metaplex Account Compression program.
+    #[account(address = MPL_ACCOUNT_COMPRESSION_PROGRAM_ID)]
+    pub compression_program: UncheckedAccount<'info>,
+
+    /// CHECK: Metaplex Core program
+    #[account(address = MPL_CORE_PROGRAM_ID)]
+    pub mpl_core_program: UncheckedAccount<'info>,
+
+    /// CHECK: Bubblegum -> MPL-Core CPI signer.
+    #[account(address = MPL_CORE_CPI_SIGNER)]
+    pub mpl_core_cpi_signer: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: Drop royalties PDA (seller fee + creators for receipts). May be uninitialized.
+    #[account(seeds = [SEED_ROYALTIES, config.key().as_ref()], bump)]
+    pub royalties: UncheckedAccount<'info>,
+
+    /// CHECK: Drop receipt tree registry PDA; once initialized, `merkle_tree` must be the active
+    /// registered tree and the config PDA signs the mints (before that the cosigner signs for its
+    /// own tree).
+    #[account(mut, seeds = [SEED_RECEIPT_TREES, config.key().as_ref()], bump)]
+    pub receipt_trees: UncheckedAccount<'info>,
+
+    /// CHECK: Drop receipt ledger PDA; once initialized, already-issued ids are skipped.
+    #[account(mut, seeds = [SEED_RECEIPT_LEDGER, config.key().as_ref()], bump)]
+    pub receipt_ledger: UncheckedAccount<'info>,
+
+    /// CHECK: Registered receipts tree after `merkle_tree`; required when the receipts don't all
+    /// fit in `merkle_tree`.
+    #[account(mut)]
+    pub next_merkle_tree: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Bubblegum tree config PDA for `next_merkle_tree`.
+    #[account(mut)]
+    pub next_tree_config: Option<UncheckedAccount<'info>>,
 }
 
 const MAX_MPL_CORE_NAME_BYTES: usize = 128;
@@ -3566,7 +9036,7 @@ fn verify_core_asset_owned_by_uri(
     legacy_drop_base: Option<&str>,
     expected_uri_suffix: &str,
     expected_ref_id: Option<u32>,
-) -> Result<()> {
+) -> Result<u32> {
     require_keys_eq!(
         *asset_ai.owner,
         MPL_CORE_PROGRAM_ID,
@@ -3591,7 +9061,7 @@ fn verify_core_asset_owned_by_uri(
     if let Some(expected) = expected_ref_id {
         require!(parsed == expected, BoxMinterError::InvalidAssetMetadata);
     }
-    Ok(())
+    Ok(parsed)
 }
 
 fn borsh_push_string(out: &mut Vec<u8>, value: &str) -> Result<()> {
@@ -3748,6 +9218,66 @@ pub enum BoxMinterError {
     InvalidSplitPaymentsPercentages,
     #[msg("Invalid delivery receiver")]
     InvalidDeliveryReceiver,
+    #[msg("Box variant config is required for this drop")]
+    BoxVariantConfigMissing,
+    #[msg("Invalid variant open settings")]
+    InvalidBoxVariantConfig,
+    #[msg("Minting has already started")]
+    MintAlreadyStarted,
+    #[msg("Variant table account is required for this drop")]
+    VariantTableMissing,
+    #[msg("Variant range change overlaps already-issued ids")]
+    VariantRangeOverlapsIssuedIds,
+    #[msg("Invalid royalty configuration")]
+    InvalidRoyalties,
+    #[msg("URI base too long for receipt URIs; shorten it with set_uri_base")]
+    ReceiptUriBaseTooLong,
+    #[msg("No receipt tree capacity left for this drop")]
+    ReceiptTreesFull,
+    #[msg("Merkle tree is not the drop's active receipts tree")]
+    ReceiptsTreeNotActive,
+    #[msg("Receipts exceed the active tree's remaining capacity")]
+    ReceiptsTreeCapacityExceeded,
+    #[msg("Receipt already issued for this id")]
+    ReceiptAlreadyIssued,
+    #[msg("Receipt ledger is not fully allocated")]
+    ReceiptLedgerNotAllocated,
+    #[msg("Invalid receipt asset id bump")]
+    InvalidReceiptAssetBump,
+    #[msg("Invalid delivery status transition")]
+    InvalidDeliveryStatusTransition,
+    #[msg("Tracking hash is required to ship a delivery")]
+    MissingTrackingHash,
+    #[msg("Receipts were already issued for this delivery")]
+    DeliveryReceiptsIssued,
+    #[msg("Delivery record cannot be closed in its current status")]
+    DeliveryNotClosable,
+    #[msg("Payer does not match the delivery record")]
+    InvalidDeliveryPayer,
+    #[msg("Assets do not match the delivery record")]
+    InvalidDeliveryAssets,
+    #[msg("Invalid delivery fee quote")]
+    InvalidDeliveryQuote,
+    #[msg("Delivery fee quote expired")]
+    DeliveryQuoteExpired,
+    #[msg("Invalid delivery fee schedule")]
+    InvalidDeliveryFeeSchedule,
+    #[msg("Delivery zone is not in the fee schedule")]
+    InvalidDeliveryZone,
+    #[msg("Delivery fee does not match the fee schedule")]
+    DeliveryFeeMismatch,
+    #[msg("Split delivery fees cannot be refunded on-chain")]
+    DeliveryFeeNotRefundable,
+    #[msg("Delivery session is not open")]
+    DeliveryNotOpen,
+    #[msg("Delivery items do not match the paid item count")]
+    InvalidDeliveryItemCount,
+    #[msg("Invalid delivered receipt")]
+    InvalidDeliveredReceipt,
+    #[msg("Invalid custody vault")]
+    InvalidCustodyVault,
+    #[msg("Delivery mode not supported here")]
+    InvalidDeliveryMode,
 }
 
 #[cfg(test)]
@@ -3939,7 +9469,7 @@ mod tests {
         let mut cfg = test_standard_cfg();
         cfg.name_prefix = "12345678".to_string();
         cfg.symbol = "1234567890".to_string();
-        cfg.uri_base = "u".repeat(BoxMinterConfig::MAX_URI_BASE);
+        cfg.uri_base = "u".repeat(BoxMinterConfig::URI_BASE_SPACE);
         cfg.figure_name_prefix = "123456789012".to_string();
 
         let split = test_split_payments_v1_three();
@@ -4066,6 +9596,8 @@ mod tests {
             order_hash: [9u8; 32],
             variant_index: 1,
             quantity: 2,
+            with_receipt_asset_data: false,
+            asset_id_bumps: Vec::new(),
         };
         let record = AdminDeliveryOrderRecord {
             order_hash: args.order_hash,
@@ -4075,6 +9607,11 @@ mod tests {
             receipt_owner,
             created_slot: 42,
             bump: 251,
+            receipt_merkle_tree: Pubkey::default(),
+            first_receipt_leaf_index: 0,
+            rollover_receipt_count: 0,
+            rollover_merkle_tree: Pubkey::default(),
+            rollover_first_leaf_index: 0,
         };
 
         assert!(admin_order_is_valid_retry(
@@ -4105,63 +9642,494 @@ mod tests {
             251
         ));
 
-        wrong_args = args.clone();
-        wrong_args.order_hash = [8u8; 32];
-        assert!(!admin_order_is_valid_retry(
-            &record,
-            &wrong_args,
-            args.variant_index,
-            receipt_owner,
-            251
-        ));
-        assert!(!admin_order_is_valid_retry(
-            &record,
-            &args,
-            args.variant_index,
-            Pubkey::new_unique(),
-            251
-        ));
-        assert!(!admin_order_is_valid_retry(
-            &record,
-            &args,
-            args.variant_index,
-            receipt_owner,
-            250
-        ));
+        wrong_args = args.clone();
+        wrong_args.order_hash = [8u8; 32];
+        assert!(!admin_order_is_valid_retry(
+            &record,
+            &wrong_args,
+            args.variant_index,
+            receipt_owner,
+            251
+        ));
+        assert!(!admin_order_is_valid_retry(
+            &record,
+            &args,
+            args.variant_index,
+            Pubkey::new_unique(),
+            251
+        ));
+        assert!(!admin_order_is_valid_retry(
+            &record,
+            &args,
+            args.variant_index,
+            receipt_owner,
+            250
+        ));
+
+        let invalid_record = AdminDeliveryOrderRecord {
+            first_metadata_id: 0,
+            ..record
+        };
+        assert!(!admin_order_is_valid_retry(
+            &invalid_record,
+            &args,
+            args.variant_index,
+            receipt_owner,
+            251
+        ));
+
+        let zero_quantity_args = AdminDeliverVariantOrderArgs {
+            order_hash: [7u8; 32],
+            variant_index: 1,
+            quantity: 0,
+            with_receipt_asset_data: false,
+            asset_id_bumps: Vec::new(),
+        };
+        let zero_quantity_record = AdminDeliveryOrderRecord {
+            order_hash: zero_quantity_args.order_hash,
+            variant_index: zero_quantity_args.variant_index,
+            quantity: zero_quantity_args.quantity,
+            first_metadata_id: 16,
+            receipt_owner,
+            created_slot: 42,
+            bump: 251,
+            receipt_merkle_tree: Pubkey::default(),
+            first_receipt_leaf_index: 0,
+            rollover_receipt_count: 0,
+            rollover_merkle_tree: Pubkey::default(),
+            rollover_first_leaf_index: 0,
+        };
+        assert!(!admin_order_is_valid_retry(
+            &zero_quantity_record,
+            &zero_quantity_args,
+            zero_quantity_args.variant_index,
+            receipt_owner,
+            251
+        ));
+    }
+
+    #[test]
+    fn delivery_record_decodes_legacy_layout_without_counts() {
+        let record = DeliveryRecord {
+            payer: Pubkey::new_unique(),
+            delivery_fee_lamports: 25_000,
+            item_count: 3,
+            box_count: 1,
+            figure_count: 2,
+            assets_hash: delivery_assets_hash(&[Pubkey::new_unique(), Pubkey::new_unique()]),
+            address_cipher_hash: [7u8; 32],
+            created_slot: 99,
+            status: DeliveryStatus::Shipped,
+            tracking_hash: [8u8; 32],
+            paid_item_count: 3,
+            receipt_count: 1,
+            frozen_in_place: true,
+            fulfilled: true,
+            receipts_issued: true,
+            fee_split: true,
+        };
+        let mut data = Vec::with_capacity(DeliveryRecord::SPACE);
+        record.try_serialize(&mut data).unwrap();
+        assert_eq!(data.len(), DeliveryRecord::SPACE);
+
+        let decoded = decode_delivery_record(&data).unwrap();
+        assert_eq!((decoded.box_count, decoded.figure_count), (1, 2));
+        assert_eq!(decoded.assets_hash, record.assets_hash);
+        assert_eq!(decoded.address_cipher_hash, [7u8; 32]);
+        assert_eq!(decoded.created_slot, 99);
+        assert_eq!(decoded.status, DeliveryStatus::Shipped);
+        assert_eq!(decoded.tracking_hash, [8u8; 32]);
+        assert_eq!(decoded.paid_item_count, 3);
+        assert_eq!(decoded.receipt_count, 1);
+        assert!(decoded.frozen_in_place);
+        assert!(decoded.fulfilled);
+        assert!(decoded.receipts_issued);
+        assert!(decoded.fee_split);
+
+        let legacy = decode_delivery_record(&data[..DeliveryRecord::LEGACY_SPACE]).unwrap();
+        assert_eq!(legacy.payer, record.payer);
+        assert_eq!(legacy.delivery_fee_lamports, 25_000);
+        assert_eq!(legacy.item_count, 3);
+        assert_eq!((legacy.box_count, legacy.figure_count), (0, 0));
+        assert_eq!(legacy.assets_hash, [0u8; 32]);
+        assert_eq!(legacy.created_slot, 0);
+        assert_eq!(legacy.status, DeliveryStatus::Paid);
+
+        // Only the legacy and current layouts exist on-chain.
+        assert!(decode_delivery_record(&data[..DeliveryRecord::SPACE - 1]).is_err());
+    }
+
+    #[test]
+    fn delivery_records_close_once_delivered_or_receipts_issued() {
+        use DeliveryStatus::*;
+        let mut record = DeliveryRecord {
+            payer: Pubkey::new_unique(),
+            delivery_fee_lamports: 25_000,
+            item_count: 1,
+            box_count: 1,
+            figure_count: 0,
+            assets_hash: [1u8; 32],
+            address_cipher_hash: [2u8; 32],
+            created_slot: 1,
+            status: Paid,
+            tracking_hash: [0u8; 32],
+            paid_item_count: 1,
+            receipt_count: 0,
+            frozen_in_place: false,
+            fulfilled: false,
+            receipts_issued: false,
+            fee_split: false,
+        };
+        for status in [Paid, Packed, Shipped, Returned, Open] {
+            record.status = status;
+            assert!(!delivery_record_is_closable(&record, DeliveryRecord::SPACE));
+        }
+        // Receipts minted against the record (its assets burned) also let it close.
+        record.receipts_issued = true;
+        assert!(delivery_record_is_closable(&record, DeliveryRecord::SPACE));
+        record.receipts_issued = false;
+        record.status = Delivered;
+        assert!(delivery_record_is_closable(&record, DeliveryRecord::SPACE));
+
+        // Frozen assets must be burned by fulfillment first.
+        record.frozen_in_place = true;
+        assert!(!delivery_record_is_closable(&record, DeliveryRecord::SPACE));
+        record.fulfilled = true;
+        assert!(delivery_record_is_closable(&record, DeliveryRecord::SPACE));
+
+        // Records without a status field decode as `Paid` but predate the lifecycle.
+        record.status = Paid;
+        record.frozen_in_place = false;
+        assert!(delivery_record_is_closable(
+            &record,
+            DeliveryRecord::LEGACY_SPACE
+        ));
+    }
+
+    #[test]
+    fn delivery_status_allows_only_forward_transitions_and_returns() {
+        use DeliveryStatus::*;
+        let allowed = [
+            (Paid, Packed),
+            (Packed, Shipped),
+            (Shipped, Delivered),
+            (Shipped, Returned),
+            (Delivered, Returned),
+        ];
+        for from in [Paid, Packed, Shipped, Delivered, Returned, Open] {
+            for to in [Paid, Packed, Shipped, Delivered, Returned, Open] {
+                assert_eq!(
+                    from.can_transition_to(to),
+                    allowed.contains(&(from, to)),
+                    "{from:?} -> {to:?}"
+                );
+            }
+            assert_eq!(
+                from.is_cancellable(),
+                matches!(from, Paid | Packed | Returned | Open)
+            );
+            assert_eq!(
+                from.allows_receipts(),
+                matches!(from, Packed | Shipped | Delivered)
+            );
+        }
+    }
+
+    fn test_ed25519_ix(signer: &Pubkey, message: &[u8], instruction_index: u16) -> Instruction {
+        let public_key_offset = 16u16;
+        let signature_offset = public_key_offset + 32;
+        let message_offset = signature_offset + 64;
+        let mut data = vec![1u8, 0];
+        for value in [
+            signature_offset,
+            instruction_index,
+            public_key_offset,
+            instruction_index,
+            message_offset,
+            message.len() as u16,
+            instruction_index,
+        ] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.extend_from_slice(signer.as_ref());
+        data.extend_from_slice(&[0u8; 64]);
+        data.extend_from_slice(message);
+        Instruction {
+            program_id: solana_sdk::ed25519_program::ID,
+            accounts: Vec::new(),
+            data,
+        }
+    }
+
+    #[test]
+    fn delivery_quote_requires_inline_ed25519_signature_by_admin() {
+        let admin = Pubkey::new_unique();
+        let payer = Pubkey::new_unique();
+        let config = Pubkey::new_unique();
+        let delivery = DeliverArgs {
+            delivery_id: 7,
+            delivery_fee_lamports: 25_000,
+            delivery_bump: 255,
+            address_cipher_hash: [5u8; 32],
+            delivery_zone: None,
+            receipts: Vec::new(),
+            freeze_in_place: false,
+            asset_id_bumps: Vec::new(),
+        };
+        let quote = |payer: &Pubkey, delivery: &DeliverArgs| {
+            delivery_quote_message(&config, payer, delivery, &[4u8; 32], 1_000).unwrap()
+        };
+        let message = quote(&payer, &delivery);
+        assert!(message.starts_with(DELIVERY_QUOTE_DOMAIN));
+        assert_eq!(
+            message.len(),
+            DELIVERY_QUOTE_DOMAIN.len() + 32 + 4 + 32 + 8 + 32 + 32 + 1 + 32 + 1 + 8
+        );
+
+        let ix = test_ed25519_ix(&admin, &message, u16::MAX);
+        assert_eq!(ix.program_id, ED25519_PROGRAM_ID);
+        verify_ed25519_instruction(&ix, &admin, &message).unwrap();
+
+        assert!(verify_ed25519_instruction(&ix, &Pubkey::new_unique(), &message).is_err());
+        let other_payer = quote(&Pubkey::new_unique(), &delivery);
+        assert!(verify_ed25519_instruction(&ix, &admin, &other_payer).is_err());
+        // Every delivery term the payer picks is covered by the signature.
+        let tweaks: [fn(&mut DeliverArgs); 5] = [
+            |delivery| delivery.delivery_fee_lamports = 1,
+            |delivery| delivery.address_cipher_hash = [6u8; 32],
+            |delivery| delivery.delivery_zone = Some(0),
+            |delivery| delivery.freeze_in_place = true,
+            |delivery| {
+                delivery.receipts.push(DeliveredReceiptArgs {
+                    root: [0u8; 32],
+                    data_hash: [0u8; 32],
+                    creator_hash: [0u8; 32],
+                    asset_data_hash: None,
+                    flags: None,
+                    leaf_index: 0,
+                    proof_len: 0,
+                    burn: false,
+                })
+            },
+        ];
+        for tweak in tweaks {
+            let mut other = delivery.clone();
+            tweak(&mut other);
+            let other = quote(&payer, &other);
+            assert!(verify_ed25519_instruction(&ix, &admin, &other).is_err());
+        }
+
+        // Signature data pointing at another instruction is not accepted.
+        let external = test_ed25519_ix(&admin, &message, 0);
+        assert!(verify_ed25519_instruction(&external, &admin, &message).is_err());
+
+        let mut wrong_program = ix.clone();
+        wrong_program.program_id = Pubkey::new_unique();
+        assert!(verify_ed25519_instruction(&wrong_program, &admin, &message).is_err());
+    }
+
+    #[test]
+    fn delivery_fee_schedule_computes_capped_zone_fee() {
+        let domestic = DeliveryFeeZone {
+            zone_id: 1,
+            base_fee_lamports: 10_000,
+            per_item_fee_lamports: 2_000,
+            max_fee_lamports: 20_000,
+        };
+        let schedule = DeliveryFeeSchedule {
+            config: Pubkey::new_unique(),
+            zones: vec![domestic.clone()],
+            bump: 255,
+        };
+        assert_eq!(domestic.fee_for(1), 12_000);
+        assert_eq!(domestic.fee_for(5), 20_000);
+        assert_eq!(domestic.fee_for(32), 20_000);
+
+        let mut args = DeliverArgs {
+            delivery_id: 1,
+            delivery_fee_lamports: 16_000,
+            delivery_bump: 255,
+            address_cipher_hash: [0u8; 32],
+            delivery_zone: Some(1),
+            receipts: Vec::new(),
+            freeze_in_place: false,
+            asset_id_bumps: Vec::new(),
+        };
+        assert_eq!(
+            resolve_delivery_fee(Some(&schedule), &args, 3).unwrap(),
+            16_000
+        );
+        // A backend fee that disagrees with the schedule is rejected rather than charged.
+        assert!(resolve_delivery_fee(Some(&schedule), &args, 2).is_err());
+        args.delivery_zone = Some(2);
+        assert!(resolve_delivery_fee(Some(&schedule), &args, 3).is_err());
+        assert!(resolve_delivery_fee(None, &args, 3).is_err());
+        args.delivery_zone = None;
+        assert!(resolve_delivery_fee(Some(&schedule), &args, 3).is_err());
+        assert_eq!(resolve_delivery_fee(None, &args, 3).unwrap(), 16_000);
+
+        let duplicate = DeliveryFeeScheduleArgs {
+            zones: vec![domestic.clone(), domestic.clone()],
+        };
+        assert!(validate_delivery_fee_schedule(&duplicate).is_err());
+        let base_over_cap = DeliveryFeeScheduleArgs {
+            zones: vec![DeliveryFeeZone {
+                max_fee_lamports: 5_000,
+                ..domestic.clone()
+            }],
+        };
+        assert!(validate_delivery_fee_schedule(&base_over_cap).is_err());
+        assert!(
+            validate_delivery_fee_schedule(&DeliveryFeeScheduleArgs { zones: vec![] }).is_err()
+        );
+        validate_delivery_fee_schedule(&DeliveryFeeScheduleArgs {
+            zones: vec![domestic],
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn delivery_session_hash_matches_a_single_delivery() {
+        let first = Pubkey::new_unique();
+        let second = Pubkey::new_unique();
+        let third = Pubkey::new_unique();
+        let begun = delivery_assets_hash(&[first]);
+        let appended = extend_delivery_assets_hash(&begun, &[second, third]);
+        // Batch boundaries don't matter, so cancel can check a session's assets in one list.
+        assert_eq!(appended, delivery_assets_hash(&[first, second, third]));
+        assert_eq!(
+            appended,
+            extend_delivery_assets_hash(&extend_delivery_assets_hash(&begun, &[second]), &[third])
+        );
+        assert_ne!(
+            appended,
+            extend_delivery_assets_hash(&begun, &[third, second])
+        );
+    }
 
-        let invalid_record = AdminDeliveryOrderRecord {
-            first_metadata_id: 0,
-            ..record
+    #[test]
+    fn delivered_receipt_ix_data_matches_bubblegum_transfer_and_burn_v2() {
+        let mut receipt = DeliveredReceiptArgs {
+            root: [1u8; 32],
+            data_hash: [2u8; 32],
+            creator_hash: [3u8; 32],
+            asset_data_hash: None,
+            flags: Some(0),
+            leaf_index: 9,
+            proof_len: 3,
+            burn: false,
         };
-        assert!(!admin_order_is_valid_retry(
-            &invalid_record,
-            &args,
-            args.variant_index,
-            receipt_owner,
-            251
-        ));
+        let mut expected = IX_BUBBLEGUM_TRANSFER_V2.to_vec();
+        expected.extend_from_slice(&[1u8; 32]);
+        expected.extend_from_slice(&[2u8; 32]);
+        expected.extend_from_slice(&[3u8; 32]);
+        expected.push(0);
+        expected.extend_from_slice(&[1, 0]);
+        expected.extend_from_slice(&9u64.to_le_bytes());
+        expected.extend_from_slice(&9u32.to_le_bytes());
+        assert_eq!(delivered_receipt_ix_data(&receipt), expected);
+
+        receipt.burn = true;
+        receipt.asset_data_hash = Some([4u8; 32]);
+        receipt.flags = None;
+        let mut expected = IX_BUBBLEGUM_BURN_V2.to_vec();
+        expected.extend_from_slice(&[1u8; 32]);
+        expected.extend_from_slice(&[2u8; 32]);
+        expected.extend_from_slice(&[3u8; 32]);
+        expected.push(1);
+        expected.extend_from_slice(&[4u8; 32]);
+        expected.push(0);
+        expected.extend_from_slice(&9u64.to_le_bytes());
+        expected.extend_from_slice(&9u32.to_le_bytes());
+        assert_eq!(delivered_receipt_ix_data(&receipt), expected);
+    }
 
-        let zero_quantity_args = AdminDeliverVariantOrderArgs {
-            order_hash: [7u8; 32],
+    #[test]
+    fn freeze_in_place_plugin_ix_data_matches_mpl_core() {
+        let config = Pubkey::new_unique();
+        let mut expected = vec![2, MPL_CORE_PLUGIN_FREEZE_DELEGATE, 1, 1, 3];
+        expected.extend_from_slice(config.as_ref());
+        assert_eq!(
+            add_delegate_plugin_ix_data(&[MPL_CORE_PLUGIN_FREEZE_DELEGATE, 1], &config),
+            expected
+        );
+        let mut expected = vec![2, MPL_CORE_PLUGIN_BURN_DELEGATE, 1, 3];
+        expected.extend_from_slice(config.as_ref());
+        assert_eq!(
+            add_delegate_plugin_ix_data(&[MPL_CORE_PLUGIN_BURN_DELEGATE], &config),
+            expected
+        );
+        assert_eq!(set_frozen_ix_data(false), [6, 1, 0]);
+        assert_eq!(
+            revoke_plugin_authority_ix_data(MPL_CORE_PLUGIN_BURN_DELEGATE),
+            [10, 2]
+        );
+    }
+
+    #[test]
+    fn delivery_assets_hash_commits_to_order() {
+        let first = Pubkey::new_unique();
+        let second = Pubkey::new_unique();
+        let hash = delivery_assets_hash(&[first, second]);
+        let empty = hashv(&[]).to_bytes();
+        let chained = hashv(&[empty.as_ref(), first.as_ref()]).to_bytes();
+        assert_eq!(hash, hashv(&[chained.as_ref(), second.as_ref()]).to_bytes());
+        assert_ne!(delivery_assets_hash(&[]), [0u8; 32]);
+        assert_ne!(hash, delivery_assets_hash(&[second, first]));
+        assert_ne!(hash, delivery_assets_hash(&[first]));
+    }
+
+    #[test]
+    fn admin_order_record_decodes_legacy_and_leaf_tracking_layouts() {
+        let record = AdminDeliveryOrderRecord {
+            order_hash: [3u8; 32],
             variant_index: 1,
-            quantity: 0,
-        };
-        let zero_quantity_record = AdminDeliveryOrderRecord {
-            order_hash: zero_quantity_args.order_hash,
-            variant_index: zero_quantity_args.variant_index,
-            quantity: zero_quantity_args.quantity,
-            first_metadata_id: 16,
-            receipt_owner,
+            quantity: 2,
+            first_metadata_id: 5,
+            receipt_owner: Pubkey::new_unique(),
             created_slot: 42,
             bump: 251,
+            receipt_merkle_tree: Pubkey::new_unique(),
+            first_receipt_leaf_index: 77,
+            rollover_receipt_count: 1,
+            rollover_merkle_tree: Pubkey::new_unique(),
+            rollover_first_leaf_index: 0,
         };
-        assert!(!admin_order_is_valid_retry(
-            &zero_quantity_record,
-            &zero_quantity_args,
-            zero_quantity_args.variant_index,
-            receipt_owner,
-            251
-        ));
+        let mut data = Vec::with_capacity(AdminDeliveryOrderRecord::SPACE);
+        record.try_serialize(&mut data).unwrap();
+        assert_eq!(data.len(), AdminDeliveryOrderRecord::SPACE);
+
+        let decoded = decode_admin_order_record(&data).unwrap();
+        assert_eq!(decoded.receipt_merkle_tree, record.receipt_merkle_tree);
+        assert_eq!(decoded.first_receipt_leaf_index, 77);
+        assert_eq!(decoded.rollover_receipt_count, 1);
+        assert_eq!(decoded.rollover_merkle_tree, record.rollover_merkle_tree);
+
+        let legacy =
+            decode_admin_order_record(&data[..AdminDeliveryOrderRecord::LEGACY_SPACE]).unwrap();
+        assert_eq!(legacy.order_hash, record.order_hash);
+        assert_eq!(legacy.first_metadata_id, 5);
+        assert_eq!(legacy.bump, 251);
+        assert_eq!(legacy.receipt_merkle_tree, Pubkey::default());
+        assert_eq!(legacy.first_receipt_leaf_index, 0);
+        assert_eq!(legacy.rollover_receipt_count, 0);
+
+        assert!(decode_admin_order_record(&data[..AdminDeliveryOrderRecord::SPACE - 1]).is_err());
+    }
+
+    #[test]
+    fn receipt_asset_id_is_bubblegum_asset_pda() {
+        let merkle_tree = Pubkey::new_unique();
+        let (expected, bump) = Pubkey::find_program_address(
+            &[b"asset", merkle_tree.as_ref(), &9u64.to_le_bytes()],
+            &BUBBLEGUM_PROGRAM_ID,
+        );
+        assert_eq!(receipt_asset_id(&merkle_tree, 9), expected);
+        assert_ne!(receipt_asset_id(&merkle_tree, 10), expected);
+        assert_eq!(
+            receipt_asset_id_with_bump(&merkle_tree, 9, bump).unwrap(),
+            expected
+        );
     }
 
     #[test]
@@ -4171,6 +10139,8 @@ mod tests {
             order_hash: [9u8; 32],
             variant_index: 2,
             quantity: 1,
+            with_receipt_asset_data: false,
+            asset_id_bumps: Vec::new(),
         };
         let record = AdminDeliveryOrderRecord {
             order_hash: args.order_hash,
@@ -4180,6 +10150,11 @@ mod tests {
             receipt_owner,
             created_slot: 42,
             bump: 251,
+            receipt_merkle_tree: Pubkey::default(),
+            first_receipt_leaf_index: 0,
+            rollover_receipt_count: 0,
+            rollover_merkle_tree: Pubkey::default(),
+            rollover_first_leaf_index: 0,
         };
 
         assert!(admin_order_is_valid_retry(
@@ -4195,7 +10170,7 @@ mod tests {
     fn admin_variant_delivery_reserves_next_size_id() {
         let mut cfg = test_size_variant_cfg();
 
-        let id = reserve_admin_delivery_metadata_ids(&mut cfg, 1, 2).unwrap();
+        let id = reserve_admin_delivery_metadata_ids(&mut cfg, None, 1, 2).unwrap();
 
         assert_eq!(id, 16);
         assert_eq!(cfg.minted, 2);
@@ -4207,7 +10182,7 @@ mod tests {
         let mut cfg = test_size_variant_cfg();
         cfg.mint_variant_next_ids[0] = 15;
 
-        assert!(reserve_admin_delivery_metadata_ids(&mut cfg, 0, 2).is_err());
+        assert!(reserve_admin_delivery_metadata_ids(&mut cfg, None, 0, 2).is_err());
         assert_eq!(cfg.minted, 0);
         assert_eq!(cfg.mint_variant_next_ids[0], 15);
     }
@@ -4215,14 +10190,14 @@ mod tests {
     #[test]
     fn admin_variant_delivery_requires_valid_quantity() {
         let mut cfg = test_size_variant_cfg();
-        assert!(reserve_admin_delivery_metadata_ids(&mut cfg, 0, 0).is_err());
+        assert!(reserve_admin_delivery_metadata_ids(&mut cfg, None, 0, 0).is_err());
 
         cfg.max_per_tx = 1;
-        assert!(reserve_admin_delivery_metadata_ids(&mut cfg, 0, 2).is_err());
+        assert!(reserve_admin_delivery_metadata_ids(&mut cfg, None, 0, 2).is_err());
 
         let mut boxed_variant_cfg = test_size_variant_cfg();
         boxed_variant_cfg.items_per_box = 1;
-        assert!(reserve_admin_delivery_metadata_ids(&mut boxed_variant_cfg, 0, 1).is_ok());
+        assert!(reserve_admin_delivery_metadata_ids(&mut boxed_variant_cfg, None, 0, 1).is_ok());
     }
 
     #[test]
@@ -4230,7 +10205,7 @@ mod tests {
         let mut cfg = test_standard_cfg();
         cfg.minted = 4;
 
-        let id = reserve_admin_delivery_metadata_ids(&mut cfg, 2, 3).unwrap();
+        let id = reserve_admin_delivery_metadata_ids(&mut cfg, None, 2, 3).unwrap();
 
         assert_eq!(id, 5);
         assert_eq!(cfg.minted, 7);
@@ -4242,7 +10217,7 @@ mod tests {
         let mut cfg = test_size_variant_cfg();
         cfg.started = false;
 
-        assert!(reserve_admin_delivery_metadata_ids(&mut cfg, 1, 2).is_err());
+        assert!(reserve_admin_delivery_metadata_ids(&mut cfg, None, 1, 2).is_err());
         assert_eq!(cfg.minted, 0);
         assert_eq!(cfg.mint_variant_next_ids, [1, 16, 31]);
     }
@@ -4452,6 +10427,707 @@ mod tests {
         );
     }
 
+    fn test_table_variant_cfg() -> BoxMinterConfig {
+        let mut cfg = test_size_variant_cfg();
+        cfg.mint_variant_kind = MINT_VARIANT_KIND_TABLE;
+        cfg.mint_variant_start_ids = [0; MINT_VARIANT_OPTION_COUNT];
+        cfg.mint_variant_end_ids = [0; MINT_VARIANT_OPTION_COUNT];
+        cfg.mint_variant_next_ids = [0; MINT_VARIANT_OPTION_COUNT];
+        cfg
+    }
+
+    fn test_variant_option_args(label: &str, start_id: u32, end_id: u32) -> VariantOptionArgs {
+        VariantOptionArgs {
+            label: label.to_string(),
+            start_id,
+            end_id,
+            price_lamports: 10 * u64::from(start_id),
+            discount_price_lamports: 5 * u64::from(start_id),
+            items_per_box: 0,
+            figure_start_id: 0,
+            figure_end_id: 0,
+        }
+    }
+
+    fn test_variant_table(options: &[VariantOptionArgs]) -> VariantTable {
+        VariantTable {
+            config: Pubkey::new_unique(),
+            options: options
+                .iter()
+                .map(|option| VariantOption {
+                    label: option.label.clone(),
+                    start_id: option.start_id,
+                    end_id: option.end_id,
+                    next_id: option.start_id,
+                    price_lamports: option.price_lamports,
+                    discount_price_lamports: option.discount_price_lamports,
+                    sold_out: false,
+                    items_per_box: option.items_per_box,
+                    figure_start_id: option.figure_start_id,
+                    figure_end_id: option.figure_end_id,
+                })
+                .collect(),
+            bump: 255,
+        }
+    }
+
+    fn test_openable_table_variant_cfg() -> BoxMinterConfig {
+        let mut cfg = test_table_variant_cfg();
+        cfg.items_per_box = 3;
+        cfg
+    }
+
+    fn test_openable_variant_option_args() -> Vec<VariantOptionArgs> {
+        [(1, 15, 2, 1, 30), (16, 30, 2, 31, 60), (31, 34, 3, 61, 72)]
+            .into_iter()
+            .map(
+                |(start_id, end_id, items_per_box, figure_start_id, figure_end_id)| {
+                    VariantOptionArgs {
+                        items_per_box,
+                        figure_start_id,
+                        figure_end_id,
+                        ..test_variant_option_args("v", start_id, end_id)
+                    }
+                },
+            )
+            .collect()
+    }
+
+    #[test]
+    fn variant_table_open_settings_require_disjoint_sufficient_pools() {
+        let cfg = test_openable_table_variant_cfg();
+        let options = test_openable_variant_option_args();
+        assert!(validate_variant_table_options(&cfg, &options).is_ok());
+
+        let mut invalid = options.clone();
+        invalid[2].items_per_box = 4;
+        assert!(validate_variant_table_options(&cfg, &invalid).is_err());
+
+        invalid = options.clone();
+        invalid[0].items_per_box = 0;
+        assert!(validate_variant_table_options(&cfg, &invalid).is_err());
+
+        invalid = options.clone();
+        invalid[0].figure_end_id = 29;
+        assert!(validate_variant_table_options(&cfg, &invalid).is_err());
+
+        invalid = options.clone();
+        invalid[1].figure_start_id = 30;
+        assert!(validate_variant_table_options(&cfg, &invalid).is_err());
+
+        invalid = options.clone();
+        invalid[2].figure_end_id = 103;
+        assert!(validate_variant_table_options(&cfg, &invalid).is_err());
+
+        // Rebalancing re-checks pools: the last variant's 12 figures fit at most 4 boxes.
+        let settings: Vec<BoxOpenSettings> = test_variant_table(&options)
+            .options
+            .iter()
+            .map(VariantOption::open_settings)
+            .collect();
+        let grown = test_variant_ranges(&[(1, 15, 1), (16, 29, 16), (30, 34, 30)]);
+        assert!(validate_variant_open_settings(&cfg, &grown, &settings).is_err());
+
+        // Direct-delivery tables carry no open settings.
+        assert!(validate_variant_table_options(&test_table_variant_cfg(), &options).is_err());
+    }
+
+    #[test]
+    fn box_open_settings_resolve_per_table_variant() {
+        let cfg = test_openable_table_variant_cfg();
+        let table = test_variant_table(&test_openable_variant_option_args());
+
+        assert_eq!(
+            box_open_settings(&cfg, Some(&table), 16).unwrap(),
+            BoxOpenSettings {
+                items_per_box: 2,
+                figure_start_id: 31,
+                figure_end_id: 60,
+            }
+        );
+        assert_eq!(
+            box_open_settings(&cfg, Some(&table), 34).unwrap(),
+            BoxOpenSettings {
+                items_per_box: 3,
+                figure_start_id: 61,
+                figure_end_id: 72,
+            }
+        );
+        assert!(box_open_settings(&cfg, Some(&table), 35).is_err());
+        assert!(box_open_settings(&cfg, None, 16).is_err());
+        assert!(box_open_settings(&test_size_variant_cfg(), Some(&table), 16).is_err());
+
+        let standard = test_standard_cfg();
+        assert_eq!(
+            box_open_settings(&standard, Some(&table), 16).unwrap(),
+            BoxOpenSettings {
+                items_per_box: 2,
+                figure_start_id: 1,
+                figure_end_id: 68,
+            }
+        );
+    }
+
+    #[test]
+    fn variant_table_validation_requires_contiguous_ranges() {
+        let cfg = test_table_variant_cfg();
+        let options = vec![
+            test_variant_option_args("S", 1, 10),
+            test_variant_option_args("M", 11, 20),
+            test_variant_option_args("L", 21, 30),
+            test_variant_option_args("XL", 31, 34),
+        ];
+        assert!(validate_variant_table_options(&cfg, &options).is_ok());
+
+        let mut gap = options.clone();
+        gap[1].start_id = 12;
+        assert!(validate_variant_table_options(&cfg, &gap).is_err());
+
+        let mut short = options.clone();
+        short[3].end_id = 33;
+        assert!(validate_variant_table_options(&cfg, &short).is_err());
+
+        let mut bad_label = options.clone();
+        bad_label[0].label = "x".repeat(VariantOption::MAX_LABEL + 1);
+        assert!(validate_variant_table_options(&cfg, &bad_label).is_err());
+
+        let mut bad_price = options.clone();
+        bad_price[2].discount_price_lamports = bad_price[2].price_lamports + 1;
+        assert!(validate_variant_table_options(&cfg, &bad_price).is_err());
+
+        let too_many: Vec<_> = (0..=MAX_VARIANT_TABLE_OPTIONS as u32)
+            .map(|i| test_variant_option_args("v", i + 1, i + 1))
+            .collect();
+        let mut many_cfg = test_table_variant_cfg();
+        many_cfg.max_supply = too_many.len() as u32;
+        assert!(validate_variant_table_options(&many_cfg, &too_many).is_err());
+        assert!(
+            validate_variant_table_options(&many_cfg, &too_many[..MAX_VARIANT_TABLE_OPTIONS])
+                .is_err()
+        );
+        many_cfg.max_supply = MAX_VARIANT_TABLE_OPTIONS as u32;
+        assert!(
+            validate_variant_table_options(&many_cfg, &too_many[..MAX_VARIANT_TABLE_OPTIONS])
+                .is_ok()
+        );
+
+        assert!(validate_variant_table_options(&test_size_variant_cfg(), &options).is_err());
+    }
+
+    #[test]
+    fn variant_table_reserves_ids_and_prices_per_option() {
+        let mut cfg = test_table_variant_cfg();
+        let mut table = test_variant_table(&[
+            test_variant_option_args("S", 1, 30),
+            test_variant_option_args("L", 31, 34),
+        ]);
+
+        assert_eq!(
+            variant_unit_price(&cfg, Some(&table), 1, false).unwrap(),
+            310
+        );
+        assert_eq!(
+            variant_unit_price(&cfg, Some(&table), 1, true).unwrap(),
+            155
+        );
+        assert!(variant_unit_price(&cfg, Some(&table), 2, false).is_err());
+        assert!(variant_unit_price(&cfg, None, 0, false).is_err());
+        assert_eq!(
+            variant_unit_price(&test_size_variant_cfg(), None, 0, true).unwrap(),
+            1
+        );
+
+        let id = reserve_variant_selection_metadata_ids(&mut cfg, Some(&mut table), 1, 3).unwrap();
+        assert_eq!(id, 31);
+        assert_eq!(cfg.minted, 3);
+        assert!(!table.options[1].sold_out);
+        let id = reserve_admin_delivery_metadata_ids(&mut cfg, Some(&mut table), 1, 1).unwrap();
+        assert_eq!(id, 34);
+        assert!(table.options[1].sold_out);
+        assert!(reserve_variant_selection_metadata_ids(&mut cfg, Some(&mut table), 1, 1).is_err());
+        assert!(reserve_variant_selection_metadata_ids(&mut cfg, None, 0, 1).is_err());
+
+        table.options[0].sold_out = true;
+        assert!(reserve_variant_selection_metadata_ids(&mut cfg, Some(&mut table), 0, 1).is_err());
+        table.options[0].sold_out = false;
+        let id = reserve_variant_selection_metadata_ids(&mut cfg, Some(&mut table), 0, 2).unwrap();
+        assert_eq!(id, 1);
+        assert_eq!(cfg.minted, 6);
+    }
+
+    fn test_variant_ranges(ranges: &[(u32, u32, u32)]) -> Vec<VariantRange> {
+        ranges
+            .iter()
+            .map(|&(start_id, end_id, next_id)| VariantRange {
+                start_id,
+                end_id,
+                next_id,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn variant_rebalance_moves_only_unminted_tails() {
+        // S sold out at 15, M has issued 16..=19, L untouched.
+        let ranges = test_variant_ranges(&[(1, 15, 16), (16, 30, 20), (31, 34, 31)]);
+
+        // M gives its tail to L; L has not issued anything, so its start can move.
+        assert_eq!(
+            rebalanced_variant_ranges(&ranges, &[15, 25, 34], 34).unwrap(),
+            test_variant_ranges(&[(1, 15, 16), (16, 25, 20), (26, 34, 26)])
+        );
+        // M may shrink down to its last issued id, but not past it.
+        assert!(rebalanced_variant_ranges(&ranges, &[15, 19, 34], 34).is_ok());
+        assert!(rebalanced_variant_ranges(&ranges, &[15, 18, 34], 34).is_err());
+        // S cannot grow: M has issued ids, so its start is pinned.
+        assert!(rebalanced_variant_ranges(&ranges, &[20, 30, 34], 34).is_err());
+        // S cannot shrink below what it issued.
+        assert!(rebalanced_variant_ranges(&ranges, &[14, 30, 34], 34).is_err());
+        // Last range must still end at max supply, and ranges must be non-empty.
+        assert!(rebalanced_variant_ranges(&ranges, &[15, 30, 33], 34).is_err());
+        assert!(rebalanced_variant_ranges(&ranges, &[15, 34, 34], 34).is_err());
+        assert!(rebalanced_variant_ranges(&ranges, &[15, 34], 34).is_err());
+    }
+
+    fn test_royalties_args() -> DropRoyaltiesArgs {
+        DropRoyaltiesArgs {
+            basis_points: 500,
+            creators: vec![
+                RoyaltyCreator {
+                    address: Pubkey::new_from_array([1u8; 32]),
+                    percentage: 70,
+                },
+                RoyaltyCreator {
+                    address: Pubkey::new_from_array([2u8; 32]),
+                    percentage: 30,
+                },
+            ],
+            rule_set: RoyaltyRuleSet::ProgramDenyList(vec![Pubkey::new_from_array([3u8; 32])]),
+        }
+    }
+
+    #[test]
+    fn drop_royalties_validation_checks_shares_and_limits() {
+        assert!(validate_drop_royalties(&test_royalties_args()).is_ok());
+
+        let mut args = test_royalties_args();
+        args.basis_points = MAX_ROYALTY_BASIS_POINTS + 1;
+        assert!(validate_drop_royalties(&args).is_err());
+
+        let mut args = test_royalties_args();
+        args.creators[1].percentage = 29;
+        assert!(validate_drop_royalties(&args).is_err());
+
+        let mut args = test_royalties_args();
+        args.creators[1].address = args.creators[0].address;
+        assert!(validate_drop_royalties(&args).is_err());
+
+        let mut args = test_royalties_args();
+        args.creators.clear();
+        assert!(validate_drop_royalties(&args).is_err());
+
+        let mut args = test_royalties_args();
+        args.rule_set = RoyaltyRuleSet::ProgramAllowList(vec![
+            Pubkey::default();
+            MAX_ROYALTY_RULE_SET_PROGRAMS + 1
+        ]);
+        assert!(validate_drop_royalties(&args).is_err());
+    }
+
+    #[test]
+    fn create_plugins_data_encodes_royalties_and_attributes() {
+        assert_eq!(create_plugins_data(None, &[]).unwrap(), vec![0]);
+
+        let args = test_royalties_args();
+        let royalties = DropRoyalties {
+            config: Pubkey::new_unique(),
+            basis_points: args.basis_points,
+            creators: args.creators,
+            rule_set: args.rule_set,
+            bump: 255,
+        };
+        let data = create_plugins_data(Some(&royalties), &[]).unwrap();
+
+        let mut expected = vec![1, 1, 0, 0, 0, MPL_CORE_PLUGIN_ROYALTIES];
+        expected.extend_from_slice(&500u16.to_le_bytes());
+        expected.extend_from_slice(&2u32.to_le_bytes());
+        expected.extend_from_slice(&[1u8; 32]);
+        expected.push(70);
+        expected.extend_from_slice(&[2u8; 32]);
+        expected.push(30);
+        expected.push(2); // RuleSet::ProgramDenyList
+        expected.extend_from_slice(&1u32.to_le_bytes());
+        expected.extend_from_slice(&[3u8; 32]);
+        expected.push(0); // authority: None
+        assert_eq!(data, expected);
+        assert!(data.len() <= DropRoyalties::SPACE);
+
+        let data = create_plugins_data(Some(&royalties), &[("box", "12")]).unwrap();
+        expected[1] = 2; // two PluginAuthorityPairs
+        expected.push(MPL_CORE_PLUGIN_ATTRIBUTES);
+        expected.extend_from_slice(&1u32.to_le_bytes());
+        expected.extend_from_slice(&3u32.to_le_bytes());
+        expected.extend_from_slice(b"box");
+        expected.extend_from_slice(&2u32.to_le_bytes());
+        expected.extend_from_slice(b"12");
+        expected.push(0); // authority: None
+        assert_eq!(data, expected);
+
+        let data = create_plugins_data(None, &[("box", "12")]).unwrap();
+        assert_eq!(&data[..6], &[1, 1, 0, 0, 0, MPL_CORE_PLUGIN_ATTRIBUTES]);
+    }
+
+    #[test]
+    fn box_attribute_values_cover_variant_and_drop_seed() {
+        assert_eq!(hex_bytes32(&[0xab; 32]), "ab".repeat(32));
+        assert_eq!(
+            variant_attribute_value(&test_standard_cfg(), None, 2).unwrap(),
+            None
+        );
+        assert_eq!(
+            variant_attribute_value(&test_size_variant_cfg(), None, 2).unwrap(),
+            Some("2".to_string())
+        );
+        let table = test_variant_table(&[
+            test_variant_option_args("S", 1, 30),
+            test_variant_option_args("XL", 31, 34),
+        ]);
+        let cfg = test_table_variant_cfg();
+        assert_eq!(
+            variant_attribute_value(&cfg, Some(&table), 1).unwrap(),
+            Some("XL".to_string())
+        );
+        assert!(variant_attribute_value(&cfg, None, 1).is_err());
+    }
+
+    #[test]
+    fn variant_rebalance_recomputes_sold_out() {
+        let option = |end_id, next_id, sold_out| VariantOption {
+            label: "M".to_string(),
+            start_id: 16,
+            end_id,
+            next_id,
+            price_lamports: 1,
+            discount_price_lamports: 1,
+            sold_out,
+            items_per_box: 0,
+            figure_start_id: 0,
+            figure_end_id: 0,
+        };
+        let range = |end_id, next_id| VariantRange {
+            start_id: 16,
+            end_id,
+            next_id,
+        };
+
+        // Exhausted range that grows is available again.
+        assert!(!rebalanced_sold_out(&option(19, 20, true), &range(25, 20)));
+        // Shrinking down to the last issued id sells it out.
+        assert!(rebalanced_sold_out(&option(30, 20, false), &range(19, 20)));
+        // An admin pause survives a rebalance; an open range stays open.
+        assert!(rebalanced_sold_out(&option(30, 20, true), &range(25, 20)));
+        assert!(!rebalanced_sold_out(&option(30, 20, false), &range(25, 20)));
+    }
+
+    #[test]
+    fn receipt_ledger_skips_already_issued_ids() {
+        let mut bits = vec![0u8; ReceiptLedger::bitmap_len(10)];
+        assert_eq!(bits.len(), 2);
+
+        let mut box_ids = vec![1u32, 9, 10];
+        retain_unissued_receipt_ids(&mut bits, &mut box_ids).unwrap();
+        assert_eq!(box_ids, vec![1, 9, 10]);
+        assert_eq!(bits, vec![0b0000_0001, 0b0000_0011]);
+
+        let mut retry = vec![10u32, 2, 1];
+        retain_unissued_receipt_ids(&mut bits, &mut retry).unwrap();
+        assert_eq!(retry, vec![2]);
+        assert!(!mark_receipt_issued(&mut bits, 2).unwrap());
+
+        assert!(mark_receipt_issued(&mut bits, 0).is_err());
+        assert!(mark_receipt_issued(&mut bits, 17).is_err());
+    }
+
+    #[test]
+    fn receipt_ledger_bitmaps_follow_the_header_once_allocated() {
+        let ledger = ReceiptLedger {
+            config: Pubkey::new_unique(),
+            box_bits_len: 2,
+            figure_bits_len: 3,
+            bump: 255,
+        };
+        assert_eq!(ledger.space(), ReceiptLedger::HEADER_SPACE + 5);
+        let mut header = Vec::new();
+        ledger.try_serialize(&mut header).unwrap();
+        assert_eq!(header.len(), ReceiptLedger::HEADER_SPACE);
+
+        let mut partial = vec![0u8; ledger.space() - 1];
+        assert!(ledger.bitmaps(&mut partial).is_err());
+
+        let mut data = vec![0u8; ledger.space()];
+        let (box_bits, figure_bits) = ledger.bitmaps(&mut data).unwrap();
+        assert_eq!((box_bits.len(), figure_bits.len()), (2, 3));
+        assert!(mark_receipt_issued(box_bits, 9).unwrap());
+        assert!(mark_receipt_issued(figure_bits, 1).unwrap());
+        assert_eq!(data[ReceiptLedger::HEADER_SPACE..], [0, 1, 1, 0, 0]);
+    }
+
+    #[test]
+    fn receipt_tree_leaves_roll_over_to_next_registered_tree() {
+        let first = Pubkey::new_unique();
+        let second = Pubkey::new_unique();
+        let mut trees = ReceiptTrees {
+            config: Pubkey::new_unique(),
+            active_index: 0,
+            trees: vec![
+                ReceiptTree {
+                    merkle_tree: first,
+                    capacity: 4,
+                    minted: 0,
+                },
+                ReceiptTree {
+                    merkle_tree: second,
+                    capacity: 4,
+                    minted: 0,
+                },
+            ],
+            bump: 255,
+        };
+
+        assert_eq!(
+            reserve_receipt_tree_leaves(&mut trees, first, None, 2).unwrap(),
+            2
+        );
+        assert!(reserve_receipt_tree_leaves(&mut trees, second, None, 1).is_err());
+        assert!(reserve_receipt_tree_leaves(&mut trees, Pubkey::new_unique(), None, 1).is_err());
+        // Overflowing the active tree needs the next registered tree.
+        assert!(reserve_receipt_tree_leaves(&mut trees, first, None, 3).is_err());
+        assert!(
+            reserve_receipt_tree_leaves(&mut trees, first, Some(Pubkey::new_unique()), 3).is_err()
+        );
+        // More than the two trees can hold.
+        assert!(reserve_receipt_tree_leaves(&mut trees, first, Some(second), 7).is_err());
+        assert_eq!(trees.trees[0].minted, 2);
+        assert_eq!(trees.trees[1].minted, 0);
+
+        // Rolls over mid-batch: two leaves fill the first tree, one lands in the second.
+        assert_eq!(
+            reserve_receipt_tree_leaves(&mut trees, first, Some(second), 3).unwrap(),
+            2
+        );
+        assert_eq!(trees.active_index, 1);
+        assert_eq!(trees.trees[1].minted, 1);
+
+        assert_eq!(
+            reserve_receipt_tree_leaves(&mut trees, second, None, 3).unwrap(),
+            3
+        );
+        assert_eq!(trees.active_index, 2);
+        assert!(reserve_receipt_tree_leaves(&mut trees, second, None, 1).is_err());
+    }
+
+    #[test]
+    fn receipt_bumps_planned_for_the_full_batch_line_up_after_ledger_filtering() {
+        let first = Pubkey::new_unique();
+        let second = Pubkey::new_unique();
+        let trees = ReceiptTrees {
+            config: Pubkey::new_unique(),
+            active_index: 0,
+            trees: vec![
+                ReceiptTree {
+                    merkle_tree: first,
+                    capacity: 4,
+                    minted: 2,
+                },
+                ReceiptTree {
+                    merkle_tree: second,
+                    capacity: 4,
+                    minted: 0,
+                },
+            ],
+            bump: 255,
+        };
+        // The client derives bumps for all four requested receipts: two in `first`, then two in
+        // `second`. Whatever the ledger leaves, the active tree takes at most those first two
+        // leaves, so the next tree's mints always start at bump index 2.
+        let planned_active_leaves = 2;
+        for minted in 1..=4u64 {
+            let mut reserved = trees.clone();
+            let next = (minted > planned_active_leaves).then_some(second);
+            let active_tree_leaves =
+                reserve_receipt_tree_leaves(&mut reserved, first, next, minted).unwrap();
+            assert_eq!(active_tree_leaves, minted.min(planned_active_leaves));
+        }
+    }
+
+    #[test]
+    fn admin_order_receipts_record_the_tree_they_roll_over_into() {
+        let first = Pubkey::new_unique();
+        let second = Pubkey::new_unique();
+        let mut trees = ReceiptTrees {
+            config: Pubkey::new_unique(),
+            active_index: 0,
+            trees: vec![
+                ReceiptTree {
+                    merkle_tree: first,
+                    capacity: 4,
+                    minted: 3,
+                },
+                ReceiptTree {
+                    merkle_tree: second,
+                    capacity: 4,
+                    minted: 0,
+                },
+            ],
+            bump: 255,
+        };
+
+        // A batch of three crosses the boundary: leaf 3 of `first`, then leaves 0 and 1 of `second`.
+        let active_tree_leaves =
+            reserve_receipt_tree_leaves(&mut trees, first, Some(second), 3).unwrap();
+        assert_eq!(active_tree_leaves, 1);
+        assert_eq!(
+            admin_order_receipt_rollover(3, active_tree_leaves, Some((second, 0))).unwrap(),
+            (2, second, 0)
+        );
+        assert!(admin_order_receipt_rollover(3, active_tree_leaves, None).is_err());
+
+        // Orders that fit (or mint into a drop's unregistered tree) record no rollover.
+        assert_eq!(
+            admin_order_receipt_rollover(2, 2, Some((second, 1))).unwrap(),
+            (0, Pubkey::default(), 0)
+        );
+        assert_eq!(
+            admin_order_receipt_rollover(2, u64::MAX, None).unwrap(),
+            (0, Pubkey::default(), 0)
+        );
+    }
+
+    #[test]
+    fn create_receipts_tree_data_is_private_v2_tree() {
+        let data = create_receipts_tree_v2_data(&CreateReceiptsTreeArgs {
+            max_depth: 14,
+            max_buffer_size: 64,
+        });
+        assert_eq!(&data[..8], &IX_BUBBLEGUM_CREATE_TREE_V2);
+        assert_eq!(&data[8..12], &14u32.to_le_bytes());
+        assert_eq!(&data[12..16], &64u32.to_le_bytes());
+        assert_eq!(&data[16..], &[1, 0]);
+    }
+
+    #[test]
+    fn receipt_mint_data_carries_symbol_creators_and_order_uri() {
+        let cfg = test_standard_cfg();
+        let args = test_royalties_args();
+        let royalties = DropRoyalties {
+            config: Pubkey::new_unique(),
+            basis_points: args.basis_points,
+            creators: args.creators,
+            rule_set: args.rule_set,
+            bump: 255,
+        };
+        let asset_data = ReceiptAssetData {
+            order_hash: [4u8; 32],
+            delivery_id: 7,
+        };
+        let metadata = new_receipt_metadata(&cfg, Some(&royalties), Some(&asset_data));
+        let mut data = Vec::new();
+        write_receipt_mint_data(&mut data, &metadata, "n", "u").unwrap();
+
+        let mut expected = IX_BUBBLEGUM_MINT_V2.to_vec();
+        borsh_push_string(&mut expected, "n").unwrap();
+        borsh_push_string(&mut expected, &cfg.symbol).unwrap();
+        borsh_push_string(
+            &mut expected,
+            &format!("u?order={}&delivery=7", "04".repeat(32)),
+        )
+        .unwrap();
+        expected.extend_from_slice(&500u16.to_le_bytes());
+        expected.extend_from_slice(&[0, 1, 1, 0]);
+        expected.extend_from_slice(&2u32.to_le_bytes());
+        expected.extend_from_slice(&[1u8; 32]);
+        expected.extend_from_slice(&[0, 70]);
+        expected.extend_from_slice(&[2u8; 32]);
+        expected.extend_from_slice(&[0, 30]);
+        expected.push(1);
+        expected.extend_from_slice(cfg.core_collection.as_ref());
+        // No `assetData`: Bubblegum v2 rejects it.
+        expected.extend_from_slice(&[0, 0]);
+        assert_eq!(data, expected);
+
+        let long_uri = "u".repeat(MAX_RECEIPT_URI_LEN);
+        assert!(write_receipt_mint_data(&mut data, &metadata, "n", &long_uri).is_err());
+
+        // The longest accepted base still fits the longest receipt URI and query.
+        assert_eq!(BoxMinterConfig::MAX_URI_BASE, 91);
+        let longest_base = format!(
+            "https://e.com/{}",
+            "a".repeat(BoxMinterConfig::MAX_URI_BASE - "https://e.com/".len())
+        );
+        let drop_base = normalized_metadata_base(&longest_base).unwrap();
+        assert!(normalized_metadata_base(&format!("{longest_base}a")).is_err());
+        let worst_case = new_receipt_metadata(
+            &cfg,
+            None,
+            Some(&ReceiptAssetData {
+                order_hash: [0xff; 32],
+                delivery_id: u32::MAX,
+            }),
+        );
+        let worst_uri = format!("{drop_base}{URI_PREFIX_RECEIPTS_BOXES}{}.json", u32::MAX);
+        write_receipt_mint_data(&mut data, &worst_case, "n", &worst_uri).unwrap();
+
+        // Bases stored under the earlier 96-byte limit are refused before any receipt is minted.
+        let mut stored = test_standard_cfg();
+        stored.uri_base = format!("{longest_base}aaaaa");
+        assert!(receipt_uri_base(&stored).is_err());
+        stored.uri_base = longest_base.clone();
+        assert_eq!(receipt_uri_base(&stored).unwrap(), longest_base);
+
+        let bare = new_receipt_metadata(&cfg, None, None);
+        write_receipt_mint_data(&mut data, &bare, "n", &long_uri).unwrap();
+        assert_eq!(&data[data.len() - 2..], &[0, 0]);
+        assert_eq!(bare.seller_fee_basis_points, 0);
+        assert!(bare.creators.is_empty());
+    }
+
+    #[test]
+    fn open_box_batch_cap_scales_with_items_per_box() {
+        let mut cfg = test_standard_cfg();
+        cfg.items_per_box = 1;
+        assert_eq!(cfg.max_open_boxes_per_tx(), 6);
+        cfg.items_per_box = 2;
+        assert_eq!(cfg.max_open_boxes_per_tx(), 4);
+        cfg.items_per_box = MAX_ITEMS_PER_BOX;
+        assert_eq!(cfg.max_open_boxes_per_tx(), 2);
+    }
+
+    #[test]
+    fn finalize_batch_cap_scales_with_items_per_box() {
+        let mut cfg = test_standard_cfg();
+        cfg.items_per_box = 1;
+        assert_eq!(cfg.max_finalize_boxes_per_tx(), 4);
+        cfg.items_per_box = 3;
+        assert_eq!(cfg.max_finalize_boxes_per_tx(), 1);
+        cfg.items_per_box = 2;
+        assert_eq!(cfg.max_finalize_boxes_per_tx(), 2);
+        cfg.items_per_box = MAX_ITEMS_PER_BOX;
+        assert_eq!(cfg.max_finalize_boxes_per_tx(), 1);
+    }
+
+    #[test]
+    fn pending_open_pda_validation_requires_matching_box() {
+        let program_id = Pubkey::new_unique();
+        let box_asset = Pubkey::new_unique();
+        let (pending, bump) =
+            Pubkey::find_program_address(&[SEED_PENDING_OPEN, box_asset.as_ref()], &program_id);
+
+        assert!(validate_pending_open_pda(&program_id, box_asset, pending, bump).is_ok());
+        assert!(
+            validate_pending_open_pda(&program_id, Pubkey::new_unique(), pending, bump).is_err()
+        );
+    }
+
     #[test]
     fn zero_drop_seed_is_rejected() {
         assert!(!has_any_non_zero_byte(&[0u8; 32]));
@@ -2,17 +2,22 @@
 
 use anchor_lang::{AccountDeserialize, InstructionData, ToAccountMetas};
 use box_minter::{
-    BoxMinterConfig, DeliverArgs, DeliveryRecord, InitializeArgs, SplitPaymentsV1Args,
+    AppendDeliveryItemsArgs, BeginDeliveryArgs, BoxMinterConfig, CancelDeliveryArgs,
+    CloseDeliveryArgs, CustodyVault, DeliverArgs, DeliverWithQuoteArgs, DeliveryFeeRoute,
+    DeliveryRecord, DeliveryStatus, FulfillFrozenDeliveryArgs, InitializeArgs,
+    ReleaseCustodyAssetsArgs, SealDeliveryArgs, SetDeliveryStatusArgs, SplitPaymentsV1Args,
 };
 use litesvm::{types::FailedTransactionMetadata, LiteSVM};
 use solana_program_runtime::declare_process_instruction;
 use solana_sdk::{
     account::Account,
+    clock::Clock,
     instruction::{AccountMeta, Instruction, InstructionError},
     message::Message,
     native_loader,
     pubkey::Pubkey,
-    system_program,
+    signature::{Keypair, Signer},
+    system_program, sysvar,
     transaction::Transaction,
 };
 use solana_sha256_hasher::hashv;
@@ -83,6 +88,25 @@ fn account_with_owner(lamports: u64, owner: Pubkey) -> Account {
     }
 }
 
+/// MPL Core `AssetV1` base layout: key, owner, collection update authority, name, uri, seq.
+fn core_asset_account(owner: Pubkey, collection: Pubkey, uri: &str) -> Account {
+    let mut data = vec![1u8];
+    data.extend_from_slice(owner.as_ref());
+    data.push(2);
+    data.extend_from_slice(collection.as_ref());
+    data.extend_from_slice(&0u32.to_le_bytes());
+    data.extend_from_slice(&(uri.len() as u32).to_le_bytes());
+    data.extend_from_slice(uri.as_bytes());
+    data.push(0);
+    Account {
+        lamports: 1_000_000,
+        data,
+        owner: MPL_CORE_ID,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
 fn program_path() -> PathBuf {
     let path =
         PathBuf::from(env::var_os("BOX_MINTER_SBF_PATH").expect("BOX_MINTER_SBF_PATH must be set"));
@@ -356,6 +380,14 @@ fn discount_record(config: Pubkey, payer: Pubkey) -> Pubkey {
     .0
 }
 
+fn royalties(config: Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"royalties", config.as_ref()], &box_minter::ID).0
+}
+
+fn custody_vault(config: Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"custody_vault", config.as_ref()], &box_minter::ID).0
+}
+
 fn append_remaining(ix: &mut Instruction, assets: &[Pubkey], recipients: &[(Pubkey, bool)]) {
     ix.accounts
         .extend(assets.iter().map(|key| AccountMeta::new(*key, false)));
@@ -406,6 +438,7 @@ fn mint_boxes_ix(
             core_collection: harness.collection,
             mpl_core_program: MPL_CORE_ID,
             system_program: system_program::ID,
+            royalties: royalties(config),
         },
         box_minter::instruction::MintBoxes {
             quantity,
@@ -430,13 +463,15 @@ fn mint_variant_ix(
 ) -> Instruction {
     let (asset, bump) = asset(config, payer, mint_id, 0);
     let mut ix = instruction(
-        box_minter::accounts::MintBoxes {
+        box_minter::accounts::MintVariantBox {
             config,
             payer,
             treasury: harness.delivery_receiver,
             core_collection: harness.collection,
             mpl_core_program: MPL_CORE_ID,
             system_program: system_program::ID,
+            royalties: royalties(config),
+            variant_table: None,
         },
         box_minter::instruction::MintVariantBox {
             variant_index,
@@ -467,6 +502,8 @@ fn mint_discounted_ix(
             core_collection: harness.collection,
             mpl_core_program: MPL_CORE_ID,
             system_program: system_program::ID,
+            royalties: royalties(config),
+            variant_table: None,
         },
         box_minter::instruction::MintDiscountedBox {
             mint_id,
@@ -499,6 +536,8 @@ fn mint_discounted_variant_ix(
             core_collection: harness.collection,
             mpl_core_program: MPL_CORE_ID,
             system_program: system_program::ID,
+            royalties: royalties(config),
+            variant_table: None,
         },
         box_minter::instruction::MintDiscountedVariantBox {
             variant_index,
@@ -607,7 +646,14 @@ fn split_initialize_setter_delivery_and_legacy_compatibility() {
     let delivered_asset = Pubkey::new_unique();
     harness
         .svm
-        .set_account(delivered_asset, system_account(1_000_000))
+        .set_account(
+            delivered_asset,
+            core_asset_account(
+                delivery_payer,
+                harness.collection,
+                "https://assets.mons.link/runtime/b/1.json",
+            ),
+        )
         .unwrap();
     let delivery_id = 77u32;
     let (delivery, delivery_bump) = Pubkey::find_program_address(
@@ -632,12 +678,25 @@ fn split_initialize_setter_delivery_and_legacy_compatibility() {
             system_program: system_program::ID,
             log_wrapper: SPL_NOOP_ID,
             delivery,
+            custody_vault: custody_vault(delivery_split.key),
+            delivery_fees: delivery_fees(delivery_split.key),
+            delivery_fee_routing: delivery_fee_routing(delivery_split.key),
+            receipt_trees: receipt_trees(delivery_split.key),
+            bubblegum_program: None,
+            receipts_log_wrapper: None,
+            compression_program: None,
+            mpl_core_cpi_signer: None,
         },
         box_minter::instruction::Deliver {
             args: DeliverArgs {
                 delivery_id,
                 delivery_fee_lamports: delivery_fee,
                 delivery_bump,
+                address_cipher_hash: [5u8; 32],
+                delivery_zone: None,
+                receipts: Vec::new(),
+                freeze_in_place: false,
+                asset_id_bumps: Vec::new(),
             },
         },
     );
@@ -658,6 +717,13 @@ fn split_initialize_setter_delivery_and_legacy_compatibility() {
     assert_eq!(record.payer, delivery_payer);
     assert_eq!(record.delivery_fee_lamports, delivery_fee);
     assert_eq!(record.item_count, 1);
+    assert_eq!(record.box_count, 1);
+    assert_eq!(record.figure_count, 0);
+    assert_eq!(
+        record.assets_hash,
+        delivered_assets_hash(&[delivered_asset])
+    );
+    assert_eq!(record.address_cipher_hash, [5u8; 32]);
     assert_eq!(
         &config_account(&harness, delivery_split.key).data[CONFIG_SPACE..],
         delivery_tail.as_slice()
@@ -810,6 +876,7 @@ fn split_mints_fail_closed_on_missing_wrong_and_readonly_recipients() {
             core_collection: harness.collection,
             mpl_core_program: MPL_CORE_ID,
             system_program: system_program::ID,
+            royalties: royalties(legacy.key),
         },
         box_minter::instruction::MintBoxes {
             quantity: 1,
@@ -872,3 +939,1053 @@ fn late_second_mint_failure_rolls_back_payments_config_record_and_prefund_sweep(
     assert_eq!(balance(&harness, prefunded_asset), 2_000_000);
     assert!(harness.svm.get_account(&discount).is_none());
 }
+
+fn delivery_pda(config: Pubkey, delivery_id: u32) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"delivery", config.as_ref(), &delivery_id.to_le_bytes()],
+        &box_minter::ID,
+    )
+}
+
+fn delivered_box(harness: &mut Harness, owner: Pubkey) -> Pubkey {
+    let key = Pubkey::new_unique();
+    harness
+        .svm
+        .set_account(
+            key,
+            core_asset_account(
+                owner,
+                harness.collection,
+                "https://assets.mons.link/runtime/b/1.json",
+            ),
+        )
+        .unwrap();
+    key
+}
+
+fn deliver_box_ix(
+    harness: &Harness,
+    config: Pubkey,
+    delivery_id: u32,
+    asset: Pubkey,
+) -> Instruction {
+    let (delivery, delivery_bump) = delivery_pda(config, delivery_id);
+    let mut ix = instruction(
+        box_minter::accounts::Deliver {
+            config,
+            cosigner: harness.admin,
+            payer: harness.payer,
+            treasury: harness.delivery_receiver,
+            core_collection: harness.collection,
+            mpl_core_program: MPL_CORE_ID,
+            system_program: system_program::ID,
+            log_wrapper: SPL_NOOP_ID,
+            delivery,
+            custody_vault: custody_vault(config),
+            delivery_fees: delivery_fees(config),
+            delivery_fee_routing: delivery_fee_routing(config),
+            receipt_trees: receipt_trees(config),
+            bubblegum_program: None,
+            receipts_log_wrapper: None,
+            compression_program: None,
+            mpl_core_cpi_signer: None,
+        },
+        box_minter::instruction::Deliver {
+            args: DeliverArgs {
+                delivery_id,
+                delivery_fee_lamports: 10_000_000,
+                delivery_bump,
+                address_cipher_hash: [3u8; 32],
+                delivery_zone: None,
+                receipts: Vec::new(),
+                freeze_in_place: false,
+                asset_id_bumps: Vec::new(),
+            },
+        },
+    );
+    append_remaining(&mut ix, &[asset], &[]);
+    ix
+}
+
+fn delivery_record(harness: &Harness, delivery: Pubkey) -> DeliveryRecord {
+    let account = harness.svm.get_account(&delivery).unwrap();
+    DeliveryRecord::try_deserialize(&mut account.data.as_slice()).unwrap()
+}
+
+fn set_delivery_status_ix(
+    harness: &Harness,
+    config: Pubkey,
+    delivery_id: u32,
+    status: DeliveryStatus,
+    tracking_hash: Option<[u8; 32]>,
+) -> Instruction {
+    let (delivery, delivery_bump) = Pubkey::find_program_address(
+        &[b"delivery", config.as_ref(), &delivery_id.to_le_bytes()],
+        &box_minter::ID,
+    );
+    instruction(
+        box_minter::accounts::SetDeliveryStatus {
+            config,
+            cosigner: harness.admin,
+            delivery,
+            system_program: system_program::ID,
+        },
+        box_minter::instruction::SetDeliveryStatus {
+            args: SetDeliveryStatusArgs {
+                delivery_id,
+                delivery_bump,
+                status,
+                tracking_hash,
+            },
+        },
+    )
+}
+
+#[test]
+fn delivery_status_only_moves_along_the_shipping_path() {
+    let mut harness = new_harness();
+    let payer = harness.payer;
+    let admin = harness.admin;
+    let config = initialize_split(&mut harness, 14, false, "box", payer);
+    let asset = delivered_box(&mut harness, payer);
+    let deliver_ix = deliver_box_ix(&harness, config.key, 1, asset);
+    send_payer(&mut harness, deliver_ix);
+    let (delivery, _) = delivery_pda(config.key, 1);
+
+    let skip_ix = set_delivery_status_ix(
+        &harness,
+        config.key,
+        1,
+        DeliveryStatus::Shipped,
+        Some([1u8; 32]),
+    );
+    let error = send_error(&mut harness, admin, skip_ix);
+    assert_error_contains(&error, "InvalidDeliveryStatusTransition");
+
+    let packed_ix = set_delivery_status_ix(&harness, config.key, 1, DeliveryStatus::Packed, None);
+    send_admin(&mut harness, packed_ix);
+    let untracked_ix =
+        set_delivery_status_ix(&harness, config.key, 1, DeliveryStatus::Shipped, None);
+    let error = send_error(&mut harness, admin, untracked_ix);
+    assert_error_contains(&error, "MissingTrackingHash");
+    let shipped_ix = set_delivery_status_ix(
+        &harness,
+        config.key,
+        1,
+        DeliveryStatus::Shipped,
+        Some([1u8; 32]),
+    );
+    send_admin(&mut harness, shipped_ix);
+
+    let back_ix = set_delivery_status_ix(&harness, config.key, 1, DeliveryStatus::Packed, None);
+    let error = send_error(&mut harness, admin, back_ix);
+    assert_error_contains(&error, "InvalidDeliveryStatusTransition");
+    let record = delivery_record(&harness, delivery);
+    assert_eq!(record.status, DeliveryStatus::Shipped);
+    assert_eq!(record.tracking_hash, [1u8; 32]);
+}
+
+fn cancel_delivery_ix(
+    harness: &Harness,
+    config: Pubkey,
+    delivery_id: u32,
+    delivery_bump: u8,
+    assets: &[Pubkey],
+) -> Instruction {
+    let (delivery, _) = delivery_pda(config, delivery_id);
+    let mut ix = instruction(
+        box_minter::accounts::CancelDelivery {
+            config,
+            cosigner: harness.admin,
+            treasury: harness.delivery_receiver,
+            payer: harness.payer,
+            core_collection: harness.collection,
+            mpl_core_program: MPL_CORE_ID,
+            system_program: system_program::ID,
+            log_wrapper: SPL_NOOP_ID,
+            delivery,
+            custody_vault: custody_vault(config),
+            bubblegum_program: None,
+            receipts_log_wrapper: None,
+            compression_program: None,
+        },
+        box_minter::instruction::CancelDelivery {
+            args: CancelDeliveryArgs {
+                delivery_id,
+                delivery_bump,
+                receipts: Vec::new(),
+            },
+        },
+    );
+    append_remaining(&mut ix, assets, &[]);
+    ix
+}
+
+#[test]
+fn shipped_deliveries_cannot_be_cancelled() {
+    let mut harness = new_harness();
+    let payer = harness.payer;
+    let admin = harness.admin;
+    let config = initialize_split(&mut harness, 15, false, "box", payer);
+    let asset = delivered_box(&mut harness, payer);
+    let deliver_ix = deliver_box_ix(&harness, config.key, 1, asset);
+    send_payer(&mut harness, deliver_ix);
+    let (delivery, delivery_bump) = delivery_pda(config.key, 1);
+    let packed_ix = set_delivery_status_ix(&harness, config.key, 1, DeliveryStatus::Packed, None);
+    send_admin(&mut harness, packed_ix);
+    let shipped_ix = set_delivery_status_ix(
+        &harness,
+        config.key,
+        1,
+        DeliveryStatus::Shipped,
+        Some([2u8; 32]),
+    );
+    send_admin(&mut harness, shipped_ix);
+
+    let treasury_before = balance(&harness, harness.delivery_receiver);
+    let cancel_ix = cancel_delivery_ix(&harness, config.key, 1, delivery_bump, &[asset]);
+    let error = send_error(&mut harness, admin, cancel_ix);
+    assert_error_contains(&error, "InvalidDeliveryStatusTransition");
+    assert_eq!(
+        delivery_record(&harness, delivery).status,
+        DeliveryStatus::Shipped
+    );
+    assert_eq!(
+        balance(&harness, harness.delivery_receiver),
+        treasury_before
+    );
+}
+
+#[test]
+fn close_delivery_returns_rent_to_the_recorded_payer_only() {
+    let mut harness = new_harness();
+    let payer = harness.payer;
+    let admin = harness.admin;
+    let config = initialize_split(&mut harness, 16, false, "box", payer);
+    let asset = delivered_box(&mut harness, payer);
+    let deliver_ix = deliver_box_ix(&harness, config.key, 1, asset);
+    send_payer(&mut harness, deliver_ix);
+    let (delivery, delivery_bump) = delivery_pda(config.key, 1);
+    let close_ix = |payer| {
+        instruction(
+            box_minter::accounts::CloseDelivery {
+                config: config.key,
+                cosigner: admin,
+                delivery,
+                system_program: system_program::ID,
+                payer,
+            },
+            box_minter::instruction::CloseDelivery {
+                _args: CloseDeliveryArgs {
+                    delivery_id: 1,
+                    delivery_bump,
+                },
+            },
+        )
+    };
+
+    let early_close = close_ix(payer);
+    let error = send_error(&mut harness, admin, early_close);
+    assert_error_contains(&error, "DeliveryNotClosable");
+    for (status, tracking_hash) in [
+        (DeliveryStatus::Packed, None),
+        (DeliveryStatus::Shipped, Some([3u8; 32])),
+        (DeliveryStatus::Delivered, None),
+    ] {
+        let status_ix = set_delivery_status_ix(&harness, config.key, 1, status, tracking_hash);
+        send_admin(&mut harness, status_ix);
+    }
+
+    let stranger = Pubkey::new_unique();
+    harness
+        .svm
+        .set_account(stranger, system_account(1_000_000))
+        .unwrap();
+    let wrong_payer = close_ix(stranger);
+    let error = send_error(&mut harness, admin, wrong_payer);
+    assert_error_contains(&error, "InvalidDeliveryPayer");
+
+    let rent = balance(&harness, delivery);
+    let payer_before = balance(&harness, payer);
+    let admin_before = balance(&harness, admin);
+    let close = close_ix(payer);
+    send_admin(&mut harness, close);
+    assert!(harness.svm.get_account(&delivery).is_none());
+    assert_eq!(balance(&harness, payer) - payer_before, rent);
+    assert_eq!(balance(&harness, stranger), 1_000_000);
+    assert!(balance(&harness, admin) <= admin_before);
+}
+
+fn quote_message(
+    config: Pubkey,
+    payer: Pubkey,
+    args: &DeliverArgs,
+    assets: &[Pubkey],
+    expiry_slot: u64,
+) -> Vec<u8> {
+    let mut message = b"box_minter:delivery_quote:v1".to_vec();
+    message.extend_from_slice(config.as_ref());
+    message.extend_from_slice(&args.delivery_id.to_le_bytes());
+    message.extend_from_slice(payer.as_ref());
+    message.extend_from_slice(&args.delivery_fee_lamports.to_le_bytes());
+    message.extend_from_slice(&delivered_assets_hash(assets));
+    message.extend_from_slice(&args.address_cipher_hash);
+    message.push(0); // delivery_zone: None
+    message.extend_from_slice(&hashv(&[&0u32.to_le_bytes()]).to_bytes()); // no receipts
+    message.push(u8::from(args.freeze_in_place));
+    message.extend_from_slice(&expiry_slot.to_le_bytes());
+    message
+}
+
+/// Ed25519 program instruction carrying `signer`'s signature over `message` inline.
+fn ed25519_quote_ix(signer: &Keypair, message: &[u8]) -> Instruction {
+    let public_key_offset = 16u16;
+    let signature_offset = public_key_offset + 32;
+    let message_offset = signature_offset + 64;
+    let mut data = vec![1u8, 0];
+    for value in [
+        signature_offset,
+        u16::MAX,
+        public_key_offset,
+        u16::MAX,
+        message_offset,
+        message.len() as u16,
+        u16::MAX,
+    ] {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    data.extend_from_slice(signer.pubkey().as_ref());
+    data.extend_from_slice(signer.sign_message(message).as_ref());
+    data.extend_from_slice(message);
+    Instruction {
+        program_id: solana_sdk::ed25519_program::ID,
+        accounts: Vec::new(),
+        data,
+    }
+}
+
+#[test]
+fn delivery_quotes_expire_and_cannot_be_replayed_or_tampered_with() {
+    let mut harness = new_harness();
+    let payer = harness.payer;
+    let config = initialize_split(&mut harness, 17, false, "box", payer);
+    // Quotes are signed by config.admin; swap in a key the test can sign with.
+    let quoter = Keypair::new();
+    let mut config_data = config_account(&harness, config.key);
+    config_data.data[8..40].copy_from_slice(quoter.pubkey().as_ref());
+    harness.svm.set_account(config.key, config_data).unwrap();
+
+    let quoted_ix =
+        |harness: &mut Harness, delivery_id: u32, fee: u64, signed_fee: u64, expiry_slot: u64| {
+            let asset = delivered_box(harness, payer);
+            let (delivery, delivery_bump) = delivery_pda(config.key, delivery_id);
+            let mut args = DeliverArgs {
+                delivery_id,
+                delivery_fee_lamports: signed_fee,
+                delivery_bump,
+                address_cipher_hash: [6u8; 32],
+                delivery_zone: None,
+                receipts: Vec::new(),
+                freeze_in_place: false,
+                asset_id_bumps: Vec::new(),
+            };
+            let message = quote_message(config.key, payer, &args, &[asset], expiry_slot);
+            args.delivery_fee_lamports = fee;
+            let mut ix = instruction(
+                box_minter::accounts::DeliverWithQuote {
+                    config: config.key,
+                    vault: quoter.pubkey(),
+                    payer,
+                    treasury: harness.delivery_receiver,
+                    core_collection: harness.collection,
+                    mpl_core_program: MPL_CORE_ID,
+                    system_program: system_program::ID,
+                    log_wrapper: SPL_NOOP_ID,
+                    delivery,
+                    custody_vault: custody_vault(config.key),
+                    instructions: sysvar::instructions::ID,
+                    delivery_fees: delivery_fees(config.key),
+                    delivery_fee_routing: delivery_fee_routing(config.key),
+                },
+                box_minter::instruction::DeliverWithQuote {
+                    args: DeliverWithQuoteArgs {
+                        delivery: args,
+                        quote_expiry_slot: expiry_slot,
+                    },
+                },
+            );
+            append_remaining(&mut ix, &[asset], &[]);
+            [ed25519_quote_ix(&quoter, &message), ix]
+        };
+    let send_quoted = |harness: &mut Harness, instructions: &[Instruction]| {
+        let tx = transaction(&harness.svm, payer, instructions);
+        harness.svm.send_transaction(tx)
+    };
+    let slot = harness.svm.get_sysvar::<Clock>().slot;
+
+    // The payer cannot lower the fee the cosigner quoted.
+    let tampered = quoted_ix(&mut harness, 1, 1_000, 10_000_000, slot + 10);
+    let error = send_quoted(&mut harness, &tampered).unwrap_err();
+    assert_error_contains(&error, "InvalidDeliveryQuote");
+
+    // Quotes may not outlive a blockhash.
+    let long_lived = quoted_ix(&mut harness, 1, 10_000_000, 10_000_000, slot + 151);
+    let error = send_quoted(&mut harness, &long_lived).unwrap_err();
+    assert_error_contains(&error, "InvalidDeliveryQuote");
+
+    let treasury_before = balance(&harness, harness.delivery_receiver);
+    let quoted = quoted_ix(&mut harness, 1, 10_000_000, 10_000_000, slot + 10);
+    send_quoted(&mut harness, &quoted).unwrap();
+    assert_eq!(
+        balance(&harness, harness.delivery_receiver) - treasury_before,
+        10_000_000
+    );
+
+    // The delivery id is taken while its record exists, so the same quote cannot pay twice.
+    harness.svm.expire_blockhash();
+    let error = send_quoted(&mut harness, &quoted).unwrap_err();
+    assert_error_contains(&error, "already in use");
+
+    let expiring = quoted_ix(&mut harness, 2, 10_000_000, 10_000_000, slot + 10);
+    harness.svm.warp_to_slot(slot + 11);
+    let error = send_quoted(&mut harness, &expiring).unwrap_err();
+    assert_error_contains(&error, "DeliveryQuoteExpired");
+    assert!(harness
+        .svm
+        .get_account(&delivery_pda(config.key, 2).0)
+        .is_none());
+}
+
+fn delivery_fees(config: Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"delivery_fees", config.as_ref()], &box_minter::ID).0
+}
+
+fn delivery_fee_routing(config: Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"delivery_fee_routing", config.as_ref()], &box_minter::ID).0
+}
+
+fn receipt_trees(config: Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"receipt_trees", config.as_ref()], &box_minter::ID).0
+}
+
+fn routed_deliver_ix(
+    harness: &mut Harness,
+    config: Pubkey,
+    payer: Pubkey,
+    delivery_id: u32,
+    recipients: &[(Pubkey, bool)],
+) -> Instruction {
+    let delivered_asset = Pubkey::new_unique();
+    harness
+        .svm
+        .set_account(
+            delivered_asset,
+            core_asset_account(
+                payer,
+                harness.collection,
+                "https://assets.mons.link/runtime/b/1.json",
+            ),
+        )
+        .unwrap();
+    let (delivery, delivery_bump) = Pubkey::find_program_address(
+        &[b"delivery", config.as_ref(), &delivery_id.to_le_bytes()],
+        &box_minter::ID,
+    );
+    let mut ix = instruction(
+        box_minter::accounts::Deliver {
+            config,
+            cosigner: harness.admin,
+            payer,
+            treasury: harness.delivery_receiver,
+            core_collection: harness.collection,
+            mpl_core_program: MPL_CORE_ID,
+            system_program: system_program::ID,
+            log_wrapper: SPL_NOOP_ID,
+            delivery,
+            custody_vault: custody_vault(config),
+            delivery_fees: delivery_fees(config),
+            delivery_fee_routing: delivery_fee_routing(config),
+            receipt_trees: receipt_trees(config),
+            bubblegum_program: None,
+            receipts_log_wrapper: None,
+            compression_program: None,
+            mpl_core_cpi_signer: None,
+        },
+        box_minter::instruction::Deliver {
+            args: DeliverArgs {
+                delivery_id,
+                delivery_fee_lamports: 25_000_000,
+                delivery_bump,
+                address_cipher_hash: [0u8; 32],
+                delivery_zone: None,
+                receipts: Vec::new(),
+                freeze_in_place: false,
+                asset_id_bumps: Vec::new(),
+            },
+        },
+    );
+    append_remaining(&mut ix, &[delivered_asset], recipients);
+    ix
+}
+
+#[test]
+fn delivery_fees_follow_the_mint_split_or_their_own_route() {
+    let mut harness = new_harness();
+    let payer = harness.payer;
+    let split = initialize_split(&mut harness, 10, false, "box", payer);
+    let routing_ix = instruction(
+        box_minter::accounts::InitializeDeliveryFeeRouting {
+            config: split.key,
+            admin: harness.admin,
+            delivery_fee_routing: delivery_fee_routing(split.key),
+            system_program: system_program::ID,
+        },
+        box_minter::instruction::InitializeDeliveryFeeRouting {
+            route: DeliveryFeeRoute::MintSplit,
+        },
+    );
+    send_admin(&mut harness, routing_ix);
+
+    let treasury_before = balance(&harness, harness.delivery_receiver);
+    let initial_balances = harness.recipients.map(|key| balance(&harness, key));
+    let missing = routed_deliver_ix(&mut harness, split.key, payer, 1, &[]);
+    let missing_error = send_error(&mut harness, payer, missing);
+    assert_error_contains(&missing_error, "InvalidRemainingAccounts");
+
+    let mut wrong = split_recipient_metas(&harness);
+    wrong.swap(0, 1);
+    let wrong_ix = routed_deliver_ix(&mut harness, split.key, payer, 2, &wrong);
+    let wrong_error = send_error(&mut harness, payer, wrong_ix);
+    assert_error_contains(&wrong_error, "InvalidSplitPaymentsRecipients");
+    assert_eq!(
+        harness.recipients.map(|key| balance(&harness, key)),
+        initial_balances
+    );
+
+    let recipients = split_recipient_metas(&harness);
+    let mint_split_ix = routed_deliver_ix(&mut harness, split.key, payer, 3, &recipients);
+    send_payer(&mut harness, mint_split_ix);
+    assert_recipient_delta(&harness, initial_balances, [17_500_000, 7_500_000, 0]);
+    let (split_delivery, _) = Pubkey::find_program_address(
+        &[b"delivery", split.key.as_ref(), &3u32.to_le_bytes()],
+        &box_minter::ID,
+    );
+    let split_record = harness.svm.get_account(&split_delivery).unwrap();
+    let split_record = DeliveryRecord::try_deserialize(&mut split_record.data.as_slice()).unwrap();
+    // Split fees were paid out to recipients, so cancel refuses to refund them.
+    assert!(split_record.fee_split);
+
+    let own_split_ix = instruction(
+        box_minter::accounts::SetDeliveryFeeRouting {
+            config: split.key,
+            admin: harness.admin,
+            delivery_fee_routing: delivery_fee_routing(split.key),
+        },
+        box_minter::instruction::SetDeliveryFeeRouting {
+            route: DeliveryFeeRoute::Split(three_recipient_split_args(harness.recipients)),
+        },
+    );
+    send_admin(&mut harness, own_split_ix);
+    let own_before = harness.recipients.map(|key| balance(&harness, key));
+    let recipients = three_recipient_metas(&harness);
+    let own_route_ix = routed_deliver_ix(&mut harness, split.key, payer, 4, &recipients);
+    send_payer(&mut harness, own_route_ix);
+    assert_recipient_delta(&harness, own_before, [17_500_000, 5_000_000, 2_500_000]);
+    assert_eq!(
+        balance(&harness, harness.delivery_receiver),
+        treasury_before
+    );
+}
+
+/// Mirrors the program's chained `assets_hash` of delivered asset keys.
+fn delivered_assets_hash(assets: &[Pubkey]) -> [u8; 32] {
+    assets.iter().fold(hashv(&[]).to_bytes(), |hash, key| {
+        hashv(&[hash.as_ref(), key.as_ref()]).to_bytes()
+    })
+}
+
+fn session_deliver_accounts(
+    harness: &Harness,
+    config: Pubkey,
+    payer: Pubkey,
+    delivery: Pubkey,
+) -> box_minter::accounts::Deliver {
+    box_minter::accounts::Deliver {
+        config,
+        cosigner: harness.admin,
+        payer,
+        treasury: harness.delivery_receiver,
+        core_collection: harness.collection,
+        mpl_core_program: MPL_CORE_ID,
+        system_program: system_program::ID,
+        log_wrapper: SPL_NOOP_ID,
+        delivery,
+        custody_vault: custody_vault(config),
+        delivery_fees: delivery_fees(config),
+        delivery_fee_routing: delivery_fee_routing(config),
+        receipt_trees: receipt_trees(config),
+        bubblegum_program: None,
+        receipts_log_wrapper: None,
+        compression_program: None,
+        mpl_core_cpi_signer: None,
+    }
+}
+
+#[test]
+fn delivery_session_takes_payment_once_and_seals_after_all_items() {
+    let mut harness = new_harness();
+    let payer = harness.payer;
+    let config = initialize_split(&mut harness, 11, false, "box", payer);
+    let delivery_id = 5u32;
+    let (delivery, delivery_bump) = Pubkey::find_program_address(
+        &[b"delivery", config.key.as_ref(), &delivery_id.to_le_bytes()],
+        &box_minter::ID,
+    );
+    let assets = [
+        delivered_box(&mut harness, payer),
+        delivered_box(&mut harness, payer),
+        delivered_box(&mut harness, payer),
+    ];
+    let treasury_before = balance(&harness, harness.delivery_receiver);
+
+    let mut begin_ix = instruction(
+        session_deliver_accounts(&harness, config.key, payer, delivery),
+        box_minter::instruction::BeginDelivery {
+            args: BeginDeliveryArgs {
+                delivery: DeliverArgs {
+                    delivery_id,
+                    delivery_fee_lamports: 40_000_000,
+                    delivery_bump,
+                    address_cipher_hash: [6u8; 32],
+                    delivery_zone: None,
+                    receipts: Vec::new(),
+                    freeze_in_place: false,
+                    asset_id_bumps: Vec::new(),
+                },
+                item_count: 3,
+            },
+        },
+    );
+    append_remaining(&mut begin_ix, &assets[..1], &[]);
+    send_payer(&mut harness, begin_ix);
+    assert_eq!(
+        balance(&harness, harness.delivery_receiver) - treasury_before,
+        40_000_000
+    );
+
+    let admin = harness.admin;
+    let seal_ix = || {
+        instruction(
+            box_minter::accounts::SealDelivery {
+                config: config.key,
+                cosigner: admin,
+                delivery,
+            },
+            box_minter::instruction::SealDelivery {
+                args: SealDeliveryArgs {
+                    delivery_id,
+                    delivery_bump,
+                },
+            },
+        )
+    };
+    let early_seal = seal_ix();
+    let early_error = send_error(&mut harness, payer, early_seal);
+    assert_error_contains(&early_error, "InvalidDeliveryItemCount");
+
+    let mut append_ix = instruction(
+        session_deliver_accounts(&harness, config.key, payer, delivery),
+        box_minter::instruction::AppendDeliveryItems {
+            args: AppendDeliveryItemsArgs {
+                delivery_id,
+                delivery_bump,
+            },
+        },
+    );
+    append_remaining(&mut append_ix, &assets[1..], &[]);
+    send_payer(&mut harness, append_ix);
+    let sealed_ix = seal_ix();
+    send_payer(&mut harness, sealed_ix);
+
+    let delivery_account = harness.svm.get_account(&delivery).unwrap();
+    let record = DeliveryRecord::try_deserialize(&mut delivery_account.data.as_slice()).unwrap();
+    assert_eq!(record.item_count, 3);
+    assert_eq!(record.paid_item_count, 3);
+    assert_eq!(record.box_count, 3);
+    assert_eq!(record.status, DeliveryStatus::Paid);
+    assert_eq!(record.assets_hash, delivered_assets_hash(&assets));
+    assert_eq!(
+        balance(&harness, harness.delivery_receiver) - treasury_before,
+        40_000_000
+    );
+}
+
+#[test]
+fn open_delivery_session_can_be_cancelled() {
+    let mut harness = new_harness();
+    let payer = harness.payer;
+    let config = initialize_split(&mut harness, 11, false, "box", payer);
+    let delivery_id = 6u32;
+    let (delivery, delivery_bump) = Pubkey::find_program_address(
+        &[b"delivery", config.key.as_ref(), &delivery_id.to_le_bytes()],
+        &box_minter::ID,
+    );
+    let assets = [
+        delivered_box(&mut harness, payer),
+        delivered_box(&mut harness, payer),
+    ];
+    let mut begin_ix = instruction(
+        session_deliver_accounts(&harness, config.key, payer, delivery),
+        box_minter::instruction::BeginDelivery {
+            args: BeginDeliveryArgs {
+                delivery: DeliverArgs {
+                    delivery_id,
+                    delivery_fee_lamports: 40_000_000,
+                    delivery_bump,
+                    address_cipher_hash: [6u8; 32],
+                    delivery_zone: None,
+                    receipts: Vec::new(),
+                    freeze_in_place: false,
+                    asset_id_bumps: Vec::new(),
+                },
+                item_count: 3,
+            },
+        },
+    );
+    append_remaining(&mut begin_ix, &assets[..1], &[]);
+    send_payer(&mut harness, begin_ix);
+    let mut append_ix = instruction(
+        session_deliver_accounts(&harness, config.key, payer, delivery),
+        box_minter::instruction::AppendDeliveryItems {
+            args: AppendDeliveryItemsArgs {
+                delivery_id,
+                delivery_bump,
+            },
+        },
+    );
+    append_remaining(&mut append_ix, &assets[1..], &[]);
+    send_payer(&mut harness, append_ix);
+
+    // The mock MPL-Core does not move assets; hand the appended boxes to the admin.
+    for asset in assets {
+        let held = core_asset_account(
+            harness.admin,
+            harness.collection,
+            "https://assets.mons.link/runtime/b/1.json",
+        );
+        harness.svm.set_account(asset, held).unwrap();
+    }
+    let payer_before = balance(&harness, payer);
+    let treasury_before = balance(&harness, harness.delivery_receiver);
+    let cancel_ix = cancel_delivery_ix(&harness, config.key, delivery_id, delivery_bump, &assets);
+    send_admin(&mut harness, cancel_ix);
+    assert!(harness.svm.get_account(&delivery).is_none());
+    assert_eq!(
+        treasury_before - balance(&harness, harness.delivery_receiver),
+        40_000_000
+    );
+    assert!(balance(&harness, payer) - payer_before > 40_000_000);
+}
+
+fn release_custody_ix(
+    harness: &Harness,
+    config: Pubkey,
+    delivery_id: u32,
+    assets: &[Pubkey],
+) -> Instruction {
+    let (delivery, delivery_bump) = Pubkey::find_program_address(
+        &[b"delivery", config.as_ref(), &delivery_id.to_le_bytes()],
+        &box_minter::ID,
+    );
+    let mut ix = instruction(
+        box_minter::accounts::ReleaseCustodyAssets {
+            config,
+            cosigner: harness.admin,
+            custody_vault: custody_vault(config),
+            recipient: harness.admin,
+            core_collection: harness.collection,
+            mpl_core_program: MPL_CORE_ID,
+            system_program: system_program::ID,
+            log_wrapper: SPL_NOOP_ID,
+            delivery,
+        },
+        box_minter::instruction::ReleaseCustodyAssets {
+            args: ReleaseCustodyAssetsArgs {
+                delivery_id,
+                delivery_bump,
+                receipt_asset_ids: Vec::new(),
+                burn: true,
+            },
+        },
+    );
+    append_remaining(&mut ix, assets, &[]);
+    ix
+}
+
+#[test]
+fn custody_vault_holds_delivered_items_and_signs_their_return_and_burn() {
+    let mut harness = new_harness();
+    let payer = harness.payer;
+    let admin = harness.admin;
+    let config = initialize_split(&mut harness, 12, false, "box", payer);
+    let vault = custody_vault(config.key);
+
+    let without_vault = delivered_box(&mut harness, vault);
+    let release_ix = release_custody_ix(&harness, config.key, 8, &[without_vault]);
+    let error = send_error(&mut harness, admin, release_ix);
+    assert_error_contains(&error, "InvalidCustodyVault");
+
+    let init_ix = instruction(
+        box_minter::accounts::InitializeCustodyVault {
+            config: config.key,
+            admin,
+            custody_vault: vault,
+            system_program: system_program::ID,
+        },
+        box_minter::instruction::InitializeCustodyVault {},
+    );
+    send_admin(&mut harness, init_ix);
+    let vault_account = harness.svm.get_account(&vault).unwrap();
+    let state = CustodyVault::try_deserialize(&mut vault_account.data.as_slice()).unwrap();
+    assert_eq!(state.config, config.key);
+
+    let delivery_id = 8u32;
+    let (delivery, delivery_bump) = Pubkey::find_program_address(
+        &[b"delivery", config.key.as_ref(), &delivery_id.to_le_bytes()],
+        &box_minter::ID,
+    );
+    let asset = delivered_box(&mut harness, payer);
+    let mut deliver_ix = instruction(
+        session_deliver_accounts(&harness, config.key, payer, delivery),
+        box_minter::instruction::Deliver {
+            args: DeliverArgs {
+                delivery_id,
+                delivery_fee_lamports: 10_000_000,
+                delivery_bump,
+                address_cipher_hash: [8u8; 32],
+                delivery_zone: None,
+                receipts: Vec::new(),
+                freeze_in_place: false,
+                asset_id_bumps: Vec::new(),
+            },
+        },
+    );
+    append_remaining(&mut deliver_ix, &[asset], &[]);
+    send_payer(&mut harness, deliver_ix);
+
+    // The mock MPL-Core does not move assets; hand the delivered box to the vault.
+    let held = core_asset_account(
+        vault,
+        harness.collection,
+        "https://assets.mons.link/runtime/b/1.json",
+    );
+    harness.svm.set_account(asset, held).unwrap();
+    let payer_before = balance(&harness, payer);
+    let cancel_ix = cancel_delivery_ix(&harness, config.key, delivery_id, delivery_bump, &[asset]);
+    send_admin(&mut harness, cancel_ix);
+    assert!(harness.svm.get_account(&delivery).is_none());
+    assert!(balance(&harness, payer) - payer_before > 10_000_000);
+
+    // Only a delivery that can no longer be cancelled releases its own assets from the vault.
+    let (shipped_delivery, shipped_bump) = Pubkey::find_program_address(
+        &[b"delivery", config.key.as_ref(), &9u32.to_le_bytes()],
+        &box_minter::ID,
+    );
+    let shipped = delivered_box(&mut harness, payer);
+    let mut deliver_ix = instruction(
+        session_deliver_accounts(&harness, config.key, payer, shipped_delivery),
+        box_minter::instruction::Deliver {
+            args: DeliverArgs {
+                delivery_id: 9,
+                delivery_fee_lamports: 10_000_000,
+                delivery_bump: shipped_bump,
+                address_cipher_hash: [8u8; 32],
+                delivery_zone: None,
+                receipts: Vec::new(),
+                freeze_in_place: false,
+                asset_id_bumps: Vec::new(),
+            },
+        },
+    );
+    append_remaining(&mut deliver_ix, &[shipped], &[]);
+    send_payer(&mut harness, deliver_ix);
+    let held = core_asset_account(
+        vault,
+        harness.collection,
+        "https://assets.mons.link/runtime/b/1.json",
+    );
+    harness.svm.set_account(shipped, held).unwrap();
+
+    let release_ix = release_custody_ix(&harness, config.key, 9, &[shipped]);
+    let error = send_error(&mut harness, admin, release_ix);
+    assert_error_contains(&error, "InvalidDeliveryStatusTransition");
+
+    let packed_ix = set_delivery_status_ix(&harness, config.key, 9, DeliveryStatus::Packed, None);
+    send_admin(&mut harness, packed_ix);
+    let shipped_ix = set_delivery_status_ix(
+        &harness,
+        config.key,
+        9,
+        DeliveryStatus::Shipped,
+        Some([5u8; 32]),
+    );
+    send_admin(&mut harness, shipped_ix);
+
+    // A vault-held box outside the delivery (e.g. a pending open) is not released with it.
+    let unrelated = delivered_box(&mut harness, vault);
+    let release_ix = release_custody_ix(&harness, config.key, 9, &[unrelated]);
+    let error = send_error(&mut harness, admin, release_ix);
+    assert_error_contains(&error, "InvalidDeliveryAssets");
+
+    let release_ix = release_custody_ix(&harness, config.key, 9, &[shipped]);
+    send_admin(&mut harness, release_ix);
+}
+
+fn set_collection_writable(ix: &mut Instruction, collection: Pubkey) {
+    for meta in ix.accounts.iter_mut() {
+        if meta.pubkey == collection {
+            meta.is_writable = true;
+        }
+    }
+}
+
+fn frozen_deliver_ix(
+    harness: &Harness,
+    config: Pubkey,
+    delivery_id: u32,
+    asset: Pubkey,
+) -> (Instruction, Pubkey, u8) {
+    let (delivery, delivery_bump) = Pubkey::find_program_address(
+        &[b"delivery", config.as_ref(), &delivery_id.to_le_bytes()],
+        &box_minter::ID,
+    );
+    let mut ix = instruction(
+        session_deliver_accounts(harness, config, harness.payer, delivery),
+        box_minter::instruction::Deliver {
+            args: DeliverArgs {
+                delivery_id,
+                delivery_fee_lamports: 10_000_000,
+                delivery_bump,
+                address_cipher_hash: [9u8; 32],
+                delivery_zone: None,
+                receipts: Vec::new(),
+                freeze_in_place: true,
+                asset_id_bumps: Vec::new(),
+            },
+        },
+    );
+    append_remaining(&mut ix, &[asset], &[]);
+    (ix, delivery, delivery_bump)
+}
+
+#[test]
+fn frozen_in_place_deliveries_thaw_on_cancel_and_burn_once_shipped() {
+    let mut harness = new_harness();
+    let payer = harness.payer;
+    let admin = harness.admin;
+    let collection = harness.collection;
+    let config = initialize_split(&mut harness, 13, false, "box", payer);
+
+    let cancelled = delivered_box(&mut harness, payer);
+    let (readonly_ix, _, _) = frozen_deliver_ix(&harness, config.key, 1, cancelled);
+    let error = send_error(&mut harness, payer, readonly_ix);
+    assert_error_contains(&error, "InvalidDeliveryMode");
+
+    let (mut deliver_ix, delivery, delivery_bump) =
+        frozen_deliver_ix(&harness, config.key, 1, cancelled);
+    set_collection_writable(&mut deliver_ix, collection);
+    send_payer(&mut harness, deliver_ix);
+    let delivery_account = harness.svm.get_account(&delivery).unwrap();
+    let record = DeliveryRecord::try_deserialize(&mut delivery_account.data.as_slice()).unwrap();
+    assert!(record.frozen_in_place);
+
+    let mut cancel_ix = cancel_delivery_ix(&harness, config.key, 1, delivery_bump, &[cancelled]);
+    set_collection_writable(&mut cancel_ix, collection);
+    send_admin(&mut harness, cancel_ix);
+    assert!(harness.svm.get_account(&delivery).is_none());
+
+    let shipped = delivered_box(&mut harness, payer);
+    let (mut deliver_ix, delivery, delivery_bump) =
+        frozen_deliver_ix(&harness, config.key, 2, shipped);
+    set_collection_writable(&mut deliver_ix, collection);
+    send_payer(&mut harness, deliver_ix);
+    let fulfill_ix = || {
+        let mut ix = instruction(
+            box_minter::accounts::FulfillFrozenDelivery {
+                config: config.key,
+                cosigner: admin,
+                core_collection: collection,
+                mpl_core_program: MPL_CORE_ID,
+                system_program: system_program::ID,
+                log_wrapper: SPL_NOOP_ID,
+                delivery,
+            },
+            box_minter::instruction::FulfillFrozenDelivery {
+                _args: FulfillFrozenDeliveryArgs {
+                    delivery_id: 2,
+                    delivery_bump,
+                },
+            },
+        );
+        append_remaining(&mut ix, &[shipped], &[]);
+        ix
+    };
+    let early_fulfill = fulfill_ix();
+    let error = send_error(&mut harness, admin, early_fulfill);
+    assert_error_contains(&error, "InvalidDeliveryStatusTransition");
+
+    let status_ix = |status, tracking_hash| {
+        instruction(
+            box_minter::accounts::SetDeliveryStatus {
+                config: config.key,
+                cosigner: admin,
+                delivery,
+                system_program: system_program::ID,
+            },
+            box_minter::instruction::SetDeliveryStatus {
+                args: SetDeliveryStatusArgs {
+                    delivery_id: 2,
+                    delivery_bump,
+                    status,
+                    tracking_hash,
+                },
+            },
+        )
+    };
+    let close_ix = || {
+        instruction(
+            box_minter::accounts::CloseDelivery {
+                config: config.key,
+                cosigner: admin,
+                delivery,
+                system_program: system_program::ID,
+                payer,
+            },
+            box_minter::instruction::CloseDelivery {
+                _args: CloseDeliveryArgs {
+                    delivery_id: 2,
+                    delivery_bump,
+                },
+            },
+        )
+    };
+    send_admin(&mut harness, status_ix(DeliveryStatus::Packed, None));
+    send_admin(
+        &mut harness,
+        status_ix(DeliveryStatus::Shipped, Some([4u8; 32])),
+    );
+    send_admin(&mut harness, status_ix(DeliveryStatus::Delivered, None));
+
+    // Delivered but still frozen: the record stays until fulfillment burns the assets.
+    let error = send_error(&mut harness, admin, close_ix());
+    assert_error_contains(&error, "DeliveryNotClosable");
+
+    let fulfill = fulfill_ix();
+    send_admin(&mut harness, fulfill);
+    let delivery_account = harness.svm.get_account(&delivery).unwrap();
+    let record = DeliveryRecord::try_deserialize(&mut delivery_account.data.as_slice()).unwrap();
+    assert!(record.fulfilled);
+
+    send_admin(&mut harness, close_ix());
+    assert!(harness.svm.get_account(&delivery).is_none());
+}
@@ -15,12 +15,16 @@ import {
 } from '../../functions/src/shared/boxMinterConfigCodec.ts';
 import {
   BOX_MINTER_CONFIG_SEED as CONFIG_SEED,
+  BOX_MINTER_CUSTODY_VAULT_SEED as CUSTODY_VAULT_SEED,
   BOX_MINTER_MAX_ITEMS_PER_BOX as MAX_ITEMS_PER_BOX,
   BOX_MINTER_MIN_OPENABLE_ITEMS_PER_BOX as MIN_OPENABLE_ITEMS_PER_BOX,
   BOX_MINTER_MINT_VARIANT_KIND_NONE as MINT_VARIANT_KIND_NONE,
   BOX_MINTER_MINT_VARIANT_KIND_SIZE as MINT_VARIANT_KIND_SIZE,
+  BOX_MINTER_MINT_VARIANT_KIND_TABLE as MINT_VARIANT_KIND_TABLE,
   BOX_MINTER_MINT_VARIANT_OPTION_COUNT as MINT_VARIANT_OPTION_COUNT,
   BOX_MINTER_PENDING_OPEN_SEED as PENDING_OPEN_SEED,
+  BOX_MINTER_ROYALTIES_SEED as ROYALTIES_SEED,
+  BOX_MINTER_VARIANT_TABLE_SEED as VARIANT_TABLE_SEED,
   isOpenableBoxMinterItemsPerBox,
   type BoxMinterMintVariantTuple,
 } from '../../functions/src/shared/boxMinterProtocol.ts';
@@ -347,6 +351,27 @@ function pendingOpenPda(boxAsset: PublicKey, programId: PublicKey): [PublicKey,
   return PublicKey.findProgramAddressSync([Buffer.from(PENDING_OPEN_SEED), boxAsset.toBuffer()], programId);
 }
 
+function royaltiesMeta(cfg: BoxMinterConfigAccount, programId: PublicKey) {
+  const [pubkey] = PublicKey.findProgramAddressSync([utf8(ROYALTIES_SEED), cfg.pubkey.toBuffer()], programId);
+  return { pubkey, isSigner: false, isWritable: false };
+}
+
+function custodyVaultPda(configPda: PublicKey, programId: PublicKey): PublicKey {
+  return PublicKey.findProgramAddressSync([utf8(CUSTODY_VAULT_SEED), configPda.toBuffer()], programId)[0];
+}
+
+function variantTablePda(configPda: PublicKey, programId: PublicKey): [PublicKey, number] {
+  return PublicKey.findProgramAddressSync([utf8(VARIANT_TABLE_SEED), configPda.toBuffer()], programId);
+}
+
+// Optional Anchor account: table drops pass the variant table PDA, every other drop passes the program id.
+function variantTableMeta(cfg: BoxMinterConfigAccount, programId: PublicKey, isWritable: boolean) {
+  if (cfg.mintVariantKind !== MINT_VARIANT_KIND_TABLE) {
+    return { pubkey: programId, isSigner: false, isWritable: false };
+  }
+  return { pubkey: variantTablePda(cfg.pubkey, programId)[0], isSigner: false, isWritable };
+}
+
 function pendingDudeAssetPda(
   pending: PublicKey,
   index: number,
@@ -388,7 +413,7 @@ function resolveMintVariantIndex(
   dropConfig: DropProgramConfig,
   variantKey: string,
 ): number {
-  if (cfg.mintVariantKind !== MINT_VARIANT_KIND_SIZE) {
+  if (cfg.mintVariantKind !== MINT_VARIANT_KIND_SIZE && cfg.mintVariantKind !== MINT_VARIANT_KIND_TABLE) {
     throw new Error('This drop does not use variant minting');
   }
   const selection = dropConfig.mintSelection;
@@ -399,9 +424,11 @@ function resolveMintVariantIndex(
   if (index === -1) {
     throw new Error(`Unknown mint variant: ${variantKey}`);
   }
+  // Table drops keep their ranges in the variant table rather than on the config.
   if (
-    selection.options[index].startId !== cfg.mintVariantStartIds[index] ||
-    selection.options[index].endId !== cfg.mintVariantEndIds[index]
+    cfg.mintVariantKind === MINT_VARIANT_KIND_SIZE &&
+    (selection.options[index].startId !== cfg.mintVariantStartIds[index] ||
+      selection.options[index].endId !== cfg.mintVariantEndIds[index])
   ) {
     throw new Error('Drop mint selection is out of sync with on-chain variant ranges');
   }
@@ -678,6 +705,7 @@ function buildMintBoxesInstructionPlan(
         { pubkey: cfg.coreCollection, isSigner: false, isWritable: true },
         { pubkey: MPL_CORE_PROGRAM_ID, isSigner: false, isWritable: false },
         { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+        royaltiesMeta(cfg, programId),
         ...boxAccounts.map((pubkey) => ({ pubkey, isSigner: false, isWritable: true })),
         ...splitPaymentRecipientMetas(cfg),
       ],
@@ -714,6 +742,7 @@ function buildMintDiscountedBoxInstructionPlan(
         { pubkey: cfg.coreCollection, isSigner: false, isWritable: true },
         { pubkey: MPL_CORE_PROGRAM_ID, isSigner: false, isWritable: false },
         { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+        royaltiesMeta(cfg, programId),
         ...boxAccounts.map((pubkey) => ({ pubkey, isSigner: false, isWritable: true })),
         ...splitPaymentRecipientMetas(cfg),
       ],
@@ -742,6 +771,8 @@ function buildMintVariantInstructionPlan(
         { pubkey: cfg.coreCollection, isSigner: false, isWritable: true },
         { pubkey: MPL_CORE_PROGRAM_ID, isSigner: false, isWritable: false },
         { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+        royaltiesMeta(cfg, programId),
+        variantTableMeta(cfg, programId, true),
         { pubkey: boxAccounts[0], isSigner: false, isWritable: true },
         ...splitPaymentRecipientMetas(cfg),
       ],
@@ -773,6 +804,8 @@ function buildMintDiscountedVariantInstructionPlan(
         { pubkey: cfg.coreCollection, isSigner: false, isWritable: true },
         { pubkey: MPL_CORE_PROGRAM_ID, isSigner: false, isWritable: false },
         { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+        royaltiesMeta(cfg, programId),
+        variantTableMeta(cfg, programId, true),
         { pubkey: boxAccounts[0], isSigner: false, isWritable: true },
         ...splitPaymentRecipientMetas(cfg),
       ],
@@ -867,11 +900,14 @@ function buildStartOpenBoxInstructionPlan(
         { pubkey: payer, isSigner: true, isWritable: true },
         { pubkey: boxAsset, isSigner: false, isWritable: true },
         { pubkey: cfg.admin, isSigner: false, isWritable: false },
+        { pubkey: custodyVaultPda(cfg.pubkey, programId), isSigner: false, isWritable: false },
         { pubkey: cfg.coreCollection, isSigner: false, isWritable: false },
         { pubkey: MPL_CORE_PROGRAM_ID, isSigner: false, isWritable: false },
         { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
         { pubkey: SPL_NOOP_PROGRAM_ID, isSigner: false, isWritable: false },
         { pubkey: pendingPda, isSigner: false, isWritable: true },
+        royaltiesMeta(cfg, programId),
+        variantTableMeta(cfg, programId, false),
         ...dudePdas.map((pubkey) => ({ pubkey, isSigner: false, isWritable: true })),
       ],
       data: Buffer.from(IX_START_OPEN_BOX),
@@ -2,6 +2,9 @@ import assert from 'node:assert/strict';
 import test from 'node:test';
 import {
   BOX_MINTER_CONFIG_SEED,
+  BOX_MINTER_CUSTODY_VAULT_SEED,
+  BOX_MINTER_DELIVERY_FEE_ROUTING_SEED,
+  BOX_MINTER_DELIVERY_FEES_SEED,
   BOX_MINTER_MAX_DISCOUNT_MINTS_PER_WALLET,
   BOX_MINTER_MAX_ITEMS_PER_BOX,
   BOX_MINTER_MIN_CONFIGURED_ITEMS_PER_BOX,
@@ -9,8 +12,13 @@ import {
   BOX_MINTER_MIN_OPENABLE_ITEMS_PER_BOX,
   BOX_MINTER_MINT_VARIANT_KIND_NONE,
   BOX_MINTER_MINT_VARIANT_KIND_SIZE,
+  BOX_MINTER_MINT_VARIANT_KIND_TABLE,
   BOX_MINTER_MINT_VARIANT_OPTION_COUNT,
   BOX_MINTER_PENDING_OPEN_SEED,
+  BOX_MINTER_RECEIPT_LEDGER_SEED,
+  BOX_MINTER_RECEIPT_TREES_SEED,
+  BOX_MINTER_ROYALTIES_SEED,
+  BOX_MINTER_VARIANT_TABLE_SEED,
   isBoxMinterDiscountMintsPerWallet,
   isBoxMinterMintVariantKind,
   isConfiguredBoxMinterItemsPerBox,
@@ -21,6 +29,13 @@ import {
 test('box-minter item predicates preserve configured and openable boundaries', () => {
   assert.equal(BOX_MINTER_CONFIG_SEED, 'config');
   assert.equal(BOX_MINTER_PENDING_OPEN_SEED, 'open');
+  assert.equal(BOX_MINTER_VARIANT_TABLE_SEED, 'variants');
+  assert.equal(BOX_MINTER_ROYALTIES_SEED, 'royalties');
+  assert.equal(BOX_MINTER_RECEIPT_TREES_SEED, 'receipt_trees');
+  assert.equal(BOX_MINTER_RECEIPT_LEDGER_SEED, 'receipt_ledger');
+  assert.equal(BOX_MINTER_DELIVERY_FEES_SEED, 'delivery_fees');
+  assert.equal(BOX_MINTER_DELIVERY_FEE_ROUTING_SEED, 'delivery_fee_routing');
+  assert.equal(BOX_MINTER_CUSTODY_VAULT_SEED, 'custody_vault');
   assert.equal(BOX_MINTER_MIN_CONFIGURED_ITEMS_PER_BOX, 0);
   assert.equal(BOX_MINTER_MIN_OPENABLE_ITEMS_PER_BOX, 1);
   assert.equal(BOX_MINTER_MAX_ITEMS_PER_BOX, 5);
@@ -45,6 +60,7 @@ test('box-minter discount and variant predicates preserve protocol boundaries',
   assert.equal(BOX_MINTER_MAX_DISCOUNT_MINTS_PER_WALLET, 3);
   assert.equal(BOX_MINTER_MINT_VARIANT_KIND_NONE, 0);
   assert.equal(BOX_MINTER_MINT_VARIANT_KIND_SIZE, 1);
+  assert.equal(BOX_MINTER_MINT_VARIANT_KIND_TABLE, 2);
   assert.equal(BOX_MINTER_MINT_VARIANT_OPTION_COUNT, 3);
 
   for (const value of [0, 1.5, 4, Number.NaN, Number.POSITIVE_INFINITY, '1']) {
@@ -56,8 +72,9 @@ test('box-minter discount and variant predicates preserve protocol boundaries',
 
   assert.equal(isBoxMinterMintVariantKind(BOX_MINTER_MINT_VARIANT_KIND_NONE), true);
   assert.equal(isBoxMinterMintVariantKind(BOX_MINTER_MINT_VARIANT_KIND_SIZE), true);
+  assert.equal(isBoxMinterMintVariantKind(BOX_MINTER_MINT_VARIANT_KIND_TABLE), true);
   assert.equal(isBoxMinterMintVariantKind(-1), false);
-  assert.equal(isBoxMinterMintVariantKind(2), false);
+  assert.equal(isBoxMinterMintVariantKind(3), false);
   assert.equal(isBoxMinterMintVariantKind('1'), false);
 });
 
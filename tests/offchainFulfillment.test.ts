@@ -339,18 +339,36 @@ test('admin order PDA and instruction args use the on-chain seed and discriminat
   const configPda = pubkey(2);
   const orderHash = Buffer.alloc(32, 9);
   const [pda] = deriveAdminOrderPda(programId, configPda, orderHash);
-  const data = encodeAdminDeliverVariantOrderArgs({ orderHash, variantIndex: 2, quantity: 1 });
-  const packData = encodeAdminDeliverVariantOrderArgs({ orderHash, variantIndex: 0, quantity: 3 });
+  const data = encodeAdminDeliverVariantOrderArgs({
+    orderHash,
+    variantIndex: 2,
+    quantity: 1,
+    withReceiptAssetData: true,
+    assetIdBumps: [254],
+  });
+  const packData = encodeAdminDeliverVariantOrderArgs({
+    orderHash,
+    variantIndex: 0,
+    quantity: 3,
+    withReceiptAssetData: false,
+    assetIdBumps: [255, 253, 251],
+  });
 
   assert.deepEqual(IX_ADMIN_DELIVER_VARIANT_ORDER, anchorDiscriminator('global', 'admin_deliver_variant_order'));
   assert.deepEqual(ACCOUNT_ADMIN_DELIVERY_ORDER, anchorDiscriminator('account', 'AdminDeliveryOrderRecord'));
   assert.ok(PublicKey.isOnCurve(pda.toBuffer()) === false);
-  assert.equal(data.length, 8 + 32 + 1 + 1);
+  assert.equal(data.length, 8 + 32 + 1 + 1 + 1 + 4 + 1);
   assert.deepEqual(data.subarray(0, 8), IX_ADMIN_DELIVER_VARIANT_ORDER);
   assert.equal(data.readUInt8(40), 2);
   assert.equal(data.readUInt8(41), 1);
+  assert.equal(data.readUInt8(42), 1);
+  assert.equal(data.readUInt32LE(43), 1);
+  assert.equal(data.readUInt8(47), 254);
   assert.equal(packData.readUInt8(40), 0);
   assert.equal(packData.readUInt8(41), 3);
+  assert.equal(packData.readUInt8(42), 0);
+  assert.equal(packData.readUInt32LE(43), 3);
+  assert.deepEqual([...packData.subarray(47)], [255, 253, 251]);
 });
 
 test('resolveMintSelectionVariantIndex maps configured size keys', () => {
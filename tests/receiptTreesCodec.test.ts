@@ -0,0 +1,167 @@
+import assert from 'node:assert/strict';
+import test from 'node:test';
+import { PublicKey } from '@solana/web3.js';
+import {
+  receiptTreeRolloverAccountMetas,
+  resolveLegacyReceiptTreeBatch,
+  resolveReceiptTreeBatch,
+} from '../functions/src/receiptTrees.ts';
+import {
+  RECEIPT_TREES_DISCRIMINATOR,
+  ReceiptTreesCodecError,
+  decodeReceiptTreesData,
+  planReceiptTreeBatch,
+} from '../functions/src/shared/receiptTreesCodec.ts';
+import { BUBBLEGUM_PROGRAM_ADDRESS } from '../functions/src/shared/solanaProgramAddresses.ts';
+
+type TreeFixture = { fill: number; capacity: bigint; minted: bigint };
+
+function encodeReceiptTrees(activeIndex: number, trees: TreeFixture[]): Uint8Array {
+  const count = Buffer.alloc(4);
+  count.writeUInt32LE(trees.length, 0);
+  const entries = trees.map((tree) => {
+    const entry = Buffer.alloc(32 + 8 + 8, tree.fill);
+    entry.writeBigUInt64LE(tree.capacity, 32);
+    entry.writeBigUInt64LE(tree.minted, 40);
+    return entry;
+  });
+  return Buffer.concat([
+    Buffer.from(RECEIPT_TREES_DISCRIMINATOR),
+    Buffer.alloc(32, 9),
+    Buffer.from([activeIndex]),
+    count,
+    ...entries,
+    Buffer.from([254]),
+  ]);
+}
+
+function codecReason(reason: string) {
+  return (error: unknown) => error instanceof ReceiptTreesCodecError && error.reason === reason;
+}
+
+test('receipt trees decode the registry layout', () => {
+  const decoded = decodeReceiptTreesData(
+    encodeReceiptTrees(1, [
+      { fill: 1, capacity: 8n, minted: 8n },
+      { fill: 2, capacity: 1024n, minted: 17n },
+    ]),
+  );
+
+  assert.deepEqual(Array.from(decoded.config), new Array(32).fill(9));
+  assert.equal(decoded.activeIndex, 1);
+  assert.equal(decoded.trees.length, 2);
+  assert.deepEqual(Array.from(decoded.trees[1].merkleTree), new Array(32).fill(2));
+  assert.equal(decoded.trees[1].capacity, 1024n);
+  assert.equal(decoded.trees[1].minted, 17n);
+  assert.equal(decoded.bump, 254);
+});
+
+test('receipt trees reject foreign and truncated accounts', () => {
+  const data = encodeReceiptTrees(0, [{ fill: 1, capacity: 8n, minted: 0n }]);
+  const foreign = Uint8Array.from(data);
+  foreign[0] ^= 0xff;
+
+  assert.throws(() => decodeReceiptTreesData(foreign), codecReason('invalid-discriminator'));
+  assert.throws(() => decodeReceiptTreesData(data.subarray(0, data.length - 2)), codecReason('truncated-vector'));
+  assert.throws(() => decodeReceiptTreesData(data.subarray(0, 12)), codecReason('too-short'));
+});
+
+test('receipt tree batches skip full trees and roll over into the next tree', () => {
+  const trees = decodeReceiptTreesData(
+    encodeReceiptTrees(0, [
+      { fill: 1, capacity: 8n, minted: 8n },
+      { fill: 2, capacity: 8n, minted: 5n },
+      { fill: 3, capacity: 8n, minted: 0n },
+    ]),
+  );
+
+  const fits = planReceiptTreeBatch(trees, 3);
+  assert.equal(fits.active.merkleTree[0], 2);
+  assert.equal(fits.active.firstLeafIndex, 5n);
+  assert.equal(fits.active.leaves, 3);
+  assert.equal(fits.next, null);
+
+  const rolled = planReceiptTreeBatch(trees, 5);
+  assert.equal(rolled.active.leaves, 3);
+  assert.ok(rolled.next);
+  assert.equal(rolled.next.merkleTree[0], 3);
+  assert.equal(rolled.next.firstLeafIndex, 0n);
+  assert.equal(rolled.next.leaves, 2);
+});
+
+test('receipt tree batches fail when the registry cannot hold them', () => {
+  const trees = decodeReceiptTreesData(
+    encodeReceiptTrees(0, [
+      { fill: 1, capacity: 8n, minted: 6n },
+      { fill: 2, capacity: 4n, minted: 0n },
+    ]),
+  );
+
+  assert.throws(() => planReceiptTreeBatch(trees, 7), codecReason('capacity-exceeded'));
+  assert.throws(() => planReceiptTreeBatch(trees, -1), codecReason('invalid-quantity'));
+
+  const last = decodeReceiptTreesData(encodeReceiptTrees(0, [{ fill: 1, capacity: 8n, minted: 6n }]));
+  assert.throws(() => planReceiptTreeBatch(last, 3), codecReason('trees-full'));
+});
+
+test('receipt tree batches carry asset id bumps for every leaf in mint order', () => {
+  const batch = resolveReceiptTreeBatch(
+    encodeReceiptTrees(0, [
+      { fill: 1, capacity: 8n, minted: 7n },
+      { fill: 2, capacity: 8n, minted: 0n },
+    ]),
+    3,
+  );
+  const assetIdBump = (fill: number, leafIndex: bigint) => {
+    const leaf = Buffer.alloc(8);
+    leaf.writeBigUInt64LE(leafIndex, 0);
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from('asset'), Buffer.alloc(32, fill), leaf],
+      new PublicKey(BUBBLEGUM_PROGRAM_ADDRESS),
+    )[1];
+  };
+
+  assert.ok(batch.merkleTree.equals(new PublicKey(Buffer.alloc(32, 1))));
+  assert.ok(batch.nextMerkleTree?.equals(new PublicKey(Buffer.alloc(32, 2))));
+  assert.deepEqual(batch.assetIdBumps, [assetIdBump(1, 7n), assetIdBump(2, 0n), assetIdBump(2, 1n)]);
+});
+
+test('resolveLegacyReceiptTreeBatch derives bumps from the tree config num_minted', () => {
+  const merkleTree = new PublicKey(Buffer.alloc(32, 3));
+  const treeConfigData = Buffer.alloc(8 + 32 + 32 + 8 + 8 + 1);
+  treeConfigData.writeBigUInt64LE(4n, 8 + 32 + 32 + 8);
+  const batch = resolveLegacyReceiptTreeBatch(merkleTree, treeConfigData, 2);
+  const assetIdBump = (leafIndex: bigint) => {
+    const leaf = Buffer.alloc(8);
+    leaf.writeBigUInt64LE(leafIndex, 0);
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from('asset'), merkleTree.toBuffer(), leaf],
+      new PublicKey(BUBBLEGUM_PROGRAM_ADDRESS),
+    )[1];
+  };
+
+  assert.ok(batch.merkleTree.equals(merkleTree));
+  assert.equal(batch.nextMerkleTree, null);
+  assert.deepEqual(batch.assetIdBumps, [assetIdBump(4n), assetIdBump(5n)]);
+  assert.throws(() => resolveLegacyReceiptTreeBatch(merkleTree, treeConfigData.subarray(0, 40), 1), /too short/);
+});
+
+test('receiptTreeRolloverAccountMetas passes the program id for the unused next tree accounts', () => {
+  const programId = new PublicKey(Buffer.alloc(32, 8));
+  const fits = resolveReceiptTreeBatch(encodeReceiptTrees(0, [{ fill: 1, capacity: 8n, minted: 0n }]), 2);
+  assert.deepEqual(
+    receiptTreeRolloverAccountMetas(fits, programId).map((meta) => meta.pubkey.toBase58()),
+    [programId.toBase58(), programId.toBase58()],
+  );
+
+  const rolls = resolveReceiptTreeBatch(
+    encodeReceiptTrees(0, [
+      { fill: 1, capacity: 8n, minted: 7n },
+      { fill: 2, capacity: 8n, minted: 0n },
+    ]),
+    3,
+  );
+  const metas = receiptTreeRolloverAccountMetas(rolls, programId);
+  assert.ok(metas[0]?.pubkey.equals(new PublicKey(Buffer.alloc(32, 2))));
+  assert.equal(metas[1]?.isWritable, true);
+});